@@ -85,6 +85,55 @@ impl Display for SystemRequestError {
     }
 }
 
+/// How the user would like the engine to recover from an error.
+///
+/// This is the driver’s answer to [`DriverHooks::sysrq_error_interaction`].
+/// The choices correspond to the ones that classic TeX offers at its `?`
+/// prompt.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum ErrorResponse {
+    /// Carry on, as the engine would if it weren’t interactive.
+    Proceed,
+
+    /// Insert the given text into the input, immediately before whatever the
+    /// engine was going to read next. No end-of-line character is appended.
+    Insert(String),
+
+    /// Delete the specified number of tokens from the input. This is only
+    /// allowed if the engine said that it can accept deletions, and the
+    /// number should be between 1 and 99.
+    Delete(usize),
+
+    /// Stop asking about errors for the rest of this engine pass, as with
+    /// TeX’s “scroll mode”.
+    Scroll,
+
+    /// Abort processing.
+    Quit,
+}
+
+/// The C-level encoding of [`ErrorResponse`].
+///
+/// cbindgen:rename-all=ScreamingSnakeCase
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[repr(C)]
+pub enum ErrorAction {
+    /// See [`ErrorResponse::Proceed`].
+    Proceed = 0,
+
+    /// See [`ErrorResponse::Insert`].
+    Insert = 1,
+
+    /// See [`ErrorResponse::Delete`].
+    Delete = 2,
+
+    /// See [`ErrorResponse::Scroll`].
+    Scroll = 3,
+
+    /// See [`ErrorResponse::Quit`].
+    Quit = 4,
+}
+
 /// The DriverHooks trait allows engines to interact with the higher-level code
 /// that is driving the TeX processing.
 ///
@@ -150,6 +199,35 @@ pub trait DriverHooks {
     ) -> StdResult<(), SystemRequestError> {
         Err(SystemRequestError::NotImplemented)
     }
+
+    /// The engine has encountered a recoverable error and is asking how to
+    /// proceed.
+    ///
+    /// This is only called if the engine has been configured to recover from
+    /// errors interactively. The error message itself will already have been
+    /// reported through the status backend. The *context* argument gives the
+    /// engine’s description of where the error occurred, in the traditional
+    /// TeX format: one or more pairs of lines, where the first line of each
+    /// pair shows the text that has been read and the second line, indented to
+    /// start where the first one ends, shows the text that hasn’t. The *help*
+    /// argument is the help text associated with the error, which may be
+    /// empty. If *deletions_allowed* is false, the engine can’t safely delete
+    /// tokens and [`ErrorResponse::Delete`] will be treated as
+    /// [`ErrorResponse::Proceed`].
+    ///
+    /// If the driver chooses to delete tokens, this function will be called
+    /// again for the same error once the deletion has happened.
+    ///
+    /// The default implementation returns [`ErrorResponse::Proceed`].
+    fn sysrq_error_interaction(
+        &mut self,
+        _context: &str,
+        _help: &str,
+        _deletions_allowed: bool,
+        _status: &mut dyn StatusBackend,
+    ) -> ErrorResponse {
+        ErrorResponse::Proceed
+    }
 }
 
 /// This type provides a minimal [`DriverHooks`] implementation.
@@ -1259,6 +1337,70 @@ pub unsafe extern "C" fn ttbc_shell_escape(
     libc::c_int::from(es.shell_escape(&rcmd))
 }
 
+/// Ask the driver how to recover from an error
+///
+/// The *context* and *help* strings are UTF-8 text describing the error, as
+/// passed to [`DriverHooks::sysrq_error_interaction`]. On input, *len* gives
+/// the number of characters that can be stored in *text*. On output, if the
+/// return value is `TTBC_ERROR_ACTION_INSERT`, *text* holds the Unicode scalar
+/// values of the text to insert and *len* gives their number; if the return
+/// value is `TTBC_ERROR_ACTION_DELETE`, *len* gives the number of tokens to delete.
+/// Text that doesn't fit in the buffer is dropped with a warning.
+///
+/// # Safety
+///
+/// This function is unsafe because it dereferences raw pointers from C and accepts raw C strings.
+#[no_mangle]
+pub unsafe extern "C" fn ttbc_error_interaction(
+    es: &mut CoreBridgeState,
+    context: *const libc::c_char,
+    help: *const libc::c_char,
+    deletions_allowed: bool,
+    text: *mut u32,
+    len: *mut libc::size_t,
+) -> ErrorAction {
+    let context = CStr::from_ptr(context).to_string_lossy();
+    let help = CStr::from_ptr(help).to_string_lossy();
+
+    match es
+        .hooks
+        .sysrq_error_interaction(&context, &help, deletions_allowed, es.status)
+    {
+        ErrorResponse::Proceed => ErrorAction::Proceed,
+
+        ErrorResponse::Insert(s) => {
+            let capacity = *len;
+            let mut n = 0;
+
+            for c in s.chars() {
+                if n == capacity {
+                    tt_warning!(
+                        es.status,
+                        "the inserted text was too long; only the first {} characters were used",
+                        n
+                    );
+                    break;
+                }
+
+                *text.add(n) = c as u32;
+                n += 1;
+            }
+
+            *len = n;
+            ErrorAction::Insert
+        }
+
+        ErrorResponse::Delete(n) if deletions_allowed => {
+            *len = n.clamp(1, 99);
+            ErrorAction::Delete
+        }
+
+        ErrorResponse::Delete(_) => ErrorAction::Proceed,
+        ErrorResponse::Scroll => ErrorAction::Scroll,
+        ErrorResponse::Quit => ErrorAction::Quit,
+    }
+}
+
 /// Different types of files that can be opened by TeX engines
 ///
 /// This enumeration is used to guess filename extensions to try when looking
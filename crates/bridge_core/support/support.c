@@ -323,3 +323,10 @@ ttstub_shell_escape(const unsigned short *cmd, size_t len)
 {
     return ttbc_shell_escape(tectonic_global_bridge_core, cmd, len);
 }
+
+ttbc_error_action
+ttstub_error_interaction(const char *context, const char *help, bool deletions_allowed,
+                         uint32_t *text, size_t *len)
+{
+    return ttbc_error_interaction(tectonic_global_bridge_core, context, help, deletions_allowed, text, len);
+}
@@ -250,6 +250,8 @@ int ttstub_input_close(rust_input_handle_t handle);
 int ttstub_get_file_md5(char const *path, char *digest);
 
 int ttstub_shell_escape(const unsigned short *cmd, size_t len);
+ttbc_error_action ttstub_error_interaction(const char *context, const char *help, bool deletions_allowed,
+                                           uint32_t *text, size_t *len);
 
 END_EXTERN_C
 
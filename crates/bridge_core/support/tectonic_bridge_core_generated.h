@@ -11,6 +11,33 @@ typedef ttbc_input_handle_t *rust_input_handle_t;
 typedef ttbc_output_handle_t *rust_output_handle_t;
 
 
+/**
+ * The C-level encoding of [`ErrorResponse`].
+ *
+ */
+typedef enum {
+  /**
+   * See [`ErrorResponse::Proceed`].
+   */
+  TTBC_ERROR_ACTION_PROCEED = 0,
+  /**
+   * See [`ErrorResponse::Insert`].
+   */
+  TTBC_ERROR_ACTION_INSERT = 1,
+  /**
+   * See [`ErrorResponse::Delete`].
+   */
+  TTBC_ERROR_ACTION_DELETE = 2,
+  /**
+   * See [`ErrorResponse::Scroll`].
+   */
+  TTBC_ERROR_ACTION_SCROLL = 3,
+  /**
+   * See [`ErrorResponse::Quit`].
+   */
+  TTBC_ERROR_ACTION_QUIT = 4,
+} ttbc_error_action;
+
 /**
  * Different types of files that can be opened by TeX engines
  *
@@ -334,6 +361,28 @@ void ttbc_diag_finish(ttbc_state_t *es, ttbc_diagnostic_t *diag);
  */
 int ttbc_shell_escape(ttbc_state_t *es, const uint16_t *cmd, size_t len);
 
+/**
+ * Ask the driver how to recover from an error
+ *
+ * The *context* and *help* strings are UTF-8 text describing the error, as
+ * passed to [`DriverHooks::sysrq_error_interaction`]. On input, *len* gives
+ * the number of characters that can be stored in *text*. On output, if the
+ * return value is `TTBC_ERROR_ACTION_INSERT`, *text* holds the Unicode scalar
+ * values of the text to insert and *len* gives their number; if the return
+ * value is `TTBC_ERROR_ACTION_DELETE`, *len* gives the number of tokens to delete.
+ * Text that doesn't fit in the buffer is dropped with a warning.
+ *
+ * # Safety
+ *
+ * This function is unsafe because it dereferences raw pointers from C and accepts raw C strings.
+ */
+ttbc_error_action ttbc_error_interaction(ttbc_state_t *es,
+                                         const char *context,
+                                         const char *help,
+                                         bool deletions_allowed,
+                                         uint32_t *text,
+                                         size_t *len);
+
 #ifdef __cplusplus
 } // extern "C"
 #endif // __cplusplus
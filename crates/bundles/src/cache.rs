@@ -24,6 +24,7 @@ use std::{
     io::{BufRead, BufReader, Error as IoError, ErrorKind as IoErrorKind, Read, Write},
     path::{Path, PathBuf},
     str::FromStr,
+    time::{Duration, Instant},
 };
 use tectonic_errors::prelude::*;
 use tectonic_io_base::{
@@ -31,7 +32,7 @@ use tectonic_io_base::{
     digest::{self, Digest, DigestData},
     try_open_file, InputHandle, InputOrigin, IoProvider, OpenResult,
 };
-use tectonic_status_base::{tt_warning, StatusBackend};
+use tectonic_status_base::{tt_note, tt_warning, StatusBackend};
 
 use crate::Bundle;
 
@@ -181,6 +182,29 @@ pub trait CacheBackend: Sized {
         info: &Self::FileInfo,
         status: &mut dyn StatusBackend,
     ) -> Result<Vec<u8>>;
+
+    /// Obtain several files from the backend at once.
+    ///
+    /// This is used to prefetch files in bulk. Each file's contents should be
+    /// passed to *store*, along with the file's position in *files*, as soon
+    /// as they're available, so that the caller doesn't have to wait for all
+    /// of the files to arrive, or hold them all in memory. The files can be
+    /// passed in any order. If *store* returns an error, the backend should
+    /// stop and return that error. The default implementation just calls
+    /// [`Self::get_file`] for each file in turn, but backends that can batch
+    /// or parallelize their requests should do so.
+    fn get_files(
+        &mut self,
+        files: &[(&str, &Self::FileInfo)],
+        status: &mut dyn StatusBackend,
+        store: &mut dyn FnMut(usize, Vec<u8>) -> Result<()>,
+    ) -> Result<()> {
+        for (i, (name, info)) in files.iter().enumerate() {
+            store(i, self.get_file(name, info, status)?)?;
+        }
+
+        Ok(())
+    }
 }
 
 /// Information about a cached file.
@@ -287,6 +311,9 @@ pub struct CachingBundle<CB: CacheBackend> {
     /// This directory contains the actual cached file contents, in a directory
     /// structured based on the digest of each file’s content.
     data_base: PathBuf,
+
+    /// The total time spent waiting on the backend.
+    network_time: Duration,
 }
 
 /// A locally-cached analogue of [`BackendPullData`].
@@ -325,6 +352,7 @@ impl<CB: CacheBackend> CachingBundle<CB> {
         // that describe the overall backend contents.
 
         let mut backend = None;
+        let mut network_time = Duration::ZERO;
 
         let cached_pull_data =
             match load_cached_pull_data::<CB>(&digest_path, &resolved_base, &index_base)? {
@@ -333,7 +361,9 @@ impl<CB: CacheBackend> CachingBundle<CB> {
                     // Some portion of the required cached data is missing. We need to
                     // do a complete pull and then cache the results.
 
+                    let t0 = Instant::now();
                     let (new_backend, pull_data) = CB::open_with_pull(start_url, status)?;
+                    network_time += t0.elapsed();
                     backend = Some(new_backend);
 
                     let digest_text = pull_data.digest.to_string();
@@ -435,6 +465,7 @@ impl<CB: CacheBackend> CachingBundle<CB> {
             only_cached,
             backend,
             index: cached_pull_data.index,
+            network_time,
         })
     }
 
@@ -493,9 +524,11 @@ impl<CB: CacheBackend> CachingBundle<CB> {
         // Do the quick check. If anything goes wrong, eat the error and try a
         // fresh pull.
         if let Some(info) = self.index.get(digest::DIGEST_NAME) {
-            if let Ok(Some((backend, digest))) =
-                CB::open_with_quick_check(&self.resolved_url, info, status)
-            {
+            let t0 = Instant::now();
+            let result = CB::open_with_quick_check(&self.resolved_url, info, status);
+            self.network_time += t0.elapsed();
+
+            if let Ok(Some((backend, digest))) = result {
                 if self.cached_digest == digest {
                     // We managed to pull some data that match the digest. We
                     // can be quite confident that the bundle is what we expect
@@ -508,7 +541,10 @@ impl<CB: CacheBackend> CachingBundle<CB> {
 
         // The quick check failed. Try to pull all data to make sure that it
         // wasn't a network error or that the resolved URL hasn't been updated.
-        let (new_backend, pull_data) = CB::open_with_pull(&self.start_url, status)?;
+        let t0 = Instant::now();
+        let pull_result = CB::open_with_pull(&self.start_url, status);
+        self.network_time += t0.elapsed();
+        let (new_backend, pull_data) = pull_result?;
 
         if self.cached_digest != pull_data.digest {
             // Crap! The backend isn't what we thought it was. We may have been
@@ -579,21 +615,31 @@ impl<CB: CacheBackend> CachingBundle<CB> {
         // have returned incorrect data for previous requests that hit the
         // cache.
 
-        let content = match self.backend.as_mut().unwrap().get_file(name, &info, status) {
+        let t0 = Instant::now();
+        let result = self.backend.as_mut().unwrap().get_file(name, &info, status);
+        self.network_time += t0.elapsed();
+
+        let content = match result {
             Ok(c) => c,
             Err(e) => return OpenResult::Err(e),
         };
 
+        match self.store_file(name, &content) {
+            Ok(p) => OpenResult::Ok(p),
+            Err(e) => OpenResult::Err(e),
+        }
+    }
+
+    /// Save the contents of a file fetched from the backend into the cache,
+    /// and return its filesystem path.
+    fn store_file(&mut self, name: &str, content: &[u8]) -> Result<PathBuf> {
         let length = content.len();
 
         let mut digest_builder = digest::create();
-        digest_builder.update(&content);
+        digest_builder.update(content);
         let digest = DigestData::from(digest_builder);
 
-        let final_path = match digest.create_two_part_path(&self.data_base) {
-            Ok(p) => p,
-            Err(e) => return OpenResult::Err(e),
-        };
+        let final_path = digest.create_two_part_path(&self.data_base)?;
 
         // Perform a racy check for the destination existing, because this
         // matters on Windows: if the destination is already there, we'll get
@@ -602,36 +648,103 @@ impl<CB: CacheBackend> CachingBundle<CB> {
         // subject to the race once.
 
         if !final_path.exists() {
-            if let Err(e) = file_create_write(&final_path, |f| f.write_all(&content)) {
-                return OpenResult::Err(e);
-            }
+            file_create_write(&final_path, |f| f.write_all(content))?;
 
             // Now we can make the file readonly. It would be nice to set the
             // permissions using the already-open file handle owned by the
             // tempfile, but mkstemp doesn't give us access.
-            let mut perms = match fs::metadata(&final_path) {
-                Ok(p) => p,
-                Err(e) => {
-                    return OpenResult::Err(e.into());
-                }
-            }
-            .permissions();
+            let mut perms = fs::metadata(&final_path)?.permissions();
             perms.set_readonly(true);
-
-            if let Err(e) = fs::set_permissions(&final_path, perms) {
-                return OpenResult::Err(e.into());
-            }
+            fs::set_permissions(&final_path, perms)?;
         }
 
         // And finally add a record of this file to our manifest. Note that
         // we're opening and closing the manifest every time we cache a new
         // file; not so efficient, but whatever.
 
-        if let Err(e) = self.save_to_manifest(name, length as u64, digest) {
-            return OpenResult::Err(e);
+        self.save_to_manifest(name, length as u64, digest)?;
+        Ok(final_path)
+    }
+
+    /// Make sure that a set of files is available in the local cache,
+    /// downloading any that aren't.
+    ///
+    /// Unlike the on-demand fetching that happens as an engine opens files,
+    /// this hands all of the needed files to the backend at once, so that it
+    /// can batch its requests. Names that aren't in the bundle are skipped with
+    /// a warning. Returns the number of files that were downloaded.
+    pub fn prefetch<S: AsRef<str>>(
+        &mut self,
+        names: &[S],
+        status: &mut dyn StatusBackend,
+    ) -> Result<usize> {
+        let mut needed = Vec::new();
+
+        for name in names {
+            let name = name.as_ref();
+
+            if self.contents.contains_key(name) {
+                continue;
+            }
+
+            match self.index.get(name) {
+                Some(info) => needed.push((name.to_owned(), info.clone())),
+                None => tt_warning!(status, "file `{}` is not in the bundle", name),
+            }
+        }
+
+        needed.sort_by(|a, b| a.0.cmp(&b.0));
+        needed.dedup_by(|a, b| a.0 == b.0);
+
+        if needed.is_empty() {
+            return Ok(0);
         }
 
-        OpenResult::Ok(final_path)
+        if self.only_cached {
+            bail!(
+                "{} requested files are not cached, but only cached files may be used",
+                needed.len()
+            );
+        }
+
+        self.ensure_backend_validity(status)?;
+
+        tt_note!(status, "downloading {} files", needed.len());
+        let request: Vec<_> = needed.iter().map(|(n, i)| (n.as_ref(), i)).collect();
+
+        // Each file is stored as soon as it arrives, so that whatever has been
+        // downloaded is kept even if a later file fails. The backend has to be
+        // taken out of `self` while it's working so that we can do that.
+        let mut backend = self.backend.take().unwrap();
+        let mut n_stored = 0;
+        let mut store_time = Duration::ZERO;
+        let t0 = Instant::now();
+
+        let result = backend.get_files(&request, status, &mut |i, content| {
+            let t1 = Instant::now();
+            self.store_file(&needed[i].0, &content)?;
+            store_time += t1.elapsed();
+            n_stored += 1;
+            Ok(())
+        });
+
+        self.network_time += t0.elapsed().saturating_sub(store_time);
+        self.backend = Some(backend);
+
+        if let Err(e) = result {
+            if n_stored > 0 {
+                tt_note!(
+                    status,
+                    "{} of the {} files were downloaded before the failure, and have been kept",
+                    n_stored,
+                    needed.len()
+                );
+            }
+
+            return Err(e);
+        }
+
+        Ok(n_stored)
     }
 }
 
@@ -671,6 +784,14 @@ impl<CB: CacheBackend> Bundle for CachingBundle<CB> {
         }
         Ok(self.index.keys().cloned().collect())
     }
+
+    fn prefetch(&mut self, names: &[String], status: &mut dyn StatusBackend) -> Result<usize> {
+        CachingBundle::prefetch(self, names, status)
+    }
+
+    fn network_time(&self) -> Duration {
+        self.network_time
+    }
 }
 
 /// Load the cached "pull" data for a backend.
@@ -764,3 +885,85 @@ fn ensure_cache_dir(root: &Path, path: &str) -> Result<PathBuf> {
 fn make_txt_path(base: &Path, name: &str) -> PathBuf {
     base.join(name).with_extension("txt")
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tectonic_status_base::NoopStatusBackend;
+
+    /// A backend whose files are all available, except for `bad.tex`.
+    struct TestBackend;
+
+    impl CacheBackend for TestBackend {
+        type FileInfo = ();
+
+        fn open_with_pull(
+            _start_url: &str,
+            _status: &mut dyn StatusBackend,
+        ) -> Result<(Self, BackendPullData)> {
+            let pull_data = BackendPullData {
+                resolved_url: "test:".to_owned(),
+                digest: DigestData::zeros(),
+                index: "a.tex\nb.tex\nba.tex\nbad.tex\nc.tex\n".to_owned(),
+            };
+            Ok((TestBackend, pull_data))
+        }
+
+        fn open_with_quick_check(
+            _resolved_url: &str,
+            _digest_file_info: &Self::FileInfo,
+            _status: &mut dyn StatusBackend,
+        ) -> Result<Option<(Self, DigestData)>> {
+            Ok(Some((TestBackend, DigestData::zeros())))
+        }
+
+        fn parse_index_line(line: &str) -> Result<(String, Self::FileInfo)> {
+            Ok((line.to_owned(), ()))
+        }
+
+        fn get_file(
+            &mut self,
+            name: &str,
+            _info: &Self::FileInfo,
+            _status: &mut dyn StatusBackend,
+        ) -> Result<Vec<u8>> {
+            ensure!(name != "bad.tex", "failed to get `{}`", name);
+            Ok(name.as_bytes().to_owned())
+        }
+    }
+
+    #[test]
+    fn prefetch_keeps_partial_progress() {
+        let root = tempfile::tempdir().unwrap();
+        let mut cache = Cache::get_for_custom_directory(root.path());
+        let status = &mut NoopStatusBackend {};
+        let mut bundle = cache.open::<TestBackend>("test:", false, status).unwrap();
+
+        assert_eq!(bundle.prefetch(&["b.tex", "a.tex"], status).unwrap(), 2);
+        assert_eq!(
+            bundle
+                .prefetch(&["a.tex", "nonexistent.tex"], status)
+                .unwrap(),
+            0
+        );
+
+        // The files are fetched in sorted order, so `c.tex` is never reached,
+        // but what came before the failure is kept.
+        assert!(bundle
+            .prefetch(&["c.tex", "bad.tex", "ba.tex"], status)
+            .is_err());
+        assert!(bundle.contents.contains_key("ba.tex"));
+        assert!(!bundle.contents.contains_key("c.tex"));
+
+        // The cached files are still usable without the backend.
+        let mut bundle = cache.open::<TestBackend>("test:", true, status).unwrap();
+        let mut data = Vec::new();
+        bundle
+            .input_open_name("a.tex", status)
+            .must_exist()
+            .unwrap()
+            .read_to_end(&mut data)
+            .unwrap();
+        assert_eq!(data, b"a.tex");
+    }
+}
@@ -14,17 +14,46 @@
 //! resource, the index file merely contains a byte offset and length that are
 //! then used to construct an HTTP Range request to obtain the file as needed.
 
-use flate2::read::GzDecoder;
-use std::{convert::TryInto, io::Read, str::FromStr};
+use flate2::{read::GzDecoder, write::GzEncoder, Compression};
+use std::{
+    convert::TryInto,
+    io::{Read, Write},
+    str::FromStr,
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        mpsc,
+    },
+    thread,
+    time::Duration,
+};
 use tectonic_errors::prelude::*;
 use tectonic_geturl::{DefaultBackend, DefaultRangeReader, GetUrlBackend, RangeReader};
 use tectonic_io_base::digest::{self, DigestData};
 use tectonic_status_base::{tt_note, tt_warning, StatusBackend};
 
-use crate::cache::{BackendPullData, CacheBackend};
+use crate::{
+    cache::{BackendPullData, CacheBackend},
+    Bundle,
+};
 
 const MAX_HTTP_ATTEMPTS: usize = 4;
 
+/// How long to wait before the first retry of a failed download. The delay
+/// doubles with each further attempt.
+const RETRY_DELAY: Duration = Duration::from_millis(500);
+
+/// The maximum number of simultaneous connections to use when fetching many
+/// files at once.
+const MAX_PARALLEL_DOWNLOADS: usize = 8;
+
+/// The maximum amount of data to fetch in one batch when fetching many files
+/// at once, unless a single file is bigger than this. At most two batches per
+/// connection are held in memory at any time.
+const MAX_BATCH_LENGTH: usize = 4 * 1024 * 1024;
+
+/// The size of a block in the tar format.
+const TAR_BLOCK_SIZE: usize = 512;
+
 /// The internal file-information struct used by the [`IndexedTarBackend`].
 #[derive(Clone, Copy, Debug)]
 pub struct FileInfo {
@@ -39,6 +68,7 @@ pub struct FileInfo {
 /// framework.
 #[derive(Debug)]
 pub struct IndexedTarBackend {
+    url: String,
     reader: DefaultRangeReader,
 }
 
@@ -66,6 +96,7 @@ impl CacheBackend for IndexedTarBackend {
         // Step 3: get digest, setting up instance as we go
 
         let mut cache_backend = IndexedTarBackend {
+            url: resolved_url.clone(),
             reader: geturl_backend.open_range_reader(&resolved_url),
         };
 
@@ -109,6 +140,7 @@ impl CacheBackend for IndexedTarBackend {
         status: &mut dyn StatusBackend,
     ) -> Result<Option<(Self, DigestData)>> {
         let mut cache_backend = IndexedTarBackend {
+            url: resolved_url.to_owned(),
             reader: DefaultBackend::default().open_range_reader(resolved_url),
         };
 
@@ -197,4 +229,334 @@ impl CacheBackend for IndexedTarBackend {
 
         Ok(buf)
     }
+
+    fn get_files(
+        &mut self,
+        files: &[(&str, &Self::FileInfo)],
+        status: &mut dyn StatusBackend,
+        store: &mut dyn FnMut(usize, Vec<u8>) -> Result<()>,
+    ) -> Result<()> {
+        let ranges: Vec<(u64, usize)> = files
+            .iter()
+            .map(|(_, info)| Ok((info.offset, info.length.try_into()?)))
+            .collect::<Result<_>>()?;
+
+        // Sort the requests by their location in the tarball, and split them
+        // into batches of limited size, so that nearby files can be coalesced
+        // into single range requests without having too much data in memory
+        // at once. The workers, each with its own connection, take batches in
+        // turn and send the data back to us to be stored.
+
+        let mut order: Vec<usize> = (0..files.len()).collect();
+        order.sort_by_key(|&i| ranges[i].0);
+
+        let mut batches: Vec<Vec<usize>> = Vec::new();
+        let mut batch_length = 0;
+
+        for i in order {
+            let length = ranges[i].1;
+
+            match batches.last_mut() {
+                Some(batch) if batch_length + length <= MAX_BATCH_LENGTH => {
+                    batch.push(i);
+                    batch_length += length;
+                }
+                _ => {
+                    batches.push(vec![i]);
+                    batch_length = length;
+                }
+            }
+        }
+
+        let url = &self.url;
+        let next_batch = AtomicUsize::new(0);
+        let stop = AtomicBool::new(false);
+        let (tx, rx) = mpsc::sync_channel(MAX_PARALLEL_DOWNLOADS);
+
+        thread::scope(|scope| {
+            for _ in 0..MAX_PARALLEL_DOWNLOADS.min(batches.len()) {
+                let (batches, ranges, next_batch, stop) = (&batches, &ranges, &next_batch, &stop);
+                let tx = tx.clone();
+
+                scope.spawn(move || {
+                    let mut reader = DefaultBackend::default().open_range_reader(url);
+
+                    while !stop.load(Ordering::Relaxed) {
+                        let batch = match batches.get(next_batch.fetch_add(1, Ordering::Relaxed)) {
+                            Some(b) => b,
+                            None => break,
+                        };
+
+                        let batch_ranges: Vec<_> = batch.iter().map(|&i| ranges[i]).collect();
+                        let mut failures = 0;
+
+                        let outcome = loop {
+                            match reader.read_ranges(&batch_ranges) {
+                                Ok(data) => break Ok(data),
+                                Err(e) => {
+                                    failures += 1;
+
+                                    if failures >= MAX_HTTP_ATTEMPTS {
+                                        break Err(e);
+                                    }
+
+                                    thread::sleep(RETRY_DELAY * 2u32.pow(failures as u32 - 1));
+                                    reader = DefaultBackend::default().open_range_reader(url);
+                                }
+                            }
+                        };
+
+                        if tx.send((batch, outcome, failures)).is_err() {
+                            break;
+                        }
+                    }
+                });
+            }
+
+            // Only the workers should be able to keep the channel open.
+            drop(tx);
+
+            let mut first_error = None;
+            let mut any_retried = false;
+
+            for (batch, outcome, failures) in rx {
+                any_retried |= failures > 0;
+
+                let result = match outcome {
+                    Ok(data) => batch
+                        .iter()
+                        .zip(data)
+                        .try_for_each(|(&i, data)| store(i, data)),
+
+                    Err(e) => {
+                        if first_error.is_none() {
+                            tt_warning!(status, "failure downloading files from network"; e);
+                        }
+
+                        Err(anyhow!(
+                            "failed to retrieve files from the network; \
+                            this most probably is not Tectonic's fault \
+                            -- please check your network connection."
+                        ))
+                    }
+                };
+
+                // Keep storing the files that other workers have already
+                // downloaded, but don't start on any more.
+                if let Err(e) = result {
+                    stop.store(true, Ordering::Relaxed);
+                    first_error.get_or_insert(e);
+                }
+            }
+
+            match first_error {
+                Some(e) => Err(e),
+                None => {
+                    if any_retried {
+                        tt_note!(status, "download succeeded after retry");
+                    }
+
+                    Ok(())
+                }
+            }
+        })
+    }
+}
+
+/// Write out the contents of a bundle in the “indexed tar” format.
+///
+/// This converts any kind of bundle into the form expected by the
+/// [`IndexedTarBackend`]. The tar archive is written to *tar*, and the
+/// gzipped index to *index*. Files are added to the archive in sorted order, so
+/// the output depends only on the bundle contents. The bundle must provide a
+/// digest file; it is added to the archive in normalized form even if the
+/// bundle doesn't list it in [`Bundle::all_files`].
+pub fn write_indexed_tar<B, W1, W2>(
+    bundle: &mut B,
+    mut tar: W1,
+    index: W2,
+    status: &mut dyn StatusBackend,
+) -> Result<()>
+where
+    B: Bundle + ?Sized,
+    W1: Write,
+    W2: Write,
+{
+    // Make sure that we have a valid digest before doing all the work. We
+    // write out the digest file ourselves, since the backend is pickier about
+    // its format than other bundle types are.
+    let digest_text = bundle.get_digest(status)?.to_string();
+
+    let mut names = bundle.all_files(status)?;
+
+    if !names.iter().any(|n| n == digest::DIGEST_NAME) {
+        names.push(digest::DIGEST_NAME.to_owned());
+    }
+
+    names.sort();
+    names.dedup();
+
+    let mut index = GzEncoder::new(index, Compression::default());
+    let mut offset = 0;
+
+    for name in &names {
+        let mut contents = Vec::new();
+
+        if name == digest::DIGEST_NAME {
+            contents.extend_from_slice(digest_text.as_bytes());
+        } else {
+            bundle
+                .input_open_name(name, status)
+                .must_exist()?
+                .read_to_end(&mut contents)?;
+        }
+
+        tar.write_all(&tar_header(name, contents.len() as u64)?)?;
+        offset += TAR_BLOCK_SIZE as u64;
+
+        tar.write_all(&contents)?;
+        writeln!(index, "{} {} {}", name, offset, contents.len())?;
+
+        let padding = (TAR_BLOCK_SIZE - contents.len() % TAR_BLOCK_SIZE) % TAR_BLOCK_SIZE;
+        tar.write_all(&[0; TAR_BLOCK_SIZE][..padding])?;
+        offset += (contents.len() + padding) as u64;
+    }
+
+    // A tar archive ends with two empty blocks.
+    tar.write_all(&[0; 2 * TAR_BLOCK_SIZE])?;
+    tar.flush()?;
+    index.finish()?.flush()?;
+    Ok(())
+}
+
+/// Create a POSIX “ustar” header for a regular file.
+fn tar_header(name: &str, size: u64) -> Result<[u8; TAR_BLOCK_SIZE]> {
+    let mut header = [0; TAR_BLOCK_SIZE];
+
+    fn set_field(header: &mut [u8], offset: usize, value: &[u8]) {
+        header[offset..offset + value.len()].copy_from_slice(value);
+    }
+
+    ensure!(
+        name.len() < 100,
+        "file name `{}` is too long for the tar format",
+        name
+    );
+
+    set_field(&mut header, 0, name.as_bytes());
+    set_field(&mut header, 100, b"0000644\0"); // mode
+    set_field(&mut header, 108, b"0000000\0"); // uid
+    set_field(&mut header, 116, b"0000000\0"); // gid
+    set_field(&mut header, 124, format!("{size:011o}\0").as_bytes());
+    set_field(&mut header, 136, b"00000000000\0"); // mtime
+    set_field(&mut header, 148, b"        "); // checksum placeholder
+    set_field(&mut header, 156, b"0"); // regular file
+    set_field(&mut header, 257, b"ustar\0");
+    set_field(&mut header, 263, b"00");
+
+    let checksum: u32 = header.iter().map(|b| *b as u32).sum();
+    set_field(&mut header, 148, format!("{checksum:06o}\0 ").as_bytes());
+    Ok(header)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+    use tectonic_io_base::{InputHandle, InputOrigin, IoProvider, OpenResult};
+    use tectonic_status_base::NoopStatusBackend;
+
+    const FILES: &[(&str, &[u8])] = &[("b.sty", b"\\def\\b{}\n"), ("a.tex", &[b'x'; 600])];
+
+    struct TestBundle;
+
+    impl IoProvider for TestBundle {
+        fn input_open_name(
+            &mut self,
+            name: &str,
+            _status: &mut dyn StatusBackend,
+        ) -> OpenResult<InputHandle> {
+            match FILES.iter().find(|(n, _)| *n == name) {
+                Some((_, data)) => OpenResult::Ok(InputHandle::new(
+                    name,
+                    Cursor::new(data.to_vec()),
+                    InputOrigin::Other,
+                )),
+                None => OpenResult::NotAvailable,
+            }
+        }
+    }
+
+    impl Bundle for TestBundle {
+        fn get_digest(&mut self, _status: &mut dyn StatusBackend) -> Result<DigestData> {
+            Ok(DigestData::zeros())
+        }
+
+        fn all_files(&mut self, _status: &mut dyn StatusBackend) -> Result<Vec<String>> {
+            Ok(FILES.iter().map(|(n, _)| n.to_string()).collect())
+        }
+    }
+
+    #[test]
+    fn header() {
+        let header = tar_header("foo.sty", 1000).unwrap();
+        assert_eq!(&header[..8], b"foo.sty\0");
+        assert_eq!(&header[124..136], b"00000001750\0");
+        assert_eq!(&header[257..263], b"ustar\0");
+
+        // The checksum is computed with its own field treated as spaces.
+        let stored = std::str::from_utf8(&header[148..154]).unwrap();
+        let stored = u32::from_str_radix(stored, 8).unwrap();
+        let mut blanked = header;
+        blanked[148..156].copy_from_slice(b"        ");
+        assert_eq!(stored, blanked.iter().map(|b| *b as u32).sum::<u32>());
+
+        assert!(tar_header(&"x".repeat(100), 0).is_err());
+    }
+
+    #[test]
+    fn indexed_tar() {
+        let mut tar = Vec::new();
+        let mut index = Vec::new();
+        write_indexed_tar(
+            &mut TestBundle,
+            &mut tar,
+            &mut index,
+            &mut NoopStatusBackend {},
+        )
+        .unwrap();
+
+        assert_eq!(tar.len() % TAR_BLOCK_SIZE, 0);
+        assert!(tar[tar.len() - 2 * TAR_BLOCK_SIZE..]
+            .iter()
+            .all(|b| *b == 0));
+
+        let mut index_text = String::new();
+        GzDecoder::new(&index[..])
+            .read_to_string(&mut index_text)
+            .unwrap();
+
+        let entries: Vec<_> = index_text
+            .lines()
+            .map(|l| IndexedTarBackend::parse_index_line(l).unwrap())
+            .collect();
+        let names: Vec<_> = entries.iter().map(|(n, _)| &**n).collect();
+        assert_eq!(names, ["SHA256SUM", "a.tex", "b.sty"]);
+
+        for (name, info) in &entries {
+            let start = info.offset as usize;
+            let data = &tar[start..start + info.length as usize];
+
+            // Each file is preceded by its header.
+            assert_eq!(
+                &tar[start - TAR_BLOCK_SIZE..][..name.len()],
+                name.as_bytes()
+            );
+
+            match FILES.iter().find(|(n, _)| n == name) {
+                Some((_, expected)) => assert_eq!(data, *expected),
+                None => assert_eq!(data, DigestData::zeros().to_string().as_bytes()),
+            }
+        }
+    }
 }
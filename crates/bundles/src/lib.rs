@@ -16,8 +16,11 @@
 //! - [`dir::DirBundle`] turns a directory full of files into a bundle; it is
 //!   useful for testing and lightweight usage.
 //! - [`zip::ZipBundle`] for a ZIP-format bundle.
+//!
+//! The [`search`] module can index the TeX definitions made in a bundle’s
+//! package files.
 
-use std::{io::Read, str::FromStr};
+use std::{io::Read, str::FromStr, time::Duration};
 use tectonic_errors::{anyhow::bail, atry, Result};
 use tectonic_io_base::{digest, digest::DigestData, IoProvider, OpenResult};
 use tectonic_status_base::StatusBackend;
@@ -25,6 +28,7 @@ use tectonic_status_base::StatusBackend;
 pub mod cache;
 pub mod dir;
 pub mod itar;
+pub mod search;
 pub mod zip;
 
 /// A trait for bundles of Tectonic support files.
@@ -83,6 +87,25 @@ pub trait Bundle: IoProvider {
     /// might be fairly substantial (although we are talking megabytes, not
     /// gigabytes).
     fn all_files(&mut self, status: &mut dyn StatusBackend) -> Result<Vec<String>>;
+
+    /// Make sure that the named files are available locally.
+    ///
+    /// Bundles backed by network resources can use this to download a known
+    /// set of files in bulk, rather than one at a time as an engine happens to
+    /// request them. The return value is the number of files that had to be
+    /// fetched. The default implementation does nothing, which is appropriate
+    /// for bundles whose files are always locally available.
+    fn prefetch(&mut self, _names: &[String], _status: &mut dyn StatusBackend) -> Result<usize> {
+        Ok(0)
+    }
+
+    /// Get the total time that this bundle has spent talking to the network.
+    ///
+    /// This is used for profiling builds. The default implementation returns
+    /// zero, which is appropriate for bundles that never use the network.
+    fn network_time(&self) -> Duration {
+        Duration::ZERO
+    }
 }
 
 impl<B: Bundle + ?Sized> Bundle for Box<B> {
@@ -93,6 +116,14 @@ impl<B: Bundle + ?Sized> Bundle for Box<B> {
     fn all_files(&mut self, status: &mut dyn StatusBackend) -> Result<Vec<String>> {
         (**self).all_files(status)
     }
+
+    fn prefetch(&mut self, names: &[String], status: &mut dyn StatusBackend) -> Result<usize> {
+        (**self).prefetch(names, status)
+    }
+
+    fn network_time(&self) -> Duration {
+        (**self).network_time()
+    }
 }
 
 /// Get the URL of the default bundle.
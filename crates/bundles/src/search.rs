@@ -0,0 +1,644 @@
+// Copyright 2026 the Tectonic Project
+// Licensed under the MIT License.
+
+//! Searching the contents of a bundle for TeX definitions.
+//!
+//! Filename matching through [`Bundle::all_files`] can't answer questions like
+//! “which package provides `\foo`?” This module scans the LaTeX package and
+//! class files (`.sty` and `.cls`) in a bundle for definitions made with
+//! `\def` and friends, `\newcommand`, `\DeclareRobustCommand`, and
+//! `\newenvironment`, and records which files each package loads. The results
+//! are gathered into a [`SearchIndex`].
+//!
+//! Building an index requires reading every package file in the bundle, which
+//! for a network-based bundle means downloading all of them. Since a bundle's
+//! contents are fully determined by its digest, a finished index can be saved
+//! and reused indefinitely; [`SearchIndex::load_or_build`] does this using a
+//! local [`crate::cache::Cache`]. An index that is missing some files, because
+//! they couldn't be fetched, is never saved, so that the next search tries
+//! again.
+
+use std::{
+    fmt,
+    fs::{self, File},
+    io::{BufRead, BufReader, Read, Write},
+    path::{Path, PathBuf},
+    process,
+    str::FromStr,
+};
+use tectonic_errors::prelude::*;
+use tectonic_io_base::{digest::DigestData, OpenResult};
+use tectonic_status_base::{tt_note, tt_warning, StatusBackend};
+
+use crate::{cache::Cache, Bundle};
+
+/// Commands that define control sequences, which are followed by the name
+/// being defined.
+const COMMAND_DEFINERS: &[&str] = &[
+    "def",
+    "gdef",
+    "edef",
+    "xdef",
+    "newcommand",
+    "DeclareRobustCommand",
+];
+
+/// Commands that define environments, which are followed by the environment
+/// name in braces.
+const ENVIRONMENT_DEFINERS: &[&str] = &["newenvironment"];
+
+/// Commands that load other files, which are followed by a comma-separated
+/// list of names in braces, and the extension that TeX will add to them.
+const LOADERS: &[(&str, &str)] = &[
+    ("RequirePackage", ".sty"),
+    ("RequirePackageWithOptions", ".sty"),
+    ("usepackage", ".sty"),
+    ("LoadClass", ".cls"),
+    ("LoadClassWithOptions", ".cls"),
+    ("input", ""),
+];
+
+/// The different kinds of things that can be defined in a package file.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum DefinitionKind {
+    /// A control sequence, like `\foo`.
+    Command,
+
+    /// A LaTeX environment, used with `\begin{foo}`.
+    Environment,
+}
+
+impl DefinitionKind {
+    fn as_code(&self) -> &'static str {
+        match self {
+            DefinitionKind::Command => "c",
+            DefinitionKind::Environment => "e",
+        }
+    }
+}
+
+impl FromStr for DefinitionKind {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "c" => Ok(DefinitionKind::Command),
+            "e" => Ok(DefinitionKind::Environment),
+            _ => bail!("unrecognized definition kind code `{}`", s),
+        }
+    }
+}
+
+/// A definition found in a bundle file.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Definition {
+    /// The name that is defined.
+    ///
+    /// For commands, this does not include the leading backslash.
+    pub name: String,
+
+    /// What kind of thing is defined.
+    pub kind: DefinitionKind,
+
+    /// The name of the defining command, without its leading backslash, such
+    /// as `newcommand`.
+    pub definer: String,
+
+    /// The name of the bundle file containing the definition.
+    pub file: String,
+
+    /// The line number of the definition within the file, starting at 1.
+    pub line: usize,
+}
+
+impl fmt::Display for Definition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.kind {
+            DefinitionKind::Command => write!(f, "\\{}", self.name)?,
+            DefinitionKind::Environment => write!(f, "{{{}}}", self.name)?,
+        }
+
+        write!(f, " {}:{} (\\{})", self.file, self.line, self.definer)
+    }
+}
+
+/// A record that one bundle file loads another.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct LoadEdge {
+    /// The name of the package or class file doing the loading.
+    pub loader: String,
+
+    /// The name of the loaded file, with the extension that TeX would add to
+    /// it, if any.
+    pub loaded: String,
+}
+
+/// An index of the definitions in a bundle's package and class files.
+#[derive(Clone, Debug)]
+pub struct SearchIndex {
+    definitions: Vec<Definition>,
+    loads: Vec<LoadEdge>,
+    complete: bool,
+}
+
+impl SearchIndex {
+    /// Build a new index by scanning all of the `.sty` and `.cls` files in a
+    /// bundle.
+    ///
+    /// Files that can't be read are skipped with a warning, and the index is
+    /// marked as incomplete.
+    pub fn build<B: Bundle + ?Sized>(
+        bundle: &mut B,
+        status: &mut dyn StatusBackend,
+    ) -> Result<Self> {
+        let mut files: Vec<String> = bundle
+            .all_files(status)?
+            .into_iter()
+            .filter(|n| n.ends_with(".sty") || n.ends_with(".cls"))
+            .collect();
+        files.sort();
+
+        tt_note!(
+            status,
+            "indexing definitions in {} package and class files",
+            files.len()
+        );
+
+        let mut index = SearchIndex::new();
+
+        for name in files {
+            let mut ih = match bundle.input_open_name(&name, status) {
+                OpenResult::Ok(h) => h,
+                OpenResult::NotAvailable => {
+                    index.complete = false;
+                    continue;
+                }
+                OpenResult::Err(e) => {
+                    tt_warning!(status, "couldn't open bundle file `{}`", name; e);
+                    index.complete = false;
+                    continue;
+                }
+            };
+
+            let mut data = Vec::new();
+
+            if let Err(e) = ih.read_to_end(&mut data) {
+                tt_warning!(status, "couldn't read bundle file `{}`", name; e.into());
+                index.complete = false;
+                continue;
+            }
+
+            index.scan_file(&name, &String::from_utf8_lossy(&data));
+        }
+
+        Ok(index)
+    }
+
+    /// Load an index for the bundle from the cache, building and saving it if
+    /// needed.
+    ///
+    /// The cached index is keyed on the bundle's digest. If the bundle can't
+    /// provide a digest, or some of its files couldn't be read, the index is
+    /// built but not saved. If *rebuild* is true, any existing cached index is
+    /// ignored and overwritten.
+    pub fn load_or_build<B: Bundle + ?Sized>(
+        bundle: &mut B,
+        cache: &Cache,
+        rebuild: bool,
+        status: &mut dyn StatusBackend,
+    ) -> Result<Self> {
+        let path = match bundle.get_digest(status) {
+            Ok(d) => Some(Self::cache_path(cache, &d)?),
+            Err(e) => {
+                tt_note!(status, "bundle has no digest, so its search index won't be cached"; e);
+                None
+            }
+        };
+
+        if let (Some(p), false) = (path.as_ref(), rebuild) {
+            if let Ok(f) = File::open(p) {
+                return Self::read(BufReader::new(f));
+            }
+        }
+
+        let index = Self::build(bundle, status)?;
+
+        match path {
+            Some(p) if index.complete => index.save(&p)?,
+            Some(_) => tt_warning!(
+                status,
+                "some bundle files couldn't be read, so the search index won't be cached"
+            ),
+            None => {}
+        }
+
+        Ok(index)
+    }
+
+    fn new() -> Self {
+        SearchIndex {
+            definitions: Vec::new(),
+            loads: Vec::new(),
+            complete: true,
+        }
+    }
+
+    /// Save the index to a file, without ever leaving a partially written file
+    /// at *path*.
+    fn save(&self, path: &Path) -> Result<()> {
+        let tmp_path = path.with_extension(format!("{}.tmp", process::id()));
+
+        let mut f = atry!(
+            File::create(&tmp_path);
+            ["couldn't open {} for writing", tmp_path.display()]
+        );
+
+        let result = self.write(&mut f).and_then(|_| f.sync_all());
+        drop(f);
+
+        if result.is_err() {
+            let _ = fs::remove_file(&tmp_path);
+        }
+
+        atry!(result; ["couldn't write to {}", tmp_path.display()]);
+        atry!(
+            fs::rename(&tmp_path, path);
+            ["couldn't rename {} to {}", tmp_path.display(), path.display()]
+        );
+        Ok(())
+    }
+
+    fn cache_path(cache: &Cache, digest: &DigestData) -> Result<PathBuf> {
+        let base = cache.root().join("search");
+        atry!(
+            fs::create_dir_all(&base);
+            ["failed to create directory `{}` or one of its parents", base.display()]
+        );
+        Ok(base.join(digest.to_string()).with_extension("txt"))
+    }
+
+    /// Read an index that was saved with [`Self::write`].
+    ///
+    /// Malformed lines are ignored.
+    pub fn read<R: BufRead>(reader: R) -> Result<Self> {
+        let mut index = SearchIndex::new();
+
+        for line in reader.lines() {
+            let line = line?;
+            let bits: Vec<&str> = line.split(' ').collect();
+
+            match bits[..] {
+                ["d", kind, name, definer, file, lineno] => {
+                    let (kind, line) = match (kind.parse(), lineno.parse()) {
+                        (Ok(k), Ok(l)) => (k, l),
+                        _ => continue,
+                    };
+
+                    index.definitions.push(Definition {
+                        name: name.to_owned(),
+                        kind,
+                        definer: definer.to_owned(),
+                        file: file.to_owned(),
+                        line,
+                    });
+                }
+
+                ["l", loader, loaded] => {
+                    index.loads.push(LoadEdge {
+                        loader: loader.to_owned(),
+                        loaded: loaded.to_owned(),
+                    });
+                }
+
+                _ => {}
+            }
+        }
+
+        Ok(index)
+    }
+
+    /// Save this index in a simple line-based text format.
+    pub fn write<W: Write>(&self, dest: &mut W) -> std::io::Result<()> {
+        for d in &self.definitions {
+            writeln!(
+                dest,
+                "d {} {} {} {} {}",
+                d.kind.as_code(),
+                d.name,
+                d.definer,
+                d.file,
+                d.line
+            )?;
+        }
+
+        for l in &self.loads {
+            writeln!(dest, "l {} {}", l.loader, l.loaded)?;
+        }
+
+        Ok(())
+    }
+
+    /// Whether every package and class file in the bundle was scanned when
+    /// this index was built.
+    pub fn is_complete(&self) -> bool {
+        self.complete
+    }
+
+    /// Scan the text of one file and add its definitions and loads to the
+    /// index.
+    pub fn scan_file(&mut self, file: &str, text: &str) {
+        // Names containing whitespace would break our line-based storage
+        // format, but TeX support files basically never have them.
+        if file.contains(char::is_whitespace) {
+            return;
+        }
+
+        for (i, line) in text.lines().enumerate() {
+            let line = strip_comment(line);
+            let mut rest = line;
+
+            while let Some(pos) = rest.find('\\') {
+                let (cs, after) = split_control_word(&rest[pos + 1..]);
+                rest = after;
+
+                if COMMAND_DEFINERS.contains(&cs) {
+                    if let Some((name, after)) = parse_defined_command(after) {
+                        self.definitions.push(Definition {
+                            name: name.to_owned(),
+                            kind: DefinitionKind::Command,
+                            definer: cs.to_owned(),
+                            file: file.to_owned(),
+                            line: i + 1,
+                        });
+                        rest = after;
+                    }
+                } else if ENVIRONMENT_DEFINERS.contains(&cs) {
+                    if let Some((name, after)) = parse_braced(skip_star(after)) {
+                        if !name.is_empty() && !name.contains(char::is_whitespace) {
+                            self.definitions.push(Definition {
+                                name: name.to_owned(),
+                                kind: DefinitionKind::Environment,
+                                definer: cs.to_owned(),
+                                file: file.to_owned(),
+                                line: i + 1,
+                            });
+                        }
+                        rest = after;
+                    }
+                } else if let Some((_, ext)) = LOADERS.iter().find(|(l, _)| *l == cs) {
+                    let (names, after) = parse_loaded_names(after);
+                    rest = after;
+
+                    for name in names {
+                        let loaded = if ext.is_empty() || name.ends_with(ext) {
+                            name.to_owned()
+                        } else {
+                            format!("{name}{ext}")
+                        };
+
+                        self.loads.push(LoadEdge {
+                            loader: file.to_owned(),
+                            loaded,
+                        });
+                    }
+                }
+            }
+        }
+    }
+
+    /// Find the definitions of a name.
+    ///
+    /// If *kind* is `None`, both commands and environments are matched. A
+    /// leading backslash on *name* is ignored.
+    pub fn find_definitions<'a>(
+        &'a self,
+        name: &'a str,
+        kind: Option<DefinitionKind>,
+    ) -> impl Iterator<Item = &'a Definition> + 'a {
+        let name = name.strip_prefix('\\').unwrap_or(name);
+
+        self.definitions
+            .iter()
+            .filter(move |d| d.name == name && kind.map(|k| k == d.kind).unwrap_or(true))
+    }
+
+    /// Find the package and class files that load the named file.
+    ///
+    /// Since `\input` adds a `.tex` extension if needed, a search for
+    /// `foo.tex` will also match loads of plain `foo`.
+    pub fn find_loaders<'a>(&'a self, file: &'a str) -> impl Iterator<Item = &'a str> + 'a {
+        let stem = file.strip_suffix(".tex");
+
+        self.loads
+            .iter()
+            .filter(move |l| l.loaded == file || Some(&*l.loaded) == stem)
+            .map(|l| &*l.loader)
+    }
+}
+
+/// Remove a TeX comment from the end of a line.
+fn strip_comment(line: &str) -> &str {
+    let mut escaped = false;
+
+    for (i, c) in line.char_indices() {
+        match c {
+            '\\' => escaped = !escaped,
+            '%' if !escaped => return &line[..i],
+            _ => escaped = false,
+        }
+    }
+
+    line
+}
+
+/// Given text just past a backslash, split off the control sequence name.
+///
+/// In package files `@` is a letter, so we treat it that way here.
+fn split_control_word(text: &str) -> (&str, &str) {
+    let end = text
+        .find(|c: char| !(c.is_ascii_alphabetic() || c == '@'))
+        .unwrap_or(text.len());
+
+    if end == 0 {
+        // A control symbol like `\\` or `\{`: consume the one character so
+        // that escaped backslashes don't start a new control sequence.
+        let n = text.chars().next().map(|c| c.len_utf8()).unwrap_or(0);
+        (&text[..n], &text[n..])
+    } else {
+        (&text[..end], &text[end..])
+    }
+}
+
+fn skip_star(text: &str) -> &str {
+    let text = text.trim_start();
+    text.strip_prefix('*').unwrap_or(text).trim_start()
+}
+
+/// Parse `{contents}`, returning the contents and the remaining text.
+fn parse_braced(text: &str) -> Option<(&str, &str)> {
+    let text = text.trim_start().strip_prefix('{')?;
+    let end = text.find('}')?;
+    Some((text[..end].trim(), &text[end + 1..]))
+}
+
+/// Parse the control sequence that follows a defining command, which may or
+/// may not be wrapped in braces: `\def\foo`, `\newcommand*{\foo}`.
+fn parse_defined_command(text: &str) -> Option<(&str, &str)> {
+    let text = skip_star(text);
+    let (inner, rest) = match parse_braced(text) {
+        Some((inner, rest)) => (inner, Some(rest)),
+        None => (text, None),
+    };
+
+    let (name, after) = split_control_word(inner.strip_prefix('\\')?);
+
+    if name.is_empty() {
+        return None;
+    }
+
+    Some((name, rest.unwrap_or(after)))
+}
+
+/// Parse the names of the files loaded by a loader command: either a
+/// comma-separated list in braces, possibly after an `[options]` group, or a
+/// bare word for the `\input foo` syntax.
+fn parse_loaded_names(text: &str) -> (Vec<&str>, &str) {
+    let mut text = text.trim_start();
+
+    if let Some(t) = text.strip_prefix('[') {
+        match t.find(']') {
+            Some(end) => text = t[end + 1..].trim_start(),
+            None => return (Vec::new(), ""),
+        }
+    }
+
+    if let Some((inner, rest)) = parse_braced(text) {
+        let names = inner
+            .split(',')
+            .map(|s| s.trim())
+            .filter(|s| !s.is_empty() && !s.contains(|c: char| c.is_whitespace() || c == '\\'))
+            .collect();
+        return (names, rest);
+    }
+
+    let end = text
+        .find(|c: char| c.is_whitespace() || c == '\\' || c == '{' || c == '}')
+        .unwrap_or(text.len());
+
+    if end == 0 {
+        (Vec::new(), text)
+    } else {
+        (vec![&text[..end]], &text[end..])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+    use tectonic_io_base::{InputHandle, InputOrigin, IoProvider};
+    use tectonic_status_base::NoopStatusBackend;
+
+    fn scan(text: &str) -> SearchIndex {
+        let mut index = SearchIndex::new();
+        index.scan_file("test.sty", text);
+        index
+    }
+
+    #[test]
+    fn finds_definitions() {
+        let index = scan(
+            "\\def\\foo#1{#1}\n\
+             \\newcommand*{\\bar}[1]{}\\newcommand\\baz{}\n\
+             % \\def\\commented{}\n\
+             \\DeclareRobustCommand{\\q@ux}{}\n\
+             \\newenvironment{thing}{}{}\n",
+        );
+
+        let names: Vec<_> = index.definitions.iter().map(|d| &*d.name).collect();
+        assert_eq!(names, ["foo", "bar", "baz", "q@ux", "thing"]);
+        assert_eq!(index.definitions[2].line, 2);
+        assert_eq!(
+            index.find_definitions("\\thing", None).next().unwrap().kind,
+            DefinitionKind::Environment
+        );
+        assert!(index
+            .find_definitions("thing", Some(DefinitionKind::Command))
+            .next()
+            .is_none());
+    }
+
+    #[test]
+    fn finds_loads() {
+        let index = scan(
+            "\\RequirePackage[opt]{alpha, beta}\n\
+             \\LoadClass{article}\\input gamma.code.tex\n\
+             \\input{delta}\n",
+        );
+
+        assert_eq!(
+            index.find_loaders("beta.sty").collect::<Vec<_>>(),
+            ["test.sty"]
+        );
+        assert_eq!(index.find_loaders("article.cls").count(), 1);
+        assert_eq!(index.find_loaders("gamma.code.tex").count(), 1);
+        assert_eq!(index.find_loaders("delta.tex").count(), 1);
+    }
+
+    /// A bundle with one readable package file and one that can't be fetched.
+    struct PartialBundle;
+
+    impl IoProvider for PartialBundle {
+        fn input_open_name(
+            &mut self,
+            name: &str,
+            _status: &mut dyn StatusBackend,
+        ) -> OpenResult<InputHandle> {
+            match name {
+                "present.sty" => OpenResult::Ok(InputHandle::new(
+                    name,
+                    Cursor::new(b"\\def\\foo{}".to_vec()),
+                    InputOrigin::Other,
+                )),
+                _ => OpenResult::NotAvailable,
+            }
+        }
+    }
+
+    impl Bundle for PartialBundle {
+        fn get_digest(&mut self, _status: &mut dyn StatusBackend) -> Result<DigestData> {
+            Ok(DigestData::zeros())
+        }
+
+        fn all_files(&mut self, _status: &mut dyn StatusBackend) -> Result<Vec<String>> {
+            Ok(vec!["present.sty".to_owned(), "missing.sty".to_owned()])
+        }
+    }
+
+    #[test]
+    fn partial_index_not_cached() {
+        let root = std::env::temp_dir().join(format!("tectonic-search-test-{}", process::id()));
+        let cache = Cache::get_for_custom_directory(&root);
+        let mut status = NoopStatusBackend::default();
+
+        let index =
+            SearchIndex::load_or_build(&mut PartialBundle, &cache, false, &mut status).unwrap();
+        assert!(!index.is_complete());
+        assert_eq!(index.find_definitions("foo", None).count(), 1);
+
+        let cached = SearchIndex::cache_path(&cache, &DigestData::zeros()).unwrap();
+        assert!(!cached.exists());
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn roundtrip() {
+        let index = scan("\\def\\foo{}\\RequirePackage{bar}\n");
+        let mut buf = Vec::new();
+        index.write(&mut buf).unwrap();
+        let index2 = SearchIndex::read(&buf[..]).unwrap();
+        assert_eq!(index.definitions, index2.definitions);
+        assert_eq!(index.loads, index2.loads);
+    }
+}
@@ -12,7 +12,7 @@
 //! processing, in the `tectonic::docmodel` module.
 
 use std::{
-    collections::HashMap,
+    collections::{BTreeMap, HashMap},
     env, fs,
     io::{Read, Write},
     path::{Component, Path, PathBuf},
@@ -188,6 +188,11 @@ impl Document {
             BuildTargetType::Html => {
                 p.push("index.html");
             }
+
+            BuildTargetType::Epub => {
+                p.push(&profile.name);
+                p.set_extension("epub");
+            }
         }
 
         p
@@ -230,11 +235,128 @@ pub struct OutputProfile {
     /// Directory is not managed and any files created in it will not be deleted.
     ///
     pub shell_escape_cwd: Option<String>,
+
+    /// Whether HTML-based outputs should load Tectonic's LaTeX compatibility
+    /// layer.
+    ///
+    /// The layer maps standard LaTeX constructs such as sectioning commands,
+    /// lists, and tables to semantic HTML, so that ordinary documents can be
+    /// built without the tdux-aware support files. It has no effect on PDF
+    /// outputs.
+    pub html_compat: bool,
+
+    /// Where to write a manifest of content-hashed asset names for HTML
+    /// outputs.
+    ///
+    /// If set, font files are given names that include a hash of their
+    /// contents, so that they can be cached indefinitely by web clients. The
+    /// manifest maps the plain names to the hashed ones. The path is relative
+    /// to the output directory.
+    pub asset_manifest: Option<String>,
+
+    /// The level of PDF/A conformance targeted by PDF outputs, if any.
+    pub pdfa: Option<PdfAConformance>,
+
+    /// Whether PDF outputs should be tagged with the document's logical
+    /// structure, for accessibility.
+    pub tagged_pdf: bool,
+
+    /// How PDF outputs should be encrypted, if at all.
+    pub encryption: Option<EncryptionSettings>,
+
+    /// Metadata describing the document, which take precedence over any that
+    /// the TeX source sets itself.
+    pub metadata: Metadata,
+}
+
+/// Metadata describing a document.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Metadata {
+    /// The document title.
+    pub title: Option<String>,
+
+    /// The name of the document's author.
+    pub author: Option<String>,
+
+    /// The subject of the document.
+    pub subject: Option<String>,
+
+    /// Keywords associated with the document.
+    pub keywords: Vec<String>,
+
+    /// The language of the document, as a language tag such as `en-US`.
+    pub language: Option<String>,
+
+    /// Custom properties.
+    pub custom: BTreeMap<String, String>,
+}
+
+/// Settings for encrypting PDF outputs.
+///
+/// Passwords aren't stored in the document definition. Instead, it names
+/// environment variables that will provide them at build time.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct EncryptionSettings {
+    /// The name of the environment variable holding the owner password.
+    pub owner_password_env: String,
+
+    /// The name of the environment variable holding the user password. If
+    /// unset, the user password is empty, so that anyone can open the
+    /// document, subject to its permissions.
+    pub user_password_env: Option<String>,
+
+    /// The length of the encryption key in bits: 40, 128, or 256.
+    pub key_bits: u32,
+
+    /// The operations permitted to readers who use the user password. If
+    /// none are listed in the document definition, all are permitted.
+    pub permissions: Vec<PdfPermission>,
+}
+
+/// An operation that may be permitted to readers of an encrypted PDF.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum PdfPermission {
+    /// Printing the document.
+    Print,
+
+    /// Printing the document at full quality.
+    PrintHighQuality,
+
+    /// Modifying the document.
+    Modify,
+
+    /// Copying text and graphics.
+    Copy,
+
+    /// Extracting text and graphics for accessibility purposes.
+    Accessibility,
+
+    /// Adding annotations and filling in forms.
+    Annotate,
+
+    /// Filling in forms.
+    FillForms,
+
+    /// Inserting, rotating, and deleting pages.
+    Assemble,
+}
+
+/// A level of PDF/A conformance for PDF outputs.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum PdfAConformance {
+    /// PDF/A-2b.
+    A2b,
+
+    /// PDF/A-3b, which also allows arbitrary embedded files.
+    A3b,
 }
 
 /// The output target type of a document build.
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
 pub enum BuildTargetType {
+    /// Output an EPUB 3 e-book.
+    Epub,
+
     /// Output a tree of HTML files
     Html,
 
@@ -305,6 +427,12 @@ pub(crate) fn default_outputs() -> HashMap<String, OutputProfile> {
             postamble_file: DEFAULT_POSTAMBLE_FILE.to_owned(),
             shell_escape: false,
             shell_escape_cwd: None,
+            html_compat: false,
+            asset_manifest: None,
+            pdfa: None,
+            tagged_pdf: false,
+            encryption: None,
+            metadata: Metadata::default(),
         },
     );
     outputs
@@ -314,6 +442,7 @@ pub(crate) fn default_outputs() -> HashMap<String, OutputProfile> {
 mod syntax {
     use super::{DEFAULT_INDEX_FILE, DEFAULT_POSTAMBLE_FILE, DEFAULT_PREAMBLE_FILE};
     use serde::{de::Error, Deserialize, Deserializer, Serialize, Serializer};
+    use std::collections::BTreeMap;
 
     #[derive(Debug, Deserialize, Serialize)]
     #[serde(deny_unknown_fields)]
@@ -346,6 +475,12 @@ mod syntax {
         pub postamble_file: Option<String>,
         pub shell_escape: Option<bool>,
         pub shell_escape_cwd: Option<String>,
+        pub html_compat: Option<bool>,
+        pub asset_manifest: Option<String>,
+        pub pdfa: Option<PdfAConformance>,
+        pub tagged_pdf: Option<bool>,
+        pub encryption: Option<EncryptionSettings>,
+        pub metadata: Option<Metadata>,
     }
 
     impl OutputProfile {
@@ -376,6 +511,8 @@ mod syntax {
 
             let shell_escape = if !rt.shell_escape { None } else { Some(true) };
             let shell_escape_cwd = rt.shell_escape_cwd.clone();
+            let html_compat = if !rt.html_compat { None } else { Some(true) };
+            let tagged_pdf = if !rt.tagged_pdf { None } else { Some(true) };
 
             OutputProfile {
                 name: rt.name.clone(),
@@ -386,6 +523,12 @@ mod syntax {
                 postamble_file,
                 shell_escape,
                 shell_escape_cwd,
+                html_compat,
+                asset_manifest: rt.asset_manifest.clone(),
+                pdfa: rt.pdfa.as_ref().map(PdfAConformance::from_runtime),
+                tagged_pdf,
+                encryption: rt.encryption.as_ref().map(EncryptionSettings::from_runtime),
+                metadata: Metadata::from_runtime(&rt.metadata),
             }
         }
 
@@ -415,12 +558,23 @@ mod syntax {
                     .unwrap_or_else(|| DEFAULT_POSTAMBLE_FILE.to_owned()),
                 shell_escape: self.shell_escape.unwrap_or(shell_escape_default),
                 shell_escape_cwd: self.shell_escape_cwd.clone(),
+                html_compat: self.html_compat.unwrap_or(false),
+                asset_manifest: self.asset_manifest.clone(),
+                pdfa: self.pdfa.map(PdfAConformance::to_runtime),
+                tagged_pdf: self.tagged_pdf.unwrap_or(false),
+                encryption: self.encryption.as_ref().map(|e| e.to_runtime()),
+                metadata: self
+                    .metadata
+                    .as_ref()
+                    .map(|m| m.to_runtime())
+                    .unwrap_or_default(),
             }
         }
     }
 
     #[derive(Clone, Copy, Debug, Eq, PartialEq)]
     pub enum BuildTargetType {
+        Epub,
         Html,
         Pdf,
     }
@@ -428,6 +582,7 @@ mod syntax {
     impl BuildTargetType {
         pub fn from_runtime(rt: &super::BuildTargetType) -> Self {
             match rt {
+                super::BuildTargetType::Epub => BuildTargetType::Epub,
                 super::BuildTargetType::Html => BuildTargetType::Html,
                 super::BuildTargetType::Pdf => BuildTargetType::Pdf,
             }
@@ -435,6 +590,7 @@ mod syntax {
 
         pub fn to_runtime(self) -> super::BuildTargetType {
             match self {
+                BuildTargetType::Epub => super::BuildTargetType::Epub,
                 BuildTargetType::Html => super::BuildTargetType::Html,
                 BuildTargetType::Pdf => super::BuildTargetType::Pdf,
             }
@@ -447,6 +603,7 @@ mod syntax {
             S: Serializer,
         {
             serializer.serialize_str(match *self {
+                BuildTargetType::Epub => "epub",
                 BuildTargetType::Html => "html",
                 BuildTargetType::Pdf => "pdf",
             })
@@ -459,12 +616,198 @@ mod syntax {
         {
             let s = String::deserialize(deserializer)?;
             Ok(match s.as_str() {
+                "epub" => BuildTargetType::Epub,
                 "html" => BuildTargetType::Html,
                 "pdf" => BuildTargetType::Pdf,
                 other => {
                     return Err(<D as Deserializer>::Error::unknown_variant(
                         other,
-                        &["html", "pdf"],
+                        &["epub", "html", "pdf"],
+                    ))
+                }
+            })
+        }
+    }
+
+    #[derive(Debug, Deserialize, Serialize)]
+    #[serde(deny_unknown_fields)]
+    pub struct Metadata {
+        pub title: Option<String>,
+        pub author: Option<String>,
+        pub subject: Option<String>,
+        pub keywords: Option<Vec<String>>,
+        pub language: Option<String>,
+        pub custom: Option<BTreeMap<String, String>>,
+    }
+
+    impl Metadata {
+        pub fn from_runtime(rt: &super::Metadata) -> Option<Self> {
+            if *rt == super::Metadata::default() {
+                return None;
+            }
+
+            Some(Metadata {
+                title: rt.title.clone(),
+                author: rt.author.clone(),
+                subject: rt.subject.clone(),
+                keywords: Some(rt.keywords.clone()).filter(|k| !k.is_empty()),
+                language: rt.language.clone(),
+                custom: Some(rt.custom.clone()).filter(|c| !c.is_empty()),
+            })
+        }
+
+        pub fn to_runtime(&self) -> super::Metadata {
+            super::Metadata {
+                title: self.title.clone(),
+                author: self.author.clone(),
+                subject: self.subject.clone(),
+                keywords: self.keywords.clone().unwrap_or_default(),
+                language: self.language.clone(),
+                custom: self.custom.clone().unwrap_or_default(),
+            }
+        }
+    }
+
+    #[derive(Debug, Deserialize, Serialize)]
+    #[serde(deny_unknown_fields)]
+    pub struct EncryptionSettings {
+        pub owner_password_env: String,
+        pub user_password_env: Option<String>,
+        pub key_bits: Option<u32>,
+        pub permissions: Option<Vec<PdfPermission>>,
+    }
+
+    impl EncryptionSettings {
+        pub fn from_runtime(rt: &super::EncryptionSettings) -> Self {
+            EncryptionSettings {
+                owner_password_env: rt.owner_password_env.clone(),
+                user_password_env: rt.user_password_env.clone(),
+                key_bits: if rt.key_bits == 256 {
+                    None
+                } else {
+                    Some(rt.key_bits)
+                },
+                permissions: Some(
+                    rt.permissions
+                        .iter()
+                        .map(PdfPermission::from_runtime)
+                        .collect(),
+                ),
+            }
+        }
+
+        pub fn to_runtime(&self) -> super::EncryptionSettings {
+            super::EncryptionSettings {
+                owner_password_env: self.owner_password_env.clone(),
+                user_password_env: self.user_password_env.clone(),
+                key_bits: self.key_bits.unwrap_or(256),
+                permissions: self
+                    .permissions
+                    .as_ref()
+                    .map(|p| p.iter().map(|p| p.to_runtime()).collect())
+                    .unwrap_or_else(|| {
+                        vec![
+                            super::PdfPermission::Print,
+                            super::PdfPermission::PrintHighQuality,
+                            super::PdfPermission::Modify,
+                            super::PdfPermission::Copy,
+                            super::PdfPermission::Accessibility,
+                            super::PdfPermission::Annotate,
+                            super::PdfPermission::FillForms,
+                            super::PdfPermission::Assemble,
+                        ]
+                    }),
+            }
+        }
+    }
+
+    #[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+    #[serde(rename_all = "snake_case")]
+    pub enum PdfPermission {
+        Print,
+        PrintHighQuality,
+        Modify,
+        Copy,
+        Accessibility,
+        Annotate,
+        FillForms,
+        Assemble,
+    }
+
+    impl PdfPermission {
+        pub fn from_runtime(rt: &super::PdfPermission) -> Self {
+            match rt {
+                super::PdfPermission::Print => PdfPermission::Print,
+                super::PdfPermission::PrintHighQuality => PdfPermission::PrintHighQuality,
+                super::PdfPermission::Modify => PdfPermission::Modify,
+                super::PdfPermission::Copy => PdfPermission::Copy,
+                super::PdfPermission::Accessibility => PdfPermission::Accessibility,
+                super::PdfPermission::Annotate => PdfPermission::Annotate,
+                super::PdfPermission::FillForms => PdfPermission::FillForms,
+                super::PdfPermission::Assemble => PdfPermission::Assemble,
+            }
+        }
+
+        pub fn to_runtime(self) -> super::PdfPermission {
+            match self {
+                PdfPermission::Print => super::PdfPermission::Print,
+                PdfPermission::PrintHighQuality => super::PdfPermission::PrintHighQuality,
+                PdfPermission::Modify => super::PdfPermission::Modify,
+                PdfPermission::Copy => super::PdfPermission::Copy,
+                PdfPermission::Accessibility => super::PdfPermission::Accessibility,
+                PdfPermission::Annotate => super::PdfPermission::Annotate,
+                PdfPermission::FillForms => super::PdfPermission::FillForms,
+                PdfPermission::Assemble => super::PdfPermission::Assemble,
+            }
+        }
+    }
+
+    #[derive(Clone, Copy, Debug, Eq, PartialEq)]
+    pub enum PdfAConformance {
+        A2b,
+        A3b,
+    }
+
+    impl PdfAConformance {
+        pub fn from_runtime(rt: &super::PdfAConformance) -> Self {
+            match rt {
+                super::PdfAConformance::A2b => PdfAConformance::A2b,
+                super::PdfAConformance::A3b => PdfAConformance::A3b,
+            }
+        }
+
+        pub fn to_runtime(self) -> super::PdfAConformance {
+            match self {
+                PdfAConformance::A2b => super::PdfAConformance::A2b,
+                PdfAConformance::A3b => super::PdfAConformance::A3b,
+            }
+        }
+    }
+
+    impl Serialize for PdfAConformance {
+        fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+        where
+            S: Serializer,
+        {
+            serializer.serialize_str(match *self {
+                PdfAConformance::A2b => "2b",
+                PdfAConformance::A3b => "3b",
+            })
+        }
+    }
+    impl<'de> Deserialize<'de> for PdfAConformance {
+        fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+        where
+            D: Deserializer<'de>,
+        {
+            let s = String::deserialize(deserializer)?;
+            Ok(match s.as_str() {
+                "2b" => PdfAConformance::A2b,
+                "3b" => PdfAConformance::A3b,
+                other => {
+                    return Err(<D as Deserializer>::Error::unknown_variant(
+                        other,
+                        &["2b", "3b"],
                     ))
                 }
             })
@@ -512,4 +855,81 @@ mod tests {
         let doc = Document::new_from_toml(".", ".", &mut c).unwrap();
         assert!(doc.outputs.get("o").unwrap().shell_escape);
     }
+
+    #[test]
+    fn encryption() {
+        const TOML: &str = r#"
+        [doc]
+        name = "test"
+        bundle = "na"
+
+        [[output]]
+        name = "o"
+        type = "pdf"
+
+        [output.encryption]
+        owner_password_env = "OWNER_PW"
+        permissions = ["print", "accessibility"]
+        "#;
+
+        let mut c = Cursor::new(TOML.as_bytes());
+        let doc = Document::new_from_toml(".", ".", &mut c).unwrap();
+        let enc = doc.outputs.get("o").unwrap().encryption.as_ref().unwrap();
+        assert_eq!(enc.owner_password_env, "OWNER_PW");
+        assert_eq!(enc.user_password_env, None);
+        assert_eq!(enc.key_bits, 256);
+        assert_eq!(
+            enc.permissions,
+            [PdfPermission::Print, PdfPermission::Accessibility]
+        );
+    }
+
+    #[test]
+    fn metadata() {
+        const TOML: &str = r#"
+        [doc]
+        name = "test"
+        bundle = "na"
+
+        [[output]]
+        name = "o"
+        type = "pdf"
+
+        [output.metadata]
+        title = "A Title"
+        keywords = ["one", "two"]
+
+        [output.metadata.custom]
+        ISBN = "978-3-16-148410-0"
+        "#;
+
+        let mut c = Cursor::new(TOML.as_bytes());
+        let doc = Document::new_from_toml(".", ".", &mut c).unwrap();
+        let md = &doc.outputs.get("o").unwrap().metadata;
+        assert_eq!(md.title.as_deref(), Some("A Title"));
+        assert_eq!(md.author, None);
+        assert_eq!(md.keywords, ["one", "two"]);
+        assert_eq!(md.custom.get("ISBN").unwrap(), "978-3-16-148410-0");
+    }
+
+    #[test]
+    fn pdfa() {
+        const TOML: &str = r#"
+        [doc]
+        name = "test"
+        bundle = "na"
+
+        [[output]]
+        name = "o"
+        type = "pdf"
+        pdfa = "3b"
+        "#;
+
+        let mut c = Cursor::new(TOML.as_bytes());
+        let doc = Document::new_from_toml(".", ".", &mut c).unwrap();
+        assert_eq!(
+            doc.outputs.get("o").unwrap().pdfa,
+            Some(PdfAConformance::A3b)
+        );
+    }
 }
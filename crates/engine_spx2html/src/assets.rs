@@ -6,12 +6,12 @@
 use serde::Serialize;
 use std::{
     borrow::Cow,
-    collections::{hash_map::Iter, HashMap},
-    fs::File,
+    collections::{hash_map::Iter, BTreeMap, HashMap},
     io::{Read, Write},
     path::{Path, PathBuf},
 };
 use tectonic_errors::{anyhow::Context, prelude::*};
+use tectonic_io_base::digest::{self, Digest};
 use tectonic_status_base::tt_warning;
 
 use crate::{fonts::FontEnsemble, specials::Special, Common};
@@ -20,6 +20,9 @@ use crate::{fonts::FontEnsemble, specials::Special, Common};
 #[derive(Debug, Default)]
 pub(crate) struct Assets {
     paths: HashMap<String, AssetOrigin>,
+
+    /// Search-index information about the HTML pages that have been emitted.
+    search_pages: Vec<syntax::SearchPage>,
 }
 
 /// Different kinds of non-font assets that can be defined at runtime.
@@ -31,6 +34,9 @@ enum AssetOrigin {
     /// Emit a CSS file containing information about the ensemble of fonts
     /// that have been used.
     FontCss,
+
+    /// Emit a JSON search index describing the text of the HTML pages.
+    SearchIndex,
 }
 
 impl Assets {
@@ -66,6 +72,11 @@ impl Assets {
                         self.emit_font_css(dest_path);
                         true
                     }
+                    "search-index" => {
+                        self.paths
+                            .insert(dest_path.to_owned(), AssetOrigin::SearchIndex);
+                        true
+                    }
                     _ => {
                         tt_warning!(common.status, "ignoring unsupported special `{}`", special);
                         false
@@ -84,22 +95,85 @@ impl Assets {
         );
     }
 
+    /// Register an included image to be copied into the output tree, returning
+    /// its output path.
+    ///
+    /// Images go into an `images` directory, mirroring their source paths as
+    /// far as possible. Absolute and parent-directory components are dropped,
+    /// so a number is added to the name if that leads to a conflict.
+    pub(crate) fn add_image(&mut self, src_path: &str) -> String {
+        let mut pieces = vec!["images"];
+        pieces.extend(
+            src_path
+                .split(['/', '\\'])
+                .filter(|p| !p.is_empty() && *p != "." && *p != ".."),
+        );
+        let base = pieces.join("/");
+        let mut dest = base.clone();
+        let mut n = 1;
+
+        loop {
+            match self.paths.get(&dest) {
+                Some(AssetOrigin::Copy(s)) if s == src_path => return dest,
+                None => break,
+                _ => {}
+            }
+
+            dest = match base.rsplit_once('.') {
+                Some((stem, ext)) if !ext.contains('/') => format!("{stem}-{n}.{ext}"),
+                _ => format!("{base}-{n}"),
+            };
+            n += 1;
+        }
+
+        self.copy_file(src_path, &dest);
+        dest
+    }
+
     fn emit_font_css<S: ToString>(&mut self, dest_path: S) {
         self.paths
             .insert(dest_path.to_string(), AssetOrigin::FontCss);
     }
 
+    /// Record the content of an HTML page for the search index.
+    ///
+    /// If the page path has already been used, the new content replaces the
+    /// old.
+    pub(crate) fn add_search_page(&mut self, path: &str, content: &str) {
+        let page = crate::search::index_page(path, content);
+        syntax::merge_search_pages(&mut self.search_pages, std::slice::from_ref(&page));
+    }
+
     /// This functional must only be called if `common.out_path` is not None.
-    pub(crate) fn emit(mut self, mut fonts: FontEnsemble, common: &mut Common) -> Result<()> {
-        let faces = fonts.emit(common.out_base)?;
+    ///
+    /// If `manifest_path` is provided, font files are given content-hashed
+    /// names, and a JSON manifest mapping their plain names to the hashed ones
+    /// is written to that path.
+    pub(crate) fn emit(
+        mut self,
+        mut fonts: FontEnsemble,
+        manifest_path: Option<&str>,
+        common: &mut Common,
+    ) -> Result<()> {
+        let mut manifest = manifest_path.map(|_| BTreeMap::new());
+        let faces = fonts.emit(common.out_base, manifest.as_mut())?;
 
         for (dest_path, origin) in self.paths.drain() {
             match origin {
                 AssetOrigin::Copy(ref src_path) => emit_copied_file(src_path, &dest_path, common),
                 AssetOrigin::FontCss => emit_font_css(&dest_path, &faces, common),
+                AssetOrigin::SearchIndex => {
+                    emit_search_index(&dest_path, &self.search_pages, common)
+                }
             }?;
         }
 
+        if let (Some(dest_path), Some(manifest)) = (manifest_path, manifest) {
+            let out_path = create_output_path(dest_path, common)?.0.unwrap();
+            let data = serde_json::to_vec_pretty(&manifest)?;
+            write_if_changed(&out_path, &data)?;
+        }
+
         Ok(())
     }
 
@@ -110,6 +184,11 @@ impl Assets {
             let info = match origin {
                 AssetOrigin::Copy(src_path) => syntax::AssetOrigin::Copy(src_path),
                 AssetOrigin::FontCss => syntax::AssetOrigin::FontCss(css_data.clone()),
+                AssetOrigin::SearchIndex => {
+                    syntax::AssetOrigin::SearchIndex(syntax::SearchIndexAssetData {
+                        pages: self.search_pages.clone(),
+                    })
+                }
             };
             assets.0.insert(dest_path, info);
         }
@@ -125,14 +204,14 @@ fn emit_copied_file(src_tex_path: &str, dest_path: &str, common: &mut Common) ->
         ["unable to open provideFile source `{}`", &src_tex_path]
     );
 
-    {
-        let (mut out_file, out_path) = create_asset_file(dest_path, common)?;
+    let mut data = Vec::new();
+    atry!(
+        ih.read_to_end(&mut data);
+        ["unable to read provideFile source `{}`", &src_tex_path]
+    );
 
-        atry!(
-            std::io::copy(&mut ih, &mut out_file);
-            ["cannot copy to output file `{}`", out_path.display()]
-        );
-    }
+    let out_path = create_output_path(dest_path, common)?.0.unwrap();
+    write_if_changed(&out_path, &data)?;
 
     let (name, digest_opt) = ih.into_name_digest();
     common
@@ -143,26 +222,78 @@ fn emit_copied_file(src_tex_path: &str, dest_path: &str, common: &mut Common) ->
 
 /// This functional must only be called if `common.out_path` is not None.
 fn emit_font_css(dest_path: &str, faces: &str, common: &mut Common) -> Result<()> {
-    let (mut out_file, out_path) = create_asset_file(dest_path, common)?;
-
-    atry!(
-        write!(&mut out_file, "{faces}");
-        ["cannot write output file `{}`", out_path.display()]
-    );
-
+    let out_path = create_output_path(dest_path, common)?.0.unwrap();
+    write_if_changed(&out_path, faces.as_bytes())?;
     Ok(())
 }
 
 /// This functional must only be called if `common.out_path` is not None.
-fn create_asset_file(dest_path: &str, common: &mut Common) -> Result<(File, PathBuf)> {
+///
+/// In the "precomputed assets" mode, the pages described in the precomputed
+/// index are included too, so that the index covers the whole document even
+/// if this session only emitted some of it.
+fn emit_search_index(
+    dest_path: &str,
+    pages: &[syntax::SearchPage],
+    common: &mut Common,
+) -> Result<()> {
+    let mut all_pages = Vec::new();
+
+    if let Some(syntax::AssetOrigin::SearchIndex(pre)) = common
+        .precomputed_assets
+        .and_then(|a| a.0 .0.get(dest_path))
+    {
+        all_pages = pre.pages.clone();
+    }
+
+    syntax::merge_search_pages(&mut all_pages, pages);
+
+    let data = serde_json::to_vec(&syntax::SearchIndexAssetData { pages: all_pages })?;
     let out_path = create_output_path(dest_path, common)?.0.unwrap();
+    write_if_changed(&out_path, &data)?;
+    Ok(())
+}
 
-    let out_file = atry!(
-        File::create(&out_path);
-        ["cannot open output file `{}`", out_path.display()]
+/// Write an output file, unless it already exists with the same contents.
+///
+/// Leaving unchanged files alone preserves their modification times, so that
+/// tools that synchronize the output tree with a web server only need to
+/// upload what has actually changed. Returns whether the file was written.
+pub(crate) fn write_if_changed(out_path: &Path, data: &[u8]) -> Result<bool> {
+    if let Ok(md) = std::fs::metadata(out_path) {
+        if md.is_file() && md.len() == data.len() as u64 {
+            if let Ok(existing) = std::fs::read(out_path) {
+                if existing == data {
+                    return Ok(false);
+                }
+            }
+        }
+    }
+
+    atry!(
+        std::fs::write(out_path, data);
+        ["cannot write output file `{}`", out_path.display()]
     );
 
-    Ok((out_file, out_path))
+    Ok(true)
+}
+
+/// Compute a name for an output file that includes a hash of its contents.
+///
+/// The hash is inserted before the file extension, so that the result still
+/// has the right type as far as web servers are concerned.
+pub(crate) fn hashed_name(path: &str, data: &[u8]) -> String {
+    let mut dc = digest::create();
+    dc.update(data);
+    let hash = digest::bytes_to_hex(&dc.finalize());
+    let hash = &hash[..16];
+
+    match path.rsplit_once('.') {
+        Some((stem, ext)) if !ext.contains('/') && !stem.ends_with('/') => {
+            format!("{stem}.{hash}.{ext}")
+        }
+        _ => format!("{path}.{hash}"),
+    }
 }
 
 /// Process a TeX output path into one for the actual filesystem.
@@ -291,6 +422,10 @@ impl AssetSpecification {
                         syntax::merge_font_ensembles(&mut cur_fe.0, &new_fe.0)?;
                     }
 
+                    (AO::SearchIndex(new_si), AO::SearchIndex(cur_si)) => {
+                        syntax::merge_search_pages(&mut cur_si.pages, &new_si.pages);
+                    }
+
                     (new2, cur2) => {
                         bail!(
                             "disagreeing origin types {} and {} for output asset `{}`",
@@ -370,6 +505,8 @@ impl AssetSpecification {
 
                     (AssetOrigin::FontCss, syntax::AssetOrigin::FontCss(_)) => {}
 
+                    (AssetOrigin::SearchIndex, syntax::AssetOrigin::SearchIndex(_)) => {}
+
                     _ => {
                         bail!(
                             "this session and the precomputed assets disagree on `{}`",
@@ -391,6 +528,7 @@ impl AssetSpecification {
             let mapped = match pre_origin {
                 syntax::AssetOrigin::Copy(pre_path) => AssetOrigin::Copy(pre_path.to_owned()),
                 syntax::AssetOrigin::FontCss(_) => AssetOrigin::FontCss,
+                syntax::AssetOrigin::SearchIndex(_) => AssetOrigin::SearchIndex,
                 syntax::AssetOrigin::FontFile(_) => continue,
             };
 
@@ -483,6 +621,9 @@ pub(crate) mod syntax {
         /// An OpenType/TrueType font file and variants with customized CMAP tables
         /// allowing access to unusual glyphs.
         FontFile(FontFileAssetData),
+
+        /// Emit a JSON search index describing the text of the HTML pages.
+        SearchIndex(SearchIndexAssetData),
     }
 
     impl std::fmt::Display for AssetOrigin {
@@ -512,6 +653,10 @@ pub(crate) mod syntax {
                 AssetOrigin::FontFile(ff) => {
                     write!(f, "font face #{} from `{}`", ff.face_index, ff.source)
                 }
+
+                AssetOrigin::SearchIndex(si) => {
+                    write!(f, "search index of {} pages", si.pages.len())
+                }
             }
         }
     }
@@ -645,4 +790,77 @@ pub(crate) mod syntax {
         /// The bold-italic font a current family.
         BoldItalic,
     }
+
+    /// The search index. This is also the format of the emitted JSON file.
+    #[derive(Clone, Debug, Default, Deserialize, Serialize)]
+    pub struct SearchIndexAssetData {
+        /// The HTML pages, in the order that they were emitted.
+        pub pages: Vec<SearchPage>,
+    }
+
+    /// Merge one list of search-index pages into another. Pages with the same
+    /// path are replaced.
+    pub(crate) fn merge_search_pages(cur: &mut Vec<SearchPage>, new: &[SearchPage]) {
+        for page in new {
+            if let Some(cur_page) = cur.iter_mut().find(|p| p.path == page.path) {
+                *cur_page = page.clone();
+            } else {
+                cur.push(page.clone());
+            }
+        }
+    }
+
+    #[derive(Clone, Debug, Default, Deserialize, Serialize)]
+    pub struct SearchPage {
+        /// The output path of the page, relative to the output root.
+        pub path: String,
+
+        /// The text of the page's first heading, if it has one.
+        pub title: Option<String>,
+
+        /// The headings in the page.
+        pub headings: Vec<SearchHeading>,
+
+        /// The distinct words in the page, lowercased, in order of first
+        /// appearance.
+        pub tokens: Vec<String>,
+    }
+
+    #[derive(Clone, Debug, Default, Deserialize, Serialize)]
+    pub struct SearchHeading {
+        /// The heading level, from 1 to 6.
+        pub level: u8,
+
+        /// The text of the heading.
+        pub text: String,
+
+        /// The ID of an anchor that can be used to link to the heading.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pub id: Option<String>,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unchanged_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("a.css");
+
+        assert!(write_if_changed(&path, b"one").unwrap());
+        assert!(!write_if_changed(&path, b"one").unwrap());
+        assert!(write_if_changed(&path, b"two").unwrap());
+        assert_eq!(std::fs::read(&path).unwrap(), b"two");
+    }
+
+    #[test]
+    fn hashed_names() {
+        let a = hashed_name("font.otf", b"one");
+        assert!(a.starts_with("font.") && a.ends_with(".otf"));
+        assert_eq!(a.len(), "font..otf".len() + 16);
+        assert_ne!(a, hashed_name("font.otf", b"two"));
+        assert!(hashed_name("noext", b"one").starts_with("noext."));
+    }
 }
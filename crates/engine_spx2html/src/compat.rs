@@ -0,0 +1,189 @@
+// Copyright 2026 the Tectonic Project
+// Licensed under the MIT License.
+
+//! A LaTeX compatibility layer for HTML output.
+//!
+//! Documents written for the tdux-aware support files get good semantic HTML
+//! because their macros emit `tdux:` specials directly. Ordinary LaTeX
+//! documents don't, and would otherwise come out as a single stream of
+//! paragraphs. The compatibility layer is a LaTeX package,
+//! `tectonic-html-compat`, that patches the standard kernel and class
+//! internals to emit the specials for sectioning headings, lists, tables,
+//! footnotes, figures, cross-references, and the structure of math. This
+//! module bundles that package and its supporting files, and provides an I/O
+//! layer that injects it into a document.
+
+use std::{
+    io::{Cursor, Read},
+    path::PathBuf,
+};
+use tectonic_errors::prelude::*;
+use tectonic_io_base::{InputHandle, InputOrigin, IoProvider, OpenResult, OutputHandle};
+use tectonic_status_base::StatusBackend;
+
+/// The LaTeX package implementing the compatibility layer.
+const COMPAT_STY: &str = include_str!("../compat/tectonic-html-compat.sty");
+
+/// The format-independent macros that record the structure of math.
+const MATH_TEX: &str = include_str!("../compat/tectonic-html-math.tex");
+
+/// The Tera template used by the compatibility layer.
+const COMPAT_TEMPLATE: &str = include_str!("../compat/tectonic-html-compat.html");
+
+/// The stylesheet provided alongside the compatibility layer's output.
+const COMPAT_CSS: &str = include_str!("../compat/tectonic-html-compat.css");
+
+/// The text prepended to the primary input to activate the layer.
+///
+/// There is deliberately no trailing newline, so that line numbers in error
+/// messages still match the user's file. The `\ifdefined` guard keeps
+/// non-LaTeX formats working.
+const PRIMARY_PREFIX: &str = r"\ifdefined\RequirePackage\RequirePackage{tectonic-html-compat}\fi";
+
+/// An I/O layer that activates the LaTeX compatibility layer for HTML output.
+///
+/// This wraps another [`IoProvider`], usually the one providing the primary
+/// input. It serves the files making up the compatibility layer and modifies
+/// the primary input so that it loads the layer's package before anything
+/// else. Everything else is passed through to the wrapped provider.
+pub struct CompatLayerIo {
+    inner: Box<dyn IoProvider>,
+}
+
+impl CompatLayerIo {
+    /// Create a new compatibility layer wrapping the given provider.
+    pub fn new(inner: Box<dyn IoProvider>) -> Self {
+        CompatLayerIo { inner }
+    }
+
+    fn bundled_file(name: &str) -> Option<&'static str> {
+        match name {
+            "tectonic-html-compat.sty" => Some(COMPAT_STY),
+            "tectonic-html-math.tex" => Some(MATH_TEX),
+            "tectonic-html-compat.html" => Some(COMPAT_TEMPLATE),
+            "tectonic-html-compat.css" => Some(COMPAT_CSS),
+            _ => None,
+        }
+    }
+
+    /// Prepend the activation text to a primary input handle.
+    fn wrap_primary(mut handle: InputHandle) -> Result<InputHandle> {
+        let name = handle.name().to_owned();
+        let origin = handle.origin();
+        let mut data = PRIMARY_PREFIX.as_bytes().to_owned();
+        atry!(
+            handle.read_to_end(&mut data);
+            ["failed to read the primary input to prepare it for HTML compatibility mode"]
+        );
+        Ok(InputHandle::new(name, Cursor::new(data), origin))
+    }
+}
+
+impl IoProvider for CompatLayerIo {
+    fn output_open_name(&mut self, name: &str) -> OpenResult<OutputHandle> {
+        self.inner.output_open_name(name)
+    }
+
+    fn output_open_stdout(&mut self) -> OpenResult<OutputHandle> {
+        self.inner.output_open_stdout()
+    }
+
+    fn input_open_name(
+        &mut self,
+        name: &str,
+        status: &mut dyn StatusBackend,
+    ) -> OpenResult<InputHandle> {
+        if let Some(text) = Self::bundled_file(name) {
+            return OpenResult::Ok(InputHandle::new_read_only(
+                name,
+                Cursor::new(text.as_bytes().to_owned()),
+                InputOrigin::Other,
+            ));
+        }
+
+        self.inner.input_open_name(name, status)
+    }
+
+    fn input_open_name_with_abspath(
+        &mut self,
+        name: &str,
+        status: &mut dyn StatusBackend,
+    ) -> OpenResult<(InputHandle, Option<PathBuf>)> {
+        if Self::bundled_file(name).is_some() {
+            return match self.input_open_name(name, status) {
+                OpenResult::Ok(h) => OpenResult::Ok((h, None)),
+                OpenResult::Err(e) => OpenResult::Err(e),
+                OpenResult::NotAvailable => OpenResult::NotAvailable,
+            };
+        }
+
+        self.inner.input_open_name_with_abspath(name, status)
+    }
+
+    fn input_open_primary(&mut self, status: &mut dyn StatusBackend) -> OpenResult<InputHandle> {
+        match self.inner.input_open_primary(status) {
+            OpenResult::Ok(h) => match Self::wrap_primary(h) {
+                Ok(h) => OpenResult::Ok(h),
+                Err(e) => OpenResult::Err(e),
+            },
+            other => other,
+        }
+    }
+
+    fn input_open_primary_with_abspath(
+        &mut self,
+        status: &mut dyn StatusBackend,
+    ) -> OpenResult<(InputHandle, Option<PathBuf>)> {
+        match self.inner.input_open_primary_with_abspath(status) {
+            OpenResult::Ok((h, path)) => match Self::wrap_primary(h) {
+                Ok(h) => OpenResult::Ok((h, path)),
+                Err(e) => OpenResult::Err(e),
+            },
+            other => other,
+        }
+    }
+
+    fn input_open_format(
+        &mut self,
+        name: &str,
+        status: &mut dyn StatusBackend,
+    ) -> OpenResult<InputHandle> {
+        self.inner.input_open_format(name, status)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tectonic_io_base::stdstreams::BufferedPrimaryIo;
+    use tectonic_status_base::NoopStatusBackend;
+
+    #[test]
+    fn serves_layer() {
+        let mut status = NoopStatusBackend::default();
+        let mut io = CompatLayerIo::new(Box::new(BufferedPrimaryIo::from_text(
+            "\\documentclass{article}\n",
+        )));
+
+        let mut text = String::new();
+        io.input_open_primary(&mut status)
+            .unwrap()
+            .read_to_string(&mut text)
+            .unwrap();
+        assert_eq!(
+            text,
+            format!("{}\\documentclass{{article}}\n", PRIMARY_PREFIX)
+        );
+
+        let mut text = String::new();
+        io.input_open_name("tectonic-html-compat.sty", &mut status)
+            .unwrap()
+            .read_to_string(&mut text)
+            .unwrap();
+        assert!(text.contains("\\ProvidesPackage{tectonic-html-compat}"));
+
+        assert!(io
+            .input_open_name("article.cls", &mut status)
+            .is_not_available());
+    }
+}
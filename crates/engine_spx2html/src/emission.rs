@@ -6,9 +6,11 @@
 use std::{
     collections::HashMap,
     fmt::{Arguments, Error as FmtError, Write as FmtWrite},
+    io::Read,
     result::Result as StdResult,
 };
 use tectonic_errors::prelude::*;
+use tectonic_io_base::OpenResult;
 use tectonic_status_base::tt_warning;
 
 use crate::{
@@ -16,7 +18,11 @@ use crate::{
     finalization::FinalizingState,
     fonts::{FamilyRelativeFontId, FontEnsemble, FontFamilyAnalysis, PathToNewFont},
     html::Element,
+    images::{self, ImageSpecial},
+    links::{self, HyperTex, LinkTarget},
+    mathml::MathBuilder,
     specials::Special,
+    svg::{self, SvgCanvas},
     templating::Templating,
     Common, FixedPoint, TexFontNum,
 };
@@ -31,6 +37,15 @@ pub(crate) struct EmittingState {
     rems_per_tex: f32,
     elem_stack: Vec<ElementState>,
     current_canvas: Option<CanvasState>,
+
+    /// The number of canvases that have been dissolved with
+    /// `tdux:dissolveCanvas` but whose closing specials have not yet been
+    /// seen.
+    dissolved_canvases: usize,
+
+    /// For each link (or HyperTeX anchor) that is currently open, whether we
+    /// emitted an `<a>` element for it.
+    open_links: Vec<bool>,
 }
 
 #[derive(Debug, Default)]
@@ -53,6 +68,43 @@ impl ContentState {
         self.current_content.push(ch);
     }
 
+    fn len(&self) -> usize {
+        self.current_content.len()
+    }
+
+    /// Discard everything emitted after the byte offset *start*.
+    fn truncate(&mut self, start: usize) {
+        self.current_content.truncate(start);
+    }
+
+    /// Check whether anything besides markup and whitespace has been emitted
+    /// since the byte offset *start*. Images, SVGs, and MathML count as
+    /// content even though they are tags.
+    fn has_content_since(&self, start: usize) -> bool {
+        let mut in_tag = false;
+        let text = &self.current_content[start..];
+
+        for (i, c) in text.char_indices() {
+            if in_tag {
+                if c == '>' {
+                    in_tag = false;
+                }
+            } else if c == '<' {
+                let rest = &text[i + 1..];
+
+                if rest.starts_with("img") || rest.starts_with("svg") || rest.starts_with("math") {
+                    return true;
+                }
+
+                in_tag = true;
+            } else if !c.is_whitespace() {
+                return true;
+            }
+        }
+
+        false
+    }
+
     fn push_close_tag(&mut self, tag: &str) {
         self.current_content.push('<');
         self.current_content.push('/');
@@ -189,6 +241,11 @@ pub(crate) struct ElementState {
     /// The currently active font, as we understand it, relative to the
     /// currently active font family.
     active_font: FamilyRelativeFontId,
+
+    /// If set, this element should be dropped entirely if it ends up without
+    /// any content. The value is the offset in the current content at which
+    /// its start tag begins.
+    drop_if_empty: Option<usize>,
 }
 
 impl ElementState {
@@ -223,6 +280,8 @@ struct CanvasState {
     y0: i32,
     glyphs: Vec<GlyphInfo>,
     rules: Vec<RuleInfo>,
+    images: Vec<ImageInfo>,
+    math: MathBuilder,
 }
 
 impl CanvasState {
@@ -234,8 +293,20 @@ impl CanvasState {
             y0,
             glyphs: Vec::new(),
             rules: Vec::new(),
+            images: Vec::new(),
+            math: MathBuilder::default(),
         }
     }
+
+    fn push_glyph(&mut self, x: i32, y: i32, font_num: TexFontNum, glyph: u16) {
+        self.glyphs.push(GlyphInfo {
+            dx: x - self.x0,
+            dy: y - self.y0,
+            glyph,
+            font_num,
+        });
+        self.math.push_glyph(font_num, glyph, x);
+    }
 }
 
 #[derive(Debug)]
@@ -254,6 +325,17 @@ struct RuleInfo {
     height: i32,
 }
 
+#[derive(Debug)]
+struct ImageInfo {
+    dx: i32,
+    dy: i32,
+    width: i32,
+    height: i32,
+
+    /// The value of the `src` attribute.
+    src: String,
+}
+
 impl EmittingState {
     pub(crate) fn new_from_init(
         fonts: FontEnsemble,
@@ -280,8 +362,11 @@ impl EmittingState {
                 do_auto_spaces: true,
                 font_family_id: main_body_font_num.unwrap_or_default(),
                 active_font: FamilyRelativeFontId::Regular,
+                drop_if_empty: None,
             }],
             current_canvas: None,
+            dissolved_canvases: 0,
+            open_links: Vec::new(),
         })
     }
 
@@ -340,10 +425,25 @@ impl EmittingState {
         // Refuse the close the root element
         if self.elem_stack.len() > 1 {
             let cur = self.elem_stack.pop().unwrap();
+            self.close_elstate(&cur);
+        }
+    }
 
-            if let Some(e) = cur.elem.as_ref() {
-                self.content.push_close_tag(e.name());
+    /// Emit the closing tag for an element that has been popped off of the
+    /// stack, or remove the element entirely if it was marked as droppable
+    /// and turned out to be empty.
+    fn close_elstate(&mut self, cur: &ElementState) {
+        if let Some(e) = cur.elem.as_ref() {
+            if let Some(start) = cur.drop_if_empty {
+                // The offset can be stale if the content was emitted while
+                // the element was open.
+                if start <= self.content.len() && !self.content.has_content_since(start) {
+                    self.content.truncate(start);
+                    return;
+                }
             }
+
+            self.content.push_close_tag(e.name());
         }
     }
 
@@ -378,6 +478,7 @@ impl EmittingState {
                 elem: Some(el),
                 origin,
                 font_family_id,
+                drop_if_empty: None,
                 ..*cur
             }
         };
@@ -393,9 +494,9 @@ impl EmittingState {
 
         while self.elem_stack.len() > 1 {
             let cur = self.elem_stack.pop().unwrap();
+            self.close_elstate(&cur);
 
             if let Some(e) = cur.elem.as_ref() {
-                self.content.push_close_tag(e.name());
                 n_closed += 1;
 
                 if e.name() == name {
@@ -466,6 +567,8 @@ impl EmittingState {
                     if canvas.depth == 0 {
                         self.handle_end_canvas(common)?;
                     }
+                } else if self.dissolved_canvases > 0 {
+                    self.dissolved_canvases -= 1;
                 } else {
                     tt_warning!(
                         common.status,
@@ -476,6 +579,107 @@ impl EmittingState {
                 Ok(())
             }
 
+            Special::DissolveCanvas => {
+                // A dissolvable canvas is one that has only just been
+                // started, so that the TeX code can decide to emit its
+                // contents as regular HTML after all. Its glyphs then flow
+                // through the usual text path.
+                match self.current_canvas.as_ref() {
+                    Some(c)
+                        if c.depth == 1
+                            && c.glyphs.is_empty()
+                            && c.rules.is_empty()
+                            && c.images.is_empty() =>
+                    {
+                        self.current_canvas = None;
+                        self.dissolved_canvases += 1;
+                    }
+
+                    _ => {
+                        tt_warning!(
+                            common.status,
+                            "ignoring tdux:dissolveCanvas special that does not \
+                             immediately follow the start of a canvas"
+                        );
+                    }
+                }
+                Ok(())
+            }
+
+            Special::PdfBeginAnnotation(spec) => match links::parse_annotation(spec) {
+                Some(target) => self.start_link(x, target, common),
+                None => {
+                    // Some other kind of annotation, which we can't represent.
+                    self.open_links.push(false);
+                    Ok(())
+                }
+            },
+
+            Special::PdfEndAnnotation => {
+                self.end_link(common);
+                Ok(())
+            }
+
+            Special::PdfImage(spec) => self.handle_image(x, y, spec, common),
+
+            Special::PdfDestination(spec) => {
+                if let Some(name) = links::parse_destination(spec) {
+                    self.add_anchor(&name, common);
+                } else {
+                    tt_warning!(
+                        common.status,
+                        "ignoring unparseable destination special: {}",
+                        special
+                    );
+                }
+                Ok(())
+            }
+
+            Special::HyperTex(spec) => match links::parse_hypertex(spec) {
+                Some(HyperTex::LinkStart(target)) => self.start_link(x, target, common),
+
+                Some(HyperTex::Anchor(name)) => {
+                    self.add_anchor(&name, common);
+                    self.open_links.push(false);
+                    Ok(())
+                }
+
+                Some(HyperTex::End) => {
+                    self.end_link(common);
+                    Ok(())
+                }
+
+                None => {
+                    tt_warning!(
+                        common.status,
+                        "ignoring unsupported HyperTeX special: {}",
+                        special
+                    );
+                    Ok(())
+                }
+            },
+
+            Special::MathStart(name)
+            | Special::MathEnd(name)
+            | Special::MathScriptStart(name)
+            | Special::MathScriptEnd(name) => {
+                if let Some(canvas) = self.current_canvas.as_mut() {
+                    match special {
+                        Special::MathStart(_) => canvas.math.start(name),
+                        Special::MathEnd(_) => canvas.math.end(name),
+                        Special::MathScriptStart(_) => canvas.math.start_script(name),
+                        _ => canvas.math.end_script(name, x),
+                    }
+                } else {
+                    tt_warning!(
+                        common.status,
+                        "ignoring MathML structure special outside of a canvas: {}",
+                        special
+                    );
+                }
+                Ok(())
+            }
+
             Special::ManualFlexibleStart(spec) => {
                 self.close_automatics();
                 self.handle_flexible_start_tag(x, y, spec, common)
@@ -503,6 +707,11 @@ impl EmittingState {
                 Ok(())
             }
 
+            Special::SetCanvasFormat(format) => {
+                common.handle_set_canvas_format(format);
+                Ok(())
+            }
+
             Special::SetTemplateVariable(spec) => {
                 self.templating.handle_set_template_variable(spec, common)
             }
@@ -533,6 +742,7 @@ impl EmittingState {
     /// Dname value % Add a double-quoted attribute
     /// NAS % Turn off automatic space insertion while processing this tag
     /// NAT % Turn off automatic tag insertion while processing this tag
+    /// NEC % Drop this element entirely if it ends up with no content
     /// }
     /// ```
     ///
@@ -582,6 +792,7 @@ impl EmittingState {
                 elem: Some(el),
                 origin: ElementOrigin::Manual,
                 font_family_id,
+                drop_if_empty: None,
                 ..*cur
             }
         };
@@ -590,6 +801,7 @@ impl EmittingState {
         let mut styles = Vec::new();
         let mut unquoted_attrs = Vec::new();
         let mut double_quoted_attrs = Vec::new();
+        let mut drop_if_empty = false;
 
         for line in lines {
             if let Some(cls) = line.strip_prefix('C') {
@@ -679,6 +891,8 @@ impl EmittingState {
                 elstate.do_auto_spaces = false;
             } else if line == "NAT" {
                 elstate.do_auto_tags = false;
+            } else if line == "NEC" {
+                drop_if_empty = true;
             } else {
                 tt_warning!(
                     common.status,
@@ -689,6 +903,11 @@ impl EmittingState {
         }
 
         self.push_space_if_needed(x, None);
+
+        if drop_if_empty {
+            elstate.drop_if_empty = Some(self.content.len());
+        }
+
         self.content.push_char('<');
         self.content.push_with_html_escaping(tagname);
 
@@ -759,6 +978,197 @@ impl EmittingState {
         Ok(())
     }
 
+    /// Start a hyperlink.
+    ///
+    /// Links can't be nested in HTML, and we can't put them inside of
+    /// canvases, so in those cases we just ignore them.
+    fn start_link(&mut self, x: i32, target: LinkTarget, common: &mut Common) -> Result<()> {
+        let in_link = self
+            .elem_stack
+            .iter()
+            .any(|e| matches!(e.elem, Some(Element::A)));
+
+        if in_link || self.current_canvas.is_some() {
+            self.open_links.push(false);
+            return Ok(());
+        }
+
+        let href = match target {
+            LinkTarget::Internal(name) => common.links.internal_href(&name),
+            LinkTarget::External(url) => url,
+        };
+
+        self.close_automatics();
+        self.push_space_if_needed(x, None);
+        self.content.push_str("<a href=\"");
+        self.content
+            .push_with_html_double_quoted_attribute_escaping(&href);
+        self.content.push_str("\">");
+        self.push_elem(Element::A, ElementOrigin::Manual);
+        self.open_links.push(true);
+        Ok(())
+    }
+
+    fn end_link(&mut self, common: &mut Common) {
+        match self.open_links.pop() {
+            Some(true) => {
+                // The element might already have been closed implicitly, e.g.
+                // by the end of a paragraph.
+                if self
+                    .elem_stack
+                    .iter()
+                    .any(|e| matches!(e.elem, Some(Element::A)))
+                {
+                    self.pop_elem("a", common);
+                }
+            }
+
+            Some(false) => {}
+
+            None => {
+                tt_warning!(common.status, "ignoring unpaired end-of-link special");
+            }
+        }
+    }
+
+    /// Emit a link destination as an element ID.
+    fn add_anchor(&mut self, name: &str, common: &mut Common) {
+        if common.links.add_anchor(name, true) {
+            self.content.push_str("<a id=\"");
+            self.content
+                .push_with_html_double_quoted_attribute_escaping(links::anchor_id(name));
+            self.content.push_str("\"></a>");
+        }
+    }
+
+    /// Handle an included image.
+    ///
+    /// Images are copied into the output tree as assets. Browsers can't
+    /// display PDF or PostScript figures, but documents often come with SVG
+    /// versions of them, so we look for those.
+    fn handle_image(&mut self, x: i32, y: i32, spec: &str, common: &mut Common) -> Result<()> {
+        let image = match ImageSpecial::parse(spec) {
+            Some(i) => i,
+            None => {
+                tt_warning!(
+                    common.status,
+                    "ignoring unparseable image special `pdf:image {}`",
+                    spec
+                );
+                return Ok(());
+            }
+        };
+
+        let (stem, ext) = match image.path.rsplit_once('.') {
+            Some((stem, ext)) => (stem, ext.to_ascii_lowercase()),
+            None => (image.path.as_ref(), String::new()),
+        };
+
+        let src_path = match ext.as_ref() {
+            "png" | "jpg" | "jpeg" | "svg" => image.path.clone(),
+            "pdf" | "eps" | "ps" => format!("{stem}.svg"),
+            _ => {
+                tt_warning!(
+                    common.status,
+                    "cannot include image `{}` in HTML output: unsupported file format",
+                    image.path
+                );
+                return Ok(());
+            }
+        };
+
+        let mut ih = match common.hooks.io().input_open_name(&src_path, common.status) {
+            OpenResult::Ok(ih) => ih,
+
+            OpenResult::NotAvailable => {
+                if src_path != image.path {
+                    tt_warning!(
+                        common.status,
+                        "cannot include figure `{}` in HTML output; provide an SVG version of it as `{}`",
+                        image.path,
+                        src_path
+                    );
+                } else {
+                    tt_warning!(common.status, "cannot find image file `{}`", src_path);
+                }
+                return Ok(());
+            }
+
+            OpenResult::Err(e) => return Err(e),
+        };
+
+        let mut data = Vec::new();
+        atry!(
+            ih.read_to_end(&mut data);
+            ["failed to read image file `{}`", &src_path]
+        );
+
+        let (name, digest_opt) = ih.into_name_digest();
+        common
+            .hooks
+            .event_input_closed(name, digest_opt, common.status);
+
+        // XeTeX sizes PDF figures from the PDF file itself, so do the same
+        // if we can, rather than trusting the SVG version to match.
+        let pdf_size = if ext == "pdf" {
+            pdf_figure_size(&image, common)
+        } else {
+            None
+        };
+
+        let size = pdf_size
+            .or_else(|| images::natural_size(&data))
+            .map(|n| image.display_size(n));
+
+        if size.is_none() {
+            tt_warning!(
+                common.status,
+                "could not determine the size of image `{}`",
+                src_path
+            );
+        }
+
+        let dest_path = self.assets.add_image(&src_path);
+        let src = common.links.asset_href(&dest_path);
+
+        if let Some(c) = self.current_canvas.as_mut() {
+            if let Some((width, height)) = size {
+                c.images.push(ImageInfo {
+                    dx: x - c.x0,
+                    dy: y - c.y0,
+                    width,
+                    height,
+                    src,
+                });
+            }
+
+            return Ok(());
+        }
+
+        self.push_space_if_needed(x, None);
+        write!(
+            self.content,
+            "<img class=\"tdux-img\" src=\"{src}\" alt=\"\""
+        )
+        .unwrap();
+
+        if let Some((width, height)) = size {
+            write!(
+                self.content,
+                " style=\"width: {}rem; height: {}rem\"",
+                width as f32 * self.rems_per_tex,
+                height as f32 * self.rems_per_tex,
+            )
+            .unwrap();
+
+            let cur_space_width = self.fonts.maybe_get_font_space_width(None);
+            self.content.update_content_pos(x + width, cur_space_width);
+        }
+
+        self.content.push_char('>');
+        Ok(())
+    }
+
     pub(crate) fn handle_text_and_glyphs(
         &mut self,
         font_num: TexFontNum,
@@ -770,12 +1180,7 @@ impl EmittingState {
     ) -> Result<()> {
         if let Some(c) = self.current_canvas.as_mut() {
             for i in 0..glyphs.len() {
-                c.glyphs.push(GlyphInfo {
-                    dx: xs[i] - c.x0,
-                    dy: ys[i] - c.y0,
-                    glyph: glyphs[i],
-                    font_num,
-                });
+                c.push_glyph(xs[i], ys[i], font_num, glyphs[i]);
             }
         } else if !glyphs.is_empty() {
             self.set_up_for_font(xs[0], font_num, common);
@@ -815,12 +1220,7 @@ impl EmittingState {
     ) -> Result<()> {
         if let Some(c) = self.current_canvas.as_mut() {
             for i in 0..glyphs.len() {
-                c.glyphs.push(GlyphInfo {
-                    dx: xs[i] - c.x0,
-                    dy: ys[i] - c.y0,
-                    glyph: glyphs[i],
-                    font_num,
-                });
+                c.push_glyph(xs[i], ys[i], font_num, glyphs[i]);
             }
         } else {
             let cur_space_width = self.fonts.maybe_get_font_space_width(Some(font_num));
@@ -929,6 +1329,7 @@ impl EmittingState {
                 elem: Some(Element::B),
                 origin: ElementOrigin::FontAuto,
                 active_font: af,
+                drop_if_empty: None,
                 ..*self.cur_elstate()
             });
         }
@@ -940,6 +1341,7 @@ impl EmittingState {
                 elem: Some(Element::I),
                 origin: ElementOrigin::FontAuto,
                 active_font: af,
+                drop_if_empty: None,
                 ..*self.cur_elstate()
             });
         }
@@ -953,6 +1355,7 @@ impl EmittingState {
                 elem: Some(Element::Span),
                 origin: ElementOrigin::FontAuto,
                 active_font: desired_af,
+                drop_if_empty: None,
                 ..*self.cur_elstate()
             });
         }
@@ -981,9 +1384,10 @@ impl EmittingState {
                 width,
                 height,
             });
-        } else {
+        } else if self.dissolved_canvases == 0 {
             // Not sure what to do here. Does this even happen in
-            // non-pathological cases?
+            // non-pathological cases? Inside dissolved canvases it does:
+            // those are tables whose rules we can't represent.
             tt_warning!(
                 common.status,
                 "ignoring rule outside of Tectonic HTML canvas"
@@ -1070,6 +1474,79 @@ impl EmittingState {
             }
         }
 
+        for ii in &canvas.images[..] {
+            let xmin = ii.dx;
+            let xmax = ii.dx + ii.width;
+            let ymin = ii.dy - ii.height;
+            let ymax = ii.dy;
+
+            if first {
+                x_min_tex = xmin;
+                x_max_tex = xmax;
+                y_min_tex = ymin;
+                y_max_tex = ymax;
+                first = false;
+            } else {
+                x_min_tex = std::cmp::min(x_min_tex, xmin);
+                x_max_tex = std::cmp::max(x_max_tex, xmax);
+                y_min_tex = std::cmp::min(y_min_tex, ymin);
+                y_max_tex = std::cmp::max(y_max_tex, ymax);
+            }
+        }
+
+        // If the TeX code told us the structure of this math, we can emit
+        // MathML instead of positioned glyphs. If that doesn't work out, we
+        // fall back to the positioned glyphs. Neither MathML nor our SVG
+        // drawing can include images, though.
+
+        let math = std::mem::take(&mut canvas.math);
+
+        if matches!(canvas.kind.as_ref(), "math" | "dmath")
+            && math.has_structure()
+            && canvas.images.is_empty()
+        {
+            let fonts = &mut self.fonts;
+
+            match math.render(!inline, |fnum, glyph| {
+                fonts.get_glyph_base_char(fnum, glyph).ok().flatten()
+            }) {
+                Ok(mathml) => {
+                    self.content.push_str(&mathml);
+                    let cur_space_width = self.fonts.maybe_get_font_space_width(None);
+                    self.content
+                        .update_content_pos(x_max_tex + canvas.x0, cur_space_width);
+                    return Ok(());
+                }
+
+                Err(e) => {
+                    tt_warning!(
+                        common.status,
+                        "falling back to positioned glyphs for math: {}",
+                        e
+                    );
+                }
+            }
+        }
+
+        // If requested, draw the canvas as an SVG image. This can fail if we
+        // can't get the outlines of the glyphs, in which case we fall back to
+        // positioned glyphs too.
+
+        if common.svg_canvases && canvas.images.is_empty() {
+            let bounds = [x_min_tex, y_min_tex, x_max_tex, y_max_tex];
+
+            match self.emit_svg_canvas(&canvas, inline, bounds, common) {
+                Ok(()) => return Ok(()),
+                Err(e) => {
+                    tt_warning!(
+                        common.status,
+                        "falling back to positioned glyphs for canvas: {}",
+                        e
+                    );
+                }
+            }
+        }
+
         // Now that we have that information, we can lay out the individual
         // glyphs.
         //
@@ -1151,6 +1628,19 @@ impl EmittingState {
             .unwrap();
         }
 
+        for ii in canvas.images.drain(..) {
+            write!(
+                inner_content,
+                "<img class=\"cimg\" src=\"{}\" alt=\"\" style=\"position: absolute; top: {}rem; left: {}rem; width: {}rem; height: {}rem;\">",
+                ii.src,
+                (-y_min_tex + ii.dy - ii.height) as f32 * self.rems_per_tex,
+                ii.dx as f32 * self.rems_per_tex,
+                ii.width as f32 * self.rems_per_tex,
+                ii.height as f32 * self.rems_per_tex,
+            )
+            .unwrap();
+        }
+
         // Wrap it up.
 
         let (element, layout_class, valign) = if inline {
@@ -1197,9 +1687,95 @@ impl EmittingState {
         Ok(())
     }
 
+    /// Emit a canvas as a standalone SVG image.
+    ///
+    /// The *bounds* are the canvas bounds computed from the glyph metrics, as
+    /// `[x_min, y_min, x_max, y_max]` in TeX units.
+    fn emit_svg_canvas(
+        &mut self,
+        canvas: &CanvasState,
+        inline: bool,
+        bounds: [i32; 4],
+        common: &mut Common,
+    ) -> Result<()> {
+        let mut svg = SvgCanvas::default();
+        let mut alt = String::new();
+
+        if !canvas.glyphs.is_empty() || !canvas.rules.is_empty() {
+            svg.include(
+                bounds[0] as f32,
+                bounds[1] as f32,
+                bounds[2] as f32,
+                bounds[3] as f32,
+            );
+        }
+
+        for gi in &canvas.glyphs[..] {
+            atry!(
+                svg.add_glyph(&self.fonts, gi.dx, gi.dy, gi.font_num, gi.glyph);
+                ["cannot draw glyph {} of font {}", gi.glyph, gi.font_num]
+            );
+
+            if let Ok(Some(ch)) = self.fonts.get_glyph_base_char(gi.font_num, gi.glyph) {
+                alt.push(ch);
+            }
+        }
+
+        for ri in &canvas.rules[..] {
+            svg.add_rule(ri.dx, ri.dy, ri.width, ri.height);
+        }
+
+        let (text, [x_min, y_min, x_max, y_max]) = svg.finish(self.rems_per_tex);
+        let name = svg::file_name(&text);
+        self.templating.add_companion_file(name.clone(), text);
+
+        // Like the positioned-glyph version, the image extends to the left of
+        // the canvas origin if needed, but isn't moved leftwards of it.
+        let mut style = format!(
+            "width: {}rem; height: {}rem",
+            (x_max - x_min) * self.rems_per_tex,
+            (y_max - y_min) * self.rems_per_tex,
+        );
+
+        if x_min > 0. {
+            write!(style, "; margin-left: {}rem", x_min * self.rems_per_tex).unwrap();
+        }
+
+        if inline {
+            write!(style, "; vertical-align: {}rem", -y_max * self.rems_per_tex).unwrap();
+        }
+
+        let img = format!(
+            "<img class=\"canvas-svg\" src=\"{}\" alt=\"{}\" style=\"{}\">",
+            name,
+            html_escape::encode_double_quoted_attribute(&alt),
+            style,
+        );
+
+        if inline {
+            self.content.push_str(&img);
+        } else {
+            let element = self.create_elem("div", true, common);
+            write!(
+                self.content,
+                "<{0} class=\"canvas canvas-block\">{1}</{0}>",
+                element.name(),
+                img
+            )
+            .unwrap();
+        }
+
+        let cur_space_width = self.fonts.maybe_get_font_space_width(None);
+        self.content
+            .update_content_pos(x_max as i32 + canvas.x0, cur_space_width);
+        Ok(())
+    }
+
     fn finish_file(&mut self, common: &mut Common) -> Result<()> {
-        self.templating
-            .set_variable("tduxContent", self.content.take());
+        let content = self.content.take();
+        self.assets
+            .add_search_page(self.templating.next_output_path(), &content);
+        self.templating.set_variable("tduxContent", content);
         self.templating.emit(common)?;
 
         let cur_space_width = self.fonts.maybe_get_font_space_width(None);
@@ -1222,3 +1798,38 @@ impl EmittingState {
         FinalizingState::new(self.fonts, self.templating, self.assets)
     }
 }
+
+/// Get the natural size of a PDF figure from the PDF file, if it's available.
+fn pdf_figure_size(image: &ImageSpecial, common: &mut Common) -> Option<(f64, f64)> {
+    let mut ih = match common
+        .hooks
+        .io()
+        .input_open_name(&image.path, common.status)
+    {
+        OpenResult::Ok(ih) => ih,
+        _ => return None,
+    };
+
+    let mut data = Vec::new();
+    let read_ok = ih.read_to_end(&mut data).is_ok();
+    let (name, digest_opt) = ih.into_name_digest();
+    common
+        .hooks
+        .event_input_closed(name, digest_opt, common.status);
+
+    let size = if read_ok {
+        image.pdf_natural_size(&data)
+    } else {
+        None
+    };
+
+    if size.is_none() {
+        tt_warning!(
+            common.status,
+            "could not read PDF figure `{}`; sizing it from its SVG version instead",
+            image.path
+        );
+    }
+
+    size
+}
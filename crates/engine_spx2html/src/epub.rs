@@ -0,0 +1,401 @@
+// Copyright 2026 the Tectonic Project
+// Licensed under the MIT License.
+
+//! Packaging HTML output as an EPUB 3 publication.
+//!
+//! The HTML tree is emitted to disk as usual, and then bundled up into a Zip
+//! archive along with the EPUB boilerplate: the `mimetype` marker, the
+//! container file, an OPF package document listing every file, and a
+//! navigation document linking to each templated page. The pages form the
+//! reading order in the order that they were emitted, and are converted to
+//! XHTML on the way in. Everything else in the tree, such as fonts, CSS, and
+//! images, is included as-is.
+
+use std::{
+    fmt::Write as FmtWrite,
+    fs::File,
+    io::Write,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+use tectonic_errors::prelude::*;
+use tectonic_io_base::digest::{self, Digest};
+use zip::{write::FileOptions, CompressionMethod, ZipWriter};
+
+use crate::xhtml::to_xhtml;
+
+/// The directory within the archive holding the publication content.
+const CONTENT_DIR: &str = "EPUB";
+
+const NAV_NAME: &str = "nav.xhtml";
+const PACKAGE_NAME: &str = "package.opf";
+
+const CONTAINER_XML: &str = "<?xml version=\"1.0\" encoding=\"UTF-8\"?>
+<container version=\"1.0\" xmlns=\"urn:oasis:names:tc:opendocument:xmlns:container\">
+  <rootfiles>
+    <rootfile full-path=\"EPUB/package.opf\" media-type=\"application/oebps-package+xml\"/>
+  </rootfiles>
+</container>
+";
+
+/// A content document, ready to be added to the package.
+struct Page {
+    path: String,
+    xhtml: String,
+    title: Option<String>,
+    properties: Vec<&'static str>,
+}
+
+/// Package an emitted HTML tree as an EPUB file.
+///
+/// *pages* lists the paths of the templated HTML outputs, relative to *tree*,
+/// in reading order.
+pub(crate) fn package(
+    tree: &Path,
+    pages: &[String],
+    dest: &Path,
+    modified: SystemTime,
+) -> Result<()> {
+    ensure!(
+        !pages.is_empty(),
+        "no HTML pages were emitted, so there is nothing to put in the EPUB"
+    );
+
+    let mut files = Vec::new();
+    collect_files(tree, "", &mut files)?;
+    files.sort();
+
+    ensure!(
+        !files.iter().any(|f| f == NAV_NAME || f == PACKAGE_NAME),
+        "the HTML output contains a file named `{}` or `{}`, which the EPUB packaging needs",
+        NAV_NAME,
+        PACKAGE_NAME
+    );
+
+    let mut content = Vec::new();
+    let mut lang = None;
+
+    for path in pages {
+        let full = tree.join(path);
+        let html = atry!(
+            std::fs::read_to_string(&full);
+            ["cannot read HTML output file `{}`", full.display()]
+        );
+
+        if lang.is_none() {
+            lang = html_lang(&html);
+        }
+
+        let mut properties = Vec::new();
+
+        for (needle, prop) in &[
+            ("<svg", "svg"),
+            ("<math", "mathml"),
+            ("<script", "scripted"),
+        ] {
+            if html.contains(needle) {
+                properties.push(*prop);
+            }
+        }
+
+        content.push(Page {
+            path: path.clone(),
+            title: element_text(&html, "title"),
+            xhtml: to_xhtml(&html),
+            properties,
+        });
+    }
+
+    let title = content[0]
+        .title
+        .clone()
+        .unwrap_or_else(|| "Untitled".to_owned());
+    let lang = lang.unwrap_or_else(|| "en".to_owned());
+
+    let nav = make_nav(&content, &title, &lang);
+    let opf = make_package(&files, &content, &title, &lang, modified);
+
+    // Now write it all out. The `mimetype` file must come first and must not
+    // be compressed.
+
+    let out = atry!(
+        File::create(dest);
+        ["cannot create EPUB file `{}`", dest.display()]
+    );
+    let mut zip = ZipWriter::new(out);
+    let stored = FileOptions::default().compression_method(CompressionMethod::Stored);
+    let deflated = FileOptions::default().compression_method(CompressionMethod::Deflated);
+
+    zip.start_file("mimetype", stored)?;
+    zip.write_all(b"application/epub+zip")?;
+    zip.start_file("META-INF/container.xml", deflated)?;
+    zip.write_all(CONTAINER_XML.as_bytes())?;
+    zip.start_file(format!("{CONTENT_DIR}/{PACKAGE_NAME}"), deflated)?;
+    zip.write_all(opf.as_bytes())?;
+    zip.start_file(format!("{CONTENT_DIR}/{NAV_NAME}"), deflated)?;
+    zip.write_all(nav.as_bytes())?;
+
+    for file in &files {
+        zip.start_file(format!("{CONTENT_DIR}/{file}"), deflated)?;
+
+        if let Some(page) = content.iter().find(|p| &p.path == file) {
+            zip.write_all(page.xhtml.as_bytes())?;
+        } else {
+            let full = tree.join(file);
+            let data = atry!(
+                std::fs::read(&full);
+                ["cannot read HTML output file `{}`", full.display()]
+            );
+            zip.write_all(&data)?;
+        }
+    }
+
+    zip.finish()?;
+    Ok(())
+}
+
+/// Recursively list the files in a directory, as `/`-separated relative paths.
+fn collect_files(base: &Path, prefix: &str, files: &mut Vec<String>) -> Result<()> {
+    let dir: PathBuf = if prefix.is_empty() {
+        base.to_owned()
+    } else {
+        base.join(prefix)
+    };
+
+    for entry in atry!(dir.read_dir(); ["cannot read directory `{}`", dir.display()]) {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().into_owned();
+
+        let rel = if prefix.is_empty() {
+            name
+        } else {
+            format!("{prefix}/{name}")
+        };
+
+        if entry.file_type()?.is_dir() {
+            collect_files(base, &rel, files)?;
+        } else {
+            files.push(rel);
+        }
+    }
+
+    Ok(())
+}
+
+fn media_type(path: &str) -> &'static str {
+    let ext = path.rsplit('.').next().unwrap_or("").to_ascii_lowercase();
+
+    match ext.as_str() {
+        "html" | "htm" | "xhtml" => "application/xhtml+xml",
+        "css" => "text/css",
+        "js" => "application/javascript",
+        "svg" => "image/svg+xml",
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "otf" => "font/otf",
+        "ttf" => "font/ttf",
+        "woff" => "font/woff",
+        "woff2" => "font/woff2",
+        _ => "application/octet-stream",
+    }
+}
+
+/// Extract the text content of the first instance of a simple element.
+fn element_text(html: &str, name: &str) -> Option<String> {
+    let start = html.find(&format!("<{name}"))?;
+    let start = start + html[start..].find('>')? + 1;
+    let end = start + html[start..].find(&format!("</{name}>"))?;
+    let text = html_escape::decode_html_entities(html[start..end].trim()).into_owned();
+
+    if text.is_empty() {
+        None
+    } else {
+        Some(text)
+    }
+}
+
+/// Get the `lang` attribute of the `<html>` element.
+fn html_lang(html: &str) -> Option<String> {
+    let start = html.find("<html")?;
+    let tag = &html[start..start + html[start..].find('>')?];
+    let ofs = tag.find("lang=")? + 5;
+    let value = tag[ofs..].trim_start_matches(['"', '\'']);
+    let end = value
+        .find(|c: char| c == '"' || c == '\'' || c.is_whitespace())
+        .unwrap_or(value.len());
+    Some(value[..end].to_owned())
+}
+
+fn make_nav(content: &[Page], title: &str, lang: &str) -> String {
+    let mut nav = format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>
+<!DOCTYPE html>
+<html xmlns=\"http://www.w3.org/1999/xhtml\" xmlns:epub=\"http://www.idpf.org/2007/ops\" lang=\"{0}\" xml:lang=\"{0}\">
+<head><title>{1}</title></head>
+<body>
+<nav epub:type=\"toc\" id=\"toc\">
+<h1>{1}</h1>
+<ol>
+",
+        html_escape::encode_double_quoted_attribute(lang),
+        html_escape::encode_text(title),
+    );
+
+    for page in content {
+        writeln!(
+            nav,
+            "<li><a href=\"{}\">{}</a></li>",
+            html_escape::encode_double_quoted_attribute(&page.path),
+            html_escape::encode_text(page.title.as_deref().unwrap_or(&page.path)),
+        )
+        .unwrap();
+    }
+
+    nav.push_str("</ol>\n</nav>\n</body>\n</html>\n");
+    nav
+}
+
+fn make_package(
+    files: &[String],
+    content: &[Page],
+    title: &str,
+    lang: &str,
+    modified: SystemTime,
+) -> String {
+    // The identifier should be stable across rebuilds of the same book, so
+    // derive it from the title and structure rather than making it random.
+    let mut dc = digest::create();
+    dc.update(title.as_bytes());
+
+    for page in content {
+        dc.update(b"\0");
+        dc.update(page.path.as_bytes());
+    }
+
+    let hash = digest::bytes_to_hex(&dc.finalize());
+    let uuid = format!(
+        "{}-{}-5{}-a{}-{}",
+        &hash[0..8],
+        &hash[8..12],
+        &hash[13..16],
+        &hash[17..20],
+        &hash[20..32]
+    );
+
+    let mut opf = format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>
+<package xmlns=\"http://www.idpf.org/2007/opf\" version=\"3.0\" unique-identifier=\"uid\">
+<metadata xmlns:dc=\"http://purl.org/dc/elements/1.1/\">
+<dc:identifier id=\"uid\">urn:uuid:{}</dc:identifier>
+<dc:title>{}</dc:title>
+<dc:language>{}</dc:language>
+<meta property=\"dcterms:modified\">{}</meta>
+</metadata>
+<manifest>
+<item id=\"nav\" href=\"{}\" media-type=\"application/xhtml+xml\" properties=\"nav\"/>
+",
+        uuid,
+        html_escape::encode_text(title),
+        html_escape::encode_text(lang),
+        format_timestamp(modified),
+        NAV_NAME,
+    );
+
+    let mut spine = String::new();
+
+    for (i, file) in files.iter().enumerate() {
+        let id = format!("item{i}");
+        let page = content.iter().find(|p| &p.path == file);
+
+        let mut properties = String::new();
+
+        if let Some(page) = page {
+            if !page.properties.is_empty() {
+                properties = format!(" properties=\"{}\"", page.properties.join(" "));
+            }
+        }
+
+        writeln!(
+            opf,
+            "<item id=\"{}\" href=\"{}\" media-type=\"{}\"{}/>",
+            id,
+            html_escape::encode_double_quoted_attribute(file),
+            if page.is_some() {
+                "application/xhtml+xml"
+            } else {
+                media_type(file)
+            },
+            properties,
+        )
+        .unwrap();
+    }
+
+    // The spine follows the emission order, not the alphabetical file order.
+    for page in content {
+        if let Some(i) = files.iter().position(|f| f == &page.path) {
+            writeln!(spine, "<itemref idref=\"item{i}\"/>").unwrap();
+        }
+    }
+
+    write!(opf, "</manifest>\n<spine>\n{spine}</spine>\n</package>\n").unwrap();
+    opf
+}
+
+/// Format a timestamp in the `YYYY-MM-DDThh:mm:ssZ` form required by EPUB.
+fn format_timestamp(t: SystemTime) -> String {
+    let secs = t
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    let days = (secs / 86400) as i64;
+    let rem = secs % 86400;
+
+    // Convert days since the epoch to a civil date; see
+    // <http://howardhinnant.github.io/date_algorithms.html>.
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        rem / 3600,
+        (rem / 60) % 60,
+        rem % 60
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn timestamps() {
+        assert_eq!(format_timestamp(UNIX_EPOCH), "1970-01-01T00:00:00Z");
+        assert_eq!(
+            format_timestamp(UNIX_EPOCH + Duration::from_secs(1_709_251_199)),
+            "2024-02-29T23:59:59Z"
+        );
+    }
+
+    #[test]
+    fn page_metadata() {
+        assert_eq!(html_lang("<html lang=fr>").as_deref(), Some("fr"));
+
+        let html = "<html lang=\"de\"><head><title>A &amp; B</title></head></html>";
+        assert_eq!(element_text(html, "title").as_deref(), Some("A & B"));
+        assert_eq!(html_lang(html).as_deref(), Some("de"));
+        assert_eq!(media_type("fonts/x.OTF"), "font/otf");
+    }
+}
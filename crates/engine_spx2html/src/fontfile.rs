@@ -18,8 +18,13 @@ use pinot::{
     types::{FWord, Tag, UfWord},
     FontDataRef, TableProvider,
 };
-use std::{collections::HashMap, num::Wrapping, path::Path};
+use std::{
+    collections::{BTreeMap, HashMap},
+    num::Wrapping,
+    path::Path,
+};
 use tectonic_errors::prelude::*;
+use tectonic_font_outlines::{GlyphOutline, Outlines};
 
 use crate::FixedPoint;
 
@@ -82,6 +87,9 @@ pub struct FontFileData {
     /// The offset of the HEAD table within the font data. We need
     /// this for the variant cmap munging.
     fontdata_head_offset: u32,
+
+    /// How to get glyph outlines out of the font data, if we can.
+    outlines: Option<Outlines>,
 }
 
 /// Information about the reverse-mapping of a glyph to Unicode.
@@ -294,6 +302,8 @@ impl FontFileData {
             }
         }
 
+        let outlines = Outlines::new(&buffer, face_index);
+
         // All done!
 
         Ok(FontFileData {
@@ -310,6 +320,7 @@ impl FontFileData {
             no_new_variants: false,
             fontdata_head_offset,
             fontdata_cmap_trec_idx,
+            outlines,
         })
     }
 
@@ -328,6 +339,23 @@ impl FontFileData {
         self.baseline_factor
     }
 
+    /// Get the outline of a glyph.
+    ///
+    /// An error is returned if the font's outlines are in a format that we
+    /// don't support.
+    pub fn glyph_outline(&self, glyph: GlyphId) -> Result<GlyphOutline> {
+        let outlines = a_ok_or!(
+            self.outlines.as_ref();
+            ["the font's glyph outlines are in an unsupported format"]
+        );
+        outlines.glyph_outline(&self.buffer, glyph)
+    }
+
+    /// Get the number of font units per em.
+    pub fn units_per_em(&self) -> UfWord {
+        self.units_per_em
+    }
+
     /// Attempt to retrieve metrics information for the given glyph.
     pub fn lookup_metrics(&self, glyph: GlyphId, tex_size: FixedPoint) -> Option<GlyphMetrics> {
         // As of Rust 1.45, the float-to-int cast saturates, which I think
@@ -414,27 +442,35 @@ impl FontFileData {
     /// `out_base` is the output directory, or None if we shouldn't be writing
     /// anything to disk.
     ///
+    /// If `manifest` is provided, the files are given names that include a
+    /// hash of their contents, and the mapping from the plain names to the
+    /// hashed ones is recorded in it.
+    ///
     /// Return value is a vec of (variant-map-index, CSS-src-field).
     pub fn emit(
         self,
         out_base: Option<&Path>,
         rel_path: &str,
+        mut manifest: Option<&mut BTreeMap<String, String>>,
     ) -> Result<Vec<(Option<usize>, String)>> {
         // Write the main font file ... maybe.
 
         let mut out_path = out_base.map(|p| p.to_owned());
+        let mut out_name = rel_path.to_owned();
 
         if let Some(out_path) = out_path.as_mut() {
-            out_path.push(rel_path);
-            atry!(
-                std::fs::write(&out_path, &self.buffer);
-                ["cannot write output file `{}`", out_path.display()]
-            );
+            if let Some(manifest) = manifest.as_deref_mut() {
+                out_name = crate::assets::hashed_name(rel_path, &self.buffer);
+                manifest.insert(rel_path.to_owned(), out_name.clone());
+            }
+
+            out_path.push(&out_name);
+            crate::assets::write_if_changed(out_path, &self.buffer)?;
         }
 
         // CSS info for the main font.
 
-        let rel_url = utf8_percent_encode(rel_path, CONTROLS).to_string();
+        let rel_url = utf8_percent_encode(&out_name, CONTROLS).to_string();
         let mut rv = vec![(None, format!(r#"url("{rel_url}") format("opentype")"#))];
 
         // Variants until we're done
@@ -458,7 +494,7 @@ impl FontFileData {
             // We have some variants to emit! If we're not actually writing
             // files, we might not have much work to actually do though.
 
-            let varname = format!("vg{cur_map_index}{rel_path}");
+            let mut varname = format!("vg{cur_map_index}{rel_path}");
 
             if let Some(out_path) = out_path.as_mut() {
                 // Step 1: create new CMAP, appending to buffer.
@@ -488,12 +524,14 @@ impl FontFileData {
 
                 // step 4: write new file
 
+                if let Some(manifest) = manifest.as_deref_mut() {
+                    let hashed = crate::assets::hashed_name(&varname, &buffer);
+                    manifest.insert(std::mem::replace(&mut varname, hashed.clone()), hashed);
+                }
+
                 out_path.pop();
                 out_path.push(&varname);
-                atry!(
-                    std::fs::write(&out_path, &buffer);
-                    ["cannot write output file `{}`", out_path.display()]
-                );
+                crate::assets::write_if_changed(out_path, &buffer)?;
             }
 
             // step 5: update CSS
@@ -6,8 +6,14 @@
 //! Here a "font family" is interpreted in the HTML sense, meaning a set of
 //! related fonts. In typography you might call this a typeface.
 
-use std::{collections::HashMap, fmt::Write, io::Read, path::Path};
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt::Write,
+    io::Read,
+    path::Path,
+};
 use tectonic_errors::prelude::*;
+use tectonic_font_outlines::GlyphOutline;
 use tectonic_io_base::InputHandle;
 use tectonic_status_base::{tt_warning, StatusBackend};
 
@@ -255,6 +261,50 @@ impl FontEnsemble {
             .lookup_metrics(glyph, tfi.size))
     }
 
+    /// Get the Unicode character that a glyph represents.
+    ///
+    /// Unlike [`Self::process_glyph_for_canvas`], this gives the "base"
+    /// character for glyphs that are sub/super-script or enlarged variants, and
+    /// does not set up any custom font mappings. This is appropriate when the
+    /// consumer of the text, such as a MathML renderer, will take care of
+    /// choosing the glyph variant itself.
+    ///
+    /// The return value is only `Err` if the font number is undeclared. If the
+    /// glyph can't be mapped to Unicode, `Ok(None)` is returned.
+    pub fn get_glyph_base_char(
+        &mut self,
+        fnum: TexFontNum,
+        glyph: GlyphId,
+    ) -> Result<Option<char>> {
+        let tfi = self.lookup_tex(fnum)?;
+
+        Ok(self.font_files[tfi.fid]
+            .details
+            .lookup_mapping(glyph)
+            .map(|mc| match mc {
+                MapEntry::Direct(c) => c,
+                MapEntry::SubSuperScript(c, _) => c,
+                MapEntry::MathGrowingVariant(c, _, _) => c,
+            }))
+    }
+
+    /// Get the outline of a glyph, for drawing it as a vector shape.
+    ///
+    /// The return value is a tuple `(outline, scale)`, where `scale` is the
+    /// number of TeX units per font unit at the size of the TeX font. An error
+    /// is returned if the font number is undeclared or the outline can't be
+    /// obtained.
+    pub fn get_glyph_outline(
+        &self,
+        fnum: TexFontNum,
+        glyph: GlyphId,
+    ) -> Result<(GlyphOutline, f32)> {
+        let tfi = self.lookup_tex(fnum)?;
+        let details = &self.font_files[tfi.fid].details;
+        let outline = details.glyph_outline(glyph)?;
+        Ok((outline, tfi.size as f32 / details.units_per_em() as f32))
+    }
+
     /// Get information needed to render a glyph in a canvas context.
     ///
     /// The return value is a tuple `(text_info, size, baseline_factor)`. In
@@ -382,13 +432,20 @@ impl FontEnsemble {
 
     /// Emit the font files and return CSS code setting up the files.
     ///
+    /// If `manifest` is provided, the font files are given content-hashed
+    /// names, which are recorded in it.
+    ///
     /// This function clears this object's internal data structures, making it
     /// effectively unusable for subsequent operations.
-    pub fn emit(&mut self, out_base: Option<&Path>) -> Result<String> {
+    pub fn emit(
+        &mut self,
+        out_base: Option<&Path>,
+        mut manifest: Option<&mut BTreeMap<String, String>>,
+    ) -> Result<String> {
         let mut faces = String::default();
 
         for font in self.font_files.drain(..) {
-            font.emit(out_base, &mut faces)?;
+            font.emit(out_base, manifest.as_deref_mut(), &mut faces)?;
         }
 
         Ok(faces)
@@ -748,8 +805,13 @@ impl Font {
         }
     }
 
-    fn emit<W: Write>(self, out_base: Option<&Path>, mut dest: W) -> Result<()> {
-        for (var_index, css_src) in self.details.emit(out_base, &self.out_rel_path)? {
+    fn emit<W: Write>(
+        self,
+        out_base: Option<&Path>,
+        manifest: Option<&mut BTreeMap<String, String>>,
+        mut dest: W,
+    ) -> Result<()> {
+        for (var_index, css_src) in self.details.emit(out_base, &self.out_rel_path, manifest)? {
             // This is almost identical to `selection_style_text`. A major
             // factor is that we're consuming `self`, with `self.details`
             // already consumed by the `emit()` call, so we can't borrow &self.
@@ -0,0 +1,436 @@
+// Copyright 2026 the Tectonic Project
+// Licensed under the MIT License.
+
+//! Included graphics.
+//!
+//! XeTeX expresses pictures included with `\includegraphics` (through the
+//! `\XeTeXpicfile` and `\XeTeXpdffile` primitives) as `pdf:image` specials
+//! that give the path of the file and the transformation that should be
+//! applied to it. Here we parse those specials and work out the size at which
+//! the image should be displayed. This requires knowing the image's natural
+//! size, which we get from its file header, or for PDF figures, from the
+//! selected page of the PDF file itself.
+
+use std::{convert::TryInto, iter::Peekable, slice::Iter};
+use tectonic_pdf_io::{Document, PageBox};
+
+use crate::pdfobj::{PdfObject, PdfParser};
+
+/// The ratio of TeX points to PostScript ("big") points.
+const PT_PER_BP: f64 = 72.27 / 72.;
+
+/// The information in a `pdf:image` special.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub(crate) struct ImageSpecial {
+    /// The path of the image file.
+    pub path: String,
+
+    /// The linear part of the transformation matrix, if specified. XeTeX
+    /// always provides this.
+    matrix: Option<[f64; 4]>,
+
+    /// The requested width, in big points.
+    width: Option<f64>,
+
+    /// The requested height, in big points.
+    height: Option<f64>,
+
+    /// The requested horizontal and vertical scale factors.
+    scale: Option<(f64, f64)>,
+
+    /// The page to use, for PDF figures. Zero means the first page, and
+    /// negative numbers count back from the last page.
+    page: i32,
+
+    /// The page boundary to use, for PDF figures.
+    page_box: Option<PageBox>,
+}
+
+impl ImageSpecial {
+    /// Parse the argument of a `pdf:image` special.
+    ///
+    /// Besides the `matrix` form emitted by XeTeX, we understand the `width`,
+    /// `height`, and `scale` keys that can be used with xdvipdfmx directly.
+    pub(crate) fn parse(text: &str) -> Option<Self> {
+        let mut parser = PdfParser::new(text);
+        let mut objects = Vec::new();
+
+        while let Some(obj) = parser.parse_object() {
+            objects.push(obj);
+        }
+
+        let mut result = ImageSpecial::default();
+        let mut iter = objects.iter().peekable();
+        let number = |iter: &mut Peekable<Iter<'_, PdfObject>>| match iter.next() {
+            Some(PdfObject::Other(t)) => t.parse::<f64>().ok(),
+            _ => None,
+        };
+
+        while let Some(obj) = iter.next() {
+            match obj {
+                PdfObject::String(s) => result.path = s.clone(),
+
+                PdfObject::Other(key) => match key.as_ref() {
+                    "matrix" => {
+                        let a = number(&mut iter)?;
+                        let b = number(&mut iter)?;
+                        let c = number(&mut iter)?;
+                        let d = number(&mut iter)?;
+                        number(&mut iter)?;
+                        number(&mut iter)?;
+                        result.matrix = Some([a, b, c, d]);
+                    }
+
+                    "width" | "height" => {
+                        let value = match iter.next() {
+                            Some(PdfObject::Other(t)) => t,
+                            _ => return None,
+                        };
+
+                        // The unit may or may not be separated from the number.
+                        let dimen = match parse_dimension(value) {
+                            Some(d) => d,
+                            None => {
+                                let unit = match iter.peek() {
+                                    Some(PdfObject::Other(u)) => u,
+                                    _ => return None,
+                                };
+                                let d = parse_dimension(&format!("{value}{unit}"))?;
+                                iter.next();
+                                d
+                            }
+                        };
+
+                        if key == "width" {
+                            result.width = Some(dimen);
+                        } else {
+                            result.height = Some(dimen);
+                        }
+                    }
+
+                    "scale" => {
+                        let s = number(&mut iter)?;
+                        result.scale = Some((s, s));
+                    }
+
+                    "xscale" => {
+                        let s = number(&mut iter)?;
+                        let (_, y) = result.scale.unwrap_or((1., 1.));
+                        result.scale = Some((s, y));
+                    }
+
+                    "yscale" => {
+                        let s = number(&mut iter)?;
+                        let (x, _) = result.scale.unwrap_or((1., 1.));
+                        result.scale = Some((x, s));
+                    }
+
+                    "page" => {
+                        result.page = number(&mut iter)? as i32;
+                    }
+
+                    "pagebox" => {
+                        let name = match iter.next() {
+                            Some(PdfObject::Other(t)) => t.to_ascii_lowercase(),
+                            _ => return None,
+                        };
+
+                        result.page_box = match name.as_ref() {
+                            "cropbox" => Some(PageBox::Crop),
+                            "mediabox" => Some(PageBox::Media),
+                            "artbox" => Some(PageBox::Art),
+                            "trimbox" => Some(PageBox::Trim),
+                            "bleedbox" => Some(PageBox::Bleed),
+                            _ => None,
+                        };
+                    }
+
+                    // Other keys don't matter to us; their values will be
+                    // skipped as unrecognized keys.
+                    _ => {}
+                },
+
+                _ => {}
+            }
+        }
+
+        if result.path.is_empty() {
+            None
+        } else {
+            Some(result)
+        }
+    }
+
+    /// Determine the natural size of a PDF figure, in big points, from the
+    /// selected page box of the PDF file. This accounts for the page's
+    /// rotation. Out-of-range page numbers are clamped, as XeTeX does.
+    pub(crate) fn pdf_natural_size(&self, data: &[u8]) -> Option<(f64, f64)> {
+        let doc = Document::load(data).ok()?;
+        let n_pages = doc.page_ids().ok()?.len() as i32;
+
+        let mut page = self.page.min(n_pages);
+
+        if page < 0 {
+            page += n_pages + 1;
+        }
+
+        let info = doc.page_info(page.max(1) as usize).ok()?;
+        let b = info.displayed_box(self.page_box.unwrap_or(PageBox::Auto));
+        Some((b.width(), b.height()))
+    }
+
+    /// Compute the display size of the image in TeX units, given its natural
+    /// size in big points.
+    pub(crate) fn display_size(&self, natural: (f64, f64)) -> (i32, i32) {
+        let (nw, nh) = natural;
+
+        let (w, h) = if let Some([a, b, c, d]) = self.matrix {
+            // Find the bounding box of the transformed image.
+            let xs = [0., a * nw, c * nh, a * nw + c * nh];
+            let ys = [0., b * nw, d * nh, b * nw + d * nh];
+            let extent = |v: [f64; 4]| {
+                v.iter().copied().fold(f64::MIN, f64::max)
+                    - v.iter().copied().fold(f64::MAX, f64::min)
+            };
+            (extent(xs), extent(ys))
+        } else {
+            match (self.width, self.height) {
+                (Some(w), Some(h)) => (w, h),
+                (Some(w), None) if nw > 0. => (w, nh * w / nw),
+                (None, Some(h)) if nh > 0. => (nw * h / nh, h),
+                _ => {
+                    let (sx, sy) = self.scale.unwrap_or((1., 1.));
+                    (nw * sx, nh * sy)
+                }
+            }
+        };
+
+        let to_tex = |bp: f64| (bp * PT_PER_BP * 65536.).round() as i32;
+        (to_tex(w), to_tex(h))
+    }
+}
+
+/// Parse a TeX-style dimension like `3cm` into big points.
+fn parse_dimension(text: &str) -> Option<f64> {
+    let split = text
+        .find(|c: char| c.is_ascii_alphabetic())
+        .unwrap_or(text.len());
+    let (num, unit) = text.split_at(split);
+    let num: f64 = num.parse().ok()?;
+
+    let bp_per_unit = match unit {
+        "bp" => 1.,
+        "pt" => 1. / PT_PER_BP,
+        "pc" => 12. / PT_PER_BP,
+        "in" => 72.,
+        "cm" => 72. / 2.54,
+        "mm" => 7.2 / 2.54,
+        "dd" => 1238. / 1157. / PT_PER_BP,
+        "cc" => 12. * 1238. / 1157. / PT_PER_BP,
+        "sp" => 1. / 65536. / PT_PER_BP,
+        _ => return None,
+    };
+
+    Some(num * bp_per_unit)
+}
+
+/// Determine the natural size of an image, in big points, from its contents.
+///
+/// For raster images, this follows XeTeX in using the resolution recorded in
+/// the file, defaulting to 72 DPI. SVG lengths follow CSS, where a pixel is
+/// 1/96 of an inch.
+pub(crate) fn natural_size(data: &[u8]) -> Option<(f64, f64)> {
+    if data.starts_with(b"\x89PNG\r\n\x1a\n") {
+        png_size(data)
+    } else if data.starts_with(&[0xFF, 0xD8]) {
+        jpeg_size(data)
+    } else {
+        svg_size(std::str::from_utf8(data).ok()?)
+    }
+}
+
+fn be_u16(data: &[u8], ofs: usize) -> Option<u16> {
+    Some(u16::from_be_bytes(data.get(ofs..ofs + 2)?.try_into().ok()?))
+}
+
+fn be_u32(data: &[u8], ofs: usize) -> Option<u32> {
+    Some(u32::from_be_bytes(data.get(ofs..ofs + 4)?.try_into().ok()?))
+}
+
+fn png_size(data: &[u8]) -> Option<(f64, f64)> {
+    let width = be_u32(data, 16)? as f64;
+    let height = be_u32(data, 20)? as f64;
+    let mut dpi = (72., 72.);
+    let mut ofs = 8;
+
+    while let Some(len) = be_u32(data, ofs) {
+        let kind = data.get(ofs + 4..ofs + 8)?;
+
+        if kind == b"pHYs" && data.get(ofs + 16) == Some(&1) {
+            // Pixels per meter.
+            let x = be_u32(data, ofs + 8)? as f64 * 0.0254;
+            let y = be_u32(data, ofs + 12)? as f64 * 0.0254;
+
+            if x > 0. && y > 0. {
+                dpi = (x, y);
+            }
+        } else if kind == b"IDAT" || kind == b"IEND" {
+            break;
+        }
+
+        ofs += 12 + len as usize;
+    }
+
+    Some((width * 72. / dpi.0, height * 72. / dpi.1))
+}
+
+fn jpeg_size(data: &[u8]) -> Option<(f64, f64)> {
+    let mut dpi = (72., 72.);
+    let mut ofs = 2;
+
+    loop {
+        if *data.get(ofs)? != 0xFF {
+            return None;
+        }
+
+        let marker = *data.get(ofs + 1)?;
+
+        // Markers without a payload.
+        if marker == 0x01 || (0xD0..=0xD7).contains(&marker) || marker == 0xFF {
+            ofs += if marker == 0xFF { 1 } else { 2 };
+            continue;
+        }
+
+        let len = be_u16(data, ofs + 2)? as usize;
+        let payload = data.get(ofs + 4..ofs + 2 + len)?;
+
+        match marker {
+            0xE0 if payload.starts_with(b"JFIF\0") && payload.len() >= 12 => {
+                let xd = be_u16(payload, 8)? as f64;
+                let yd = be_u16(payload, 10)? as f64;
+
+                if xd > 0. && yd > 0. {
+                    match payload[7] {
+                        1 => dpi = (xd, yd),
+                        2 => dpi = (xd * 2.54, yd * 2.54),
+                        _ => {}
+                    }
+                }
+            }
+
+            0xC0..=0xCF if marker != 0xC4 && marker != 0xC8 && marker != 0xCC => {
+                let height = be_u16(payload, 1)? as f64;
+                let width = be_u16(payload, 3)? as f64;
+                return Some((width * 72. / dpi.0, height * 72. / dpi.1));
+            }
+
+            0xD9 | 0xDA => return None,
+
+            _ => {}
+        }
+
+        ofs += 2 + len;
+    }
+}
+
+fn svg_size(text: &str) -> Option<(f64, f64)> {
+    let start = text.find("<svg")?;
+    let tag = &text[start + 4..start + text[start..].find('>')?];
+
+    let width = svg_attribute(tag, "width").and_then(css_length);
+    let height = svg_attribute(tag, "height").and_then(css_length);
+    let view_box: Option<Vec<f64>> = svg_attribute(tag, "viewBox").and_then(|v| {
+        v.split(|c: char| c == ',' || c.is_whitespace())
+            .filter(|s| !s.is_empty())
+            .map(|s| s.parse().ok())
+            .collect()
+    });
+    let vb_size = view_box
+        .filter(|v| v.len() == 4 && v[2] > 0. && v[3] > 0.)
+        .map(|v| (v[2] * 0.75, v[3] * 0.75));
+
+    match (width, height, vb_size) {
+        (Some(w), Some(h), _) => Some((w, h)),
+        (Some(w), None, Some((vw, vh))) => Some((w, vh * w / vw)),
+        (None, Some(h), Some((vw, vh))) => Some((vw * h / vh, h)),
+        (None, None, vb) => vb,
+        _ => None,
+    }
+}
+
+/// Find a quoted attribute value in the contents of an XML start tag.
+fn svg_attribute<'a>(tag: &'a str, name: &str) -> Option<&'a str> {
+    let mut rest = tag;
+
+    loop {
+        let eq = rest.find('=')?;
+        let key = rest[..eq].trim();
+        let after = rest[eq + 1..].trim_start();
+        let quote = after.chars().next().filter(|c| *c == '"' || *c == '\'')?;
+        let end = after[1..].find(quote)?;
+
+        // The key is the last whitespace-separated word before the `=`.
+        if key.rsplit(char::is_whitespace).next() == Some(name) {
+            return Some(&after[1..end + 1]);
+        }
+
+        rest = &after[end + 2..];
+    }
+}
+
+/// Parse a CSS length into big points. Percentages aren't supported.
+fn css_length(text: &str) -> Option<f64> {
+    let text = text.trim();
+    let split = text
+        .find(|c: char| c.is_ascii_alphabetic() || c == '%')
+        .unwrap_or(text.len());
+    let (num, unit) = text.split_at(split);
+    let num: f64 = num.trim().parse().ok()?;
+
+    let bp_per_unit = match unit {
+        "" | "px" => 0.75,
+        "pt" => 1.,
+        "pc" => 12.,
+        "in" => 72.,
+        "cm" => 72. / 2.54,
+        "mm" => 7.2 / 2.54,
+        _ => return None,
+    };
+
+    Some(num * bp_per_unit)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn xetex_special() {
+        let spec = ImageSpecial::parse(
+            "matrix 0.5 0.0 0.0 0.5 0.0 0.0 page 0 pagebox cropbox (figs/plot.png)",
+        )
+        .unwrap();
+        assert_eq!(spec.path, "figs/plot.png");
+        assert_eq!(spec.display_size((144., 72.)), (4736287, 2368143));
+
+        assert_eq!(spec.page_box, Some(PageBox::Crop));
+
+        let spec = ImageSpecial::parse("width 2 in (a.jpg)").unwrap();
+        assert_eq!(spec.display_size((72., 36.)), (9472573, 4736287));
+    }
+
+    #[test]
+    fn sizes() {
+        let mut png = b"\x89PNG\r\n\x1a\n\0\0\0\x0dIHDR\0\0\0\xc8\0\0\0\x64".to_vec();
+        png.extend_from_slice(b"\x08\x02\0\0\0\0\0\0\0");
+        // 5906 pixels per meter is 150 DPI.
+        png.extend_from_slice(b"\0\0\0\x09pHYs\0\0\x17\x12\0\0\x17\x12\x01\0\0\0\0");
+        let (w, h) = natural_size(&png).unwrap();
+        assert!((w - 96.).abs() < 0.1 && (h - 48.).abs() < 0.1);
+
+        assert_eq!(
+            natural_size(b"<?xml version=\"1.0\"?>\n<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"20pt\" viewBox=\"0 0 40 10\">"),
+            Some((20., 5.))
+        );
+    }
+}
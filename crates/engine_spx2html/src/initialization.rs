@@ -125,6 +125,10 @@ impl InitializationState {
             Special::AddTemplate(t) => self.handle_add_template(t, common),
             Special::SetTemplate(t) => self.handle_set_template(t, common),
             Special::SetOutputPath(t) => self.handle_set_output_path(t, common),
+            Special::SetCanvasFormat(t) => {
+                common.handle_set_canvas_format(t);
+                Ok(())
+            }
             Special::SetTemplateVariable(t) => self.handle_set_template_variable(t, common),
             Special::StartDefineFontFamily => self.handle_start_define_font_family(),
             Special::EndDefineFontFamily => self.handle_end_define_font_family(common),
@@ -141,6 +145,16 @@ impl InitializationState {
                 Ok(())
             }
 
+            // Destinations at the very start of the document, like hyperref's
+            // `Doc-Start`, can't be attached to any element, but links to them
+            // can still go to the right page.
+            Special::PdfDestination(spec) => {
+                if let Some(name) = crate::links::parse_destination(spec) {
+                    common.links.add_anchor(&name, false);
+                }
+                Ok(())
+            }
+
             _ => Ok(()),
         }
     }
@@ -341,6 +355,10 @@ impl InitializationState {
             context.insert(varname, &varvalue);
         }
 
+        for (varname, varvalue) in common.template_variables {
+            context.insert(varname.as_str(), varvalue);
+        }
+
         let templating = Templating::new(
             tera,
             context,
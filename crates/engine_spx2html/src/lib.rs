@@ -8,21 +8,33 @@
 //! SPX is essentially the same thing as XDV, but we identify it differently to
 //! mark that the semantics of the content wil be set up for HTML output.
 
-use std::path::{Path, PathBuf};
+use std::{
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
 use tectonic_bridge_core::DriverHooks;
 use tectonic_errors::prelude::*;
-use tectonic_status_base::StatusBackend;
+use tectonic_status_base::{tt_warning, StatusBackend};
 use tectonic_xdv::{FileType, XdvEvents, XdvParser};
 
 mod assets;
+pub mod compat;
 mod emission;
+mod epub;
 mod finalization;
 mod fontfile;
 mod fonts;
 mod html;
+mod images;
 mod initialization;
+mod links;
+mod mathml;
+mod pdfobj;
+mod search;
 mod specials;
+mod svg;
 mod templating;
+mod xhtml;
 
 use self::{
     assets::Assets, emission::EmittingState, finalization::FinalizingState, fonts::FontEnsemble,
@@ -36,6 +48,11 @@ pub struct Spx2HtmlEngine {
     precomputed_assets: Option<AssetSpecification>,
     assets_spec_path: Option<String>,
     do_not_emit_assets: bool,
+    svg_canvases: bool,
+    epub_path: Option<PathBuf>,
+    build_date: Option<SystemTime>,
+    asset_manifest_path: Option<String>,
+    template_variables: Vec<(String, serde_json::Value)>,
 }
 
 #[derive(Debug, Default)]
@@ -113,6 +130,77 @@ impl Spx2HtmlEngine {
         self
     }
 
+    /// Give font files content-hashed names and write a manifest of them.
+    ///
+    /// By default, each font file is written with a name derived from its
+    /// source. If this method is called, the name will also include a hash of
+    /// the file's contents, so that the files can be served with long-lived
+    /// caching headers: if a font changes, so does its name. The generated font
+    /// CSS refers to the hashed names. A JSON manifest mapping each plain name
+    /// to its hashed counterpart is written to the given path, relative to the
+    /// output root.
+    ///
+    /// Note that [`AssetSpecification::output_paths`] reports the plain names.
+    pub fn asset_manifest<S: ToString>(&mut self, path: S) -> &mut Self {
+        self.asset_manifest_path = Some(path.to_string());
+        self
+    }
+
+    /// Specify whether canvases should be drawn as standalone SVG images.
+    ///
+    /// Canvases are regions of the output, like equations, whose contents are
+    /// laid out precisely rather than as flowing text. By default, each canvas
+    /// is emitted as a box of absolutely-positioned glyphs that rely on the
+    /// generated web fonts. If this option is enabled, each canvas is instead
+    /// drawn as an SVG image, using the glyph outlines from the fonts, and
+    /// referenced with an `<img>` tag. The images are written next to the HTML
+    /// files that use them. This is useful for output formats, like EPUB, that
+    /// don't handle the positioned glyphs well.
+    ///
+    /// The TeX code can also switch modes with a `tdux:setCanvasFormat`
+    /// special, whose argument is either `html` or `svg`. This setting only
+    /// provides the initial mode.
+    pub fn svg_canvases(&mut self, enabled: bool) -> &mut Self {
+        self.svg_canvases = enabled;
+        self
+    }
+
+    /// Package the HTML output as an EPUB file.
+    ///
+    /// The HTML tree is still emitted to the directory given by
+    /// [`Self::output_base`], and is then bundled into an EPUB 3 publication
+    /// at the specified path. The templated HTML files become the content
+    /// documents, in the order that they were emitted, and all other files in
+    /// the tree are included as resources. This requires that assets be
+    /// emitted normally.
+    pub fn epub_output(&mut self, path: impl Into<PathBuf>) -> &mut Self {
+        self.epub_path = Some(path.into());
+        self
+    }
+
+    /// Set the modification date recorded in EPUB output.
+    ///
+    /// If unspecified, the current time is used.
+    pub fn build_date(&mut self, date: SystemTime) -> &mut Self {
+        self.build_date = Some(date);
+        self
+    }
+
+    /// Set a variable for use in the HTML templates.
+    ///
+    /// Unlike the variables set by the document with the
+    /// `tdux:setTemplateVariable` special, the value may be structured data
+    /// like an array or an object. Variables set here take precedence over
+    /// those set by the document.
+    pub fn template_variable(
+        &mut self,
+        name: impl Into<String>,
+        value: serde_json::Value,
+    ) -> &mut Self {
+        self.template_variables.push((name.into(), value));
+        self
+    }
+
     /// Specify the root path for output files.
     ///
     /// Because this driver will, in the generic case, produce a tree of HTML
@@ -120,6 +208,10 @@ impl Spx2HtmlEngine {
     /// engine stages, it outputs directly to disk rather than using the I/O
     /// layer. I don't like hardcoding use of the filesystem, but I don't want
     /// to build up some extra abstraction layer right now.
+    ///
+    /// Output files that already exist with the right contents are left
+    /// untouched, so that their modification times only change when their
+    /// contents do.
     pub fn output_base(&mut self, out_base: impl Into<PathBuf>) -> &mut Self {
         self.output = OutputState::Path(out_base.into());
         self
@@ -145,9 +237,17 @@ impl Spx2HtmlEngine {
         };
 
         {
-            let state = EngineState::new(hooks, status, out_base, self.precomputed_assets.as_ref());
+            let state = EngineState::new(
+                hooks,
+                status,
+                out_base,
+                self.precomputed_assets.as_ref(),
+                self.svg_canvases,
+                &self.template_variables,
+            );
             let state = XdvParser::process_with_seeks(&mut input, state)?;
             let (fonts, assets, mut common) = state.finished()?;
+            let pages = std::mem::take(&mut common.emitted_pages);
 
             if let Some(asp) = self.assets_spec_path.as_ref() {
                 let ser = assets.into_serialize(fonts);
@@ -156,7 +256,20 @@ impl Spx2HtmlEngine {
                 let (name, digest) = output.into_name_digest();
                 hooks.event_output_closed(name, digest, status);
             } else if !self.do_not_emit_assets {
-                assets.emit(fonts, &mut common)?;
+                assets.emit(fonts, self.asset_manifest_path.as_deref(), &mut common)?;
+            }
+
+            if let Some(epub_path) = self.epub_path.as_ref() {
+                let out_base = a_ok_or!(
+                    out_base;
+                    ["EPUB output requires that HTML files be emitted"]
+                );
+                ensure!(
+                    self.assets_spec_path.is_none() && !self.do_not_emit_assets,
+                    "EPUB output requires that assets be emitted"
+                );
+                let date = self.build_date.unwrap_or_else(SystemTime::now);
+                epub::package(out_base, &pages, epub_path, date)?;
             }
         }
 
@@ -178,6 +291,34 @@ struct Common<'a> {
     status: &'a mut dyn StatusBackend,
     out_base: Option<&'a Path>,
     precomputed_assets: Option<&'a AssetSpecification>,
+
+    /// Whether canvases are currently being drawn as SVG images.
+    svg_canvases: bool,
+
+    /// The templated HTML files that have been written, relative to the
+    /// output base, in the order that they were written.
+    emitted_pages: Vec<String>,
+
+    /// Hyperlinks and anchors across the whole document.
+    links: links::Links,
+
+    /// Template variables provided by the caller.
+    template_variables: &'a [(String, serde_json::Value)],
+}
+
+impl<'a> Common<'a> {
+    /// Handle a `tdux:setCanvasFormat` special.
+    fn handle_set_canvas_format(&mut self, format: &str) {
+        match format.trim() {
+            "html" => self.svg_canvases = false,
+            "svg" => self.svg_canvases = true,
+            other => tt_warning!(
+                self.status,
+                "ignoring unrecognized canvas format `{}`",
+                other
+            ),
+        }
+    }
 }
 
 impl<'a> EngineState<'a> {
@@ -186,6 +327,8 @@ impl<'a> EngineState<'a> {
         status: &'a mut dyn StatusBackend,
         out_base: Option<&'a Path>,
         precomputed_assets: Option<&'a AssetSpecification>,
+        svg_canvases: bool,
+        template_variables: &'a [(String, serde_json::Value)],
     ) -> Self {
         Self {
             common: Common {
@@ -193,6 +336,10 @@ impl<'a> EngineState<'a> {
                 status,
                 out_base,
                 precomputed_assets,
+                svg_canvases,
+                emitted_pages: Vec::new(),
+                links: Default::default(),
+                template_variables,
             },
             state: State::Initializing(InitializationState::default()),
         }
@@ -223,6 +370,8 @@ impl<'a> EngineState<'a> {
                 precomputed.check_runtime_assets(&mut assets)?;
             }
 
+            self.common.links.finish(self.common.status)?;
+
             Ok((fonts, assets, self.common))
         } else {
             panic!("invalid spx2html finalization state leaked");
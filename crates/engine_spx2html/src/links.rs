@@ -0,0 +1,457 @@
+// Copyright 2026 the Tectonic Project
+// Licensed under the MIT License.
+
+//! Hyperlinks and link anchors.
+//!
+//! Packages like `hyperref` express links with the `pdf:` specials understood
+//! by `xdvipdfmx`, or with the older HyperTeX `html:` specials. Here we
+//! translate them into `<a href>` elements and `id` attributes.
+//!
+//! A link may point to an anchor that appears later in the document, possibly
+//! in a different output file. So internal links are emitted as placeholders
+//! that are resolved as each output file is written. Files that still refer to
+//! unknown anchors at that point are fixed up once processing is complete. The
+//! same mechanism is used for references to assets like images, whose relative
+//! paths depend on the location of the output file.
+
+use percent_encoding::{utf8_percent_encode, AsciiSet, CONTROLS};
+use std::{collections::HashMap, path::PathBuf};
+use tectonic_errors::prelude::*;
+use tectonic_status_base::{tt_warning, StatusBackend};
+
+use crate::pdfobj::{dict_get, PdfObject, PdfParser};
+
+/// The character delimiting internal link placeholders in the HTML content.
+/// This is a Unicode noncharacter, so it should never appear in real text.
+const MARKER: char = '\u{FDD0}';
+
+/// Characters that must be escaped in URL fragments.
+const FRAGMENT: &AsciiSet = &CONTROLS.add(b' ').add(b'"').add(b'<').add(b'>').add(b'`');
+
+/// The target of a hyperlink.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub(crate) enum LinkTarget {
+    /// A named anchor somewhere in the document.
+    Internal(String),
+
+    /// A URL.
+    External(String),
+}
+
+/// The meaning of a HyperTeX `html:` special.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub(crate) enum HyperTex {
+    /// `<a href="...">`
+    LinkStart(LinkTarget),
+
+    /// `<a name="...">`
+    Anchor(String),
+
+    /// `</a>`
+    End,
+}
+
+/// What a placeholder refers to.
+#[derive(Debug)]
+enum Placeholder {
+    /// A named anchor.
+    Anchor(String),
+
+    /// An asset, by its path relative to the output root.
+    Asset(String),
+}
+
+#[derive(Debug)]
+struct Anchor {
+    /// The output path of the page containing the anchor.
+    page: String,
+
+    /// Whether the anchor has an element ID, or just refers to its page.
+    has_id: bool,
+}
+
+/// Document-wide state for links and anchors.
+#[derive(Debug, Default)]
+pub(crate) struct Links {
+    /// Anchors on pages that have been emitted.
+    anchors: HashMap<String, Anchor>,
+
+    /// Anchors in the content that hasn't yet been emitted.
+    pending_anchors: Vec<(String, bool)>,
+
+    /// The targets of the placeholders, by index.
+    targets: Vec<Placeholder>,
+
+    /// Output files whose text still contains unresolved placeholders, with
+    /// their page paths and their text. These are held back so that each file
+    /// is only written once.
+    unresolved_files: Vec<(PathBuf, String, String)>,
+}
+
+impl Links {
+    /// Register an anchor in the current content.
+    ///
+    /// If *has_id* is false, links to the anchor will just point to the page
+    /// containing it. Returns false if an anchor with this name already exists,
+    /// in which case the caller should not emit another element ID for it.
+    pub(crate) fn add_anchor(&mut self, name: &str, has_id: bool) -> bool {
+        if self.anchors.contains_key(name) || self.pending_anchors.iter().any(|(n, _)| n == name) {
+            return false;
+        }
+
+        self.pending_anchors.push((name.to_owned(), has_id));
+        true
+    }
+
+    /// Get the `href` value to use for a link to the named anchor.
+    ///
+    /// The value is a placeholder that will be resolved by [`Self::resolve`].
+    pub(crate) fn internal_href(&mut self, name: &str) -> String {
+        self.placeholder(Placeholder::Anchor(name.to_owned()))
+    }
+
+    /// Get the URL to use for a reference to an asset, given its path
+    /// relative to the output root.
+    ///
+    /// The value is a placeholder that will be resolved by [`Self::resolve`].
+    pub(crate) fn asset_href(&mut self, path: &str) -> String {
+        self.placeholder(Placeholder::Asset(path.to_owned()))
+    }
+
+    fn placeholder(&mut self, target: Placeholder) -> String {
+        let index = self.targets.len();
+        self.targets.push(target);
+        format!("{MARKER}{index}{MARKER}")
+    }
+
+    /// Note that the current content has been emitted into the page with the
+    /// given output path.
+    pub(crate) fn page_finished(&mut self, page: &str) {
+        let page = normalize_page(page);
+
+        for (name, has_id) in self.pending_anchors.drain(..) {
+            self.anchors.insert(
+                name,
+                Anchor {
+                    page: page.clone(),
+                    has_id,
+                },
+            );
+        }
+    }
+
+    /// Resolve the link placeholders in the rendered text of the given page.
+    ///
+    /// Placeholders whose anchors are not yet known are left in place, unless
+    /// *final_pass* is true, in which case they become dead links. The second
+    /// return value is the number of placeholders that could not be resolved.
+    pub(crate) fn resolve(&self, text: &str, page: &str, final_pass: bool) -> (String, usize) {
+        let page = normalize_page(page);
+        let mut result = String::with_capacity(text.len());
+        let mut n_unresolved = 0;
+        let mut rest = text;
+
+        while let Some(start) = rest.find(MARKER) {
+            let after = &rest[start + MARKER.len_utf8()..];
+
+            let (end, index) = match after.find(MARKER) {
+                Some(end) => (end, after[..end].parse::<usize>().ok()),
+                None => break,
+            };
+
+            let target = match index.and_then(|i| self.targets.get(i)) {
+                Some(t) => t,
+                None => {
+                    // Not one of ours, somehow. Leave it alone.
+                    result.push_str(&rest[..start + MARKER.len_utf8()]);
+                    rest = after;
+                    continue;
+                }
+            };
+
+            result.push_str(&rest[..start]);
+
+            let (name, anchor) = match target {
+                Placeholder::Anchor(name) => (name, self.anchors.get(name)),
+
+                Placeholder::Asset(path) => {
+                    let mut href = rel_top(&page);
+                    href.push_str(&normalize_page(path));
+                    html_escape::encode_double_quoted_attribute_to_string(href, &mut result);
+                    rest = &after[end + MARKER.len_utf8()..];
+                    continue;
+                }
+            };
+
+            match anchor {
+                Some(anchor) => {
+                    let href = href_to(anchor, name, &page);
+                    html_escape::encode_double_quoted_attribute_to_string(href, &mut result);
+                }
+
+                None => {
+                    n_unresolved += 1;
+
+                    if final_pass {
+                        result.push('#');
+                        html_escape::encode_double_quoted_attribute_to_string(
+                            fragment(name),
+                            &mut result,
+                        );
+                    } else {
+                        result.push_str(&rest[start..start + end + 2 * MARKER.len_utf8()]);
+                    }
+                }
+            }
+
+            rest = &after[end + MARKER.len_utf8()..];
+        }
+
+        result.push_str(rest);
+        (result, n_unresolved)
+    }
+
+    /// Hold back an output file that contains unresolved placeholders. It
+    /// will be written by [`Self::finish`].
+    pub(crate) fn defer_unresolved_file(&mut self, path: PathBuf, page: &str, text: String) {
+        self.unresolved_files.push((path, page.to_owned(), text));
+    }
+
+    /// Write out the output files that contained forward references.
+    pub(crate) fn finish(&mut self, status: &mut dyn StatusBackend) -> Result<()> {
+        let mut n_dangling = 0;
+
+        for (path, page, text) in std::mem::take(&mut self.unresolved_files) {
+            let (text, n_unresolved) = self.resolve(&text, &page, true);
+            n_dangling += n_unresolved;
+            crate::assets::write_if_changed(&path, text.as_bytes())?;
+        }
+
+        if n_dangling > 0 {
+            tt_warning!(
+                status,
+                "{} link(s) in the HTML output point to anchors that were never defined",
+                n_dangling
+            );
+        }
+
+        Ok(())
+    }
+}
+
+/// Normalize an output path in the same way as file creation does.
+fn normalize_page(page: &str) -> String {
+    page.split('/')
+        .filter(|p| !p.is_empty())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// Convert an anchor name into a value for an HTML `id` attribute, which may
+/// not contain whitespace.
+pub(crate) fn anchor_id(name: &str) -> String {
+    name.chars()
+        .map(|c| if c.is_whitespace() { '_' } else { c })
+        .collect()
+}
+
+fn fragment(name: &str) -> String {
+    utf8_percent_encode(&anchor_id(name), FRAGMENT).to_string()
+}
+
+/// Get the relative path from a page to the root of the output tree, which
+/// is empty or ends with a slash.
+fn rel_top(page: &str) -> String {
+    "../".repeat(page.split('/').count().saturating_sub(1))
+}
+
+/// Compute the `href` for a link from *from_page* to the given anchor.
+fn href_to(anchor: &Anchor, name: &str, from_page: &str) -> String {
+    let mut href = String::new();
+
+    if anchor.page != from_page {
+        href.push_str(&rel_top(from_page));
+        href.push_str(&anchor.page);
+    }
+
+    if anchor.has_id {
+        href.push('#');
+        href.push_str(&fragment(name));
+    } else if href.is_empty() {
+        href.push('#');
+    }
+
+    href
+}
+
+/// Parse the argument of a `pdf:bann` special into a link target.
+///
+/// Returns None if the annotation isn't a link that we can represent.
+pub(crate) fn parse_annotation(text: &str) -> Option<LinkTarget> {
+    let start = text.find("<<")?;
+    let dict = match PdfParser::new(&text[start..]).parse_object()? {
+        PdfObject::Dict(d) => d,
+        _ => return None,
+    };
+
+    if let Some(dest) = dict_get(&dict, "Dest") {
+        return dest.as_text().map(LinkTarget::Internal);
+    }
+
+    let action = match dict_get(&dict, "A")? {
+        PdfObject::Dict(d) => d,
+        _ => return None,
+    };
+
+    let file = || match dict_get(action, "F")? {
+        PdfObject::Dict(spec) => dict_get(spec, "UF")
+            .or_else(|| dict_get(spec, "F"))
+            .and_then(PdfObject::as_text),
+        other => other.as_text(),
+    };
+
+    match dict_get(action, "S")?.as_text()?.as_ref() {
+        "GoTo" => dict_get(action, "D")?.as_text().map(LinkTarget::Internal),
+
+        "URI" => dict_get(action, "URI")?.as_text().map(LinkTarget::External),
+
+        "GoToR" => {
+            let mut url = file()?;
+
+            if let Some(d) = dict_get(action, "D").and_then(PdfObject::as_text) {
+                url.push('#');
+                url.push_str(&fragment(&d));
+            }
+
+            Some(LinkTarget::External(url))
+        }
+
+        "Launch" => file().map(LinkTarget::External),
+
+        _ => None,
+    }
+}
+
+/// Parse the argument of a `pdf:dest` special into the destination name.
+pub(crate) fn parse_destination(text: &str) -> Option<String> {
+    PdfParser::new(text).parse_object()?.as_text()
+}
+
+/// Parse the argument of a HyperTeX `html:` special.
+pub(crate) fn parse_hypertex(text: &str) -> Option<HyperTex> {
+    let text = text.trim();
+
+    if text.eq_ignore_ascii_case("</a>") {
+        return Some(HyperTex::End);
+    }
+
+    let attrs = text
+        .strip_prefix("<a ")
+        .or_else(|| text.strip_prefix("<A "))?
+        .strip_suffix('>')?;
+
+    if let Some(href) = html_attribute(attrs, "href") {
+        Some(HyperTex::LinkStart(match href.strip_prefix('#') {
+            Some(name) => LinkTarget::Internal(name.to_owned()),
+            None => LinkTarget::External(href.to_owned()),
+        }))
+    } else {
+        html_attribute(attrs, "name").map(|n| HyperTex::Anchor(n.to_owned()))
+    }
+}
+
+/// Find a quoted attribute value in a simple HTML attribute list.
+fn html_attribute<'a>(attrs: &'a str, name: &str) -> Option<&'a str> {
+    let mut rest = attrs;
+
+    loop {
+        rest = rest.trim_start();
+        let eq = rest.find('=')?;
+        let key = rest[..eq].trim();
+        let after = rest[eq + 1..].trim_start();
+        let quote = after.chars().next().filter(|c| *c == '"' || *c == '\'')?;
+        let end = after[1..].find(quote)?;
+        let value = &after[1..end + 1];
+
+        if key.eq_ignore_ascii_case(name) {
+            return Some(value);
+        }
+
+        rest = &after[end + 2..];
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn annotations() {
+        assert_eq!(
+            parse_annotation(
+                "<</Type/Annot/Subtype/Link/Border[0 0 0]/H/I/C[1 0 0]/A<</S/GoTo/D(section.1)>>>>"
+            ),
+            Some(LinkTarget::Internal("section.1".to_owned()))
+        );
+        assert_eq!(
+            parse_annotation(
+                " << /Subtype /Link /A << /S /URI /URI (https://example.com/a\\(b\\)) >> >>"
+            ),
+            Some(LinkTarget::External("https://example.com/a(b)".to_owned()))
+        );
+        assert_eq!(
+            parse_annotation("<</Subtype/Link/A<</S/GoToR/F(other.pdf)/D(cite.x)>>>>"),
+            Some(LinkTarget::External("other.pdf#cite.x".to_owned()))
+        );
+        assert_eq!(
+            parse_annotation("<</Subtype/Link/Dest<FEFF00E9>>>"),
+            Some(LinkTarget::Internal("é".to_owned()))
+        );
+        assert_eq!(parse_annotation("<</Subtype/Widget/FT/Btn>>"), None);
+        assert_eq!(
+            parse_destination("(Doc-Start) [@thispage /XYZ @xpos @ypos null]"),
+            Some("Doc-Start".to_owned())
+        );
+    }
+
+    #[test]
+    fn hypertex() {
+        assert_eq!(
+            parse_hypertex("<a href=\"#tduxref-intro\">"),
+            Some(HyperTex::LinkStart(LinkTarget::Internal(
+                "tduxref-intro".to_owned()
+            )))
+        );
+        assert_eq!(
+            parse_hypertex("<a name='x y'>"),
+            Some(HyperTex::Anchor("x y".to_owned()))
+        );
+        assert_eq!(parse_hypertex("</a>"), Some(HyperTex::End));
+        assert_eq!(parse_hypertex("<img src=\"x\">"), None);
+    }
+
+    #[test]
+    fn resolution() {
+        let mut links = Links::default();
+        let fwd = links.internal_href("later");
+        let img = links.asset_href("images/x.png");
+        let back = links.internal_href("here");
+        assert!(links.add_anchor("here", true));
+        assert!(!links.add_anchor("here", true));
+        links.page_finished("/a/index.html");
+
+        let text = format!("<a href=\"{fwd}\">x</a><a href=\"{back}\">y</a><img src=\"{img}\">");
+        let (partial, n) = links.resolve(&text, "a/index.html", false);
+        assert_eq!(n, 1);
+        assert!(partial.ends_with("<a href=\"#here\">y</a><img src=\"../images/x.png\">"));
+
+        links.add_anchor("later", true);
+        links.page_finished("b.html");
+        let (done, n) = links.resolve(&partial, "a/index.html", true);
+        assert_eq!(n, 0);
+        assert_eq!(
+            done,
+            "<a href=\"../b.html#later\">x</a><a href=\"#here\">y</a><img src=\"../images/x.png\">"
+        );
+    }
+}
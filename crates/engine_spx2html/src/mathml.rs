@@ -0,0 +1,690 @@
+// Copyright 2026 the Tectonic Project
+// Licensed under the MIT License.
+
+//! Presentation MathML output for math canvases.
+//!
+//! Math canvases (`tdux:cs math` and `tdux:cs dmath`) are normally rendered as
+//! boxes of absolutely-positioned glyphs. If the TeX code also records the
+//! structure of the math, we can instead emit Presentation MathML, which is
+//! accessible and reflows properly. The structure is recorded with two
+//! specials:
+//!
+//! ```notest
+//! \special{tdux:mms mfrac}  % start a MathML element
+//! \special{tdux:mme mfrac}  % end it
+//! ```
+//!
+//! Glyphs drawn inside the canvas become the leaves of the tree. Outside of
+//! token elements, runs of glyphs are split into tokens automatically: digits
+//! become `<mn>`, letters `<mi>`, and everything else `<mo>`. Wrapping glyphs in
+//! an explicit `mi`, `mn`, `mo`, or `mtext` element overrides this. Rules drawn
+//! in the canvas, such as fraction bars, are ignored, since the MathML renderer
+//! draws its own, and so is the radical sign that TeX draws at the start of an
+//! `msqrt` or `mroot`.
+//!
+//! Elements that take a fixed number of arguments, such as `mfrac` or
+//! `msubsup`, must contain exactly that number of children; arguments made up
+//! of several tokens should be wrapped in `mrow`. The one exception to MathML
+//! ordering is `mroot`, whose children are given in the order that TeX draws
+//! them: the index, then the base. If the structure is malformed, or any glyph
+//! can't be mapped back to Unicode, the canvas falls back to positioned-glyph
+//! rendering.
+//!
+//! TeX macros generally can't know what a subscript or superscript is
+//! attached to, since the base has already been typeset by the time that the
+//! script is seen. So scripts can also be marked up on their own:
+//!
+//! ```notest
+//! \special{tdux:mss sup}  % start a superscript
+//! \special{tdux:mse sup}  % end it; use `sub` for subscripts
+//! ```
+//!
+//! Such a script is attached to the token or element before it, becoming an
+//! `msub`, `msup`, or `msubsup`. TeX draws a superscript before the subscript
+//! of the same base, and draws superscripts set as limits before their base.
+//! The latter case is recognized by the base overlapping the superscript
+//! horizontally.
+
+use std::str::FromStr;
+
+use crate::TexFontNum;
+
+macro_rules! math_elements {
+    ($($vname:ident $tagname:literal $arity:expr, $is_token:literal;)+) => {
+        /// A MathML element that the TeX code can create.
+        #[derive(Clone, Copy, Debug, Eq, PartialEq)]
+        pub(crate) enum MathElement {
+            $($vname,)+
+        }
+
+        impl FromStr for MathElement {
+            type Err = ();
+
+            fn from_str(s: &str) -> Result<Self, Self::Err> {
+                match s {
+                    $($tagname => Ok(MathElement::$vname),)+
+                    _ => Err(()),
+                }
+            }
+        }
+
+        impl MathElement {
+            pub fn name(&self) -> &'static str {
+                match self {
+                    $(MathElement::$vname => $tagname,)+
+                }
+            }
+
+            /// The number of children that this element requires, if fixed.
+            pub fn arity(&self) -> Option<usize> {
+                match self {
+                    $(MathElement::$vname => $arity,)+
+                }
+            }
+
+            /// Whether this is a token element, which contains text directly.
+            pub fn is_token(&self) -> bool {
+                match self {
+                    $(MathElement::$vname => $is_token,)+
+                }
+            }
+        }
+    }
+}
+
+math_elements! {
+    Mfrac "mfrac" Some(2), false;
+    Mi "mi" None, true;
+    Mn "mn" None, true;
+    Mo "mo" None, true;
+    Mover "mover" Some(2), false;
+    Mroot "mroot" Some(2), false;
+    Mrow "mrow" None, false;
+    Msqrt "msqrt" None, false;
+    Msub "msub" Some(2), false;
+    Msubsup "msubsup" Some(3), false;
+    Msup "msup" Some(2), false;
+    Mtext "mtext" None, true;
+    Munder "munder" Some(2), false;
+    Munderover "munderover" Some(3), false;
+}
+
+/// The kind of a script marked up with `tdux:mss`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(crate) enum ScriptKind {
+    Sub,
+    Sup,
+}
+
+impl FromStr for ScriptKind {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "sub" => Ok(ScriptKind::Sub),
+            "sup" => Ok(ScriptKind::Sup),
+            _ => Err(()),
+        }
+    }
+}
+
+impl ScriptKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ScriptKind::Sub => "sub",
+            ScriptKind::Sup => "sup",
+        }
+    }
+}
+
+/// Something that can be open while the structure is being built.
+#[derive(Clone, Copy, Debug)]
+enum Container {
+    Element(MathElement),
+    Script(ScriptKind),
+}
+
+impl std::fmt::Display for Container {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Container::Element(el) => write!(f, "MathML element `{}`", el.name()),
+            Container::Script(kind) => write!(f, "script `{}`", kind.name()),
+        }
+    }
+}
+
+#[derive(Debug)]
+enum MathNode {
+    Element(MathElement, Vec<MathNode>),
+
+    /// A script that has yet to be attached to its base, with the horizontal
+    /// position at which it ends.
+    Script(ScriptKind, Vec<MathNode>, i32),
+
+    /// A glyph, with its horizontal position.
+    Glyph(TexFontNum, u16, i32),
+}
+
+/// Accumulates the structure of a math canvas as it is processed.
+#[derive(Debug)]
+pub(crate) struct MathBuilder {
+    /// The stack of currently open containers. The bottom item is the implicit
+    /// top-level row of the canvas and has no container.
+    stack: Vec<(Option<Container>, Vec<MathNode>)>,
+
+    /// Whether any structure specials have been seen.
+    any_structure: bool,
+
+    /// The first problem that was encountered, if any.
+    error: Option<String>,
+}
+
+impl Default for MathBuilder {
+    fn default() -> Self {
+        MathBuilder {
+            stack: vec![(None, Vec::new())],
+            any_structure: false,
+            error: None,
+        }
+    }
+}
+
+impl MathBuilder {
+    /// Returns true if the canvas contained any structure information.
+    pub fn has_structure(&self) -> bool {
+        self.any_structure
+    }
+
+    fn fail(&mut self, message: String) {
+        if self.error.is_none() {
+            self.error = Some(message);
+        }
+    }
+
+    /// Handle a `tdux:mms` special.
+    pub fn start(&mut self, name: &str) {
+        self.any_structure = true;
+
+        match name.trim().parse() {
+            Ok(el) => self.stack.push((Some(Container::Element(el)), Vec::new())),
+            Err(_) => self.fail(format!("unsupported MathML element `{}`", name.trim())),
+        }
+    }
+
+    /// Handle a `tdux:mme` special.
+    pub fn end(&mut self, name: &str) {
+        self.any_structure = true;
+
+        let name = name.trim();
+
+        if let Some(children) = self.pop_container(
+            name,
+            |c| matches!(c, Container::Element(el) if el.name() == name),
+        ) {
+            let el = name.parse().unwrap();
+            self.push_node(MathNode::Element(el, children));
+        }
+    }
+
+    /// Handle a `tdux:mss` special.
+    pub fn start_script(&mut self, kind: &str) {
+        self.any_structure = true;
+
+        match kind.trim().parse() {
+            Ok(kind) => self.stack.push((Some(Container::Script(kind)), Vec::new())),
+            Err(_) => self.fail(format!("unsupported script kind `{}`", kind.trim())),
+        }
+    }
+
+    /// Handle a `tdux:mse` special, which happened at horizontal position *x*.
+    pub fn end_script(&mut self, kind: &str, x: i32) {
+        self.any_structure = true;
+
+        let kind = kind.trim();
+
+        if let Some(children) = self.pop_container(
+            kind,
+            |c| matches!(c, Container::Script(k) if k.name() == kind),
+        ) {
+            let kind = kind.parse().unwrap();
+            self.push_node(MathNode::Script(kind, children, x));
+        }
+    }
+
+    /// Pop the innermost open container, if it is the one that is being
+    /// ended, returning its children.
+    fn pop_container<P>(&mut self, name: &str, is_match: P) -> Option<Vec<MathNode>>
+    where
+        P: FnOnce(&Container) -> bool,
+    {
+        match self.stack.last() {
+            Some((Some(c), _)) if is_match(c) => {}
+            Some((Some(c), _)) => {
+                let msg = format!("end of `{name}` seen while {c} was open");
+                self.fail(msg);
+                return None;
+            }
+            _ => {
+                self.fail(format!("unpaired end of `{name}`"));
+                return None;
+            }
+        }
+
+        self.stack.pop().map(|(_, children)| children)
+    }
+
+    /// Add a glyph, drawn at horizontal position *x*, to the currently open
+    /// container.
+    pub fn push_glyph(&mut self, font_num: TexFontNum, glyph: u16, x: i32) {
+        self.push_node(MathNode::Glyph(font_num, glyph, x));
+    }
+
+    fn push_node(&mut self, node: MathNode) {
+        // The root entry is never popped, so the stack is never empty.
+        self.stack.last_mut().unwrap().1.push(node);
+    }
+
+    /// Render the canvas contents as a `<math>` element.
+    ///
+    /// The *get_char* callback maps glyphs back to Unicode characters. If the
+    /// structure can't be rendered, an error message is returned.
+    pub fn render<F>(mut self, display_block: bool, mut get_char: F) -> Result<String, String>
+    where
+        F: FnMut(TexFontNum, u16) -> Option<char>,
+    {
+        if let Some(e) = self.error.take() {
+            return Err(e);
+        }
+
+        if self.stack.len() != 1 {
+            let (c, _) = self.stack.last().unwrap();
+            return Err(format!("{} was never ended", c.unwrap()));
+        }
+
+        let (_, nodes) = self.stack.pop().unwrap();
+        let children = render_children(&nodes, None, &mut get_char)?;
+
+        let mut out = String::new();
+
+        if display_block {
+            out.push_str("<math display=\"block\">");
+        } else {
+            out.push_str("<math>");
+        }
+
+        for c in &children {
+            out.push_str(c);
+        }
+
+        out.push_str("</math>");
+        Ok(out)
+    }
+}
+
+/// A MathML child in the making.
+#[derive(Debug)]
+struct Item {
+    html: String,
+
+    /// The horizontal position of the item's first glyph, if it has any.
+    x: Option<i32>,
+
+    /// If this item is a script that still needs to be attached to its base,
+    /// its kind and the horizontal position at which it ends.
+    script: Option<(ScriptKind, i32)>,
+}
+
+/// Render a sequence of nodes into a list of MathML elements.
+///
+/// Each item of the returned list corresponds to one MathML child, which is
+/// what matters for elements like `mfrac` that have fixed arities. The node at
+/// index *skip*, if any, is left out.
+fn render_children<F>(
+    nodes: &[MathNode],
+    skip: Option<usize>,
+    get_char: &mut F,
+) -> Result<Vec<String>, String>
+where
+    F: FnMut(TexFontNum, u16) -> Option<char>,
+{
+    let mut items = Vec::new();
+    let mut pending = Vec::new();
+
+    for (i, node) in nodes.iter().enumerate() {
+        if Some(i) == skip {
+            continue;
+        }
+
+        match node {
+            MathNode::Glyph(fnum, glyph, x) => {
+                pending.push((glyph_char(*fnum, *glyph, get_char)?, *x))
+            }
+
+            MathNode::Script(kind, grandchildren, x_end) => {
+                tokenize(&pending, &mut items);
+                pending.clear();
+
+                let mut inner = render_children(grandchildren, None, get_char)?;
+
+                let html = if inner.len() == 1 {
+                    inner.pop().unwrap()
+                } else {
+                    format!("<mrow>{}</mrow>", inner.concat())
+                };
+
+                items.push(Item {
+                    html,
+                    x: first_x(grandchildren),
+                    script: Some((*kind, *x_end)),
+                });
+            }
+
+            MathNode::Element(el, grandchildren) => {
+                tokenize(&pending, &mut items);
+                pending.clear();
+
+                let inner = if el.is_token() {
+                    let mut text = String::new();
+
+                    for gc in grandchildren {
+                        match gc {
+                            MathNode::Glyph(fnum, glyph, _) => {
+                                text.push(glyph_char(*fnum, *glyph, get_char)?)
+                            }
+                            MathNode::Element(inner, _) => {
+                                return Err(format!(
+                                    "MathML element `{}` can't contain `{}`",
+                                    el.name(),
+                                    inner.name()
+                                ))
+                            }
+                            MathNode::Script(kind, ..) => {
+                                return Err(format!(
+                                    "MathML element `{}` can't contain script `{}`",
+                                    el.name(),
+                                    kind.name()
+                                ))
+                            }
+                        }
+                    }
+
+                    html_escape::encode_text(&text).into_owned()
+                } else {
+                    // TeX draws the radical sign, but the MathML renderer
+                    // will draw its own.
+                    let sign = if matches!(el, MathElement::Msqrt | MathElement::Mroot) {
+                        grandchildren.iter().position(|gc| {
+                            matches!(
+                                gc,
+                                MathNode::Glyph(fnum, glyph, _)
+                                    if matches!(get_char(*fnum, *glyph), Some('√' | '∛' | '∜'))
+                            )
+                        })
+                    } else {
+                        None
+                    };
+
+                    let mut items = render_children(grandchildren, sign, get_char)?;
+
+                    if let Some(n) = el.arity() {
+                        if items.len() != n {
+                            return Err(format!(
+                                "MathML element `{}` needs {} children but got {}",
+                                el.name(),
+                                n,
+                                items.len()
+                            ));
+                        }
+                    }
+
+                    if *el == MathElement::Mroot {
+                        items.swap(0, 1);
+                    }
+
+                    items.concat()
+                };
+
+                items.push(Item {
+                    html: format!("<{0}>{1}</{0}>", el.name(), inner),
+                    x: first_x(grandchildren),
+                    script: None,
+                });
+            }
+        }
+    }
+
+    tokenize(&pending, &mut items);
+    Ok(attach_scripts(items))
+}
+
+/// Get the horizontal position of the first glyph in a sequence of nodes.
+fn first_x(nodes: &[MathNode]) -> Option<i32> {
+    nodes.iter().find_map(|node| match node {
+        MathNode::Glyph(_, _, x) => Some(*x),
+        MathNode::Element(_, children) | MathNode::Script(_, children, _) => first_x(children),
+    })
+}
+
+/// Attach scripts to their bases.
+fn attach_scripts(items: Vec<Item>) -> Vec<String> {
+    let mut out = Vec::new();
+    let mut items = items.into_iter().peekable();
+
+    while let Some(item) = items.next() {
+        let html = match item.script {
+            None => item.html,
+
+            Some((ScriptKind::Sub, _)) => {
+                format!("<msub>{}{}</msub>", pop_base(&mut out), item.html)
+            }
+
+            Some((ScriptKind::Sup, x_end)) => {
+                // If the next item overlaps this superscript, this is a limit
+                // drawn above it, and it's our base.
+                let base = match items.peek() {
+                    Some(next)
+                        if next.script.is_none() && matches!(next.x, Some(x) if x < x_end) =>
+                    {
+                        items.next().unwrap().html
+                    }
+                    _ => pop_base(&mut out),
+                };
+
+                if let Some(sub) =
+                    items.next_if(|next| matches!(next.script, Some((ScriptKind::Sub, _))))
+                {
+                    format!("<msubsup>{}{}{}</msubsup>", base, sub.html, item.html)
+                } else {
+                    format!("<msup>{}{}</msup>", base, item.html)
+                }
+            }
+        };
+
+        out.push(html);
+    }
+
+    out
+}
+
+/// Take the base of a script from the items that precede it. Scripts with
+/// nothing before them, like TeX's `{}^{14}C`, get an empty base.
+fn pop_base(out: &mut Vec<String>) -> String {
+    out.pop().unwrap_or_else(|| "<mrow></mrow>".to_owned())
+}
+
+fn glyph_char<F>(fnum: TexFontNum, glyph: u16, get_char: &mut F) -> Result<char, String>
+where
+    F: FnMut(TexFontNum, u16) -> Option<char>,
+{
+    get_char(fnum, glyph)
+        .ok_or_else(|| format!("unable to map glyph {glyph} in font {fnum} to Unicode"))
+}
+
+/// Split a run of characters, with their horizontal positions, into MathML
+/// token elements.
+fn tokenize(chars: &[(char, i32)], out: &mut Vec<Item>) {
+    let mut i = 0;
+
+    while i < chars.len() {
+        let (ch, x) = chars[i];
+
+        if ch.is_whitespace() {
+            i += 1;
+            continue;
+        }
+
+        let html = if ch.is_numeric() {
+            // Gather up a number, including decimal points.
+            let mut j = i + 1;
+
+            while j < chars.len()
+                && (chars[j].0.is_numeric()
+                    || (chars[j].0 == '.' && j + 1 < chars.len() && chars[j + 1].0.is_numeric()))
+            {
+                j += 1;
+            }
+
+            let text: String = chars[i..j].iter().map(|(c, _)| c).collect();
+            i = j;
+            format!("<mn>{}</mn>", html_escape::encode_text(&text))
+        } else {
+            let tag = if ch.is_alphabetic() { "mi" } else { "mo" };
+            let mut buf = [0u8; 4];
+            i += 1;
+            format!(
+                "<{0}>{1}</{0}>",
+                tag,
+                html_escape::encode_text(ch.encode_utf8(&mut buf))
+            )
+        };
+
+        out.push(Item {
+            html,
+            x: Some(x),
+            script: None,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Build up a canvas where each glyph number is just a Unicode codepoint.
+    /// Glyphs are 10 units wide, unless an `@` item moves the current
+    /// position. Scripts are marked with `^` and `_`.
+    fn build(items: &[&str]) -> MathBuilder {
+        let mut b = MathBuilder::default();
+        let mut x = 0;
+
+        for item in items {
+            if let Some(pos) = item.strip_prefix('@') {
+                x = pos.parse().unwrap();
+            } else if let Some(name) = item.strip_prefix('+') {
+                match name {
+                    "^" => b.start_script("sup"),
+                    "_" => b.start_script("sub"),
+                    _ => b.start(name),
+                }
+            } else if let Some(name) = item.strip_prefix('-') {
+                match name {
+                    "^" => b.end_script("sup", x),
+                    "_" => b.end_script("sub", x),
+                    _ => b.end(name),
+                }
+            } else {
+                for ch in item.chars() {
+                    b.push_glyph(0, ch as u16, x);
+                    x += 10;
+                }
+            }
+        }
+
+        b
+    }
+
+    fn render(b: MathBuilder) -> Result<String, String> {
+        b.render(false, |_, g| char::from_u32(g as u32))
+    }
+
+    #[test]
+    fn fraction() {
+        let b = build(&[
+            "+mfrac", "+mrow", "x+1", "-mrow", "12.5", "-mfrac", "=", "+msup", "y", "2", "-msup",
+        ]);
+        assert!(b.has_structure());
+        assert_eq!(
+            render(b).unwrap(),
+            "<math><mfrac><mrow><mi>x</mi><mo>+</mo><mn>1</mn></mrow><mn>12.5</mn></mfrac>\
+            <mo>=</mo><msup><mi>y</mi><mn>2</mn></msup></math>"
+        );
+    }
+
+    #[test]
+    fn token_override() {
+        let b = build(&["+mi", "sin", "-mi", "+mo", "<", "-mo"]);
+        assert_eq!(render(b).unwrap(), "<math><mi>sin</mi><mo>&lt;</mo></math>");
+    }
+
+    #[test]
+    fn scripts() {
+        let b = build(&[
+            "+^", "14", "-^", "C", "=", "x", "+^", "2", "-^", "=", "y", "+^", "n", "-^", "@200",
+            "+_", "i+1", "-_",
+        ]);
+        assert_eq!(
+            render(b).unwrap(),
+            "<math><msup><mrow></mrow><mn>14</mn></msup><mi>C</mi><mo>=</mo>\
+            <msup><mi>x</mi><mn>2</mn></msup><mo>=</mo>\
+            <msubsup><mi>y</mi><mrow><mi>i</mi><mo>+</mo><mn>1</mn></mrow><mi>n</mi></msubsup></math>"
+        );
+    }
+
+    #[test]
+    fn limits() {
+        // A superscript drawn above the base: `x = \sum^n_i`, where the sum
+        // sign starts before the end of the `n`.
+        let b = build(&[
+            "x=", "@30", "+^", "n", "-^", "@25", "∑", "@30", "+_", "i", "-_",
+        ]);
+        assert_eq!(
+            render(b).unwrap(),
+            "<math><mi>x</mi><mo>=</mo><msubsup><mo>∑</mo><mi>i</mi><mi>n</mi></msubsup></math>"
+        );
+
+        // An ordinary superscript followed by something else.
+        let b = build(&["x=", "+^", "n", "-^", "@50", "∑", "+_", "i", "-_"]);
+        assert_eq!(
+            render(b).unwrap(),
+            "<math><mi>x</mi><msup><mo>=</mo><mi>n</mi></msup>\
+            <msub><mo>∑</mo><mi>i</mi></msub></math>"
+        );
+    }
+
+    #[test]
+    fn radicals() {
+        let b = build(&["+msqrt", "√", "x+1", "-msqrt"]);
+        assert_eq!(
+            render(b).unwrap(),
+            "<math><msqrt><mi>x</mi><mo>+</mo><mn>1</mn></msqrt></math>"
+        );
+
+        let b = build(&[
+            "+mroot", "+mrow", "3", "-mrow", "√", "+mrow", "x", "-mrow", "-mroot",
+        ]);
+        assert_eq!(
+            render(b).unwrap(),
+            "<math><mroot><mrow><mi>x</mi></mrow><mrow><mn>3</mn></mrow></mroot></math>"
+        );
+    }
+
+    #[test]
+    fn malformed() {
+        assert!(render(build(&["+mfrac", "x", "-mfrac"])).is_err());
+        assert!(render(build(&["+mrow", "x"])).is_err());
+        assert!(render(build(&["+mrow", "x", "-msub"])).is_err());
+        assert!(render(build(&["+mglyph", "x", "-mglyph"])).is_err());
+        assert!(render(build(&["+^", "x", "-_"])).is_err());
+        assert!(render(build(&["+mi", "+^", "x", "-^", "-mi"])).is_err());
+    }
+}
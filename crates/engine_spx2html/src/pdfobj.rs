@@ -0,0 +1,284 @@
+// Copyright 2026 the Tectonic Project
+// Licensed under the MIT License.
+
+//! Minimal parsing of the PDF object syntax used in `pdf:` specials.
+
+/// A PDF object, as far as we care about them.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum PdfObject {
+    Name(String),
+    String(String),
+    Dict(Vec<(String, PdfObject)>),
+    Array(Vec<PdfObject>),
+    Other(String),
+}
+
+impl PdfObject {
+    /// Get the textual value of a name or string.
+    pub(crate) fn as_text(&self) -> Option<String> {
+        match self {
+            PdfObject::Name(s) | PdfObject::String(s) => Some(s.clone()),
+            _ => None,
+        }
+    }
+}
+
+pub(crate) fn dict_get<'a>(dict: &'a [(String, PdfObject)], key: &str) -> Option<&'a PdfObject> {
+    dict.iter().find(|(k, _)| k == key).map(|(_, v)| v)
+}
+
+/// A minimal parser for the PDF object syntax used in `pdf:` specials.
+pub(crate) struct PdfParser<'a> {
+    text: &'a [u8],
+    pos: usize,
+}
+
+fn is_pdf_delimiter(b: u8) -> bool {
+    b.is_ascii_whitespace() || b"()<>[]{}/%".contains(&b)
+}
+
+impl<'a> PdfParser<'a> {
+    pub(crate) fn new(text: &'a str) -> Self {
+        PdfParser {
+            text: text.as_bytes(),
+            pos: 0,
+        }
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.text.get(self.pos).copied()
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(b) = self.peek() {
+            if b.is_ascii_whitespace() {
+                self.pos += 1;
+            } else if b == b'%' {
+                while !matches!(self.peek(), None | Some(b'\n') | Some(b'\r')) {
+                    self.pos += 1;
+                }
+            } else {
+                break;
+            }
+        }
+    }
+
+    pub(crate) fn parse_object(&mut self) -> Option<PdfObject> {
+        self.skip_whitespace();
+
+        match self.peek()? {
+            b'/' => {
+                self.pos += 1;
+                Some(PdfObject::Name(self.parse_name()))
+            }
+
+            b'(' => {
+                self.pos += 1;
+                self.parse_literal_string().map(PdfObject::String)
+            }
+
+            b'<' if self.text.get(self.pos + 1) == Some(&b'<') => {
+                self.pos += 2;
+                let mut items = Vec::new();
+
+                loop {
+                    self.skip_whitespace();
+
+                    if self.text[self.pos..].starts_with(b">>") {
+                        self.pos += 2;
+                        return Some(PdfObject::Dict(items));
+                    }
+
+                    let key = match self.parse_object()? {
+                        PdfObject::Name(n) => n,
+                        _ => return None,
+                    };
+                    let value = self.parse_object()?;
+                    items.push((key, value));
+                }
+            }
+
+            b'<' => {
+                self.pos += 1;
+                self.parse_hex_string().map(PdfObject::String)
+            }
+
+            b'[' => {
+                self.pos += 1;
+                let mut items = Vec::new();
+
+                loop {
+                    self.skip_whitespace();
+
+                    if self.peek()? == b']' {
+                        self.pos += 1;
+                        return Some(PdfObject::Array(items));
+                    }
+
+                    items.push(self.parse_object()?);
+                }
+            }
+
+            b')' | b'>' | b']' => None,
+
+            _ => {
+                let start = self.pos;
+
+                while let Some(b) = self.peek() {
+                    if is_pdf_delimiter(b) {
+                        break;
+                    }
+
+                    self.pos += 1;
+                }
+
+                if self.pos == start {
+                    // A stray delimiter like `{`.
+                    self.pos += 1;
+                }
+
+                Some(PdfObject::Other(
+                    String::from_utf8_lossy(&self.text[start..self.pos]).into_owned(),
+                ))
+            }
+        }
+    }
+
+    fn parse_name(&mut self) -> String {
+        let mut bytes = Vec::new();
+
+        while let Some(b) = self.peek() {
+            if is_pdf_delimiter(b) {
+                break;
+            }
+
+            self.pos += 1;
+
+            if b == b'#' {
+                if let Some(v) = self
+                    .text
+                    .get(self.pos..self.pos + 2)
+                    .and_then(|h| std::str::from_utf8(h).ok())
+                    .and_then(|h| u8::from_str_radix(h, 16).ok())
+                {
+                    bytes.push(v);
+                    self.pos += 2;
+                    continue;
+                }
+            }
+
+            bytes.push(b);
+        }
+
+        decode_pdf_text(bytes)
+    }
+
+    fn parse_literal_string(&mut self) -> Option<String> {
+        let mut bytes = Vec::new();
+        let mut depth = 0;
+
+        loop {
+            let b = self.peek()?;
+            self.pos += 1;
+
+            match b {
+                b'(' => {
+                    depth += 1;
+                    bytes.push(b);
+                }
+
+                b')' if depth == 0 => break,
+
+                b')' => {
+                    depth -= 1;
+                    bytes.push(b);
+                }
+
+                b'\\' => {
+                    let e = self.peek()?;
+                    self.pos += 1;
+
+                    match e {
+                        b'n' => bytes.push(b'\n'),
+                        b'r' => bytes.push(b'\r'),
+                        b't' => bytes.push(b'\t'),
+                        b'b' => bytes.push(0x08),
+                        b'f' => bytes.push(0x0C),
+                        b'\r' | b'\n' => {
+                            // Line continuation.
+                            if e == b'\r' && self.peek() == Some(b'\n') {
+                                self.pos += 1;
+                            }
+                        }
+                        b'0'..=b'7' => {
+                            let mut v = (e - b'0') as u32;
+
+                            for _ in 0..2 {
+                                match self.peek() {
+                                    Some(d @ b'0'..=b'7') => {
+                                        v = v * 8 + (d - b'0') as u32;
+                                        self.pos += 1;
+                                    }
+                                    _ => break,
+                                }
+                            }
+
+                            bytes.push(v as u8);
+                        }
+                        other => bytes.push(other),
+                    }
+                }
+
+                _ => bytes.push(b),
+            }
+        }
+
+        Some(decode_pdf_text(bytes))
+    }
+
+    fn parse_hex_string(&mut self) -> Option<String> {
+        let mut digits = Vec::new();
+
+        loop {
+            let b = self.peek()?;
+            self.pos += 1;
+
+            match b {
+                b'>' => break,
+                b if b.is_ascii_hexdigit() => digits.push(b),
+                b if b.is_ascii_whitespace() => {}
+                _ => return None,
+            }
+        }
+
+        if digits.len() % 2 == 1 {
+            digits.push(b'0');
+        }
+
+        let bytes = digits
+            .chunks(2)
+            .map(|pair| u8::from_str_radix(std::str::from_utf8(pair).unwrap(), 16).unwrap())
+            .collect();
+
+        Some(decode_pdf_text(bytes))
+    }
+}
+
+/// Decode the bytes of a PDF string.
+///
+/// Strings with a byte-order mark are UTF-16. Otherwise we try UTF-8, which
+/// is what XeTeX generally produces, and fall back to Latin-1.
+fn decode_pdf_text(bytes: Vec<u8>) -> String {
+    if let Some(utf16) = bytes.strip_prefix(&[0xFE, 0xFF]) {
+        let units: Vec<u16> = utf16
+            .chunks(2)
+            .map(|c| u16::from_be_bytes([c[0], *c.get(1).unwrap_or(&0)]))
+            .collect();
+        return String::from_utf16_lossy(&units);
+    }
+
+    match String::from_utf8(bytes) {
+        Ok(s) => s,
+        Err(e) => e.into_bytes().into_iter().map(char::from).collect(),
+    }
+}
@@ -0,0 +1,211 @@
+// Copyright 2026 the Tectonic Project
+// Licensed under the MIT License.
+
+//! Gathering data for client-side search indices.
+//!
+//! If the document requests a `search-index` special asset, we write out a
+//! JSON file describing the text of every HTML page that was emitted: its
+//! path, its headings, and the distinct words that it contains. This is
+//! enough for a template to provide site search with a small JavaScript
+//! library, without any server-side support.
+
+use std::collections::HashSet;
+
+use crate::assets::syntax::{SearchHeading, SearchPage};
+
+/// Elements whose contents are not document text.
+const SKIPPED_ELEMENTS: &[&str] = &["script", "style"];
+
+/// Elements that do not separate words when their tags are removed.
+const INLINE_ELEMENTS: &[&str] = &[
+    "a", "abbr", "b", "cite", "code", "em", "i", "kbd", "mark", "q", "s", "samp", "small", "span",
+    "strong", "sub", "sup", "u", "var",
+];
+
+/// Build the search-index entry for an HTML page from its content.
+///
+/// The *content* is the HTML that the engine has generated for the page,
+/// before it is inserted into the template, so that navigation and other
+/// boilerplate from the template don't pollute the index.
+pub(crate) fn index_page(path: &str, content: &str) -> SearchPage {
+    let mut page = SearchPage {
+        path: path.to_owned(),
+        ..Default::default()
+    };
+
+    let mut all_text = String::new();
+    let mut skip_depth = 0;
+    let mut heading: Option<(u8, Option<String>, String)> = None;
+    let mut last_anchor: Option<String> = None;
+    let mut rest = content;
+
+    while !rest.is_empty() {
+        let (text, tag) = match rest.find('<') {
+            Some(i) => (&rest[..i], Some(&rest[i..])),
+            None => (rest, None),
+        };
+
+        if skip_depth == 0 && !text.is_empty() {
+            let text = html_escape::decode_html_entities(text);
+            all_text.push_str(&text);
+
+            if let Some((_, _, ref mut htext)) = heading {
+                htext.push_str(&text);
+            } else if !text.trim().is_empty() {
+                last_anchor = None;
+            }
+        }
+
+        let tag = match tag {
+            Some(t) => t,
+            None => break,
+        };
+
+        let end = tag.find('>').map(|i| i + 1).unwrap_or(tag.len());
+        let (closing, name, attrs) = parse_tag(&tag[..end]);
+        rest = &tag[end..];
+
+        if SKIPPED_ELEMENTS.contains(&name.as_str()) {
+            if closing {
+                skip_depth = std::cmp::max(skip_depth, 1) - 1;
+            } else {
+                skip_depth += 1;
+            }
+            continue;
+        }
+
+        if skip_depth > 0 {
+            continue;
+        }
+
+        if !INLINE_ELEMENTS.contains(&name.as_str()) {
+            all_text.push(' ');
+
+            if let Some((_, _, ref mut htext)) = heading {
+                htext.push(' ');
+            }
+        }
+
+        let level = heading_level(&name);
+
+        if closing {
+            if let (Some(level), Some((cur_level, _, _))) = (level, heading.as_ref()) {
+                if level == *cur_level {
+                    let (level, id, text) = heading.take().unwrap();
+                    let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+
+                    if !text.is_empty() {
+                        if page.title.is_none() {
+                            page.title = Some(text.clone());
+                        }
+
+                        page.headings.push(SearchHeading { level, text, id });
+                    }
+
+                    last_anchor = None;
+                }
+            }
+        } else if let Some(level) = level {
+            if heading.is_none() {
+                let id = attribute(attrs, "id").or_else(|| last_anchor.take());
+                heading = Some((level, id, String::new()));
+            }
+        } else if name == "a" && attribute(attrs, "href").is_none() {
+            if let Some(id) = attribute(attrs, "id") {
+                last_anchor = Some(id);
+            }
+        }
+    }
+
+    let mut seen = HashSet::new();
+
+    for word in all_text
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+    {
+        let word = word.to_lowercase();
+
+        if seen.insert(word.clone()) {
+            page.tokens.push(word);
+        }
+    }
+
+    page
+}
+
+/// Split an HTML tag into whether it is a closing tag, its lowercased
+/// element name, and the text of its attributes.
+fn parse_tag(tag: &str) -> (bool, String, &str) {
+    let inner = tag.trim_start_matches('<').trim_end_matches('>');
+    let (closing, inner) = match inner.strip_prefix('/') {
+        Some(i) => (true, i),
+        None => (false, inner),
+    };
+    let end = inner
+        .find(|c: char| c.is_whitespace() || c == '/')
+        .unwrap_or(inner.len());
+    (closing, inner[..end].to_ascii_lowercase(), &inner[end..])
+}
+
+fn heading_level(name: &str) -> Option<u8> {
+    match name.as_bytes() {
+        [b'h', d @ b'1'..=b'6'] => Some(d - b'0'),
+        _ => None,
+    }
+}
+
+/// Get the value of a double-quoted attribute from the text of a tag.
+fn attribute(attrs: &str, name: &str) -> Option<String> {
+    let mut rest = attrs;
+
+    loop {
+        let ofs = rest.find(name)?;
+        let preceded_ok = rest[..ofs]
+            .chars()
+            .next_back()
+            .is_none_or(char::is_whitespace);
+        rest = &rest[ofs + name.len()..];
+
+        if preceded_ok {
+            if let Some(value) = rest.strip_prefix("=\"") {
+                let end = value.find('"')?;
+                return Some(html_escape::decode_html_entities(&value[..end]).into_owned());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn basic() {
+        let page = index_page(
+            "ch1/index.html",
+            "<style>.x { color: red }</style>\
+             <a id=\"sec1\"></a><h2 class=\"x\">Intro<span>duction</span> &amp; <em>Set</em>up</h2>\
+             <p>The intro, the <b>end</b>.</p><h3 id=\"h\">More<br>Text</h3>",
+        );
+
+        assert_eq!(page.path, "ch1/index.html");
+        assert_eq!(page.title.as_deref(), Some("Introduction & Setup"));
+        assert_eq!(page.headings.len(), 2);
+        assert_eq!(page.headings[0].level, 2);
+        assert_eq!(page.headings[0].id.as_deref(), Some("sec1"));
+        assert_eq!(page.headings[1].text, "More Text");
+        assert_eq!(page.headings[1].id.as_deref(), Some("h"));
+        assert_eq!(
+            page.tokens,
+            [
+                "introduction",
+                "setup",
+                "the",
+                "intro",
+                "end",
+                "more",
+                "text"
+            ]
+        );
+    }
+}
@@ -15,13 +15,24 @@ pub(crate) enum Special<'a> {
     CanvasStart(&'a str),
     ContentFinished,
     DirectText(&'a str),
+    DissolveCanvas,
     EndDefineFontFamily,
     EndFontFamilyTagAssociations,
     Emit,
+    HyperTex(&'a str),
     ManualEnd(&'a str),
     ManualFlexibleStart(&'a str),
+    MathEnd(&'a str),
+    MathScriptEnd(&'a str),
+    MathScriptStart(&'a str),
+    MathStart(&'a str),
+    PdfBeginAnnotation(&'a str),
+    PdfDestination(&'a str),
+    PdfEndAnnotation,
+    PdfImage(&'a str),
     ProvideFile(&'a str),
     ProvideSpecial(&'a str),
+    SetCanvasFormat(&'a str),
     SetOutputPath(&'a str),
     SetTemplate(&'a str),
     SetTemplateVariable(&'a str),
@@ -31,6 +42,31 @@ pub(crate) enum Special<'a> {
 
 impl<'a> Special<'a> {
     pub(crate) fn parse(text: &'a str, status: &mut dyn StatusBackend) -> Option<Self> {
+        // Hyperlinks can also come from the `pdf:` specials understood by
+        // xdvipdfmx, or HyperTeX `html:` specials. There are lots of other
+        // `pdf:` specials that aren't relevant to us, so we ignore them
+        // silently.
+
+        if let Some(rest) = text.strip_prefix("pdf:") {
+            let rest = rest.trim_start();
+            let cmd_len = rest
+                .find(|c: char| !c.is_ascii_alphabetic())
+                .unwrap_or(rest.len());
+            let (cmd, remainder) = rest.split_at(cmd_len);
+
+            return match cmd {
+                "bann" | "bannot" | "beginann" => Some(Special::PdfBeginAnnotation(remainder)),
+                "eann" | "eannot" | "endann" => Some(Special::PdfEndAnnotation),
+                "dest" => Some(Special::PdfDestination(remainder)),
+                "image" => Some(Special::PdfImage(remainder)),
+                _ => None,
+            };
+        }
+
+        if let Some(rest) = text.strip_prefix("html:") {
+            return Some(Special::HyperTex(rest));
+        }
+
         // str.split_once() would be nice but it was introduced in 1.52 which is
         // a bit recent for us.
 
@@ -54,9 +90,15 @@ impl<'a> Special<'a> {
             "mfs" => Special::ManualFlexibleStart(remainder),
             "me" => Special::ManualEnd(remainder),
             "dt" => Special::DirectText(remainder),
+            "dissolveCanvas" => Special::DissolveCanvas,
+            "mms" => Special::MathStart(remainder),
+            "mme" => Special::MathEnd(remainder),
+            "mss" => Special::MathScriptStart(remainder),
+            "mse" => Special::MathScriptEnd(remainder),
             "emit" => Special::Emit,
             "addTemplate" => Special::AddTemplate(remainder),
             "setTemplate" => Special::SetTemplate(remainder),
+            "setCanvasFormat" => Special::SetCanvasFormat(remainder),
             "setOutputPath" => Special::SetOutputPath(remainder),
             "setTemplateVariable" => Special::SetTemplateVariable(remainder),
             "provideFile" => Special::ProvideFile(remainder),
@@ -90,7 +132,13 @@ impl<'a> Special<'a> {
                 | Special::CanvasEnd(_)
                 | Special::ManualFlexibleStart(_)
                 | Special::ManualEnd(_)
+                | Special::MathStart(_)
+                | Special::MathEnd(_)
+                | Special::MathScriptStart(_)
+                | Special::MathScriptEnd(_)
                 | Special::DirectText(_)
+                | Special::DissolveCanvas
+                | Special::PdfImage(_)
         )
     }
 }
@@ -105,13 +153,24 @@ impl<'a> Display for Special<'a> {
             Special::CanvasStart(t) => ("cs", Some(t)),
             Special::ContentFinished => ("contentFinished", None),
             Special::DirectText(t) => ("dt", Some(t)),
+            Special::DissolveCanvas => ("dissolveCanvas", None),
             Special::EndDefineFontFamily => ("endDefineFontFamily", None),
             Special::EndFontFamilyTagAssociations => ("endFontFamilyTagAssociations", None),
             Special::Emit => ("emit", None),
+            Special::HyperTex(t) => return write!(f, "html:{t}"),
             Special::ManualEnd(t) => ("me", Some(t)),
             Special::ManualFlexibleStart(t) => ("mfs", Some(t)),
+            Special::MathEnd(t) => ("mme", Some(t)),
+            Special::MathScriptEnd(t) => ("mse", Some(t)),
+            Special::MathScriptStart(t) => ("mss", Some(t)),
+            Special::MathStart(t) => ("mms", Some(t)),
+            Special::PdfBeginAnnotation(t) => return write!(f, "pdf:bann {t}"),
+            Special::PdfDestination(t) => return write!(f, "pdf:dest {t}"),
+            Special::PdfEndAnnotation => return write!(f, "pdf:eann"),
+            Special::PdfImage(t) => return write!(f, "pdf:image {t}"),
             Special::ProvideFile(t) => ("provideFile", Some(t)),
             Special::ProvideSpecial(t) => ("provideSpecial", Some(t)),
+            Special::SetCanvasFormat(t) => ("setCanvasFormat", Some(t)),
             Special::SetOutputPath(t) => ("setOutputPath", Some(t)),
             Special::SetTemplate(t) => ("setTemplate", Some(t)),
             Special::SetTemplateVariable(t) => ("setTemplateVariable", Some(t)),
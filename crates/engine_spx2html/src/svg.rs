@@ -0,0 +1,186 @@
+// Copyright 2026 the Tectonic Project
+// Licensed under the MIT License.
+
+//! Rendering canvases as standalone SVG images.
+//!
+//! Instead of emitting a canvas as a box of absolutely-positioned HTML
+//! elements, we can draw it as an SVG image. Glyphs are drawn from their
+//! outlines, so that the image doesn't depend on any web fonts, and rules are
+//! drawn as rectangles. The images are named after a digest of their contents,
+//! so that identical canvases share the same file and rebuilds produce the same
+//! names.
+
+use std::fmt::Write;
+use tectonic_errors::prelude::*;
+use tectonic_font_outlines::svg::{svg_number, GlyphDef, GlyphDefs};
+use tectonic_io_base::digest::{self, Digest};
+
+use crate::{fontfile::GlyphId, fonts::FontEnsemble, TexFontNum};
+
+/// An SVG image of a canvas that is being built up.
+#[derive(Debug, Default)]
+pub(crate) struct SvgCanvas {
+    /// The glyph outlines used in the canvas, with the scale factor from font
+    /// units to TeX units.
+    glyphs: GlyphDefs<(TexFontNum, GlyphId), f32>,
+
+    /// Glyphs to draw, and the positions of their origins in TeX units.
+    uses: Vec<(GlyphDef<f32>, i32, i32)>,
+
+    /// Rectangles to draw: the top-left corner, width, and height in TeX
+    /// units.
+    rects: Vec<(i32, i32, i32, i32)>,
+
+    /// The bounds of the image, as `[x_min, y_min, x_max, y_max]` in TeX
+    /// units, with the Y axis pointing down.
+    bounds: Option<[f32; 4]>,
+}
+
+impl SvgCanvas {
+    /// Expand the bounds of the image to include the given box.
+    pub fn include(&mut self, x_min: f32, y_min: f32, x_max: f32, y_max: f32) {
+        self.bounds = Some(match self.bounds {
+            None => [x_min, y_min, x_max, y_max],
+            Some(b) => [
+                b[0].min(x_min),
+                b[1].min(y_min),
+                b[2].max(x_max),
+                b[3].max(y_max),
+            ],
+        });
+    }
+
+    /// Add a glyph whose baseline origin is at the given position.
+    pub fn add_glyph(
+        &mut self,
+        fonts: &FontEnsemble,
+        x: i32,
+        y: i32,
+        fnum: TexFontNum,
+        glyph: GlyphId,
+    ) -> Result<()> {
+        let def = self
+            .glyphs
+            .get_or_define((fnum, glyph), || fonts.get_glyph_outline(fnum, glyph))?;
+
+        if let Some(def) = def.cloned() {
+            // Glyphs can poke outside of the box defined by the font metrics,
+            // and unlike HTML, the SVG will be clipped to its bounds. So we
+            // take the outline into account too, wherever the glyph is used.
+            let (xf, yf, scale) = (x as f32, y as f32, def.extra);
+            self.include(
+                xf + def.bbox[0] * scale,
+                yf - def.bbox[3] * scale,
+                xf + def.bbox[2] * scale,
+                yf - def.bbox[1] * scale,
+            );
+            self.uses.push((def, x, y));
+        }
+
+        Ok(())
+    }
+
+    /// Add a rule whose bottom-left corner is at the given position.
+    pub fn add_rule(&mut self, x: i32, y: i32, width: i32, height: i32) {
+        self.include(x as f32, (y - height) as f32, (x + width) as f32, y as f32);
+        self.rects.push((x, y - height, width, height));
+    }
+
+    /// Create the SVG document.
+    ///
+    /// The return value is a tuple of the document text and the image bounds,
+    /// `[x_min, y_min, x_max, y_max]`, in TeX units. The document uses units of
+    /// rems, given the number of rems per TeX unit.
+    pub fn finish(self, rems_per_tex: f32) -> (String, [f32; 4]) {
+        let bounds = self.bounds.unwrap_or_default();
+        let (x0, y0) = (bounds[0], bounds[1]);
+        let width = num((bounds[2] - x0) * rems_per_tex);
+        let height = num((bounds[3] - y0) * rems_per_tex);
+        let defs = self.glyphs.defs();
+
+        let mut svg = format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" \
+            xmlns:xlink=\"http://www.w3.org/1999/xlink\" \
+            width=\"{width}em\" height=\"{height}em\" viewBox=\"0 0 {width} {height}\">"
+        );
+
+        if !defs.is_empty() {
+            write!(svg, "<defs>{defs}</defs>").unwrap();
+        }
+
+        for (def, x, y) in self.uses {
+            let s = (def.extra * rems_per_tex) as f64;
+            let tx = ((x as f32 - x0) * rems_per_tex) as f64;
+            let ty = ((y as f32 - y0) * rems_per_tex) as f64;
+            def.write_use(&mut svg, [s, 0., 0., -s, tx, ty], "");
+        }
+
+        for (x, y, w, h) in self.rects {
+            write!(
+                svg,
+                "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\"/>",
+                num((x as f32 - x0) * rems_per_tex),
+                num((y as f32 - y0) * rems_per_tex),
+                num(w as f32 * rems_per_tex),
+                num(h as f32 * rems_per_tex),
+            )
+            .unwrap();
+        }
+
+        svg.push_str("</svg>\n");
+        (svg, bounds)
+    }
+}
+
+fn num(v: f32) -> String {
+    svg_number(v as f64)
+}
+
+/// Get the file name to use for an SVG document, based on its contents.
+pub(crate) fn file_name(svg: &str) -> String {
+    let mut dc = digest::create();
+    dc.update(svg.as_bytes());
+    let hash = digest::bytes_to_hex(&dc.finalize());
+    format!("canvas-{}.svg", &hash[..16])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tectonic_font_outlines::GlyphOutline;
+
+    #[test]
+    fn rules() {
+        let mut c = SvgCanvas::default();
+        c.add_rule(0, 0, 65536, 32768);
+        c.add_rule(65536, 65536, 65536, 65536);
+        let (svg, bounds) = c.finish(1. / 65536.);
+
+        assert_eq!(bounds, [0., -32768., 131072., 65536.]);
+        assert!(svg.contains("viewBox=\"0 0 2 1.5\""));
+        assert!(svg.contains("<rect x=\"0\" y=\"0\" width=\"1\" height=\"0.5\"/>"));
+        assert!(svg.contains("<rect x=\"1\" y=\"0.5\" width=\"1\" height=\"1\"/>"));
+        assert!(file_name(&svg).starts_with("canvas-"));
+    }
+
+    #[test]
+    fn repeated_glyph_bounds() {
+        let fonts = FontEnsemble::default();
+        let mut c = SvgCanvas::default();
+        c.glyphs
+            .get_or_define((0, 1), || {
+                let outline = GlyphOutline {
+                    path: String::new(),
+                    bbox: Some([-10., -20., 60., 100.]),
+                };
+                Ok((outline, 1.))
+            })
+            .unwrap();
+
+        c.add_glyph(&fonts, 0, 0, 0, 1).unwrap();
+        c.add_glyph(&fonts, 10000, 5000, 0, 1).unwrap();
+        let (_, bounds) = c.finish(1.);
+
+        assert_eq!(bounds, [-10., -100., 10060., 5020.]);
+    }
+}
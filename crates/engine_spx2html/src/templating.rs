@@ -3,10 +3,7 @@
 
 //! State relating to handling the Tera templating and file emission.
 
-use std::{
-    fs::File,
-    io::{Read, Write},
-};
+use std::io::Read;
 use tectonic_errors::prelude::*;
 use tectonic_status_base::tt_warning;
 
@@ -18,6 +15,10 @@ pub(crate) struct Templating {
     context: tera::Context,
     next_template_path: String,
     next_output_path: String,
+
+    /// Extra files, such as canvas images, to be written into the same
+    /// directory as the next output file.
+    companion_files: Vec<(String, String)>,
 }
 
 impl Templating {
@@ -32,6 +33,7 @@ impl Templating {
             context,
             next_template_path,
             next_output_path,
+            companion_files: Vec::new(),
         }
     }
 
@@ -67,6 +69,17 @@ impl Templating {
         self.context.insert(name, value.as_ref());
     }
 
+    /// Register a file to be written alongside the next output file.
+    ///
+    /// The HTML content can refer to it by its bare name.
+    pub(crate) fn add_companion_file(&mut self, name: String, contents: String) {
+        self.companion_files.push((name, contents));
+    }
+
+    pub(crate) fn next_output_path(&self) -> &str {
+        &self.next_output_path
+    }
+
     pub(crate) fn ready_to_output(&self) -> bool {
         !self.next_template_path.is_empty() && !self.next_output_path.is_empty()
     }
@@ -123,20 +136,44 @@ impl Templating {
             ["failed to render HTML template `{}` while creating `{}`", &self.next_template_path, &self.next_output_path]
         );
 
+        // Resolve the links that we can. If there are links to anchors that we
+        // haven't seen yet, the file is held back until the end of processing.
+
+        common.links.page_finished(&self.next_output_path);
+        let (rendered, n_unresolved) =
+            common
+                .links
+                .resolve(&rendered, &self.next_output_path, false);
+
         // Save it. Unless we shouldn't, actually.
 
         if let Some(out_path) = out_path {
-            let mut out_file = atry!(
-                File::create(&out_path);
-                ["cannot open output file `{}`", out_path.display()]
-            );
+            if n_unresolved > 0 {
+                common.links.defer_unresolved_file(
+                    out_path.clone(),
+                    &self.next_output_path,
+                    rendered,
+                );
+            } else {
+                crate::assets::write_if_changed(&out_path, rendered.as_bytes())?;
+            }
 
-            atry!(
-                out_file.write_all(rendered.as_bytes());
-                ["cannot write output file `{}`", out_path.display()]
-            );
+            for (name, contents) in &self.companion_files {
+                let path = out_path.with_file_name(name);
+                crate::assets::write_if_changed(&path, contents.as_bytes())?;
+            }
+
+            if let Some(rel) = common.out_base.and_then(|b| out_path.strip_prefix(b).ok()) {
+                let rel: Vec<_> = rel
+                    .components()
+                    .map(|c| c.as_os_str().to_string_lossy())
+                    .collect();
+                common.emitted_pages.push(rel.join("/"));
+            }
         }
 
+        self.companion_files.clear();
+
         // Clear the output path, because we don't want people to be accidentally
         // overwriting the same file by failing to update it.
 
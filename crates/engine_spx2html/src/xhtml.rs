@@ -0,0 +1,297 @@
+// Copyright 2026 the Tectonic Project
+// Licensed under the MIT License.
+
+//! Converting HTML output to XHTML.
+//!
+//! EPUB content documents must be well-formed XML, but the HTML templates used
+//! with spx2html are usually written as HTML5, which allows things like
+//! unclosed `<meta>` tags and attributes without values. This module does a
+//! lightweight, tag-level rewrite to fix up the common cases. It doesn't try
+//! to repair truly malformed markup.
+
+/// Elements that never have content.
+const VOID_ELEMENTS: &[&str] = &[
+    "area", "base", "br", "col", "embed", "hr", "img", "input", "link", "meta", "source", "track",
+    "wbr",
+];
+
+/// Elements whose content is not markup.
+const RAW_TEXT_ELEMENTS: &[&str] = &["script", "style"];
+
+const XHTML_NS: &str = "http://www.w3.org/1999/xhtml";
+
+/// Convert an HTML document to XHTML.
+pub(crate) fn to_xhtml(html: &str) -> String {
+    let mut out = String::with_capacity(html.len() + html.len() / 16);
+    let mut rest = html;
+
+    if !rest.trim_start().starts_with("<?xml") {
+        out.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    }
+
+    while let Some(idx) = rest.find(['<', '&']) {
+        out.push_str(&rest[..idx]);
+        rest = &rest[idx..];
+
+        if rest.starts_with('&') {
+            rest = convert_entity(rest, &mut out);
+            continue;
+        }
+
+        if rest.starts_with("<!--") {
+            let end = rest.find("-->").map(|i| i + 3).unwrap_or(rest.len());
+            out.push_str(&rest[..end]);
+            rest = &rest[end..];
+        } else if rest.starts_with("<!") || rest.starts_with("<?") || rest.starts_with("</") {
+            let end = rest.find('>').map(|i| i + 1).unwrap_or(rest.len());
+            out.push_str(&rest[..end]);
+            rest = &rest[end..];
+        } else if rest[1..].starts_with(|c: char| c.is_ascii_alphabetic()) {
+            let (tag, after) = parse_start_tag(rest);
+            out.push_str(&tag.render());
+            rest = after;
+
+            if RAW_TEXT_ELEMENTS.contains(&tag.name.as_str()) && !tag.self_closed {
+                let close = format!("</{}", tag.name);
+                let end = find_ascii_ci(rest, &close).unwrap_or(rest.len());
+                let content = &rest[..end];
+
+                if content.contains(['<', '&']) && !content.contains("]]>") {
+                    out.push_str("/*<![CDATA[*/");
+                    out.push_str(content);
+                    out.push_str("/*]]>*/");
+                } else {
+                    out.push_str(content);
+                }
+
+                rest = &rest[end..];
+            }
+        } else {
+            // A stray less-than sign.
+            out.push_str("&lt;");
+            rest = &rest[1..];
+        }
+    }
+
+    out.push_str(rest);
+    out
+}
+
+/// Handle an ampersand: pass XML-compatible references through, translate
+/// common HTML named entities into numeric references, and escape anything
+/// else.
+fn convert_entity<'a>(text: &'a str, out: &mut String) -> &'a str {
+    let end = text[1..]
+        .find(|c: char| !(c.is_ascii_alphanumeric() || c == '#'))
+        .map(|i| i + 1);
+
+    if let Some(end) = end {
+        if text[end..].starts_with(';') {
+            let name = &text[1..end];
+
+            let codepoint = match name {
+                "amp" | "lt" | "gt" | "quot" | "apos" => None,
+                _ if name.starts_with('#') => None,
+                "nbsp" => Some(160),
+                "copy" => Some(169),
+                "ndash" => Some(8211),
+                "mdash" => Some(8212),
+                "hellip" => Some(8230),
+                _ => {
+                    // Unknown entity: escape the ampersand.
+                    out.push_str("&amp;");
+                    return &text[1..];
+                }
+            };
+
+            match codepoint {
+                Some(cp) => out.push_str(&format!("&#{cp};")),
+                None => out.push_str(&text[..=end]),
+            }
+
+            return &text[end + 1..];
+        }
+    }
+
+    out.push_str("&amp;");
+    &text[1..]
+}
+
+/// Escape an attribute value for use in double quotes.
+fn escape_attr(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    let mut rest = value;
+
+    while let Some(idx) = rest.find(['&', '<', '"']) {
+        out.push_str(&rest[..idx]);
+        rest = &rest[idx..];
+
+        if rest.starts_with('&') {
+            rest = convert_entity(rest, &mut out);
+        } else {
+            out.push_str(if rest.starts_with('<') {
+                "&lt;"
+            } else {
+                "&quot;"
+            });
+            rest = &rest[1..];
+        }
+    }
+
+    out.push_str(rest);
+    out
+}
+
+fn find_ascii_ci(haystack: &str, needle: &str) -> Option<usize> {
+    haystack
+        .as_bytes()
+        .windows(needle.len())
+        .position(|w| w.eq_ignore_ascii_case(needle.as_bytes()))
+}
+
+struct StartTag {
+    name: String,
+    attrs: Vec<(String, Option<String>)>,
+    self_closed: bool,
+}
+
+impl StartTag {
+    fn render(&self) -> String {
+        let mut s = format!("<{}", self.name);
+        let mut has_xmlns = false;
+
+        for (name, value) in &self.attrs {
+            has_xmlns |= name == "xmlns";
+            s.push_str(&format!(
+                " {}=\"{}\"",
+                name,
+                escape_attr(value.as_deref().unwrap_or(name))
+            ));
+        }
+
+        if self.name == "html" && !has_xmlns {
+            s.push_str(&format!(" xmlns=\"{XHTML_NS}\""));
+        }
+
+        if self.self_closed || VOID_ELEMENTS.contains(&self.name.as_str()) {
+            s.push_str("/>");
+        } else {
+            s.push('>');
+        }
+
+        s
+    }
+}
+
+/// Parse a start tag, returning it and the text following it.
+fn parse_start_tag(text: &str) -> (StartTag, &str) {
+    let bytes = text.as_bytes();
+    let mut i = 1;
+
+    let is_name_char = |b: u8| !(b.is_ascii_whitespace() || b == b'/' || b == b'>' || b == b'=');
+
+    while i < bytes.len() && is_name_char(bytes[i]) {
+        i += 1;
+    }
+
+    let mut tag = StartTag {
+        name: text[1..i].to_ascii_lowercase(),
+        attrs: Vec::new(),
+        self_closed: false,
+    };
+
+    loop {
+        while i < bytes.len() && bytes[i].is_ascii_whitespace() {
+            i += 1;
+        }
+
+        if i >= bytes.len() {
+            return (tag, "");
+        }
+
+        match bytes[i] {
+            b'>' => return (tag, &text[i + 1..]),
+
+            b'/' => {
+                tag.self_closed = true;
+                i += 1;
+            }
+
+            _ => {
+                let start = i;
+
+                while i < bytes.len() && is_name_char(bytes[i]) {
+                    i += 1;
+                }
+
+                // Guard against getting stuck on a stray `=`.
+                if i == start {
+                    i += 1;
+                    continue;
+                }
+
+                let name = text[start..i].to_owned();
+
+                while i < bytes.len() && bytes[i].is_ascii_whitespace() {
+                    i += 1;
+                }
+
+                let mut value = None;
+
+                if i < bytes.len() && bytes[i] == b'=' {
+                    i += 1;
+
+                    while i < bytes.len() && bytes[i].is_ascii_whitespace() {
+                        i += 1;
+                    }
+
+                    if i < bytes.len() && (bytes[i] == b'"' || bytes[i] == b'\'') {
+                        let quote = bytes[i];
+                        let vstart = i + 1;
+                        i = vstart;
+
+                        while i < bytes.len() && bytes[i] != quote {
+                            i += 1;
+                        }
+
+                        value = Some(text[vstart..i].to_owned());
+                        i += 1;
+                    } else {
+                        let vstart = i;
+
+                        while i < bytes.len()
+                            && !(bytes[i].is_ascii_whitespace() || bytes[i] == b'>')
+                        {
+                            i += 1;
+                        }
+
+                        value = Some(text[vstart..i].to_owned());
+                    }
+                }
+
+                tag.self_closed = false;
+                tag.attrs.push((name, value));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn basic() {
+        let html = "<!DOCTYPE html>\n<html lang=en><head><meta charset=\"utf-8\">\
+            <script async src='a.js'>if (1 < 2) {}</script><style>a > b { }</style></head>\
+            <body><p>A&nbsp;B &amp; C & D<br><img src=\"x.svg?a=1&b=2\" alt='\"&amp;\"'/></p></body></html>";
+
+        assert_eq!(
+            to_xhtml(html),
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<!DOCTYPE html>\n\
+            <html lang=\"en\" xmlns=\"http://www.w3.org/1999/xhtml\"><head><meta charset=\"utf-8\"/>\
+            <script async=\"async\" src=\"a.js\">/*<![CDATA[*/if (1 < 2) {}/*]]>*/</script><style>a > b { }</style></head>\
+            <body><p>A&#160;B &amp; C &amp; D<br/><img src=\"x.svg?a=1&amp;b=2\" alt=\"&quot;&amp;&quot;\"/></p></body></html>"
+        );
+    }
+}
@@ -0,0 +1,22 @@
+// Copyright 2026 the Tectonic Project
+// Licensed under the MIT License.
+
+//! Build script for the glyph rasterization shim, which is a thin layer over
+//! the FreeType C API.
+
+use std::env;
+
+fn main() {
+    let freetype2_include_path = env::var("DEP_FREETYPE2_INCLUDE_PATH").unwrap();
+
+    let mut ccfg = cc::Build::new();
+    ccfg.warnings(true).file("raster/tectonic_xdv2png_raster.c");
+
+    for item in freetype2_include_path.split(';') {
+        ccfg.include(item);
+    }
+
+    ccfg.compile("libtectonic_xdv2png_raster.a");
+
+    println!("cargo:rerun-if-changed=raster/tectonic_xdv2png_raster.c");
+}
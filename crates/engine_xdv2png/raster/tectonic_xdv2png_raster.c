@@ -0,0 +1,106 @@
+/* Copyright 2026 the Tectonic Project
+ * Licensed under the MIT License.
+ *
+ * A minimal wrapper around FreeType for rendering individual glyphs into
+ * grayscale coverage bitmaps. Each font gets its own FT_Library, so that fonts
+ * can be used independently without any global state.
+ */
+
+#include <stdlib.h>
+
+#include <ft2build.h>
+#include FT_FREETYPE_H
+
+typedef struct {
+    FT_Library library;
+    FT_Face face;
+} ttxp_font;
+
+typedef struct {
+    int left;
+    int top;
+    unsigned int width;
+    unsigned int rows;
+    int pitch;
+    const unsigned char *buffer;
+} ttxp_bitmap;
+
+/* The font data must remain valid until the font is freed. */
+ttxp_font *
+ttxp_font_new(const unsigned char *data, size_t len, int face_index)
+{
+    ttxp_font *font = calloc(1, sizeof(ttxp_font));
+
+    if (font == NULL)
+        return NULL;
+
+    if (FT_Init_FreeType(&font->library)) {
+        free(font);
+        return NULL;
+    }
+
+    if (FT_New_Memory_Face(font->library, data, (FT_Long) len, face_index, &font->face)) {
+        FT_Done_FreeType(font->library);
+        free(font);
+        return NULL;
+    }
+
+    return font;
+}
+
+void
+ttxp_font_free(ttxp_font *font)
+{
+    if (font == NULL)
+        return;
+
+    FT_Done_Face(font->face);
+    FT_Done_FreeType(font->library);
+    free(font);
+}
+
+/* Render a glyph at the given size in pixels. The extend and slant factors
+ * are applied as a transform, and (dx, dy) is the subpixel offset of the
+ * glyph origin, with the Y axis pointing down. On success, the bitmap is
+ * filled in and remains valid until the next call with this font. */
+int
+ttxp_font_render_glyph(ttxp_font *font, unsigned int glyph, double size_px,
+                       double extend, double slant, double dx, double dy,
+                       ttxp_bitmap *out)
+{
+    FT_Error error;
+    FT_Matrix matrix;
+    FT_Vector delta;
+    FT_GlyphSlot slot;
+
+    error = FT_Set_Char_Size(font->face, 0, (FT_F26Dot6) (size_px * 64. + 0.5), 72, 72);
+    if (error)
+        return error;
+
+    matrix.xx = (FT_Fixed) (extend * 65536.);
+    matrix.xy = (FT_Fixed) (slant * 65536.);
+    matrix.yx = 0;
+    matrix.yy = 0x10000;
+    delta.x = (FT_Pos) (dx * 64.);
+    delta.y = (FT_Pos) (-dy * 64.);
+    FT_Set_Transform(font->face, &matrix, &delta);
+
+    error = FT_Load_Glyph(font->face, glyph, FT_LOAD_NO_HINTING | FT_LOAD_NO_BITMAP | FT_LOAD_RENDER);
+    if (error)
+        return error;
+
+    slot = font->face->glyph;
+
+    /* Empty glyphs, such as spaces, may not have a meaningful pixel mode. */
+    if (slot->bitmap.width > 0 && slot->bitmap.rows > 0 &&
+        slot->bitmap.pixel_mode != FT_PIXEL_MODE_GRAY)
+        return -1;
+
+    out->left = slot->bitmap_left;
+    out->top = slot->bitmap_top;
+    out->width = slot->bitmap.width;
+    out->rows = slot->bitmap.rows;
+    out->pitch = slot->bitmap.pitch;
+    out->buffer = slot->bitmap.buffer;
+    return 0;
+}
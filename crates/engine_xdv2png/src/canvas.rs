@@ -0,0 +1,141 @@
+// Copyright 2026 the Tectonic Project
+// Licensed under the MIT License.
+
+//! An RGB pixel buffer, and encoding it as PNG.
+
+use flate2::{write::ZlibEncoder, Compression, Crc};
+use std::io::{self, Write};
+use tectonic_engine_xdv2svg::specials::Color;
+
+use crate::raster::GlyphBitmap;
+
+/// An opaque RGB image with a white background.
+pub(crate) struct Canvas {
+    width: usize,
+    height: usize,
+    pixels: Vec<u8>,
+}
+
+impl Canvas {
+    pub fn new(width: usize, height: usize) -> Self {
+        Canvas {
+            width,
+            height,
+            pixels: vec![0xff; width * height * 3],
+        }
+    }
+
+    /// Create a canvas of a different size, keeping the overlapping part of
+    /// the current contents.
+    pub fn resized(&self, width: usize, height: usize) -> Self {
+        let mut new = Canvas::new(width, height);
+        let copy_width = 3 * width.min(self.width);
+
+        for y in 0..height.min(self.height) {
+            let src = 3 * y * self.width;
+            let dst = 3 * y * width;
+            new.pixels[dst..dst + copy_width].copy_from_slice(&self.pixels[src..src + copy_width]);
+        }
+
+        new
+    }
+
+    /// Paint a pixel with the given color and coverage, between 0 and 1.
+    fn blend(&mut self, x: i64, y: i64, color: Color, coverage: f32) {
+        if x < 0 || y < 0 || x >= self.width as i64 || y >= self.height as i64 || coverage <= 0. {
+            return;
+        }
+
+        let ofs = 3 * (y as usize * self.width + x as usize);
+        let coverage = coverage.min(1.);
+
+        for (i, c) in [color.0, color.1, color.2].iter().enumerate() {
+            let dst = self.pixels[ofs + i] as f32;
+            let src = c.clamp(0., 1.) * 255.;
+            self.pixels[ofs + i] = (dst + (src - dst) * coverage).round() as u8;
+        }
+    }
+
+    /// Paint a glyph coverage bitmap whose top-left corner is at the given
+    /// pixel.
+    pub fn draw_bitmap(&mut self, x0: i64, y0: i64, bm: &GlyphBitmap, color: Color, alpha: f32) {
+        for row in 0..bm.rows {
+            for col in 0..bm.width {
+                let v = bm.buffer[row * bm.pitch + col];
+
+                if v != 0 {
+                    let coverage = v as f32 / 255. * alpha;
+                    self.blend(x0 + col as i64, y0 + row as i64, color, coverage);
+                }
+            }
+        }
+    }
+
+    /// Fill a rectangle, given in fractional pixels, antialiasing its edges.
+    pub fn fill_rect(&mut self, x0: f64, y0: f64, x1: f64, y1: f64, color: Color) {
+        let overlap = |lo: f64, hi: f64, p: f64| (hi.min(p + 1.) - lo.max(p)).max(0.);
+
+        for y in (y0.floor() as i64)..(y1.ceil() as i64) {
+            let cy = overlap(y0, y1, y as f64);
+
+            for x in (x0.floor() as i64)..(x1.ceil() as i64) {
+                let cx = overlap(x0, x1, x as f64);
+                self.blend(x, y, color, (cx * cy) as f32);
+            }
+        }
+    }
+
+    /// Encode the canvas as a PNG file.
+    pub fn write_png<W: Write>(&self, mut dest: W) -> io::Result<()> {
+        fn chunk<W: Write>(dest: &mut W, kind: &[u8], data: &[u8]) -> io::Result<()> {
+            let mut crc = Crc::new();
+            crc.update(kind);
+            crc.update(data);
+            dest.write_all(&(data.len() as u32).to_be_bytes())?;
+            dest.write_all(kind)?;
+            dest.write_all(data)?;
+            dest.write_all(&crc.sum().to_be_bytes())
+        }
+
+        dest.write_all(b"\x89PNG\r\n\x1a\n")?;
+
+        // 8-bit RGB, no interlacing.
+        let mut ihdr = Vec::with_capacity(13);
+        ihdr.extend_from_slice(&(self.width as u32).to_be_bytes());
+        ihdr.extend_from_slice(&(self.height as u32).to_be_bytes());
+        ihdr.extend_from_slice(&[8, 2, 0, 0, 0]);
+        chunk(&mut dest, b"IHDR", &ihdr)?;
+
+        // Each row gets a filter-type byte; we don't use any filtering.
+        let mut enc = ZlibEncoder::new(Vec::new(), Compression::default());
+        let stride = 3 * self.width;
+
+        for row in self.pixels.chunks(stride.max(1)) {
+            enc.write_all(&[0])?;
+            enc.write_all(row)?;
+        }
+
+        chunk(&mut dest, b"IDAT", &enc.finish()?)?;
+        chunk(&mut dest, b"IEND", &[])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn png_structure() {
+        let mut c = Canvas::new(4, 2);
+        c.fill_rect(1., 0., 2.5, 2., Color::BLACK);
+        assert_eq!(
+            &c.pixels[..12],
+            &[255, 255, 255, 0, 0, 0, 128, 128, 128, 255, 255, 255]
+        );
+
+        let mut png = Vec::new();
+        c.write_png(&mut png).unwrap();
+        assert!(png.starts_with(b"\x89PNG\r\n\x1a\n\0\0\0\x0dIHDR\0\0\0\x04\0\0\0\x02"));
+        assert!(png.ends_with(b"IEND\xae\x42\x60\x82"));
+    }
+}
@@ -0,0 +1,581 @@
+// Copyright 2026 the Tectonic Project
+// Licensed under the MIT License.
+
+#![deny(missing_docs)]
+
+//! Rasterize XDV files into PNG images.
+//!
+//! Each selected page of the input becomes a PNG image at a chosen resolution,
+//! which is handy for previews and thumbnails. Glyphs are rendered with
+//! FreeType, rules are drawn as antialiased rectangles, and color specials are
+//! honored. The page size and specials are handled the same way as in the
+//! [`tectonic_engine_xdv2svg`] engine.
+//!
+//! Only native (OpenType and TrueType) fonts can be drawn. Characters set in
+//! TFM-based fonts are an error, unless the engine is told to allow missing
+//! glyphs. Embedded images aren't drawn either. Anything that is left out of
+//! the output is listed in the [`Xdv2PngOutcome`] returned by the engine.
+
+use std::{
+    collections::{HashMap, HashSet},
+    io::Read,
+    str::FromStr,
+};
+use tectonic_bridge_core::DriverHooks;
+use tectonic_engine_xdv2svg::specials::{parse_paper_spec, Color, Special};
+use tectonic_errors::prelude::*;
+use tectonic_io_base::OpenResult;
+use tectonic_status_base::{tt_warning, StatusBackend};
+use tectonic_xdv::{FileType, XdvEvents, XdvParser};
+
+mod canvas;
+mod raster;
+
+use self::{canvas::Canvas, raster::RasterFont};
+
+/// The number of big points per TeX scaled point.
+const BP_PER_SP: f64 = 72. / 72.27 / 65536.;
+
+/// The position of the XDV origin relative to the top-left corner of the page,
+/// in big points.
+const ORIGIN_BP: f64 = 72.;
+
+/// A selection of pages, such as `1,3-5,8-`.
+///
+/// Pages are numbered sequentially from 1, regardless of the page numbers that
+/// TeX assigned to them. The default selection contains every page.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct PageSelection {
+    /// Inclusive ranges of pages, where an upper bound of None is unlimited.
+    /// If empty, all pages are selected.
+    ranges: Vec<(usize, Option<usize>)>,
+}
+
+impl PageSelection {
+    /// Test whether the given page number is selected.
+    pub fn contains(&self, page: usize) -> bool {
+        self.ranges.is_empty()
+            || self
+                .ranges
+                .iter()
+                .any(|(lo, hi)| page >= *lo && page <= hi.unwrap_or(usize::MAX))
+    }
+}
+
+impl FromStr for PageSelection {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let mut ranges = Vec::new();
+
+        for item in s.split(',') {
+            let item = item.trim();
+
+            let parse = |t: &str| -> Result<usize> {
+                let n: usize = atry!(t.trim().parse(); ["invalid page number `{}`", t.trim()]);
+                ensure!(n > 0, "page numbers start at 1");
+                Ok(n)
+            };
+
+            let range = match item.split_once('-') {
+                Some((lo, hi)) => {
+                    let lo = parse(lo)?;
+                    let hi = if hi.trim().is_empty() {
+                        None
+                    } else {
+                        Some(parse(hi)?)
+                    };
+                    (lo, hi)
+                }
+
+                None => {
+                    let n = parse(item)?;
+                    (n, Some(n))
+                }
+            };
+
+            ranges.push(range);
+        }
+
+        Ok(PageSelection { ranges })
+    }
+}
+
+/// A summary of the results of a PNG conversion.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Xdv2PngOutcome {
+    /// The number of images written.
+    pub n_written: usize,
+
+    /// The paths of the images that were left out of the output, in the order
+    /// in which they first appear.
+    pub omitted_images: Vec<String>,
+
+    /// The number of characters in TFM-based fonts that were left out of the
+    /// output.
+    pub omitted_chars: usize,
+}
+
+/// The main engine for converting XDV to PNG.
+#[derive(Debug)]
+pub struct Xdv2PngEngine {
+    paper_spec: String,
+    dpi: f64,
+    pages: PageSelection,
+    allow_missing_glyphs: bool,
+}
+
+impl Default for Xdv2PngEngine {
+    fn default() -> Self {
+        Xdv2PngEngine {
+            paper_spec: "letter".to_owned(),
+            dpi: 96.,
+            pages: PageSelection::default(),
+            allow_missing_glyphs: false,
+        }
+    }
+}
+
+impl Xdv2PngEngine {
+    /// Set the default paper size.
+    ///
+    /// This accepts the same specifications as
+    /// [`tectonic_engine_xdv2svg::Xdv2SvgEngine::paper_spec`]. The default is
+    /// `letter`.
+    pub fn paper_spec(&mut self, spec: String) -> &mut Self {
+        self.paper_spec = spec;
+        self
+    }
+
+    /// Set the resolution of the output images, in pixels per inch. The
+    /// default is 96.
+    pub fn dpi(&mut self, dpi: f64) -> &mut Self {
+        self.dpi = dpi;
+        self
+    }
+
+    /// Set which pages to render. By default, all pages are rendered.
+    pub fn pages(&mut self, pages: PageSelection) -> &mut Self {
+        self.pages = pages;
+        self
+    }
+
+    /// Set whether characters that can't be drawn are allowed.
+    ///
+    /// Characters set in TFM-based fonts can't be drawn. By default they are
+    /// an error; if this is true, they are left out of the output and counted
+    /// in the returned [`Xdv2PngOutcome`].
+    pub fn allow_missing_glyphs(&mut self, allow: bool) -> &mut Self {
+        self.allow_missing_glyphs = allow;
+        self
+    }
+
+    /// Rasterize an XDV file into a series of PNG files.
+    ///
+    /// Page *n* of the input, counting from 1, is written to an output named
+    /// `{output_stem}-{n}.png` through the I/O layer, if it is selected. The
+    /// return value counts the images written and lists anything that was
+    /// left out of them.
+    pub fn process(
+        &mut self,
+        hooks: &mut dyn DriverHooks,
+        status: &mut dyn StatusBackend,
+        xdv: &str,
+        output_stem: &str,
+    ) -> Result<Xdv2PngOutcome> {
+        ensure!(
+            self.dpi > 0. && self.dpi <= 2400.,
+            "PNG resolution must be between 0 and 2400 DPI"
+        );
+
+        let page_size = a_ok_or!(
+            parse_paper_spec(&self.paper_spec);
+            ["unrecognized paper size specification `{}`", self.paper_spec]
+        );
+
+        let mut input = hooks.io().input_open_name(xdv, status).must_exist()?;
+
+        let outcome = {
+            let state = EngineState {
+                hooks,
+                status,
+                output_stem,
+                px_per_bp: self.dpi / 72.,
+                pages: &self.pages,
+                allow_missing_glyphs: self.allow_missing_glyphs,
+                page_size,
+                fonts: HashMap::new(),
+                font_files: HashMap::new(),
+                colors: Vec::new(),
+                base_color: Color::BLACK,
+                canvas: None,
+                n_pages: 0,
+                outcome: Xdv2PngOutcome::default(),
+                warned: HashSet::new(),
+            };
+            let (state, _) = XdvParser::process(&mut input, state)?;
+            state.outcome
+        };
+
+        let (name, digest_opt) = input.into_name_digest();
+        hooks.event_input_closed(name, digest_opt, status);
+        Ok(outcome)
+    }
+}
+
+/// A native font defined in the XDV file.
+struct FontInfo {
+    /// The key of the file in the `font_files` map.
+    file_key: (String, u32),
+
+    /// The font size, in big points.
+    size: f64,
+
+    /// The font-specific color, if any.
+    color_rgba: Option<u32>,
+
+    /// The horizontal stretch factor.
+    extend: f64,
+
+    /// The slant factor.
+    slant: f64,
+}
+
+struct EngineState<'a> {
+    hooks: &'a mut dyn DriverHooks,
+    status: &'a mut dyn StatusBackend,
+    output_stem: &'a str,
+    px_per_bp: f64,
+    pages: &'a PageSelection,
+    allow_missing_glyphs: bool,
+
+    /// The page size in big points. Page size specials apply to the page on
+    /// which they occur and all later ones.
+    page_size: (f64, f64),
+
+    fonts: HashMap<i32, FontInfo>,
+
+    /// Loaded font files, keyed by path and face index. None indicates a file
+    /// that couldn't be loaded.
+    font_files: HashMap<(String, u32), Option<RasterFont>>,
+
+    /// The color stack. Like in `xdvipdfmx`, it persists across pages.
+    colors: Vec<Color>,
+
+    /// The color at the bottom of the stack.
+    base_color: Color,
+
+    /// The image of the current page, if it is selected for output.
+    canvas: Option<Canvas>,
+
+    n_pages: usize,
+
+    /// What we've written, and left out, so far.
+    outcome: Xdv2PngOutcome,
+
+    /// Warnings that have already been issued, to avoid repeating them.
+    warned: HashSet<String>,
+}
+
+impl<'a> EngineState<'a> {
+    fn warn_once(&mut self, message: String) {
+        if self.warned.insert(message.clone()) {
+            tt_warning!(self.status, "{}", message);
+        }
+    }
+
+    fn current_color(&self) -> Color {
+        self.colors.last().copied().unwrap_or(self.base_color)
+    }
+
+    /// Convert a TeX position to a pixel position.
+    fn to_px(&self, v: i32) -> f64 {
+        (ORIGIN_BP + v as f64 * BP_PER_SP) * self.px_per_bp
+    }
+
+    fn canvas_size(&self) -> (usize, usize) {
+        (
+            (self.page_size.0 * self.px_per_bp).ceil().max(1.) as usize,
+            (self.page_size.1 * self.px_per_bp).ceil().max(1.) as usize,
+        )
+    }
+
+    fn load_font_file(&mut self, name: &str, face_index: u32) -> Option<RasterFont> {
+        let io = self.hooks.io();
+
+        // Same lookup strategy as spx2html.
+        for ext in &["", ".otf"] {
+            let texpath = format!("{name}{ext}");
+
+            let mut ih = match io.input_open_name(&texpath, self.status) {
+                OpenResult::Ok(h) => h,
+                OpenResult::NotAvailable => continue,
+                OpenResult::Err(e) => {
+                    tt_warning!(self.status, "error opening font file `{}`", texpath; e);
+                    return None;
+                }
+            };
+
+            let mut data = Vec::new();
+            let read_result = ih.read_to_end(&mut data);
+            let (name, digest_opt) = ih.into_name_digest();
+            self.hooks.event_input_closed(name, digest_opt, self.status);
+
+            if let Err(e) = read_result {
+                tt_warning!(self.status, "error reading font file `{}`", texpath; Error::from(e));
+                return None;
+            }
+
+            let font = RasterFont::new(data, face_index);
+
+            if font.is_none() {
+                tt_warning!(self.status, "unable to load font file `{}`", texpath);
+            }
+
+            return font;
+        }
+
+        tt_warning!(self.status, "unable to find font file `{}`", name);
+        None
+    }
+
+    fn draw_glyphs(&mut self, font_num: i32, glyphs: &[u16], xs: &[i32], ys: &[i32]) -> Result<()> {
+        if self.canvas.is_none() {
+            return Ok(());
+        }
+
+        let font = match self.fonts.get(&font_num) {
+            Some(f) => f,
+            None => {
+                self.warn_once(format!("ignoring glyphs from undefined font #{font_num}"));
+                return Ok(());
+            }
+        };
+
+        let (color, alpha) = match font.color_rgba {
+            Some(rgba) => {
+                let c = |shift: u32| ((rgba >> shift) & 0xff) as f32 / 255.;
+                (Color(c(24), c(16), c(8)), c(0))
+            }
+            None => (self.current_color(), 1.),
+        };
+
+        let size_px = font.size * self.px_per_bp;
+        let (extend, slant) = (font.extend, font.slant);
+        let positions: Vec<_> = xs
+            .iter()
+            .zip(ys)
+            .map(|(&x, &y)| (self.to_px(x), self.to_px(y)))
+            .collect();
+
+        let file = match self.font_files.get_mut(&font.file_key) {
+            Some(Some(f)) => f,
+            _ => return Ok(()),
+        };
+
+        // Checked above.
+        let canvas = self.canvas.as_mut().unwrap();
+
+        for (&glyph, &(x, y)) in glyphs.iter().zip(&positions) {
+            let (ix, iy) = (x.floor(), y.floor());
+            let bm = file.render_glyph(glyph, size_px, extend, slant, x - ix, y - iy)?;
+            let x0 = ix as i64 + bm.left as i64;
+            let y0 = iy as i64 - bm.top as i64;
+            canvas.draw_bitmap(x0, y0, &bm, color, alpha);
+        }
+
+        Ok(())
+    }
+
+    fn write_page(&mut self, canvas: Canvas) -> Result<()> {
+        let out_name = format!("{}-{}.png", self.output_stem, self.n_pages);
+        let mut output = self.hooks.io().output_open_name(&out_name).must_exist()?;
+        atry!(
+            canvas.write_png(&mut output);
+            ["error writing PNG output `{}`", out_name]
+        );
+        let (name, digest) = output.into_name_digest();
+        self.hooks.event_output_closed(name, digest, self.status);
+        self.outcome.n_written += 1;
+        Ok(())
+    }
+}
+
+impl<'a> XdvEvents for EngineState<'a> {
+    type Error = Error;
+
+    fn handle_header(&mut self, filetype: FileType, _comment: &[u8]) -> Result<()> {
+        if filetype != FileType::Xdv {
+            bail!("file should be XDV format but got {}", filetype);
+        }
+
+        Ok(())
+    }
+
+    fn handle_begin_page(&mut self, _counters: &[i32], _previous_bop: i32) -> Result<()> {
+        self.n_pages += 1;
+
+        self.canvas = if self.pages.contains(self.n_pages) {
+            let (w, h) = self.canvas_size();
+            Some(Canvas::new(w, h))
+        } else {
+            None
+        };
+
+        Ok(())
+    }
+
+    fn handle_end_page(&mut self) -> Result<()> {
+        if let Some(canvas) = self.canvas.take() {
+            self.write_page(canvas)?;
+        }
+
+        Ok(())
+    }
+
+    fn handle_special(&mut self, _x: i32, _y: i32, contents: &[u8]) -> Result<()> {
+        let contents = atry!(std::str::from_utf8(contents); ["could not parse \\special as UTF-8"]);
+
+        match Special::parse(contents) {
+            Special::ColorPush(c) => self.colors.push(c),
+
+            Special::ColorPop => {
+                if self.colors.pop().is_none() {
+                    self.warn_once("color stack underflow".to_owned());
+                }
+            }
+
+            Special::ColorSet(c) => match self.colors.last_mut() {
+                Some(top) => *top = c,
+                None => self.base_color = c,
+            },
+
+            Special::PageSize(w, h) => {
+                self.page_size = (w, h);
+                let (w, h) = self.canvas_size();
+
+                if let Some(canvas) = self.canvas.as_mut() {
+                    *canvas = canvas.resized(w, h);
+                }
+            }
+
+            Special::Image(spec) => {
+                let omitted = &mut self.outcome.omitted_images;
+
+                if self.canvas.is_some() && !omitted.iter().any(|p| p == spec.path) {
+                    omitted.push(spec.path.to_owned());
+                }
+            }
+
+            Special::Unsupported(text) => {
+                self.warn_once(format!("ignoring unsupported special `{text}`"))
+            }
+
+            Special::Ignored => {}
+        }
+
+        Ok(())
+    }
+
+    fn handle_char_run(&mut self, _font_num: i32, chars: &[i32]) -> Result<()> {
+        if self.canvas.is_none() {
+            return Ok(());
+        }
+
+        ensure!(
+            self.allow_missing_glyphs,
+            "PNG output only supports native fonts, but the document uses TFM-based fonts \
+             (use `--allow-missing-glyphs` to omit their characters)"
+        );
+        self.outcome.omitted_chars += chars.len();
+        Ok(())
+    }
+
+    fn handle_glyph_run(
+        &mut self,
+        font_num: i32,
+        glyphs: &[u16],
+        x: &[i32],
+        y: &[i32],
+    ) -> Result<()> {
+        self.draw_glyphs(font_num, glyphs, x, y)
+    }
+
+    fn handle_text_and_glyphs(
+        &mut self,
+        font_num: i32,
+        _text: &str,
+        _width: i32,
+        glyphs: &[u16],
+        x: &[i32],
+        y: &[i32],
+    ) -> Result<()> {
+        self.draw_glyphs(font_num, glyphs, x, y)
+    }
+
+    fn handle_define_native_font(
+        &mut self,
+        name: &str,
+        font_num: i32,
+        size: i32,
+        face_index: u32,
+        color_rgba: Option<u32>,
+        extend: Option<u32>,
+        slant: Option<u32>,
+        _embolden: Option<u32>,
+    ) -> Result<()> {
+        let file_key = (name.to_owned(), face_index);
+
+        if !self.font_files.contains_key(&file_key) {
+            let file = self.load_font_file(name, face_index);
+            self.font_files.insert(file_key.clone(), file);
+        }
+
+        // Extend and slant are 16.16 fixed-point values.
+        let fixed =
+            |v: Option<u32>, default: f64| v.map(|v| v as i32 as f64 / 65536.).unwrap_or(default);
+
+        self.fonts.insert(
+            font_num,
+            FontInfo {
+                file_key,
+                size: size as f64 * BP_PER_SP,
+                color_rgba,
+                extend: fixed(extend, 1.),
+                slant: fixed(slant, 0.),
+            },
+        );
+
+        Ok(())
+    }
+
+    fn handle_rule(&mut self, x: i32, y: i32, height: i32, width: i32) -> Result<()> {
+        let color = self.current_color();
+        let (x0, y0) = (self.to_px(x), self.to_px(y - height));
+        let (x1, y1) = (self.to_px(x + width), self.to_px(y));
+
+        if let Some(canvas) = self.canvas.as_mut() {
+            canvas.fill_rect(x0, y0, x1, y1, color);
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn page_selection() {
+        let sel: PageSelection = "1, 3-4,7-".parse().unwrap();
+        assert!(sel.contains(1));
+        assert!(!sel.contains(2));
+        assert!(sel.contains(4));
+        assert!(!sel.contains(5));
+        assert!(sel.contains(100));
+        assert!(PageSelection::default().contains(5));
+        assert!("0".parse::<PageSelection>().is_err());
+        assert!("x-2".parse::<PageSelection>().is_err());
+    }
+}
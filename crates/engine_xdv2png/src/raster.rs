@@ -0,0 +1,168 @@
+// Copyright 2026 the Tectonic Project
+// Licensed under the MIT License.
+
+//! Glyph rendering with FreeType.
+//!
+//! The FreeType API is accessed through a small C shim, since its data
+//! structures are awkward to mirror from Rust.
+
+use libc::{c_int, c_uint, size_t};
+use std::slice;
+use tectonic_errors::prelude::*;
+
+#[allow(unused_imports)]
+#[allow(clippy::single_component_path_imports)]
+use tectonic_bridge_freetype2;
+
+#[repr(C)]
+struct TtxpFont {
+    _private: [u8; 0],
+}
+
+#[repr(C)]
+struct TtxpBitmap {
+    left: c_int,
+    top: c_int,
+    width: c_uint,
+    rows: c_uint,
+    pitch: c_int,
+    buffer: *const u8,
+}
+
+extern "C" {
+    fn ttxp_font_new(data: *const u8, len: size_t, face_index: c_int) -> *mut TtxpFont;
+    fn ttxp_font_free(font: *mut TtxpFont);
+    fn ttxp_font_render_glyph(
+        font: *mut TtxpFont,
+        glyph: c_uint,
+        size_px: f64,
+        extend: f64,
+        slant: f64,
+        dx: f64,
+        dy: f64,
+        out: *mut TtxpBitmap,
+    ) -> c_int;
+}
+
+/// A font loaded into FreeType.
+pub(crate) struct RasterFont {
+    handle: *mut TtxpFont,
+
+    /// FreeType reads the font data directly from this buffer, so it must
+    /// outlive the handle.
+    _data: Vec<u8>,
+}
+
+/// A rendered glyph: an 8-bit coverage map.
+pub(crate) struct GlyphBitmap<'a> {
+    /// The horizontal offset of the left edge of the bitmap from the integer
+    /// pixel origin.
+    pub left: i32,
+
+    /// The vertical offset of the top edge of the bitmap above the integer
+    /// pixel origin.
+    pub top: i32,
+
+    pub width: usize,
+    pub rows: usize,
+    pub pitch: usize,
+    pub buffer: &'a [u8],
+}
+
+impl RasterFont {
+    /// Load a font from its file contents. Returns None if FreeType can't
+    /// handle it.
+    pub fn new(data: Vec<u8>, face_index: u32) -> Option<Self> {
+        let handle = unsafe { ttxp_font_new(data.as_ptr(), data.len(), face_index as c_int) };
+
+        if handle.is_null() {
+            None
+        } else {
+            Some(RasterFont {
+                handle,
+                _data: data,
+            })
+        }
+    }
+
+    /// Render a glyph.
+    ///
+    /// The size is the em size in pixels, and `(dx, dy)` is the fractional
+    /// pixel position of the glyph origin, with the Y axis pointing down.
+    #[allow(clippy::too_many_arguments)]
+    pub fn render_glyph(
+        &mut self,
+        glyph: u16,
+        size_px: f64,
+        extend: f64,
+        slant: f64,
+        dx: f64,
+        dy: f64,
+    ) -> Result<GlyphBitmap<'_>> {
+        let mut bm = TtxpBitmap {
+            left: 0,
+            top: 0,
+            width: 0,
+            rows: 0,
+            pitch: 0,
+            buffer: std::ptr::null(),
+        };
+
+        let error = unsafe {
+            ttxp_font_render_glyph(
+                self.handle,
+                glyph as c_uint,
+                size_px,
+                extend,
+                slant,
+                dx,
+                dy,
+                &mut bm,
+            )
+        };
+
+        ensure!(
+            error == 0,
+            "FreeType error {} rendering glyph {}",
+            error,
+            glyph
+        );
+
+        let (width, rows) = (bm.width as usize, bm.rows as usize);
+
+        if width == 0 || rows == 0 || bm.buffer.is_null() {
+            return Ok(GlyphBitmap {
+                left: 0,
+                top: 0,
+                width: 0,
+                rows: 0,
+                pitch: 0,
+                buffer: &[],
+            });
+        }
+
+        ensure!(
+            bm.pitch as usize >= width,
+            "unexpected bitmap layout rendering glyph {}",
+            glyph
+        );
+
+        let pitch = bm.pitch as usize;
+        let buffer = unsafe { slice::from_raw_parts(bm.buffer, pitch * rows) };
+
+        Ok(GlyphBitmap {
+            left: bm.left,
+            top: bm.top,
+            width,
+            rows,
+            pitch,
+            buffer,
+        })
+    }
+}
+
+impl Drop for RasterFont {
+    fn drop(&mut self) {
+        unsafe { ttxp_font_free(self.handle) };
+    }
+}
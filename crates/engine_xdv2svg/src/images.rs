@@ -0,0 +1,137 @@
+// Copyright 2026 the Tectonic Project
+// Licensed under the MIT License.
+
+//! Embedding raster images in the SVG output.
+//!
+//! We only need to understand enough of the image formats to determine their
+//! natural sizes, which `xdvipdfmx` derives from the resolution information in
+//! the files, defaulting to 72 DPI.
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use std::convert::TryInto;
+
+/// A raster image that can be embedded in an SVG file.
+#[derive(Clone, Debug)]
+pub(crate) struct Image {
+    /// The image data, as a `data:` URI.
+    pub uri: String,
+
+    /// The natural width of the image, in big points.
+    pub width: f64,
+
+    /// The natural height of the image, in big points.
+    pub height: f64,
+}
+
+impl Image {
+    /// Try to load an image from its file contents.
+    ///
+    /// Returns None if the image format isn't supported.
+    pub fn new(data: &[u8]) -> Option<Image> {
+        let (mime, width, height) = if data.starts_with(b"\x89PNG\r\n\x1a\n") {
+            let (w, h) = png_size(data)?;
+            ("image/png", w, h)
+        } else if data.starts_with(&[0xff, 0xd8]) {
+            let (w, h) = jpeg_size(data)?;
+            ("image/jpeg", w, h)
+        } else {
+            return None;
+        };
+
+        Some(Image {
+            uri: format!("data:{};base64,{}", mime, STANDARD.encode(data)),
+            width,
+            height,
+        })
+    }
+}
+
+fn be16(data: &[u8], ofs: usize) -> Option<u16> {
+    Some(u16::from_be_bytes(data.get(ofs..ofs + 2)?.try_into().ok()?))
+}
+
+fn be32(data: &[u8], ofs: usize) -> Option<u32> {
+    Some(u32::from_be_bytes(data.get(ofs..ofs + 4)?.try_into().ok()?))
+}
+
+/// Get the natural size of a PNG image, in big points.
+fn png_size(data: &[u8]) -> Option<(f64, f64)> {
+    let mut ofs = 8;
+    let mut pixels = None;
+    let mut dpi = (72., 72.);
+
+    while ofs + 8 <= data.len() {
+        let len = be32(data, ofs)? as usize;
+        let kind = data.get(ofs + 4..ofs + 8)?;
+        let body = ofs + 8;
+
+        match kind {
+            b"IHDR" => pixels = Some((be32(data, body)?, be32(data, body + 4)?)),
+
+            // Unit 1 means pixels per meter; otherwise only the aspect ratio
+            // is specified, which we ignore like xdvipdfmx does.
+            b"pHYs" if *data.get(body + 8)? == 1 => {
+                let to_dpi = |ppm: u32| ppm as f64 * 0.0254;
+                dpi = (to_dpi(be32(data, body)?), to_dpi(be32(data, body + 4)?));
+            }
+
+            b"IDAT" | b"IEND" => break,
+            _ => {}
+        }
+
+        ofs = body + len + 4;
+    }
+
+    let (w, h) = pixels?;
+    Some((w as f64 * 72. / dpi.0, h as f64 * 72. / dpi.1))
+}
+
+/// Get the natural size of a JPEG image, in big points.
+fn jpeg_size(data: &[u8]) -> Option<(f64, f64)> {
+    let mut ofs = 2;
+    let mut dpi = (72., 72.);
+
+    loop {
+        if *data.get(ofs)? != 0xff {
+            return None;
+        }
+
+        let marker = *data.get(ofs + 1)?;
+
+        if marker == 0xff {
+            // Fill byte.
+            ofs += 1;
+            continue;
+        }
+
+        let len = be16(data, ofs + 2)? as usize;
+        let body = ofs + 4;
+
+        match marker {
+            // JFIF header, which gives the resolution.
+            0xe0 if data.get(body..body + 5)? == b"JFIF\0" => {
+                let (x, y) = (be16(data, body + 8)?, be16(data, body + 10)?);
+
+                match data.get(body + 7)? {
+                    1 if x > 0 && y > 0 => dpi = (x as f64, y as f64),
+                    2 if x > 0 && y > 0 => dpi = (x as f64 * 2.54, y as f64 * 2.54),
+                    _ => {}
+                }
+            }
+
+            // Start-of-frame markers, which give the dimensions. Markers C4,
+            // C8, and CC are something else.
+            0xc0..=0xcf if marker != 0xc4 && marker != 0xc8 && marker != 0xcc => {
+                let h = be16(data, body + 1)?;
+                let w = be16(data, body + 3)?;
+                return Some((w as f64 * 72. / dpi.0, h as f64 * 72. / dpi.1));
+            }
+
+            // Start of scan: we've gone too far.
+            0xda => return None,
+            _ => {}
+        }
+
+        ofs = body + len - 2;
+    }
+}
@@ -0,0 +1,592 @@
+// Copyright 2026 the Tectonic Project
+// Licensed under the MIT License.
+
+#![deny(missing_docs)]
+
+//! Convert XDV files to SVG images.
+//!
+//! Each page of the input becomes a standalone SVG document. Glyphs are drawn
+//! from their outlines, which are embedded in the document as `<path>`
+//! definitions: only the glyphs actually used on a page are included, so each
+//! page carries its own minimal subset of each font and doesn't depend on any
+//! external files. Rules are drawn as rectangles, and the most commonly used
+//! `\special`s are supported: color changes, page sizes, and PNG and JPEG
+//! images, which are embedded as `data:` URIs.
+//!
+//! Only native (OpenType and TrueType) fonts can be drawn. Characters set in
+//! TFM-based fonts are an error, unless the engine is told to allow missing
+//! glyphs, in which case they're skipped with a warning.
+
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::Write as FmtWrite,
+    io::{Read, Write},
+};
+use tectonic_bridge_core::DriverHooks;
+use tectonic_errors::prelude::*;
+use tectonic_font_outlines::{
+    svg::{svg_number as num, GlyphDefs},
+    GlyphId, Outlines,
+};
+use tectonic_io_base::OpenResult;
+use tectonic_status_base::{tt_warning, StatusBackend};
+use tectonic_xdv::{FileType, XdvEvents, XdvParser};
+
+mod images;
+pub mod specials;
+
+use self::{
+    images::Image,
+    specials::{parse_paper_spec, Color, ImageSpecial, Special},
+};
+
+/// The number of big points per TeX scaled point.
+const BP_PER_SP: f64 = 72. / 72.27 / 65536.;
+
+/// The position of the XDV origin relative to the top-left corner of the page,
+/// in big points.
+const ORIGIN_BP: f64 = 72.;
+
+/// The main engine for converting XDV to SVG.
+#[derive(Debug)]
+pub struct Xdv2SvgEngine {
+    paper_spec: String,
+    allow_missing_glyphs: bool,
+}
+
+impl Default for Xdv2SvgEngine {
+    fn default() -> Self {
+        Xdv2SvgEngine {
+            paper_spec: "letter".to_owned(),
+            allow_missing_glyphs: false,
+        }
+    }
+}
+
+impl Xdv2SvgEngine {
+    /// Set the default paper size.
+    ///
+    /// This is either the name of a standard paper size such as `letter` or
+    /// `a4`, or explicit dimensions of the form `W,H`, such as `4in,6in`. The
+    /// default is `letter`, matching `xdvipdfmx`. The document can override
+    /// this with `papersize` or `pdf:pagesize` specials.
+    pub fn paper_spec(&mut self, spec: String) -> &mut Self {
+        self.paper_spec = spec;
+        self
+    }
+
+    /// Set whether characters that can't be drawn are allowed.
+    ///
+    /// Characters set in TFM-based fonts can't be drawn. By default they are
+    /// an error; if this is true, they are omitted with a warning instead.
+    pub fn allow_missing_glyphs(&mut self, allow: bool) -> &mut Self {
+        self.allow_missing_glyphs = allow;
+        self
+    }
+
+    /// Convert an XDV file into a series of SVG files.
+    ///
+    /// Page *n* of the input, counting from 1, is written to an output named
+    /// `{output_stem}-{n}.svg` through the I/O layer. The return value is the
+    /// number of pages.
+    pub fn process(
+        &mut self,
+        hooks: &mut dyn DriverHooks,
+        status: &mut dyn StatusBackend,
+        xdv: &str,
+        output_stem: &str,
+    ) -> Result<usize> {
+        let page_size = a_ok_or!(
+            parse_paper_spec(&self.paper_spec);
+            ["unrecognized paper size specification `{}`", self.paper_spec]
+        );
+
+        let mut input = hooks.io().input_open_name(xdv, status).must_exist()?;
+
+        let n_pages = {
+            let state = EngineState {
+                hooks,
+                status,
+                output_stem,
+                page_size,
+                allow_missing_glyphs: self.allow_missing_glyphs,
+                fonts: HashMap::new(),
+                font_files: HashMap::new(),
+                images: HashMap::new(),
+                colors: Vec::new(),
+                base_color: Color::BLACK,
+                page: None,
+                n_pages: 0,
+                warned: HashSet::new(),
+            };
+            let (state, _) = XdvParser::process(&mut input, state)?;
+            state.n_pages
+        };
+
+        let (name, digest_opt) = input.into_name_digest();
+        hooks.event_input_closed(name, digest_opt, status);
+        Ok(n_pages)
+    }
+}
+
+/// A font file whose glyphs we can draw.
+struct FontFile {
+    data: Vec<u8>,
+    outlines: Outlines,
+}
+
+/// A native font defined in the XDV file.
+struct FontInfo {
+    /// The key of the file in the `font_files` map.
+    file_key: (String, u32),
+
+    /// The font size, in big points.
+    size: f64,
+
+    /// The font-specific color, if any.
+    color_rgba: Option<u32>,
+
+    /// The horizontal stretch factor.
+    extend: f64,
+
+    /// The slant factor.
+    slant: f64,
+}
+
+/// The SVG content of the page being processed.
+#[derive(Default)]
+struct Page {
+    /// The glyph outlines used on the page.
+    glyphs: GlyphDefs<((String, u32), GlyphId)>,
+
+    /// The drawing commands.
+    body: String,
+
+    /// The page size in big points, if set by the document.
+    size: Option<(f64, f64)>,
+}
+
+struct EngineState<'a> {
+    hooks: &'a mut dyn DriverHooks,
+    status: &'a mut dyn StatusBackend,
+    output_stem: &'a str,
+
+    /// The page size in big points. Page size specials apply to the page on
+    /// which they occur and all later ones.
+    page_size: (f64, f64),
+
+    /// Whether characters that can't be drawn are omitted rather than an
+    /// error.
+    allow_missing_glyphs: bool,
+
+    fonts: HashMap<i32, FontInfo>,
+
+    /// Loaded font files, keyed by path and face index. None indicates a file
+    /// that couldn't be loaded.
+    font_files: HashMap<(String, u32), Option<FontFile>>,
+
+    /// Loaded images, keyed by path. None indicates an image that couldn't be
+    /// loaded.
+    images: HashMap<String, Option<Image>>,
+
+    /// The color stack. Like in `xdvipdfmx`, it persists across pages.
+    colors: Vec<Color>,
+
+    /// The color at the bottom of the stack.
+    base_color: Color,
+
+    page: Option<Page>,
+    n_pages: usize,
+
+    /// Warnings that have already been issued, to avoid repeating them.
+    warned: HashSet<String>,
+}
+
+impl<'a> EngineState<'a> {
+    fn warn_once(&mut self, message: String) {
+        if self.warned.insert(message.clone()) {
+            tt_warning!(self.status, "{}", message);
+        }
+    }
+
+    fn page(&mut self) -> Result<&mut Page> {
+        Ok(a_ok_or!(self.page.as_mut(); ["XDV content outside of a page"]))
+    }
+
+    fn current_color(&self) -> Color {
+        self.colors.last().copied().unwrap_or(self.base_color)
+    }
+
+    /// Get the `fill` attribute to use for the current color, which is empty
+    /// for black, the SVG default.
+    fn fill_attr(&self, color_rgba: Option<u32>) -> String {
+        if let Some(rgba) = color_rgba {
+            let mut attr = format!(" fill=\"#{:06x}\"", rgba >> 8);
+            let alpha = rgba & 0xff;
+
+            if alpha != 0xff {
+                write!(attr, " fill-opacity=\"{}\"", num(alpha as f64 / 255.)).unwrap();
+            }
+
+            return attr;
+        }
+
+        match self.current_color() {
+            c if c == Color::BLACK => String::new(),
+            c => format!(" fill=\"{}\"", c.to_css()),
+        }
+    }
+
+    fn load_font_file(&mut self, name: &str, face_index: u32) -> Option<FontFile> {
+        let io = self.hooks.io();
+
+        // Same lookup strategy as spx2html.
+        for ext in &["", ".otf"] {
+            let texpath = format!("{name}{ext}");
+
+            let mut ih = match io.input_open_name(&texpath, self.status) {
+                OpenResult::Ok(h) => h,
+                OpenResult::NotAvailable => continue,
+                OpenResult::Err(e) => {
+                    tt_warning!(self.status, "error opening font file `{}`", texpath; e);
+                    return None;
+                }
+            };
+
+            let mut data = Vec::new();
+            let read_result = ih.read_to_end(&mut data);
+            let (name, digest_opt) = ih.into_name_digest();
+            self.hooks.event_input_closed(name, digest_opt, self.status);
+
+            if let Err(e) = read_result {
+                tt_warning!(self.status, "error reading font file `{}`", texpath; Error::from(e));
+                return None;
+            }
+
+            let outlines = Outlines::new(&data, face_index);
+
+            if outlines.is_none() {
+                tt_warning!(
+                    self.status,
+                    "unable to read glyph outlines from font file `{}`",
+                    texpath
+                );
+            }
+
+            return outlines.map(|outlines| FontFile { data, outlines });
+        }
+
+        tt_warning!(self.status, "unable to find font file `{}`", name);
+        None
+    }
+
+    fn draw_glyphs(&mut self, font_num: i32, glyphs: &[u16], xs: &[i32], ys: &[i32]) -> Result<()> {
+        let font = match self.fonts.get(&font_num) {
+            Some(f) => f,
+            None => {
+                self.warn_once(format!("ignoring glyphs from undefined font #{font_num}"));
+                return Ok(());
+            }
+        };
+
+        let file = match self.font_files.get(&font.file_key) {
+            Some(Some(f)) => f,
+            _ => return Ok(()),
+        };
+
+        let fill = self.fill_attr(font.color_rgba);
+        let scale = font.size / file.outlines.units_per_em() as f64;
+        let (a, c) = (scale * font.extend, scale * font.slant);
+        let file_key = font.file_key.clone();
+
+        // We can't use `self.page()` since we're holding borrows of other
+        // fields.
+        let page = a_ok_or!(self.page.as_mut(); ["XDV content outside of a page"]);
+
+        for ((&glyph, &x), &y) in glyphs.iter().zip(xs).zip(ys) {
+            let key = (file_key.clone(), glyph);
+
+            let def = page.glyphs.get_or_define(key, || {
+                Ok((file.outlines.glyph_outline(&file.data, glyph)?, ()))
+            })?;
+
+            if let Some(def) = def {
+                let matrix = [
+                    a,
+                    0.,
+                    c,
+                    -scale,
+                    ORIGIN_BP + x as f64 * BP_PER_SP,
+                    ORIGIN_BP + y as f64 * BP_PER_SP,
+                ];
+                def.write_use(&mut page.body, matrix, &fill);
+                page.body.push('\n');
+            }
+        }
+
+        Ok(())
+    }
+
+    fn draw_image(&mut self, x: i32, y: i32, spec: &ImageSpecial) -> Result<()> {
+        if !self.images.contains_key(spec.path) {
+            let image = self.load_image(spec.path);
+            self.images.insert(spec.path.to_owned(), image);
+        }
+
+        let image = match self.images.get(spec.path) {
+            Some(Some(i)) => i,
+            _ => return Ok(()),
+        };
+
+        // An explicit width or height overrides the natural size, preserving
+        // the aspect ratio if only one is given.
+        let (w, h) = match (spec.width, spec.height) {
+            (Some(w), Some(h)) => (w, h),
+            (Some(w), None) => (w, image.height * w / image.width),
+            (None, Some(h)) => (image.width * h / image.height, h),
+            (None, None) => (image.width, image.height),
+        };
+
+        // A degenerate image size would make the SVG invalid.
+        if !(w.is_finite() && h.is_finite() && w > 0. && h > 0.) {
+            self.warn_once(format!("skipping image `{}` with zero size", spec.path));
+            return Ok(());
+        }
+
+        let m = &spec.matrix;
+
+        // The matrix is expressed in PDF conventions, with the Y axis pointing
+        // up and the image's origin at its bottom-left corner, so we flip
+        // into and out of that coordinate system.
+        let element = format!(
+            "<image width=\"{}\" height=\"{}\" preserveAspectRatio=\"none\" \
+            transform=\"translate({} {}) scale(1 -1) matrix({} {} {} {} {} {}) translate(0 {}) scale(1 -1)\" \
+            xlink:href=\"{}\"/>",
+            num(w),
+            num(h),
+            num(ORIGIN_BP + x as f64 * BP_PER_SP),
+            num(ORIGIN_BP + y as f64 * BP_PER_SP),
+            num(m[0]),
+            num(m[1]),
+            num(m[2]),
+            num(m[3]),
+            num(m[4]),
+            num(m[5]),
+            num(h),
+            image.uri,
+        );
+
+        writeln!(self.page()?.body, "{element}").unwrap();
+        Ok(())
+    }
+
+    fn load_image(&mut self, path: &str) -> Option<Image> {
+        let mut ih = match self.hooks.io().input_open_name(path, self.status) {
+            OpenResult::Ok(h) => h,
+            OpenResult::NotAvailable => {
+                tt_warning!(self.status, "unable to find image file `{}`", path);
+                return None;
+            }
+            OpenResult::Err(e) => {
+                tt_warning!(self.status, "error opening image file `{}`", path; e);
+                return None;
+            }
+        };
+
+        let mut data = Vec::new();
+        let read_result = ih.read_to_end(&mut data);
+        let (name, digest_opt) = ih.into_name_digest();
+        self.hooks.event_input_closed(name, digest_opt, self.status);
+
+        if let Err(e) = read_result {
+            tt_warning!(self.status, "error reading image file `{}`", path; Error::from(e));
+            return None;
+        }
+
+        let image = Image::new(&data);
+
+        if image.is_none() {
+            tt_warning!(
+                self.status,
+                "image `{}` is not a PNG or JPEG file; SVG output does not support it",
+                path
+            );
+        }
+
+        image
+    }
+
+    fn write_page(&mut self, page: Page) -> Result<()> {
+        let (w, h) = page.size.unwrap_or(self.page_size);
+        let (w, h) = (num(w), num(h));
+
+        let mut svg = format!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+            <svg xmlns=\"http://www.w3.org/2000/svg\" \
+            xmlns:xlink=\"http://www.w3.org/1999/xlink\" \
+            width=\"{w}pt\" height=\"{h}pt\" viewBox=\"0 0 {w} {h}\">\n"
+        );
+
+        if !page.glyphs.defs().is_empty() {
+            writeln!(svg, "<defs>{}</defs>", page.glyphs.defs()).unwrap();
+        }
+
+        svg.push_str("<rect width=\"100%\" height=\"100%\" fill=\"white\"/>\n");
+        svg.push_str(&page.body);
+        svg.push_str("</svg>\n");
+
+        let out_name = format!("{}-{}.svg", self.output_stem, self.n_pages);
+        let mut output = self.hooks.io().output_open_name(&out_name).must_exist()?;
+        atry!(
+            output.write_all(svg.as_bytes());
+            ["error writing SVG output `{}`", out_name]
+        );
+        let (name, digest) = output.into_name_digest();
+        self.hooks.event_output_closed(name, digest, self.status);
+        Ok(())
+    }
+}
+
+impl<'a> XdvEvents for EngineState<'a> {
+    type Error = Error;
+
+    fn handle_header(&mut self, filetype: FileType, _comment: &[u8]) -> Result<()> {
+        if filetype != FileType::Xdv {
+            bail!("file should be XDV format but got {}", filetype);
+        }
+
+        Ok(())
+    }
+
+    fn handle_begin_page(&mut self, _counters: &[i32], _previous_bop: i32) -> Result<()> {
+        self.n_pages += 1;
+        self.page = Some(Page::default());
+        Ok(())
+    }
+
+    fn handle_end_page(&mut self) -> Result<()> {
+        let page = a_ok_or!(self.page.take(); ["XDV page ended without beginning"]);
+        self.write_page(page)
+    }
+
+    fn handle_special(&mut self, x: i32, y: i32, contents: &[u8]) -> Result<()> {
+        let contents = atry!(std::str::from_utf8(contents); ["could not parse \\special as UTF-8"]);
+
+        match Special::parse(contents) {
+            Special::ColorPush(c) => self.colors.push(c),
+
+            Special::ColorPop => {
+                if self.colors.pop().is_none() {
+                    self.warn_once("color stack underflow".to_owned());
+                }
+            }
+
+            Special::ColorSet(c) => match self.colors.last_mut() {
+                Some(top) => *top = c,
+                None => self.base_color = c,
+            },
+
+            Special::PageSize(w, h) => {
+                self.page_size = (w, h);
+                self.page()?.size = Some((w, h));
+            }
+
+            Special::Image(spec) => self.draw_image(x, y, &spec)?,
+
+            Special::Unsupported(text) => {
+                self.warn_once(format!("ignoring unsupported special `{text}`"))
+            }
+
+            Special::Ignored => {}
+        }
+
+        Ok(())
+    }
+
+    fn handle_char_run(&mut self, _font_num: i32, _chars: &[i32]) -> Result<()> {
+        ensure!(
+            self.allow_missing_glyphs,
+            "SVG output only supports native fonts, but the document uses TFM-based fonts \
+             (use `--allow-missing-glyphs` to omit their characters)"
+        );
+        self.warn_once(
+            "SVG output only supports native fonts; characters in TFM-based fonts are omitted"
+                .to_owned(),
+        );
+        Ok(())
+    }
+
+    fn handle_glyph_run(
+        &mut self,
+        font_num: i32,
+        glyphs: &[u16],
+        x: &[i32],
+        y: &[i32],
+    ) -> Result<()> {
+        self.draw_glyphs(font_num, glyphs, x, y)
+    }
+
+    fn handle_text_and_glyphs(
+        &mut self,
+        font_num: i32,
+        _text: &str,
+        _width: i32,
+        glyphs: &[u16],
+        x: &[i32],
+        y: &[i32],
+    ) -> Result<()> {
+        self.draw_glyphs(font_num, glyphs, x, y)
+    }
+
+    fn handle_define_native_font(
+        &mut self,
+        name: &str,
+        font_num: i32,
+        size: i32,
+        face_index: u32,
+        color_rgba: Option<u32>,
+        extend: Option<u32>,
+        slant: Option<u32>,
+        _embolden: Option<u32>,
+    ) -> Result<()> {
+        let file_key = (name.to_owned(), face_index);
+
+        if !self.font_files.contains_key(&file_key) {
+            let file = self.load_font_file(name, face_index);
+            self.font_files.insert(file_key.clone(), file);
+        }
+
+        // Extend and slant are 16.16 fixed-point values.
+        let fixed =
+            |v: Option<u32>, default: f64| v.map(|v| v as i32 as f64 / 65536.).unwrap_or(default);
+
+        self.fonts.insert(
+            font_num,
+            FontInfo {
+                file_key,
+                size: size as f64 * BP_PER_SP,
+                color_rgba,
+                extend: fixed(extend, 1.),
+                slant: fixed(slant, 0.),
+            },
+        );
+
+        Ok(())
+    }
+
+    fn handle_rule(&mut self, x: i32, y: i32, height: i32, width: i32) -> Result<()> {
+        let fill = self.fill_attr(None);
+
+        writeln!(
+            self.page()?.body,
+            "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\"{}/>",
+            num(ORIGIN_BP + x as f64 * BP_PER_SP),
+            num(ORIGIN_BP + (y - height) as f64 * BP_PER_SP),
+            num(width as f64 * BP_PER_SP),
+            num(height as f64 * BP_PER_SP),
+            fill,
+        )
+        .unwrap();
+        Ok(())
+    }
+}
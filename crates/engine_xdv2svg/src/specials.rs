@@ -0,0 +1,312 @@
+// Copyright 2026 the Tectonic Project
+// Licensed under the MIT License.
+
+//! Parsing the `\special`s that the SVG engine understands.
+//!
+//! These follow the conventions of `xdvipdfmx`, since that is what the macro
+//! packages used with XeTeX target. This module is public so that other
+//! engines that draw XDV pages can share it.
+
+/// A special that affects the SVG output.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Special<'a> {
+    /// `color push <spec>`
+    ColorPush(Color),
+
+    /// `color pop`
+    ColorPop,
+
+    /// `color <spec>`, which replaces the current color.
+    ColorSet(Color),
+
+    /// `papersize=W,H` or `pdf:pagesize width W height H`, in big points.
+    PageSize(f64, f64),
+
+    /// `pdf:image`, as emitted by XeTeX for `\XeTeXpicfile` and
+    /// `\XeTeXpdffile`.
+    Image(ImageSpecial<'a>),
+
+    /// A special that we recognize but can't handle.
+    Unsupported(&'a str),
+
+    /// Anything else, which is silently ignored.
+    Ignored,
+}
+
+/// An RGB color, with components between 0 and 1.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Color(pub f32, pub f32, pub f32);
+
+impl Color {
+    /// Black, the default color.
+    pub const BLACK: Color = Color(0., 0., 0.);
+
+    /// Express the color in CSS syntax.
+    pub fn to_css(self) -> String {
+        let c = |v: f32| (v.clamp(0., 1.) * 255.).round() as u8;
+        format!("#{:02x}{:02x}{:02x}", c(self.0), c(self.1), c(self.2))
+    }
+
+    /// Parse a color specification like `rgb 1 0 0` or `gray 0.5`.
+    fn parse(spec: &str) -> Option<Color> {
+        let mut words = spec.split_whitespace();
+        let model = words.next()?;
+        let values = words
+            .map(|w| w.parse::<f32>().ok())
+            .collect::<Option<Vec<_>>>()?;
+
+        match (model, &values[..]) {
+            ("rgb", &[r, g, b]) => Some(Color(r, g, b)),
+            ("gray", &[g]) => Some(Color(g, g, g)),
+            ("cmyk", &[c, m, y, k]) => Some(Color(
+                (1. - c) * (1. - k),
+                (1. - m) * (1. - k),
+                (1. - y) * (1. - k),
+            )),
+            _ => None,
+        }
+    }
+}
+
+/// The parameters of an image special.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ImageSpecial<'a> {
+    /// The path of the image file.
+    pub path: &'a str,
+
+    /// The transform to apply to the image, relative to its natural size, in
+    /// the PDF convention: `[a b c d e f]`, with the Y axis pointing up.
+    pub matrix: [f64; 6],
+
+    /// An explicit width for the image, in big points.
+    pub width: Option<f64>,
+
+    /// An explicit height for the image, in big points.
+    pub height: Option<f64>,
+}
+
+impl<'a> Special<'a> {
+    /// Parse the contents of a special.
+    pub fn parse(text: &'a str) -> Special<'a> {
+        let text = text.trim();
+
+        if let Some(rest) = text.strip_prefix("color") {
+            let rest = rest.trim();
+
+            if rest == "pop" {
+                return Special::ColorPop;
+            }
+
+            let (push, spec) = match rest.strip_prefix("push") {
+                Some(spec) => (true, spec),
+                None => (false, rest),
+            };
+
+            return match Color::parse(spec) {
+                Some(c) if push => Special::ColorPush(c),
+                Some(c) => Special::ColorSet(c),
+                None => Special::Unsupported(text),
+            };
+        }
+
+        if let Some(rest) = text.strip_prefix("papersize=") {
+            return match rest.split_once(',') {
+                Some((w, h)) => match (parse_dimen(w), parse_dimen(h)) {
+                    (Some(w), Some(h)) => Special::PageSize(w, h),
+                    _ => Special::Unsupported(text),
+                },
+                None => Special::Unsupported(text),
+            };
+        }
+
+        if let Some(rest) = text.strip_prefix("pdf:pagesize") {
+            // TeX emits this at the start of every document.
+            if rest.trim() == "default" {
+                return Special::Ignored;
+            }
+
+            let mut width = None;
+            let mut height = None;
+            let mut words = rest.split_whitespace();
+
+            while let Some(key) = words.next() {
+                match key {
+                    "width" => width = words.next().and_then(parse_dimen),
+                    "height" => height = words.next().and_then(parse_dimen),
+                    _ => {}
+                }
+            }
+
+            return match (width, height) {
+                (Some(w), Some(h)) => Special::PageSize(w, h),
+                _ => Special::Unsupported(text),
+            };
+        }
+
+        for prefix in &["pdf:image", "pdf:epdf", "pdf:bxobj", "pdf:uxobj"] {
+            if let Some(rest) = text.strip_prefix(prefix) {
+                if *prefix == "pdf:image" {
+                    if let Some(img) = parse_image(rest) {
+                        return Special::Image(img);
+                    }
+                }
+
+                return Special::Unsupported(text);
+            }
+        }
+
+        Special::Ignored
+    }
+}
+
+/// Parse the arguments of a `pdf:image` special.
+fn parse_image(text: &str) -> Option<ImageSpecial<'_>> {
+    let (args, path) = text.split_once('(')?;
+    let path = path.trim_end().strip_suffix(')')?;
+    let mut matrix = [1., 0., 0., 1., 0., 0.];
+    let mut width = None;
+    let mut height = None;
+    let mut words = args.split_whitespace();
+
+    while let Some(key) = words.next() {
+        match key {
+            "matrix" => {
+                for item in &mut matrix {
+                    *item = words.next()?.parse().ok()?;
+                }
+            }
+
+            "width" => width = Some(parse_dimen(words.next()?)?),
+            "height" => height = Some(parse_dimen(words.next()?)?),
+
+            // These take an argument that doesn't matter to us.
+            "page" | "pagebox" => {
+                words.next()?;
+            }
+
+            _ => return None,
+        }
+    }
+
+    Some(ImageSpecial {
+        path,
+        matrix,
+        width,
+        height,
+    })
+}
+
+/// Parse a TeX-style dimension, returning its value in big points.
+pub fn parse_dimen(text: &str) -> Option<f64> {
+    let text = text.trim();
+    let split = text
+        .find(|c: char| !(c.is_ascii_digit() || c == '.' || c == '-' || c == '+'))
+        .unwrap_or(text.len());
+    let value: f64 = text[..split].parse().ok()?;
+    let unit = text[split..].trim();
+    let unit = unit.strip_prefix("true").unwrap_or(unit);
+
+    let scale = match unit {
+        "pt" => 72. / 72.27,
+        "bp" | "" => 1.,
+        "in" => 72.,
+        "cm" => 72. / 2.54,
+        "mm" => 72. / 25.4,
+        "pc" => 12. * 72. / 72.27,
+        "dd" => 1238. / 1157. * 72. / 72.27,
+        "cc" => 12. * 1238. / 1157. * 72. / 72.27,
+        "sp" => 72. / 72.27 / 65536.,
+        _ => return None,
+    };
+
+    Some(value * scale)
+}
+
+/// Parse a paper specification, returning its width and height in big points.
+///
+/// This accepts the common paper names understood by `xdvipdfmx`, as well as
+/// explicit dimensions of the form `W,H`.
+pub fn parse_paper_spec(spec: &str) -> Option<(f64, f64)> {
+    let spec = spec.trim();
+
+    if let Some((w, h)) = spec.split_once(',') {
+        return Some((parse_dimen(w)?, parse_dimen(h)?));
+    }
+
+    let mm = |w: f64, h: f64| Some((w * 72. / 25.4, h * 72. / 25.4));
+
+    match spec.to_ascii_lowercase().as_str() {
+        "letter" => Some((612., 792.)),
+        "legal" => Some((612., 1008.)),
+        "ledger" => Some((1224., 792.)),
+        "tabloid" => Some((792., 1224.)),
+        "a3" => mm(297., 420.),
+        "a4" => mm(210., 297.),
+        "a5" => mm(148., 210.),
+        "a6" => mm(105., 148.),
+        "b5" => mm(176., 250.),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn colors() {
+        assert_eq!(
+            Special::parse("color push rgb 1 0 0"),
+            Special::ColorPush(Color(1., 0., 0.))
+        );
+        assert_eq!(
+            Special::parse("color gray 0.5"),
+            Special::ColorSet(Color(0.5, 0.5, 0.5))
+        );
+        assert_eq!(Special::parse("color pop"), Special::ColorPop);
+        assert_eq!(Color(0., 0., 1.).to_css(), "#0000ff");
+
+        if let Special::ColorPush(c) = Special::parse("color push cmyk 0 1 1 0") {
+            assert_eq!(c.to_css(), "#ff0000");
+        } else {
+            panic!("cmyk color not parsed");
+        }
+    }
+
+    #[test]
+    fn page_sizes() {
+        assert_eq!(parse_paper_spec("letter"), Some((612., 792.)));
+        assert_eq!(parse_paper_spec("1in,2in"), Some((72., 144.)));
+        assert_eq!(parse_paper_spec("bogus"), None);
+        assert_eq!(
+            Special::parse("pdf:pagesize width 100bp height 2in"),
+            Special::PageSize(100., 144.)
+        );
+        assert_eq!(
+            Special::parse("papersize=72.27truept,10bp"),
+            Special::PageSize(72., 10.)
+        );
+    }
+
+    #[test]
+    fn images() {
+        assert_eq!(
+            Special::parse("pdf:image matrix 0.5 0 0 0.5 0 0 page 1 (fig.png)"),
+            Special::Image(ImageSpecial {
+                path: "fig.png",
+                matrix: [0.5, 0., 0., 0.5, 0., 0.],
+                width: None,
+                height: None,
+            })
+        );
+        assert_eq!(
+            Special::parse("pdf:image width 1in (a b.jpg)"),
+            Special::Image(ImageSpecial {
+                path: "a b.jpg",
+                matrix: [1., 0., 0., 1., 0., 0.],
+                width: Some(72.),
+                height: None,
+            })
+        );
+    }
+}
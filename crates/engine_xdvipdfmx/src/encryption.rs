@@ -0,0 +1,174 @@
+// Copyright 2026 the Tectonic Project
+// Licensed under the MIT License.
+
+//! Settings for encrypting the output PDF.
+
+use tectonic_errors::prelude::*;
+
+/// The longest password that the engine accepts, in bytes.
+const MAX_PASSWORD_LEN: usize = 127;
+
+/// Settings for password-protecting the output PDF.
+///
+/// A PDF encrypted with these settings can be opened by anyone who knows
+/// either password. Readers who use the user password are limited to the
+/// operations allowed by the [`permissions`](Self::permissions), while those
+/// who use the owner password have full access. If the user password is
+/// empty, anyone can open the document, but only with those permissions.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct PdfEncryption {
+    /// The owner password.
+    pub owner_password: String,
+
+    /// The user password, which may be empty.
+    pub user_password: String,
+
+    /// The length of the encryption key, in bits.
+    ///
+    /// This may be 40 or 128, which use the RC4 and AES-128 ciphers
+    /// respectively and are supported by all PDF readers, or 256, which uses
+    /// AES-256. With a 256-bit key, the output PDF version is raised to at
+    /// least 1.7.
+    pub key_bits: u32,
+
+    /// The operations allowed to readers who use the user password.
+    pub permissions: PdfPermissions,
+}
+
+impl PdfEncryption {
+    /// Create encryption settings with the given owner password.
+    ///
+    /// The user password is empty, the key length is 256 bits, and all
+    /// operations are permitted.
+    pub fn new<S: Into<String>>(owner_password: S) -> Self {
+        PdfEncryption {
+            owner_password: owner_password.into(),
+            user_password: String::new(),
+            key_bits: 256,
+            permissions: PdfPermissions::all(),
+        }
+    }
+
+    /// Check that these settings are usable by the engine.
+    pub(crate) fn validate(&self) -> Result<()> {
+        ensure!(
+            matches!(self.key_bits, 40 | 128 | 256),
+            "unsupported PDF encryption key length {} (expected 40, 128, or 256 bits)",
+            self.key_bits
+        );
+        ensure!(
+            !self.owner_password.is_empty(),
+            "the PDF owner password may not be empty"
+        );
+
+        for pw in &[&self.owner_password, &self.user_password] {
+            ensure!(
+                pw.len() <= MAX_PASSWORD_LEN,
+                "PDF passwords may be at most {} bytes long",
+                MAX_PASSWORD_LEN
+            );
+        }
+
+        Ok(())
+    }
+}
+
+/// Operations that may be allowed to readers of an encrypted PDF.
+///
+/// These are enforced by PDF readers rather than by the encryption itself,
+/// so they should be thought of as a statement of the author's wishes.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct PdfPermissions {
+    /// Printing the document, possibly at a degraded quality.
+    pub print: bool,
+
+    /// Printing the document at full quality. This requires `print` too.
+    pub print_high_quality: bool,
+
+    /// Modifying the document in ways not covered by the other permissions.
+    pub modify: bool,
+
+    /// Copying or otherwise extracting text and graphics.
+    pub copy: bool,
+
+    /// Extracting text and graphics for accessibility purposes.
+    pub accessibility: bool,
+
+    /// Adding or modifying annotations, and filling in forms.
+    pub annotate: bool,
+
+    /// Filling in existing form fields, even if `annotate` is not allowed.
+    pub fill_forms: bool,
+
+    /// Assembling the document: inserting, rotating, or deleting pages,
+    /// and creating bookmarks or thumbnails.
+    pub assemble: bool,
+}
+
+impl PdfPermissions {
+    /// Permissions that allow every operation.
+    pub fn all() -> Self {
+        PdfPermissions {
+            print: true,
+            print_high_quality: true,
+            modify: true,
+            copy: true,
+            accessibility: true,
+            annotate: true,
+            fill_forms: true,
+            assemble: true,
+        }
+    }
+
+    /// Permissions that allow no operations besides viewing.
+    pub fn none() -> Self {
+        Self::default()
+    }
+
+    /// Get the value of the `P` entry of the encryption dictionary.
+    pub(crate) fn bits(&self) -> u32 {
+        let flags = [
+            (self.print, 1 << 2),
+            (self.modify, 1 << 3),
+            (self.copy, 1 << 4),
+            (self.annotate, 1 << 5),
+            (self.fill_forms, 1 << 8),
+            (self.accessibility, 1 << 9),
+            (self.assemble, 1 << 10),
+            (self.print_high_quality, 1 << 11),
+        ];
+
+        flags
+            .iter()
+            .filter(|(allowed, _)| *allowed)
+            .fold(0, |acc, (_, bit)| acc | bit)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn permission_bits() {
+        assert_eq!(PdfPermissions::none().bits(), 0);
+        assert_eq!(PdfPermissions::all().bits(), 0xF3C);
+
+        let p = PdfPermissions {
+            print: true,
+            accessibility: true,
+            ..PdfPermissions::none()
+        };
+        assert_eq!(p.bits(), 0x204);
+    }
+
+    #[test]
+    fn validation() {
+        assert!(PdfEncryption::new("owner").validate().is_ok());
+        assert!(PdfEncryption::new("").validate().is_err());
+
+        let mut e = PdfEncryption::new("owner");
+        e.key_bits = 64;
+        assert!(e.validate().is_err());
+    }
+}
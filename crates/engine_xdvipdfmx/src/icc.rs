@@ -0,0 +1,191 @@
+// Copyright 2026 the Tectonic Project
+// Licensed under the MIT License.
+
+//! Generating the ICC color profile used for PDF/A output intents.
+//!
+//! PDF/A requires documents to declare an output intent with an embedded ICC
+//! profile. Rather than carrying a binary profile around, we synthesize a
+//! small ICC version 2 display profile describing the sRGB color space. Its
+//! colorants are the standard sRGB primaries adapted to the D50 profile
+//! connection space, and its tone curve is sampled from the sRGB transfer
+//! function.
+
+/// The D50 illuminant of the profile connection space.
+const D50: [f64; 3] = [0.9642, 1.0, 0.8249];
+
+/// The sRGB primaries, chromatically adapted to D50.
+const RED: [f64; 3] = [0.4361, 0.2225, 0.0139];
+const GREEN: [f64; 3] = [0.3851, 0.7169, 0.0971];
+const BLUE: [f64; 3] = [0.1431, 0.0606, 0.7141];
+
+/// The number of samples in the tone reproduction curve.
+const N_TRC_SAMPLES: usize = 1024;
+
+/// Generate an ICC profile for the sRGB color space.
+pub(crate) fn srgb_profile() -> Vec<u8> {
+    let desc = desc_tag("sRGB IEC61966-2.1");
+    let cprt = text_tag("No copyright, use freely");
+    let wtpt = xyz_tag(&D50);
+    let rxyz = xyz_tag(&RED);
+    let gxyz = xyz_tag(&GREEN);
+    let bxyz = xyz_tag(&BLUE);
+    let trc = curv_tag();
+
+    // The three TRC tags all point at the same data.
+    let tags: [(&[u8; 4], &[u8]); 9] = [
+        (b"desc", &desc),
+        (b"cprt", &cprt),
+        (b"wtpt", &wtpt),
+        (b"rXYZ", &rxyz),
+        (b"gXYZ", &gxyz),
+        (b"bXYZ", &bxyz),
+        (b"rTRC", &trc),
+        (b"gTRC", &trc),
+        (b"bTRC", &trc),
+    ];
+
+    let mut table = Vec::new();
+    let mut data: Vec<u8> = Vec::new();
+    let data_start = 128 + 4 + 12 * tags.len();
+    let mut trc_offset = None;
+
+    push_u32(&mut table, tags.len() as u32);
+
+    for (sig, tag) in &tags {
+        let is_trc = &sig[1..] == b"TRC";
+
+        let offset = match trc_offset {
+            Some(o) if is_trc => o,
+            _ => {
+                let o = data_start + data.len();
+                data.extend_from_slice(tag);
+
+                while !data.len().is_multiple_of(4) {
+                    data.push(0);
+                }
+
+                if is_trc {
+                    trc_offset = Some(o);
+                }
+
+                o
+            }
+        };
+
+        table.extend_from_slice(&sig[..]);
+        push_u32(&mut table, offset as u32);
+        push_u32(&mut table, tag.len() as u32);
+    }
+
+    let size = data_start + data.len();
+    let mut profile = Vec::with_capacity(size);
+
+    push_u32(&mut profile, size as u32);
+    profile.extend_from_slice(&[0; 4]); // preferred CMM
+    push_u32(&mut profile, 0x0210_0000); // version 2.1
+    profile.extend_from_slice(b"mntr");
+    profile.extend_from_slice(b"RGB ");
+    profile.extend_from_slice(b"XYZ ");
+
+    // A fixed creation date, so that outputs are reproducible.
+    for v in &[2021u16, 1, 1, 0, 0, 0] {
+        profile.extend_from_slice(&v.to_be_bytes());
+    }
+
+    profile.extend_from_slice(b"acsp");
+    profile.extend_from_slice(&[0; 24]); // platform, flags, device info
+    push_u32(&mut profile, 0); // perceptual rendering intent
+    push_xyz(&mut profile, &D50);
+    profile.extend_from_slice(&[0; 48]); // creator and reserved
+    debug_assert_eq!(profile.len(), 128);
+
+    profile.extend_from_slice(&table);
+    profile.extend_from_slice(&data);
+    profile
+}
+
+fn push_u32(buf: &mut Vec<u8>, v: u32) {
+    buf.extend_from_slice(&v.to_be_bytes());
+}
+
+/// Append an XYZ value in the `s15Fixed16Number` format.
+fn push_xyz(buf: &mut Vec<u8>, xyz: &[f64; 3]) {
+    for v in xyz {
+        buf.extend_from_slice(&((v * 65536.0).round() as i32).to_be_bytes());
+    }
+}
+
+fn desc_tag(text: &str) -> Vec<u8> {
+    let mut buf = b"desc\0\0\0\0".to_vec();
+    push_u32(&mut buf, text.len() as u32 + 1);
+    buf.extend_from_slice(text.as_bytes());
+    buf.push(0);
+    // Empty Unicode and ScriptCode descriptions.
+    buf.extend_from_slice(&[0; 8]);
+    buf.extend_from_slice(&[0; 3]);
+    buf.extend_from_slice(&[0; 67]);
+    buf
+}
+
+fn text_tag(text: &str) -> Vec<u8> {
+    let mut buf = b"text\0\0\0\0".to_vec();
+    buf.extend_from_slice(text.as_bytes());
+    buf.push(0);
+    buf
+}
+
+fn xyz_tag(xyz: &[f64; 3]) -> Vec<u8> {
+    let mut buf = b"XYZ \0\0\0\0".to_vec();
+    push_xyz(&mut buf, xyz);
+    buf
+}
+
+fn curv_tag() -> Vec<u8> {
+    let mut buf = b"curv\0\0\0\0".to_vec();
+    push_u32(&mut buf, N_TRC_SAMPLES as u32);
+
+    for i in 0..N_TRC_SAMPLES {
+        let c = i as f64 / (N_TRC_SAMPLES - 1) as f64;
+
+        let v = if c <= 0.04045 {
+            c / 12.92
+        } else {
+            ((c + 0.055) / 1.055).powf(2.4)
+        };
+
+        buf.extend_from_slice(&((v * 65535.0).round() as u16).to_be_bytes());
+    }
+
+    buf
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn u32_at(buf: &[u8], ofs: usize) -> usize {
+        u32::from_be_bytes([buf[ofs], buf[ofs + 1], buf[ofs + 2], buf[ofs + 3]]) as usize
+    }
+
+    #[test]
+    fn structure() {
+        let p = srgb_profile();
+
+        assert_eq!(u32_at(&p, 0), p.len());
+        assert_eq!(&p[36..40], b"acsp");
+        assert_eq!(u32_at(&p, 128), 9);
+
+        for i in 0..9 {
+            let entry = 132 + 12 * i;
+            let offset = u32_at(&p, entry + 4);
+            let size = u32_at(&p, entry + 8);
+            assert_eq!(offset % 4, 0);
+            assert!(offset + size <= p.len());
+        }
+
+        // The TRC tags share their data.
+        assert_eq!(u32_at(&p, 132 + 12 * 6 + 4), u32_at(&p, 132 + 12 * 8 + 4));
+        let trc = u32_at(&p, 132 + 12 * 6 + 4);
+        assert_eq!(&p[trc..trc + 4], b"curv");
+    }
+}
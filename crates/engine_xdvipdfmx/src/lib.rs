@@ -24,6 +24,38 @@ use std::{ffi::CString, time::SystemTime};
 use tectonic_bridge_core::{CoreBridgeLauncher, EngineAbortedError};
 use tectonic_errors::prelude::*;
 
+mod encryption;
+mod icc;
+mod metadata;
+
+pub use encryption::{PdfEncryption, PdfPermissions};
+pub use metadata::PdfMetadata;
+
+/// A level of PDF/A conformance that the engine can target.
+///
+/// PDF/A is the ISO standard for the long-term archiving of PDF documents.
+/// The engine only targets the "basic" (b) conformance level, which ensures
+/// that the visual appearance of the document is preserved.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum PdfAConformance {
+    /// PDF/A-2b, defined in ISO 19005-2.
+    A2b,
+
+    /// PDF/A-3b, defined in ISO 19005-3. This is the same as PDF/A-2b, but
+    /// allows arbitrary files to be embedded in the document.
+    A3b,
+}
+
+impl PdfAConformance {
+    /// Get the part number of the PDF/A standard for this level.
+    pub fn part(self) -> u8 {
+        match self {
+            PdfAConformance::A2b => 2,
+            PdfAConformance::A3b => 3,
+        }
+    }
+}
+
 /// A struct for invoking the `xdvipdfmx` engine.
 ///
 /// This struct has a fairly straightforward “builder” interface: you create it,
@@ -40,7 +72,11 @@ pub struct XdvipdfmxEngine {
     paper_spec: String,
     enable_compression: bool,
     deterministic_tags: bool,
+    enable_tagging: bool,
     build_date: SystemTime,
+    pdfa: Option<PdfAConformance>,
+    encryption: Option<PdfEncryption>,
+    metadata: PdfMetadata,
 }
 
 impl Default for XdvipdfmxEngine {
@@ -49,7 +85,11 @@ impl Default for XdvipdfmxEngine {
             paper_spec: "letter".to_owned(),
             enable_compression: true,
             deterministic_tags: false,
+            enable_tagging: false,
             build_date: SystemTime::UNIX_EPOCH,
+            pdfa: None,
+            encryption: None,
+            metadata: PdfMetadata::default(),
         }
     }
 }
@@ -77,6 +117,22 @@ impl XdvipdfmxEngine {
         self
     }
 
+    /// Set whether the output will be a tagged PDF.
+    ///
+    /// The default is false. When enabled, the engine builds the document's
+    /// logical structure tree from the `tdux:` specials that mark the start
+    /// and end of semantic elements like paragraphs and headings — the same
+    /// ones that drive Tectonic's HTML output. Tagged PDFs are needed for
+    /// accessibility, for instance to let screen readers follow the reading
+    /// order of the document. Content outside of any marked element is
+    /// attributed to the top-level `Document` element. If the input contains
+    /// no such specials at all, the engine warns and doesn't mark the output
+    /// as tagged.
+    pub fn enable_tagging(&mut self, enable_tagging: bool) -> &mut Self {
+        self.enable_tagging = enable_tagging;
+        self
+    }
+
     /// Sets the build date embedded in the output artifacts
     ///
     /// The default value is the Unix epoch, which is almost certainly not what
@@ -95,6 +151,40 @@ impl XdvipdfmxEngine {
         self
     }
 
+    /// Set the level of PDF/A conformance that the output should target.
+    ///
+    /// The default is `None`, in which case no attempt is made to create
+    /// PDF/A output. Otherwise, the engine adds the metadata and sRGB output
+    /// intent that the standard requires, refuses to encrypt the output, and
+    /// prints a report of any problems that would prevent the output from
+    /// conforming, such as fonts that are not embedded.
+    pub fn pdfa_conformance(&mut self, pdfa: Option<PdfAConformance>) -> &mut Self {
+        self.pdfa = pdfa;
+        self
+    }
+
+    /// Set whether and how the output PDF should be encrypted.
+    ///
+    /// The default is `None`, meaning that the output is not encrypted.
+    /// Encryption settings specified by the document itself, with the
+    /// `pdf:encrypt` special, take precedence over these. Encryption is not
+    /// allowed in PDF/A documents, so it is disabled if
+    /// [`pdfa_conformance`](Self::pdfa_conformance) is set.
+    pub fn encryption(&mut self, encryption: Option<PdfEncryption>) -> &mut Self {
+        self.encryption = encryption;
+        self
+    }
+
+    /// Set the metadata to be written into the output PDF.
+    ///
+    /// The default is empty, in which case the output contains whatever
+    /// metadata the document specifies itself. Values given here take
+    /// precedence over the document's.
+    pub fn metadata(&mut self, metadata: PdfMetadata) -> &mut Self {
+        self.metadata = metadata;
+        self
+    }
+
     /// Run xdvipdfmx.
     ///
     /// The *launcher* parameter gives overarching environmental context in
@@ -116,15 +206,59 @@ impl XdvipdfmxEngine {
             ["paper_spec may not contain internal NULs"]
         );
 
+        let (owner_password, user_password) = if let Some(enc) = self.encryption.as_ref() {
+            enc.validate()?;
+            (
+                atry!(
+                    CString::new(enc.owner_password.as_str());
+                    ["PDF passwords may not contain internal NULs"]
+                ),
+                atry!(
+                    CString::new(enc.user_password.as_str());
+                    ["PDF passwords may not contain internal NULs"]
+                ),
+            )
+        } else {
+            (CString::default(), CString::default())
+        };
+
+        let metadata = metadata::CMetadata::new(&self.metadata)?;
+
+        let icc_profile = match self.pdfa {
+            Some(_) => icc::srgb_profile(),
+            None => Vec::new(),
+        };
+
         let config = c_api::XdvipdfmxConfig {
             paperspec: paperspec_str.as_c_str().as_ptr(),
             enable_compression: u8::from(self.enable_compression),
             deterministic_tags: u8::from(self.deterministic_tags),
+            enable_tagging: u8::from(self.enable_tagging),
             build_date: self
                 .build_date
                 .duration_since(SystemTime::UNIX_EPOCH)
                 .expect("invalid build date")
                 .as_secs(),
+            pdfa_part: self.pdfa.map(PdfAConformance::part).unwrap_or(0),
+            icc_profile: icc_profile.as_ptr(),
+            icc_profile_len: icc_profile.len(),
+            enable_encryption: u8::from(self.encryption.is_some()),
+            owner_password: owner_password.as_ptr(),
+            user_password: user_password.as_ptr(),
+            key_bits: self.encryption.as_ref().map(|e| e.key_bits).unwrap_or(0),
+            permissions: self
+                .encryption
+                .as_ref()
+                .map(|e| e.permissions.bits())
+                .unwrap_or(0),
+            metadata_title: metadata::opt_ptr(&metadata.title),
+            metadata_author: metadata::opt_ptr(&metadata.author),
+            metadata_subject: metadata::opt_ptr(&metadata.subject),
+            metadata_keywords: metadata::opt_ptr(&metadata.keywords),
+            metadata_lang: metadata::opt_ptr(&metadata.language),
+            metadata_custom_keys: metadata.custom_key_ptrs.as_ptr(),
+            metadata_custom_values: metadata.custom_value_ptrs.as_ptr(),
+            metadata_custom_len: metadata.custom_key_ptrs.len(),
         };
 
         let cdvi = CString::new(dvi)?;
@@ -157,7 +291,24 @@ pub mod c_api {
         pub paperspec: *const libc::c_char,
         pub enable_compression: libc::c_uchar,
         pub deterministic_tags: libc::c_uchar,
+        pub enable_tagging: libc::c_uchar,
         pub build_date: u64,
+        pub pdfa_part: libc::c_uchar,
+        pub icc_profile: *const u8,
+        pub icc_profile_len: usize,
+        pub enable_encryption: libc::c_uchar,
+        pub owner_password: *const libc::c_char,
+        pub user_password: *const libc::c_char,
+        pub key_bits: u32,
+        pub permissions: u32,
+        pub metadata_title: *const libc::c_char,
+        pub metadata_author: *const libc::c_char,
+        pub metadata_subject: *const libc::c_char,
+        pub metadata_keywords: *const libc::c_char,
+        pub metadata_lang: *const libc::c_char,
+        pub metadata_custom_keys: *const *const libc::c_char,
+        pub metadata_custom_values: *const *const libc::c_char,
+        pub metadata_custom_len: usize,
     }
 
     #[allow(improper_ctypes)] // for CoreBridgeState
@@ -0,0 +1,158 @@
+// Copyright 2026 the Tectonic Project
+// Licensed under the MIT License.
+
+//! Document metadata supplied by the caller.
+
+use std::{collections::BTreeMap, ffi::CString};
+use tectonic_errors::prelude::*;
+
+/// Document information entries that can't be set as custom properties.
+const RESERVED_KEYS: &[&str] = &[
+    "Title",
+    "Author",
+    "Subject",
+    "Keywords",
+    "Creator",
+    "Producer",
+    "CreationDate",
+    "ModDate",
+    "Trapped",
+];
+
+/// Metadata to be written into the output PDF.
+///
+/// Any values given here take precedence over those that the document sets
+/// itself, for instance with the `hyperref` package. They are written both to
+/// the document information dictionary and to an XMP metadata stream.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct PdfMetadata {
+    /// The document title.
+    pub title: Option<String>,
+
+    /// The name of the document's author.
+    pub author: Option<String>,
+
+    /// The subject of the document.
+    pub subject: Option<String>,
+
+    /// Keywords associated with the document.
+    pub keywords: Vec<String>,
+
+    /// The language of the document, as an RFC 3066 language tag such as
+    /// `en-US`.
+    pub language: Option<String>,
+
+    /// Custom properties. The keys must be made up of ASCII letters, digits,
+    /// hyphens, and underscores, and start with a letter. They're added to
+    /// the document information dictionary as is, and to the XMP metadata in
+    /// the `pdfx` namespace, as Acrobat does with custom properties.
+    pub custom: BTreeMap<String, String>,
+}
+
+impl PdfMetadata {
+    /// Check whether any metadata have been specified.
+    pub fn is_empty(&self) -> bool {
+        *self == PdfMetadata::default()
+    }
+}
+
+/// Validate a custom property name.
+fn check_custom_key(key: &str) -> Result<()> {
+    let mut chars = key.chars();
+
+    ensure!(
+        chars.next().is_some_and(|c| c.is_ascii_alphabetic())
+            && chars.all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_'),
+        "invalid custom PDF metadata property name `{}`",
+        key
+    );
+    ensure!(
+        key.len() <= 64,
+        "custom PDF metadata property name `{}` is too long",
+        key
+    );
+    ensure!(
+        !RESERVED_KEYS.contains(&key),
+        "`{}` is a standard PDF metadata property and can't be set as a custom one",
+        key
+    );
+    Ok(())
+}
+
+fn c_string(text: &str) -> Result<CString> {
+    Ok(atry!(
+        CString::new(text);
+        ["PDF metadata may not contain internal NULs"]
+    ))
+}
+
+/// The metadata, converted to C strings for the engine.
+#[derive(Debug, Default)]
+pub(crate) struct CMetadata {
+    pub title: Option<CString>,
+    pub author: Option<CString>,
+    pub subject: Option<CString>,
+    pub keywords: Option<CString>,
+    pub language: Option<CString>,
+    // These own the strings that the pointers below refer to.
+    _custom_keys: Vec<CString>,
+    _custom_values: Vec<CString>,
+    pub custom_key_ptrs: Vec<*const libc::c_char>,
+    pub custom_value_ptrs: Vec<*const libc::c_char>,
+}
+
+impl CMetadata {
+    pub(crate) fn new(md: &PdfMetadata) -> Result<Self> {
+        let opt = |v: &Option<String>| v.as_deref().map(c_string).transpose();
+
+        let keywords = if md.keywords.is_empty() {
+            None
+        } else {
+            Some(c_string(&md.keywords.join(", "))?)
+        };
+
+        let mut custom_keys = Vec::new();
+        let mut custom_values = Vec::new();
+
+        for (key, value) in &md.custom {
+            check_custom_key(key)?;
+            custom_keys.push(c_string(key)?);
+            custom_values.push(c_string(value)?);
+        }
+
+        let custom_key_ptrs = custom_keys.iter().map(|s| s.as_ptr()).collect();
+        let custom_value_ptrs = custom_values.iter().map(|s| s.as_ptr()).collect();
+
+        Ok(CMetadata {
+            title: opt(&md.title)?,
+            author: opt(&md.author)?,
+            subject: opt(&md.subject)?,
+            keywords,
+            language: opt(&md.language)?,
+            _custom_keys: custom_keys,
+            _custom_values: custom_values,
+            custom_key_ptrs,
+            custom_value_ptrs,
+        })
+    }
+}
+
+/// Get a pointer to an optional C string, or NULL.
+pub(crate) fn opt_ptr(s: &Option<CString>) -> *const libc::c_char {
+    s.as_ref().map_or(std::ptr::null(), |s| s.as_ptr())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn custom_keys() {
+        assert!(check_custom_key("ISBN").is_ok());
+        assert!(check_custom_key("review-status_2").is_ok());
+        assert!(check_custom_key("").is_err());
+        assert!(check_custom_key("2nd").is_err());
+        assert!(check_custom_key("a b").is_err());
+        assert!(check_custom_key("Title").is_err());
+    }
+}
@@ -328,10 +328,20 @@ dvipdfmx_main (
   bool translate,
   bool compress,
   bool deterministic_tags,
+  bool enable_tagging,
   bool quiet,
   unsigned int verbose,
   time_t build_date,
-  const char *paperspec)
+  const char *paperspec,
+  int pdfa_part,
+  const unsigned char *icc_profile,
+  size_t icc_profile_len,
+  bool encrypt,
+  const char *owner_password,
+  const char *user_password,
+  int enc_key_bits,
+  int32_t enc_permission,
+  const struct pdf_metadata_setting *metadata)
 {
   double dvi2pts;
   const char *creator = NULL;
@@ -381,6 +391,16 @@ dvipdfmx_main (
   bookmark_open = 0;
   key_bits = 40;
   permission = 0x003C;
+
+  /* Tectonic: encryption settings from the API. The `pdf:encrypt` special can
+   * still override these. */
+  do_encryption = encrypt ? 1 : 0;
+  if (encrypt) {
+    key_bits = enc_key_bits;
+    permission = enc_permission;
+    strncpy(oplain, owner_password, sizeof(oplain) - 1);
+    strncpy(uplain, user_password, sizeof(uplain) - 1);
+  }
   font_dpi = 600;
   pdfdecimaldigits = 5;
   image_cache_life = -2;
@@ -414,6 +434,12 @@ dvipdfmx_main (
     kpse_set_program_enabled(kpse_pk_format, true, kpse_src_texmf_cnf);*/
   pdf_font_set_dpi(font_dpi);
 
+  /* Tectonic: AES-256 encryption requires PDF 1.7 (with an Adobe extension)
+   * or newer. */
+  if (do_encryption && key_bits == 256 &&
+      pdf_version_major == 1 && pdf_version_minor < 7)
+    pdf_version_minor = 7;
+
   settings.ver_major = pdf_version_major;
   settings.ver_minor = pdf_version_minor;
 
@@ -467,6 +493,14 @@ dvipdfmx_main (
   settings.device.precision   = pdfdecimaldigits;
   settings.device.ignore_colors = ignore_colors;
 
+  settings.enable_tagging = enable_tagging ? 1 : 0;
+
+  settings.pdfa.part            = pdfa_part;
+  settings.pdfa.icc_profile     = icc_profile;
+  settings.pdfa.icc_profile_len = icc_profile_len;
+
+  settings.metadata = *metadata;
+
   set_distiller_template(filter_template);
 
   /* Initialize PDF document creation routine. */
@@ -502,6 +536,16 @@ tt_engine_xdvipdfmx_main(
   const char *pdfname
 ) {
   int rv;
+  struct pdf_metadata_setting metadata;
+
+  metadata.title         = config->metadata_title;
+  metadata.author        = config->metadata_author;
+  metadata.subject       = config->metadata_subject;
+  metadata.keywords      = config->metadata_keywords;
+  metadata.lang          = config->metadata_lang;
+  metadata.custom_keys   = config->metadata_custom_keys;
+  metadata.custom_values = config->metadata_custom_values;
+  metadata.num_custom    = (size_t) config->metadata_custom_len;
 
   if (setjmp(*ttbc_global_engine_enter(api))) {
     ttbc_global_engine_exit();
@@ -519,10 +563,20 @@ tt_engine_xdvipdfmx_main(
     false, /* translate */
     (bool) config->enable_compression,
     (bool) config->deterministic_tags,
+    (bool) config->enable_tagging,
     false, /* quiet */
     0, /* verbose */
     (time_t) config->build_date,
-    config->paperspec
+    config->paperspec,
+    (int) config->pdfa_part,
+    config->icc_profile,
+    (size_t) config->icc_profile_len,
+    (bool) config->enable_encryption,
+    config->owner_password,
+    config->user_password,
+    (int) config->key_bits,
+    (int32_t) config->permissions,
+    &metadata
   );
 
   ttbc_global_engine_exit();
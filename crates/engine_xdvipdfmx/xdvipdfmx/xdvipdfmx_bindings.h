@@ -10,7 +10,24 @@ typedef struct {
   const char *paperspec;
   unsigned char enable_compression;
   unsigned char deterministic_tags;
+  unsigned char enable_tagging;
   uint64_t build_date;
+  unsigned char pdfa_part;
+  const uint8_t *icc_profile;
+  uintptr_t icc_profile_len;
+  unsigned char enable_encryption;
+  const char *owner_password;
+  const char *user_password;
+  uint32_t key_bits;
+  uint32_t permissions;
+  const char *metadata_title;
+  const char *metadata_author;
+  const char *metadata_subject;
+  const char *metadata_keywords;
+  const char *metadata_lang;
+  const char *const *metadata_custom_keys;
+  const char *const *metadata_custom_values;
+  uintptr_t metadata_custom_len;
 } XdvipdfmxConfig;
 
 #ifdef __cplusplus
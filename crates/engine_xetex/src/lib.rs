@@ -84,6 +84,9 @@ pub struct TexEngine {
     synctex_enabled: bool,
     semantic_pagination_enabled: bool,
     shell_escape_enabled: bool,
+    memory_stats_enabled: bool,
+    macro_profile_enabled: bool,
+    interactive_errors_enabled: bool,
     build_date: SystemTime,
 }
 
@@ -95,6 +98,9 @@ impl Default for TexEngine {
             synctex_enabled: false,
             semantic_pagination_enabled: false,
             shell_escape_enabled: false,
+            memory_stats_enabled: false,
+            macro_profile_enabled: false,
+            interactive_errors_enabled: false,
             build_date: SystemTime::UNIX_EPOCH,
         }
     }
@@ -150,6 +156,51 @@ impl TexEngine {
         self
     }
 
+    /// Configure whether the engine reports its memory usage in the log file.
+    ///
+    /// This writes the classic “Here is how much of TeX's memory you used”
+    /// summary at the end of the log, as if `\tracingstats` were positive.
+    ///
+    /// The default is false.
+    pub fn memory_stats(&mut self, enabled: bool) -> &mut Self {
+        self.memory_stats_enabled = enabled;
+        self
+    }
+
+    /// Configure whether the engine profiles macro expansion.
+    ///
+    /// In this mode, the engine periodically samples its input stack, which
+    /// records the macros being expanded and the input files and lines being
+    /// read, and charges the elapsed processor time to what it finds. When
+    /// the engine finishes, it writes the results to `<jobname>.folded`, in
+    /// the “folded stacks” format accepted by flame graph tools such as
+    /// `flamegraph.pl`, `inferno`, and speedscope. Each line gives a stack of
+    /// frames, outermost first and separated by semicolons, and the number of
+    /// microseconds spent in it.
+    ///
+    /// The default is false.
+    pub fn macro_profile(&mut self, enabled: bool) -> &mut Self {
+        self.macro_profile_enabled = enabled;
+        self
+    }
+
+    /// Configure whether the engine asks the driver how to recover from
+    /// errors.
+    ///
+    /// In this mode, whenever the engine encounters a recoverable error while
+    /// in TeX’s default “error stop” interaction mode, it calls
+    /// [`tectonic_bridge_core::DriverHooks::sysrq_error_interaction`] with
+    /// the error context and help text. The driver can then choose to proceed,
+    /// insert text, delete tokens, stop asking, or quit, just like a user at
+    /// the classic TeX `?` prompt. This only has an effect if
+    /// [`Self::halt_on_error_mode`] is false.
+    ///
+    /// The default is false.
+    pub fn interactive_errors(&mut self, enabled: bool) -> &mut Self {
+        self.interactive_errors_enabled = enabled;
+        self
+    }
+
     /// Sets the date and time used by the TeX engine. This affects things like
     /// LaTeX's \today command.
     ///
@@ -213,6 +264,18 @@ impl TexEngine {
                     b"semantic_pagination_enabled\0".as_ptr() as _,
                     self.semantic_pagination_enabled.into(),
                 );
+                tt_xetex_set_int_variable(
+                    b"memory_stats_enabled\0".as_ptr() as _,
+                    self.memory_stats_enabled.into(),
+                );
+                tt_xetex_set_int_variable(
+                    b"macro_profile_enabled\0".as_ptr() as _,
+                    self.macro_profile_enabled.into(),
+                );
+                tt_xetex_set_int_variable(
+                    b"interactive_errors_enabled\0".as_ptr() as _,
+                    self.interactive_errors_enabled.into(),
+                );
 
                 tt_engine_xetex_main(
                     state,
@@ -34,6 +34,12 @@ tt_xetex_set_int_variable (const char *var_name, int value)
         semantic_pagination_enabled = (value != 0);
     else if (streq_ptr(var_name, "shell_escape_enabled"))
         shell_escape_enabled = (value != 0);
+    else if (streq_ptr(var_name, "memory_stats_enabled"))
+        memory_stats_enabled = (value != 0);
+    else if (streq_ptr(var_name, "macro_profile_enabled"))
+        macro_profile_enabled = (value != 0);
+    else if (streq_ptr(var_name, "interactive_errors_enabled"))
+        interactive_errors_enabled = (value != 0);
     else
         return 1; /* Uh oh: unrecognized variable */
 
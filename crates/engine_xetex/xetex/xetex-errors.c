@@ -6,7 +6,10 @@
 #include "xetex-core.h"
 #include "xetex-xetexd.h"
 
+#include "xetex-stringpool.h"
+
 #include <stdarg.h>
+#include <string.h>
 
 /* WEBby error-handling code: */
 
@@ -49,14 +52,146 @@ post_error_message(int need_to_print_it)
 }
 
 
+/* The help text for the current error, as a newly allocated string. */
+static char *
+help_text(void)
+{
+    char *text;
+    size_t n = 1;
+
+    if (use_err_help) {
+        selector_t prev_selector = selector;
+        str_number s;
+
+        selector = SELECTOR_NEW_STRING;
+        give_err_help();
+        selector = prev_selector;
+        s = make_string();
+        text = gettexstring(s);
+        str_ptr--; /* "flush_string" */
+        pool_ptr = str_start[str_ptr - TOO_BIG_CHAR];
+        return text;
+    }
+
+    for (int i = 0; i < help_ptr; i++)
+        n += strlen(help_line[i]) + 1;
+
+    text = xmalloc(n);
+    text[0] = '\0';
+
+    for (int i = help_ptr - 1; i >= 0; i--) {
+        strcat(text, help_line[i]);
+        if (i > 0)
+            strcat(text, "\n");
+    }
+
+    return text;
+}
+
+
+static const char *
+capture_context(void)
+{
+    begin_text_capture();
+    show_context();
+    return end_text_capture();
+}
+
+
+/*84: "Get user's advice". In classic TeX this read commands from the
+ * terminal; in Tectonic we ask the driver, which may or may not be talking to
+ * a human. */
+static void
+get_users_advice(const char *context)
+{
+    while (true) {
+        char *help;
+        size_t len;
+        ttbc_error_action action;
+
+        /* Leave room for at least one character, as input_line() does. */
+        if (first >= buf_size - 1)
+            overflow("buffer size", buf_size);
+
+        help = help_text();
+        len = buf_size - 1 - first;
+
+        action = ttstub_error_interaction(context, help, deletions_allowed,
+                                          (uint32_t *) &buffer[first], &len);
+        free(help);
+
+        switch (action) {
+        case TTBC_ERROR_ACTION_INSERT:
+            /*87: "Introduce new material from the terminal" */
+            last = first + len;
+            if (last >= max_buf_stack) {
+                max_buf_stack = last + 1;
+                if (max_buf_stack >= buf_size - 1)
+                    overflow("buffer size", buf_size);
+            }
+            begin_file_reading();
+            cur_input.loc = first;
+            first = last;
+            cur_input.limit = last - 1; /* no end_line_char ends this line */
+            return;
+
+        case TTBC_ERROR_ACTION_DELETE: {
+            /*88: "Delete c-"0" tokens and goto continue" */
+            int32_t s1 = cur_tok, s2 = cur_cmd, s3 = cur_chr, s4 = align_state;
+
+            align_state = 1000000;
+            while (len-- > 0)
+                get_token(); /* one-level recursive call of error() is possible */
+
+            cur_tok = s1;
+            cur_cmd = s2;
+            cur_chr = s3;
+            align_state = s4;
+
+            help_ptr = 2;
+            help_line[1] = "I have just deleted some text, as you asked.";
+            help_line[0] = "You can now delete more, or insert, or whatever.";
+            context = capture_context();
+            break;
+        }
+
+        case TTBC_ERROR_ACTION_SCROLL:
+            /*86: "Change the interaction level and return" */
+            error_count = 0;
+            interaction = SCROLL_MODE;
+            print_nl_cstr("OK, entering ");
+            print_esc_cstr("scrollmode");
+            print_cstr("...");
+            print_ln();
+            return;
+
+        case TTBC_ERROR_ACTION_QUIT:
+            history = HISTORY_FATAL_ERROR;
+            post_error_message(0);
+            _tt_abort("halted on user request");
+
+        default:
+            return;
+        }
+    }
+}
+
+
 void
 error(void)
 {
+    const char *context = NULL;
+
     if (history < HISTORY_ERROR_ISSUED)
         history = HISTORY_ERROR_ISSUED;
 
     print_char('.');
-    show_context();
+
+    if (interactive_errors_enabled && interaction == ERROR_STOP_MODE)
+        context = capture_context();
+    else
+        show_context();
+
     if (halt_on_error_p) {
         history = HISTORY_FATAL_ERROR;
         post_error_message(0);
@@ -65,7 +200,11 @@ error(void)
 
     /* This used to be where there was a bunch of code if "interaction ==
      * error_stop_mode" that would let the use interactively try to solve the
-     * error. */
+     * error. Tectonic can now do the same, but it's up to the driver to talk
+     * to the user. */
+
+    if (context != NULL)
+        get_users_advice(context);
 
     error_count++;
     if (error_count == 100) {
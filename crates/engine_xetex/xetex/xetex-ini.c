@@ -348,6 +348,9 @@ scaled_t delta;
 int synctex_enabled;
 bool used_tectonic_coda_tokens;
 bool semantic_pagination_enabled;
+bool memory_stats_enabled;
+bool macro_profile_enabled;
+bool interactive_errors_enabled;
 bool gave_char_warning_help;
 
 /* These ought to live in xetex-pagebuilder.c but are shared a lot: */
@@ -3869,6 +3872,10 @@ tt_run_engine(const char *dump_name, const char *input_file_name, time_t build_d
 
     pdf_files_init();
     synctex_init_command();
+
+    if (macro_profile_enabled)
+        macro_profile_init();
+
     start_input(input_file_name);
     history = HISTORY_SPOTLESS;
     main_control();
@@ -10,6 +10,12 @@
 
 static ttbc_diagnostic_t *current_diagnostic = 0;
 
+/* Tectonic: for interactive error recovery, we need plain-text copies of
+ * the error context to pass along to the driver. */
+static char *captured_text = NULL;
+static size_t captured_len = 0, captured_alloc = 0;
+static bool capturing_text = false;
+
 void
 capture_to_diagnostic(ttbc_diagnostic_t *diagnostic)
 {
@@ -72,9 +78,48 @@ error_here_with_diagnostic(const char* message)
     return error;
 }
 
+void
+begin_text_capture(void)
+{
+    captured_len = 0;
+    capturing_text = true;
+
+    if (captured_text != NULL)
+        captured_text[0] = '\0';
+}
+
+
+const char *
+end_text_capture(void)
+{
+    capturing_text = false;
+
+    if (captured_text == NULL)
+        return "";
+
+    return captured_text;
+}
+
+
+static void
+capture_char(char c)
+{
+    if (captured_len + 2 > captured_alloc) {
+        captured_alloc = captured_alloc ? 2 * captured_alloc : 256;
+        captured_text = xrealloc(captured_text, captured_alloc);
+    }
+
+    captured_text[captured_len++] = c;
+    captured_text[captured_len] = '\0';
+}
+
+
 static void
 warn_char(int c)
 {
+    if (capturing_text)
+        capture_char(c);
+
     if (current_diagnostic) {
         char bytes[2] = { c, 0 };
         ttbc_diag_append(current_diagnostic, bytes);
@@ -40,9 +40,8 @@ XeTeX_pic.c
 #include "xetex-xetexd.h"
 #include "xetex-ext.h"
 #include "dpx-dpxutil.h"
-#include "dpx-pdfdoc.h"
-#include "dpx-pdfdraw.h"
-#include "dpx-pdfobj.h"
+#include "dpx-epdf.h"
+#include "dpx-error.h"
 #include "dpx-pngimage.h"
 #include "dpx-jpegimage.h"
 #include "dpx-bmpimage.h"
@@ -77,41 +76,39 @@ count_pdf_file_pages (void)
 {
     int pages;
     rust_input_handle_t handle;
-    pdf_file *pf;
+    TectonicPdfDocument *doc;
 
     handle = ttstub_input_open (name_of_file, TTBC_FILE_FORMAT_PICT, 0);
     if (handle == NULL)
         return 0;
 
-    if ((pf = pdf_open(name_of_file, handle)) == NULL) {
-        /* TODO: issue warning */
-        ttstub_input_close(handle);
+    /* Tectonic: use the Rust PDF reader, which warns about broken files. */
+    doc = epdf_load_document(handle, name_of_file);
+    ttstub_input_close(handle);
+
+    if (doc == NULL)
         return 0;
-    }
 
-    pages = pdf_doc_get_page_count(pf);
-    pdf_close(pf);
-    ttstub_input_close(handle);
-    return pages;
+    pages = tectonic_pdf_document_page_count(doc);
+    tectonic_pdf_document_free(doc);
+    return pages < 0 ? 0 : pages;
 }
 
 
 static int
 pdf_get_rect (char *filename, rust_input_handle_t handle, int page_num, int pdf_box, real_rect* box)
 {
-    int pages, dpx_options;
-    pdf_file *pf;
-    pdf_obj *page;
-    pdf_rect bbox;
-    pdf_tmatrix matrix;
-    pdf_coord p1, p2, p3, p4;
-
-    if ((pf = pdf_open(filename, handle)) == NULL) {
-        /* TODO: issue warning */
+    int pages, dpx_options, err;
+    TectonicPdfDocument *doc;
+    TectonicPdfPageGeometry geom;
+    char errbuf[256];
+
+    /* Tectonic: use the Rust PDF reader, so that a damaged figure produces a
+     * warning rather than an abort. */
+    if ((doc = epdf_load_document(handle, filename)) == NULL)
         return -1;
-    }
 
-    pages = pdf_doc_get_page_count(pf);
+    pages = tectonic_pdf_document_page_count(doc);
 
     if (page_num > pages)
         page_num = pages;
@@ -143,44 +140,21 @@ pdf_get_rect (char *filename, rust_input_handle_t handle, int page_num, int pdf_
         break;
     }
 
-    page = pdf_doc_get_page(pf, page_num, dpx_options, &bbox, &matrix, NULL);
-    pdf_close(pf);
+    err = tectonic_pdf_document_page_geometry(doc, page_num, dpx_options, &geom,
+                                              errbuf, sizeof(errbuf));
+    tectonic_pdf_document_free(doc);
 
-    if (page == NULL) {
-        /* TODO: issue warning */
+    if (err) {
+        dpx_warning("Cannot read page %d of PDF file \"%s\": %s", page_num, filename, errbuf);
         return -1;
     }
 
-    pdf_release_obj(page);
-
-    /* Image's attribute "bbox" here is affected by /Rotate entry of included
-     * PDF page.
-     */
-    p1.x = bbox.llx;
-    p1.y = bbox.lly;
-    pdf_dev_transform(&p1, &matrix);
-
-    p2.x = bbox.urx;
-    p2.y = bbox.lly;
-    pdf_dev_transform(&p2, &matrix);
-
-    p3.x = bbox.urx;
-    p3.y = bbox.ury;
-    pdf_dev_transform(&p3, &matrix);
-
-    p4.x = bbox.llx;
-    p4.y = bbox.ury;
-    pdf_dev_transform(&p4, &matrix);
-
-    bbox.llx = min4(p1.x, p2.x, p3.x, p4.x);
-    bbox.lly = min4(p1.y, p2.y, p3.y, p4.y);
-    bbox.urx = max4(p1.x, p2.x, p3.x, p4.x);
-    bbox.ury = max4(p1.y, p2.y, p3.y, p4.y);
-
-    box->x = 72.27 / 72 * bbox.llx;
-    box->y = 72.27 / 72 * bbox.lly;
-    box->wd = 72.27 / 72 * (bbox.urx - bbox.llx);
-    box->ht = 72.27 / 72 * (bbox.ury - bbox.lly);
+    /* The displayed box accounts for the /Rotate entry of the included PDF
+     * page. */
+    box->x = 72.27 / 72 * geom.displayed.llx;
+    box->y = 72.27 / 72 * geom.displayed.lly;
+    box->wd = 72.27 / 72 * (geom.displayed.urx - geom.displayed.llx);
+    box->ht = 72.27 / 72 * (geom.displayed.ury - geom.displayed.lly);
 
     return 0;
 }
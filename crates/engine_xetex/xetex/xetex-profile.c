@@ -0,0 +1,306 @@
+/* tectonic/xetex-profile.c: a sampling profiler for TeX macros
+   Copyright 2026 The Tectonic Project
+   Licensed under the MIT License.
+*/
+
+/* When macro profiling is enabled, every SAMPLE_INTERVAL calls to get_next()
+ * we look at the input stack, which records the macros being expanded and the
+ * files being read, and charge the processor time that has elapsed since the
+ * previous sample to the resulting "stack trace". When the engine finishes,
+ * the accumulated stacks are written to `<jobname>.folded` in the "folded"
+ * format understood by flame graph tools such as flamegraph.pl, inferno, and
+ * speedscope: one line per distinct stack, with semicolon-separated frames
+ * from the outermost inwards, followed by the number of microseconds spent
+ * there.
+ *
+ * Macro frames are named by their control sequences, and file frames by the
+ * file name and the line currently being read from it. Other kinds of token
+ * lists (backed-up tokens, \everypar, etc.) are not shown.
+ */
+
+#include "xetex-core.h"
+#include "xetex-xetexd.h"
+#include "xetex-stringpool.h"
+
+#include <stdio.h>
+#include <string.h>
+#include <time.h>
+
+#define SAMPLE_INTERVAL 256
+
+typedef struct {
+    int32_t id;   /* eqtb pointer of a macro, or the negated name of a file */
+    int32_t line; /* the line being read from a file; zero for macros */
+} frame_t;
+
+typedef struct {
+    uint64_t hash;
+    size_t offset; /* index of the first frame in `frames` */
+    size_t depth;
+    uint64_t micros;
+} profile_stack_t;
+
+static unsigned int ticks;
+static clock_t last_clock;
+
+/* Storage for the frames of all of the distinct stacks seen so far. */
+static frame_t *frames;
+static size_t n_frames, frames_alloc;
+
+/* An open-addressed hash table of stacks. `table_alloc` is a power of 2. */
+static profile_stack_t *table;
+static size_t n_stacks, table_alloc;
+
+/* The stack being sampled. */
+static frame_t *scratch;
+static size_t scratch_alloc;
+
+
+static void
+free_profile_data(void)
+{
+    free(frames);
+    frames = NULL;
+    n_frames = frames_alloc = 0;
+
+    free(table);
+    table = NULL;
+    n_stacks = table_alloc = 0;
+
+    free(scratch);
+    scratch = NULL;
+    scratch_alloc = 0;
+}
+
+
+void
+macro_profile_init(void)
+{
+    free_profile_data();
+    ticks = 0;
+    last_clock = clock();
+}
+
+
+static uint64_t
+hash_stack(const frame_t *stack, size_t depth)
+{
+    /* FNV-1a */
+    uint64_t h = 0xcbf29ce484222325ULL;
+
+    for (size_t i = 0; i < depth; i++) {
+        h = (h ^ (uint32_t) stack[i].id) * 0x100000001b3ULL;
+        h = (h ^ (uint32_t) stack[i].line) * 0x100000001b3ULL;
+    }
+
+    return h;
+}
+
+
+static profile_stack_t *
+find_slot(profile_stack_t *tbl, size_t alloc, uint64_t h, const frame_t *stack, size_t depth)
+{
+    size_t i = h & (alloc - 1);
+
+    while (tbl[i].depth != 0) {
+        if (tbl[i].hash == h && tbl[i].depth == depth &&
+            memcmp(&frames[tbl[i].offset], stack, depth * sizeof(frame_t)) == 0)
+            break;
+
+        i = (i + 1) & (alloc - 1);
+    }
+
+    return &tbl[i];
+}
+
+
+static void
+grow_table(void)
+{
+    size_t new_alloc = table_alloc ? table_alloc * 2 : 1024;
+    profile_stack_t *new_table = xcalloc(new_alloc, sizeof(profile_stack_t));
+
+    for (size_t i = 0; i < table_alloc; i++) {
+        if (table[i].depth != 0)
+            *find_slot(new_table, new_alloc, table[i].hash, &frames[table[i].offset],
+                       table[i].depth) = table[i];
+    }
+
+    free(table);
+    table = new_table;
+    table_alloc = new_alloc;
+}
+
+
+static void
+take_sample(uint64_t micros)
+{
+    size_t depth = 0;
+
+    if (scratch_alloc < (size_t) input_ptr + 1) {
+        scratch_alloc = input_ptr + 64;
+        scratch = xrealloc(scratch, scratch_alloc * sizeof(frame_t));
+    }
+
+    for (int32_t k = 0; k <= input_ptr; k++) {
+        input_state_t *level = (k == input_ptr) ? &cur_input : &input_stack[k];
+
+        if (level->state == TOKEN_LIST) {
+            if (level->index == MACRO) {
+                scratch[depth].id = level->name;
+                scratch[depth].line = 0;
+                depth++;
+            }
+        } else if (level->name > 17) {
+            scratch[depth].id = -level->name;
+            scratch[depth].line = (level->index == in_open) ? line : line_stack[level->index + 1];
+            depth++;
+        }
+    }
+
+    if (depth == 0)
+        return;
+
+    if (2 * (n_stacks + 1) > table_alloc)
+        grow_table();
+
+    uint64_t h = hash_stack(scratch, depth);
+    profile_stack_t *slot = find_slot(table, table_alloc, h, scratch, depth);
+
+    if (slot->depth == 0) {
+        if (n_frames + depth > frames_alloc) {
+            frames_alloc = (n_frames + depth) * 2;
+            frames = xrealloc(frames, frames_alloc * sizeof(frame_t));
+        }
+
+        memcpy(&frames[n_frames], scratch, depth * sizeof(frame_t));
+        slot->hash = h;
+        slot->offset = n_frames;
+        slot->depth = depth;
+        slot->micros = 0;
+        n_frames += depth;
+        n_stacks++;
+    }
+
+    slot->micros += micros;
+}
+
+
+void
+macro_profile_tick(void)
+{
+    clock_t now;
+
+    if (++ticks < SAMPLE_INTERVAL)
+        return;
+
+    ticks = 0;
+    now = clock();
+    take_sample((uint64_t) (now - last_clock) * 1000000 / CLOCKS_PER_SEC);
+    last_clock = now;
+}
+
+
+/* Get the name of a frame as a newly allocated UTF-8 string. */
+static char *
+frame_name(const frame_t *frame)
+{
+    char *name;
+    selector_t prev_selector;
+    str_number s;
+    size_t n;
+
+    if (frame->id < 0) {
+        int32_t file = -frame->id;
+
+        if (file == 18 || file == 19)
+            name = xstrdup("<scantokens>");
+        else
+            name = gettexstring(file);
+
+        n = strlen(name);
+        name = xrealloc(name, n + 16);
+        snprintf(name + n, 16, ":%d", frame->line);
+        return name;
+    }
+
+    /* Let TeX do the work of printing the control sequence into a temporary
+     * string, so that we handle all of the special cases the same way. */
+
+    prev_selector = selector;
+    selector = SELECTOR_NEW_STRING;
+    sprint_cs(frame->id);
+    selector = prev_selector;
+    s = make_string();
+    name = gettexstring(s);
+    str_ptr--; /* "flush_string" */
+    pool_ptr = str_start[str_ptr - TOO_BIG_CHAR];
+
+    /* Semicolons separate frames in the folded format, and there's no way to
+     * escape them, so replace them with the look-alike U+037E. This is needed
+     * for common control sequences like `\;`. */
+
+    if (strchr(name, ';') != NULL) {
+        n = strlen(name);
+        char *escaped = xmalloc(2 * n + 1);
+        char *p = escaped;
+
+        for (size_t i = 0; i < n; i++) {
+            if (name[i] == ';') {
+                *p++ = '\xcd';
+                *p++ = '\xbe';
+            } else {
+                *p++ = name[i];
+            }
+        }
+
+        *p = '\0';
+        free(name);
+        name = escaped;
+    }
+
+    return name;
+}
+
+
+void
+macro_profile_finish(void)
+{
+    rust_output_handle_t out;
+
+    if (job_name == 0 || n_stacks == 0) {
+        free_profile_data();
+        return;
+    }
+
+    pack_job_name(".folded");
+    out = ttstub_output_open(name_of_file, 0);
+
+    if (out == NULL) {
+        ttstub_issue_warning("could not open macro profile output file \"%s\"", name_of_file);
+        free_profile_data();
+        return;
+    }
+
+    for (size_t i = 0; i < table_alloc; i++) {
+        profile_stack_t *stack = &table[i];
+
+        if (stack->depth == 0 || stack->micros == 0)
+            continue;
+
+        for (size_t j = 0; j < stack->depth; j++) {
+            char *name = frame_name(&frames[stack->offset + j]);
+
+            if (j > 0)
+                ttstub_output_putc(out, ';');
+
+            ttstub_output_write(out, name, strlen(name));
+            free(name);
+        }
+
+        ttstub_fprintf(out, " %" PRIu64 "\n", stack->micros);
+    }
+
+    ttstub_output_close(out);
+    free_profile_data();
+}
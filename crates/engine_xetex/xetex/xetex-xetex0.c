@@ -5163,6 +5163,9 @@ get_next(void)
     small_number d;
     small_number sup_count;
 
+    if (macro_profile_enabled)
+        macro_profile_tick();
+
 restart:
     cur_cs = 0;
 
@@ -18432,6 +18435,32 @@ close_files_and_terminate(void)
     finalize_dvi_file();
     synctex_terminate(log_opened);
 
+    if (macro_profile_enabled)
+        macro_profile_finish();
+
+    /* Tectonic: restore the classic memory usage report, which the driver can
+     * request for profiling. \tracingstats alone doesn't enable it, so that
+     * the logs of existing documents don't change. */
+    if (log_opened && memory_stats_enabled) {
+        ttstub_fprintf(log_file, " \nHere is how much of TeX's memory you used:\n");
+        ttstub_fprintf(log_file, " %d string%s out of %d\n", str_ptr - init_str_ptr,
+                       (str_ptr != init_str_ptr + 1) ? "s" : "", max_strings - init_str_ptr);
+        ttstub_fprintf(log_file, " %d string characters out of %d\n", pool_ptr - init_pool_ptr,
+                       pool_size - init_pool_ptr);
+        ttstub_fprintf(log_file, " %d words of memory out of %d\n",
+                       lo_mem_max + mem_end - hi_mem_min + 2, mem_end + 1);
+        ttstub_fprintf(log_file, " %d multiletter control sequences out of %d+%d\n",
+                       cs_count, HASH_SIZE, hash_extra);
+        ttstub_fprintf(log_file, " %d words of font info for %d font%s, out of %d for %d\n",
+                       fmem_ptr, font_ptr - FONT_BASE, (font_ptr != FONT_BASE + 1) ? "s" : "",
+                       font_mem_size, font_max - FONT_BASE);
+        ttstub_fprintf(log_file, " %d hyphenation exception%s out of %d\n", hyph_count,
+                       (hyph_count != 1) ? "s" : "", hyph_size);
+        ttstub_fprintf(log_file, " %di,%dn,%dp,%db,%ds stack positions out of %di,%dn,%dp,%db,%ds\n",
+                       max_in_stack, max_nest_stack, max_param_stack, max_buf_stack + 1,
+                       max_save_stack + 6, stack_size, nest_size, param_size, buf_size, save_size);
+    }
+
     if (log_opened) {
         ttstub_output_putc (log_file, '\n');
         ttstub_output_close (log_file);
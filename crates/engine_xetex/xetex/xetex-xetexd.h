@@ -722,6 +722,9 @@ extern scaled_t delta;
 extern int synctex_enabled;
 extern bool used_tectonic_coda_tokens;
 extern bool semantic_pagination_enabled;
+extern bool memory_stats_enabled;
+extern bool macro_profile_enabled;
+extern bool interactive_errors_enabled;
 extern bool gave_char_warning_help;
 
 /*:1683*/
@@ -1095,6 +1098,12 @@ void capture_to_diagnostic(ttbc_diagnostic_t *diagnostic);
 // complete.
 ttbc_diagnostic_t *error_here_with_diagnostic(const char* message);
 
+// While a text capture is active, everything printed to the terminal or the
+// log is also saved in a buffer. end_text_capture() returns the saved text,
+// which remains valid until the next capture begins.
+void begin_text_capture(void);
+const char *end_text_capture(void);
+
 void print_ln(void);
 void print_raw_char(UTF16_code s, bool incr_offset);
 void print_char(int32_t s);
@@ -1125,6 +1134,12 @@ void print_ucs_code(UnicodeScalar n);
 void initialize_pagebuilder_variables(void);
 void build_page(void);
 
+/* xetex-profile */
+
+void macro_profile_init(void);
+void macro_profile_tick(void);
+void macro_profile_finish(void);
+
 /* xetex-scaledmath */
 
 int32_t tex_round(double);
@@ -0,0 +1,1247 @@
+// Copyright 2026 the Tectonic Project
+// Licensed under the MIT License.
+
+#![deny(missing_docs)]
+
+//! Extracting glyph outlines from OpenType fonts.
+//!
+//! Tectonic's vector output formats, like SVG, draw glyphs from their outlines
+//! so that the output doesn't depend on web fonts, which browsers won't load
+//! for SVG images referenced from `<img>` tags. The `pinot` crate doesn't parse
+//! glyph outlines, so we do it ourselves. We support TrueType (`glyf`) outlines
+//! and CFF outlines with Type 2 charstrings, which covers the fonts that TeX
+//! documents typically use. The outlines are converted into SVG path data, in
+//! font units with the Y axis pointing up.
+
+use byteorder::{BigEndian, ByteOrder};
+use pinot::{head::HEAD, types::Tag, FontDataRef, TableProvider};
+use std::{convert::TryFrom, fmt::Write};
+use tectonic_errors::prelude::*;
+
+pub mod svg;
+
+/// A numerical identifier of a glyph in a font.
+pub type GlyphId = u16;
+
+const CFF: Tag = Tag(0x43_46_46_20);
+const GLYF: Tag = Tag(0x67_6c_79_66);
+const LOCA: Tag = Tag(0x6c_6f_63_61);
+
+/// The maximum nesting of composite glyphs and charstring subroutines.
+const MAX_NESTING: usize = 10;
+
+/// The maximum size of the Type 2 charstring argument stack.
+const MAX_CHARSTRING_ARGS: usize = 48;
+
+/// The outline of a glyph.
+#[derive(Debug)]
+pub struct GlyphOutline {
+    /// SVG path data, in font units with the Y axis pointing up.
+    pub path: String,
+
+    /// A bounding box of the outline, as `[x_min, y_min, x_max, y_max]` in font
+    /// units, or None if the outline is empty. This includes curve control
+    /// points, so it may be larger than the tightest possible box.
+    pub bbox: Option<[f32; 4]>,
+}
+
+/// Information needed to extract glyph outlines from a font.
+///
+/// This holds offsets into the complete font data, rather than references to
+/// it, so that it can be stored alongside the data that it describes. The same
+/// data must be passed to [`Outlines::glyph_outline`] as was used to create the
+/// object.
+#[derive(Debug)]
+pub struct Outlines {
+    format: Format,
+    units_per_em: u16,
+}
+
+#[derive(Debug)]
+enum Format {
+    TrueType {
+        glyf: (usize, usize),
+        loca: (usize, usize),
+        long_offsets: bool,
+    },
+
+    Cff {
+        charstrings: Index,
+        global_subrs: Index,
+        local_subrs: LocalSubrs,
+    },
+}
+
+#[derive(Debug)]
+enum LocalSubrs {
+    Single(Option<Index>),
+
+    /// A CID-keyed font, where each glyph can use a different set of local
+    /// subroutines.
+    Cid {
+        fd_select: usize,
+        fd_subrs: Vec<Option<Index>>,
+    },
+}
+
+impl Outlines {
+    /// Figure out how to get outlines from a face in the given OpenType data.
+    ///
+    /// Returns None if the data can't be parsed, or the font has no outline
+    /// data that we understand.
+    pub fn new(data: &[u8], face_index: u32) -> Option<Self> {
+        let font = FontDataRef::new(data)?.get(face_index)?;
+        let units_per_em = font.head()?.units_per_em();
+
+        let table = |tag: Tag| {
+            font.records()
+                .iter()
+                .find(|rec| rec.tag == tag)
+                .map(|rec| (rec.offset as usize, rec.offset as usize + rec.len as usize))
+                .filter(|&(_, end)| end <= data.len())
+        };
+
+        let format = if let Some(cff) = table(CFF) {
+            parse_cff(data, cff.0).ok()?
+        } else {
+            let glyf = table(GLYF)?;
+            let loca = table(LOCA)?;
+            let head = table(HEAD)?;
+
+            // This is the `indexToLocFormat` field of the HEAD table.
+            let long_offsets = BigEndian::read_i16(data.get(head.0 + 50..head.0 + 52)?) != 0;
+
+            Format::TrueType {
+                glyf,
+                loca,
+                long_offsets,
+            }
+        };
+
+        Some(Outlines {
+            format,
+            units_per_em,
+        })
+    }
+
+    /// Get the number of font units per em.
+    pub fn units_per_em(&self) -> u16 {
+        self.units_per_em
+    }
+
+    /// Get the outline of a glyph.
+    ///
+    /// Glyphs without any outline, like spaces, yield an empty path.
+    pub fn glyph_outline(&self, data: &[u8], glyph: GlyphId) -> Result<GlyphOutline> {
+        let mut path = PathBuilder::default();
+
+        match &self.format {
+            Format::TrueType {
+                glyf,
+                loca,
+                long_offsets,
+            } => {
+                let font = TrueTypeFont {
+                    glyf: a_ok_or!(data.get(glyf.0..glyf.1); ["invalid glyf table"]),
+                    loca: a_ok_or!(data.get(loca.0..loca.1); ["invalid loca table"]),
+                    long_offsets: *long_offsets,
+                };
+
+                let mut contours = Vec::new();
+                font.load_contours(glyph, [1., 0., 0., 1., 0., 0.], 0, &mut contours)?;
+
+                for contour in &contours {
+                    path.quadratic_contour(contour);
+                }
+            }
+
+            Format::Cff {
+                charstrings,
+                global_subrs,
+                local_subrs,
+            } => {
+                let local_subrs = match local_subrs {
+                    LocalSubrs::Single(s) => s.as_ref(),
+                    LocalSubrs::Cid {
+                        fd_select,
+                        fd_subrs,
+                    } => {
+                        let fd = fd_select_lookup(data, *fd_select, glyph)?;
+                        a_ok_or!(fd_subrs.get(fd); ["invalid CFF FDSelect for glyph {}", glyph])
+                            .as_ref()
+                    }
+                };
+
+                let code = a_ok_or!(
+                    charstrings.get(data, glyph as usize);
+                    ["no CFF charstring for glyph {}", glyph]
+                );
+
+                let mut interp = CharstringInterpreter {
+                    data,
+                    global_subrs,
+                    local_subrs,
+                    path: &mut path,
+                    stack: Vec::new(),
+                    x: 0.,
+                    y: 0.,
+                    n_stems: 0,
+                    seen_width: false,
+                    open: false,
+                };
+
+                interp.run(code, 0)?;
+                interp.close();
+            }
+        }
+
+        Ok(GlyphOutline {
+            path: path.d,
+            bbox: path.bbox,
+        })
+    }
+}
+
+/// Accumulates SVG path data.
+#[derive(Debug, Default)]
+struct PathBuilder {
+    d: String,
+    bbox: Option<[f32; 4]>,
+}
+
+impl PathBuilder {
+    /// Add a path command. The coordinates come in (x, y) pairs.
+    fn command(&mut self, cmd: char, coords: &[f32]) {
+        self.d.push(cmd);
+
+        for (i, c) in coords.iter().enumerate() {
+            if i > 0 {
+                self.d.push(' ');
+            }
+
+            write!(self.d, "{c}").unwrap();
+        }
+
+        for pt in coords.chunks_exact(2) {
+            let (x, y) = (pt[0], pt[1]);
+
+            self.bbox = Some(match self.bbox {
+                None => [x, y, x, y],
+                Some(b) => [b[0].min(x), b[1].min(y), b[2].max(x), b[3].max(y)],
+            });
+        }
+    }
+
+    /// Add a TrueType contour of quadratic curves.
+    ///
+    /// Each point is `(x, y, on_curve)`. Two consecutive off-curve points have
+    /// an implied on-curve point midway between them.
+    fn quadratic_contour(&mut self, points: &[(f32, f32, bool)]) {
+        let n = points.len();
+
+        if n == 0 {
+            return;
+        }
+
+        let mid = |a: (f32, f32, bool), b: (f32, f32, bool)| ((a.0 + b.0) / 2., (a.1 + b.1) / 2.);
+
+        // Figure out where to start the path, and which points to process
+        // after the start point.
+        let (start, first, count) = if points[0].2 {
+            ((points[0].0, points[0].1), 1, n - 1)
+        } else if points[n - 1].2 {
+            ((points[n - 1].0, points[n - 1].1), 0, n - 1)
+        } else {
+            (mid(points[n - 1], points[0]), 0, n)
+        };
+
+        self.command('M', &[start.0, start.1]);
+        let mut control: Option<(f32, f32)> = None;
+
+        for i in 0..count {
+            let p = points[(first + i) % n];
+
+            match (p.2, control) {
+                (true, None) => self.command('L', &[p.0, p.1]),
+                (true, Some(c)) => {
+                    self.command('Q', &[c.0, c.1, p.0, p.1]);
+                    control = None;
+                }
+                (false, None) => control = Some((p.0, p.1)),
+                (false, Some(c)) => {
+                    let m = mid((c.0, c.1, false), p);
+                    self.command('Q', &[c.0, c.1, m.0, m.1]);
+                    control = Some((p.0, p.1));
+                }
+            }
+        }
+
+        if let Some(c) = control {
+            self.command('Q', &[c.0, c.1, start.0, start.1]);
+        }
+
+        self.d.push('Z');
+    }
+}
+
+// TrueType outlines
+
+struct TrueTypeFont<'a> {
+    glyf: &'a [u8],
+    loca: &'a [u8],
+    long_offsets: bool,
+}
+
+/// A 2D affine transform, `[a, b, c, d, e, f]` mapping `(x, y)` to `(ax + cy +
+/// e, bx + dy + f)`.
+type Transform = [f32; 6];
+
+impl<'a> TrueTypeFont<'a> {
+    fn glyph_data(&self, glyph: GlyphId) -> Option<&'a [u8]> {
+        let i = glyph as usize;
+
+        let (start, end) = if self.long_offsets {
+            let offsets = self.loca.get(4 * i..4 * i + 8)?;
+            (
+                BigEndian::read_u32(&offsets[..4]) as usize,
+                BigEndian::read_u32(&offsets[4..]) as usize,
+            )
+        } else {
+            let offsets = self.loca.get(2 * i..2 * i + 4)?;
+            (
+                2 * BigEndian::read_u16(&offsets[..2]) as usize,
+                2 * BigEndian::read_u16(&offsets[2..]) as usize,
+            )
+        };
+
+        if end < start {
+            return None;
+        }
+
+        self.glyf.get(start..end)
+    }
+
+    fn load_contours(
+        &self,
+        glyph: GlyphId,
+        xform: Transform,
+        depth: usize,
+        contours: &mut Vec<Vec<(f32, f32, bool)>>,
+    ) -> Result<()> {
+        ensure!(
+            depth < MAX_NESTING,
+            "composite glyphs nested too deeply in glyf table"
+        );
+
+        let data = a_ok_or!(
+            self.glyph_data(glyph);
+            ["invalid glyf/loca data for glyph {}", glyph]
+        );
+
+        // Glyphs without outlines have no data at all.
+        if data.is_empty() {
+            return Ok(());
+        }
+
+        let mut r = Reader::new(data);
+        let n_contours = r.i16()?;
+        r.skip(8)?; // bounding box
+
+        if n_contours < 0 {
+            return self.load_composite(r, xform, depth, contours);
+        }
+
+        let mut end_points = Vec::with_capacity(n_contours as usize);
+
+        for _ in 0..n_contours {
+            end_points.push(r.u16()? as usize);
+        }
+
+        let n_points = end_points.last().map(|e| e + 1).unwrap_or(0);
+        let n_instructions = r.u16()? as usize;
+        r.skip(n_instructions)?;
+
+        // Flags, which may be run-length encoded.
+
+        const ON_CURVE: u8 = 0x01;
+        const X_SHORT: u8 = 0x02;
+        const Y_SHORT: u8 = 0x04;
+        const REPEAT: u8 = 0x08;
+        const X_SAME_OR_POSITIVE: u8 = 0x10;
+        const Y_SAME_OR_POSITIVE: u8 = 0x20;
+
+        let mut flags = Vec::with_capacity(n_points);
+
+        while flags.len() < n_points {
+            let f = r.u8()?;
+            flags.push(f);
+
+            if f & REPEAT != 0 {
+                for _ in 0..r.u8()? {
+                    flags.push(f);
+                }
+            }
+        }
+
+        flags.truncate(n_points);
+
+        // Coordinates, which are delta-encoded.
+
+        let mut read_coords = |short: u8, same_or_pos: u8| -> Result<Vec<i32>> {
+            let mut coords = Vec::with_capacity(n_points);
+            let mut v = 0i32;
+
+            for f in &flags {
+                if f & short != 0 {
+                    let d = r.u8()? as i32;
+                    v += if f & same_or_pos != 0 { d } else { -d };
+                } else if f & same_or_pos == 0 {
+                    v += r.i16()? as i32;
+                }
+
+                coords.push(v);
+            }
+
+            Ok(coords)
+        };
+
+        let xs = read_coords(X_SHORT, X_SAME_OR_POSITIVE)?;
+        let ys = read_coords(Y_SHORT, Y_SAME_OR_POSITIVE)?;
+
+        let mut start = 0;
+
+        for end in end_points {
+            ensure!(
+                end >= start && end < n_points,
+                "invalid contour data for glyph {}",
+                glyph
+            );
+
+            contours.push(
+                (start..=end)
+                    .map(|i| {
+                        let (x, y) = (xs[i] as f32, ys[i] as f32);
+                        (
+                            xform[0] * x + xform[2] * y + xform[4],
+                            xform[1] * x + xform[3] * y + xform[5],
+                            flags[i] & ON_CURVE != 0,
+                        )
+                    })
+                    .collect(),
+            );
+
+            start = end + 1;
+        }
+
+        Ok(())
+    }
+
+    fn load_composite(
+        &self,
+        mut r: Reader,
+        xform: Transform,
+        depth: usize,
+        contours: &mut Vec<Vec<(f32, f32, bool)>>,
+    ) -> Result<()> {
+        const ARG_1_AND_2_ARE_WORDS: u16 = 0x0001;
+        const ARGS_ARE_XY_VALUES: u16 = 0x0002;
+        const WE_HAVE_A_SCALE: u16 = 0x0008;
+        const MORE_COMPONENTS: u16 = 0x0020;
+        const WE_HAVE_AN_X_AND_Y_SCALE: u16 = 0x0040;
+        const WE_HAVE_A_TWO_BY_TWO: u16 = 0x0080;
+
+        loop {
+            let flags = r.u16()?;
+            let component = r.u16()?;
+
+            let (dx, dy) = if flags & ARG_1_AND_2_ARE_WORDS != 0 {
+                (r.i16()? as f32, r.i16()? as f32)
+            } else {
+                (r.u8()? as i8 as f32, r.u8()? as i8 as f32)
+            };
+
+            // Positioning components by matching points isn't supported; such
+            // components are just placed at the origin.
+            let (dx, dy) = if flags & ARGS_ARE_XY_VALUES != 0 {
+                (dx, dy)
+            } else {
+                (0., 0.)
+            };
+
+            let f2dot14 = |r: &mut Reader| -> Result<f32> { Ok(r.i16()? as f32 / 16384.) };
+
+            let (a, b, c, d) = if flags & WE_HAVE_A_SCALE != 0 {
+                let s = f2dot14(&mut r)?;
+                (s, 0., 0., s)
+            } else if flags & WE_HAVE_AN_X_AND_Y_SCALE != 0 {
+                (f2dot14(&mut r)?, 0., 0., f2dot14(&mut r)?)
+            } else if flags & WE_HAVE_A_TWO_BY_TWO != 0 {
+                (
+                    f2dot14(&mut r)?,
+                    f2dot14(&mut r)?,
+                    f2dot14(&mut r)?,
+                    f2dot14(&mut r)?,
+                )
+            } else {
+                (1., 0., 0., 1.)
+            };
+
+            // Compose the component transform with the one for this glyph.
+            let composed = [
+                xform[0] * a + xform[2] * b,
+                xform[1] * a + xform[3] * b,
+                xform[0] * c + xform[2] * d,
+                xform[1] * c + xform[3] * d,
+                xform[0] * dx + xform[2] * dy + xform[4],
+                xform[1] * dx + xform[3] * dy + xform[5],
+            ];
+
+            self.load_contours(component, composed, depth + 1, contours)?;
+
+            if flags & MORE_COMPONENTS == 0 {
+                return Ok(());
+            }
+        }
+    }
+}
+
+// CFF outlines
+
+/// A CFF INDEX structure: an array of variable-sized data items.
+#[derive(Debug)]
+struct Index {
+    count: usize,
+    off_size: usize,
+
+    /// The position of the offset array in the font data.
+    offsets: usize,
+
+    /// The position just before the start of the item data, which is what the
+    /// offsets are relative to.
+    data_base: usize,
+
+    /// The position just past the end of the INDEX.
+    end: usize,
+}
+
+impl Index {
+    fn parse(data: &[u8], pos: usize) -> Result<Self> {
+        let mut r = Reader::new_at(data, pos);
+        let count = r.u16()? as usize;
+
+        if count == 0 {
+            return Ok(Index {
+                count: 0,
+                off_size: 1,
+                offsets: pos + 2,
+                data_base: pos + 2,
+                end: pos + 2,
+            });
+        }
+
+        let off_size = r.u8()? as usize;
+        ensure!((1..=4).contains(&off_size), "invalid CFF INDEX offset size");
+
+        let offsets = pos + 3;
+        let data_base = offsets + (count + 1) * off_size - 1;
+
+        let mut index = Index {
+            count,
+            off_size,
+            offsets,
+            data_base,
+            end: 0,
+        };
+
+        index.end = data_base + a_ok_or!(index.offset(data, count); ["invalid CFF INDEX"]);
+        ensure!(index.end <= data.len(), "invalid CFF INDEX");
+        Ok(index)
+    }
+
+    fn offset(&self, data: &[u8], i: usize) -> Option<usize> {
+        let pos = self.offsets + i * self.off_size;
+        let bytes = data.get(pos..pos + self.off_size)?;
+        Some(BigEndian::read_uint(bytes, self.off_size) as usize)
+    }
+
+    fn get<'a>(&self, data: &'a [u8], i: usize) -> Option<&'a [u8]> {
+        if i >= self.count {
+            return None;
+        }
+
+        let start = self.offset(data, i)?;
+        let end = self.offset(data, i + 1)?;
+
+        if start == 0 || end < start {
+            return None;
+        }
+
+        data.get(self.data_base + start..self.data_base + end)
+    }
+
+    /// Shift the positions in this INDEX, which is useful when it was parsed
+    /// from a subset of the font data.
+    fn relocate(self, delta: usize) -> Self {
+        Index {
+            offsets: self.offsets + delta,
+            data_base: self.data_base + delta,
+            end: self.end + delta,
+            ..self
+        }
+    }
+
+    /// The bias applied to subroutine numbers.
+    fn subr_bias(&self) -> i32 {
+        if self.count < 1240 {
+            107
+        } else if self.count < 33900 {
+            1131
+        } else {
+            32768
+        }
+    }
+}
+
+/// The DICT entries that we care about.
+#[derive(Debug, Default)]
+struct Dict {
+    charstrings: Option<usize>,
+    private: Option<(usize, usize)>,
+    subrs: Option<usize>,
+    fd_array: Option<usize>,
+    fd_select: Option<usize>,
+    charstring_type: Option<i32>,
+}
+
+impl Dict {
+    fn parse(data: &[u8]) -> Result<Self> {
+        let mut dict = Dict::default();
+        let mut operands: Vec<f64> = Vec::new();
+        let mut r = Reader::new(data);
+
+        while !r.is_empty() {
+            let b0 = r.u8()?;
+
+            let int_operand = |i: usize| operands.get(i).map(|v| *v as usize).unwrap_or(0);
+
+            match b0 {
+                0..=21 => {
+                    let op = if b0 == 12 {
+                        1200 + r.u8()? as u16
+                    } else {
+                        b0 as u16
+                    };
+
+                    match op {
+                        17 => dict.charstrings = Some(int_operand(0)),
+                        18 => dict.private = Some((int_operand(0), int_operand(1))),
+                        19 => dict.subrs = Some(int_operand(0)),
+                        1206 => dict.charstring_type = operands.first().map(|v| *v as i32),
+                        1236 => dict.fd_array = Some(int_operand(0)),
+                        1237 => dict.fd_select = Some(int_operand(0)),
+                        _ => {}
+                    }
+
+                    operands.clear();
+                }
+
+                28 => operands.push(r.i16()? as f64),
+                29 => operands.push(r.i32()? as f64),
+
+                30 => {
+                    // A real number, as a series of nibbles. We don't need any
+                    // real-valued entries, so we just skip it.
+                    loop {
+                        let b = r.u8()?;
+
+                        if b & 0x0F == 0x0F || b & 0xF0 == 0xF0 {
+                            break;
+                        }
+                    }
+
+                    operands.push(0.);
+                }
+
+                32..=246 => operands.push(b0 as f64 - 139.),
+                247..=250 => operands.push(((b0 as f64 - 247.) * 256.) + r.u8()? as f64 + 108.),
+                251..=254 => operands.push(-((b0 as f64 - 251.) * 256.) - r.u8()? as f64 - 108.),
+                _ => bail!("invalid CFF DICT data"),
+            }
+        }
+
+        Ok(dict)
+    }
+}
+
+/// Parse the local subroutines associated with a Private DICT.
+fn parse_private(cff: &[u8], private: Option<(usize, usize)>) -> Result<Option<Index>> {
+    let (size, offset) = match private {
+        Some(p) => p,
+        None => return Ok(None),
+    };
+
+    let pdata = a_ok_or!(cff.get(offset..offset + size); ["invalid CFF Private DICT"]);
+    let pdict = Dict::parse(pdata)?;
+
+    pdict
+        .subrs
+        .map(|subrs| Index::parse(cff, offset + subrs))
+        .transpose()
+}
+
+fn parse_cff(data: &[u8], table_start: usize) -> Result<Format> {
+    // Offsets within the CFF data are relative to the start of the table, so we
+    // work with a slice starting there, and make positions absolute at the
+    // end.
+    let cff = a_ok_or!(data.get(table_start..); ["invalid CFF table"]);
+    let mut r = Reader::new(cff);
+    let major = r.u8()?;
+    ensure!(major == 1, "unsupported CFF version {}", major);
+    r.u8()?;
+    let header_size = r.u8()? as usize;
+
+    let names = Index::parse(cff, header_size)?;
+    let top_dicts = Index::parse(cff, names.end)?;
+    let strings = Index::parse(cff, top_dicts.end)?;
+    let mut global_subrs = Index::parse(cff, strings.end)?;
+
+    let top = Dict::parse(a_ok_or!(top_dicts.get(cff, 0); ["missing CFF Top DICT"]))?;
+    ensure!(
+        top.charstring_type.unwrap_or(2) == 2,
+        "unsupported CFF charstring type"
+    );
+
+    let mut charstrings = Index::parse(
+        cff,
+        a_ok_or!(top.charstrings; ["CFF font has no charstrings"]),
+    )?;
+
+    let local_subrs = if let (Some(fd_array), Some(fd_select)) = (top.fd_array, top.fd_select) {
+        let fds = Index::parse(cff, fd_array)?;
+        let mut fd_subrs = Vec::with_capacity(fds.count);
+
+        for i in 0..fds.count {
+            let fd = Dict::parse(a_ok_or!(fds.get(cff, i); ["invalid CFF FDArray"]))?;
+            fd_subrs.push(parse_private(cff, fd.private)?.map(|s| s.relocate(table_start)));
+        }
+
+        LocalSubrs::Cid {
+            fd_select: table_start + fd_select,
+            fd_subrs,
+        }
+    } else {
+        LocalSubrs::Single(parse_private(cff, top.private)?.map(|s| s.relocate(table_start)))
+    };
+
+    global_subrs = global_subrs.relocate(table_start);
+    charstrings = charstrings.relocate(table_start);
+
+    Ok(Format::Cff {
+        charstrings,
+        global_subrs,
+        local_subrs,
+    })
+}
+
+/// Look up the Font DICT index for a glyph in a CID-keyed font.
+fn fd_select_lookup(data: &[u8], pos: usize, glyph: GlyphId) -> Result<usize> {
+    let mut r = Reader::new_at(data, pos);
+
+    match r.u8()? {
+        0 => {
+            r.skip(glyph as usize)?;
+            Ok(r.u8()? as usize)
+        }
+
+        3 => {
+            let n_ranges = r.u16()?;
+            let mut first = r.u16()?;
+
+            for _ in 0..n_ranges {
+                let fd = r.u8()? as usize;
+                let next = r.u16()?;
+
+                if (first..next).contains(&glyph) {
+                    return Ok(fd);
+                }
+
+                first = next;
+            }
+
+            bail!("glyph {} not found in CFF FDSelect", glyph)
+        }
+
+        f => bail!("unsupported CFF FDSelect format {}", f),
+    }
+}
+
+/// An interpreter for Type 2 charstrings.
+struct CharstringInterpreter<'a> {
+    data: &'a [u8],
+    global_subrs: &'a Index,
+    local_subrs: Option<&'a Index>,
+    path: &'a mut PathBuilder,
+    stack: Vec<f32>,
+    x: f32,
+    y: f32,
+    n_stems: usize,
+    seen_width: bool,
+    open: bool,
+}
+
+/// What to do after running a piece of charstring code.
+#[derive(PartialEq)]
+enum Flow {
+    Continue,
+    Return,
+    End,
+}
+
+impl<'a> CharstringInterpreter<'a> {
+    /// Deal with the optional width argument that may appear before the first
+    /// stack-clearing operator. We don't need it, so we just drop it.
+    fn maybe_drop_width(&mut self, has_extra: bool) {
+        if !self.seen_width {
+            self.seen_width = true;
+
+            if has_extra && !self.stack.is_empty() {
+                self.stack.remove(0);
+            }
+        }
+    }
+
+    fn close(&mut self) {
+        if self.open {
+            self.path.d.push('Z');
+            self.open = false;
+        }
+    }
+
+    fn move_to(&mut self, dx: f32, dy: f32) {
+        self.close();
+        self.x += dx;
+        self.y += dy;
+        self.path.command('M', &[self.x, self.y]);
+        self.open = true;
+    }
+
+    fn line_to(&mut self, dx: f32, dy: f32) {
+        self.x += dx;
+        self.y += dy;
+        self.path.command('L', &[self.x, self.y]);
+    }
+
+    fn curve_to(&mut self, d: [f32; 6]) {
+        let x1 = self.x + d[0];
+        let y1 = self.y + d[1];
+        let x2 = x1 + d[2];
+        let y2 = y1 + d[3];
+        self.x = x2 + d[4];
+        self.y = y2 + d[5];
+        self.path.command('C', &[x1, y1, x2, y2, self.x, self.y]);
+    }
+
+    fn arg(&self, i: usize) -> Result<f32> {
+        Ok(*a_ok_or!(self.stack.get(i); ["CFF charstring stack underflow"]))
+    }
+
+    fn run(&mut self, code: &[u8], depth: usize) -> Result<Flow> {
+        ensure!(
+            depth < MAX_NESTING,
+            "CFF charstring subroutines nested too deeply"
+        );
+
+        let mut r = Reader::new(code);
+
+        while !r.is_empty() {
+            let b0 = r.u8()?;
+
+            let op = match b0 {
+                28 => {
+                    self.push(r.i16()? as f32)?;
+                    continue;
+                }
+                32..=246 => {
+                    self.push(b0 as f32 - 139.)?;
+                    continue;
+                }
+                247..=250 => {
+                    self.push((b0 as f32 - 247.) * 256. + r.u8()? as f32 + 108.)?;
+                    continue;
+                }
+                251..=254 => {
+                    self.push(-(b0 as f32 - 251.) * 256. - r.u8()? as f32 - 108.)?;
+                    continue;
+                }
+                255 => {
+                    self.push(r.i32()? as f32 / 65536.)?;
+                    continue;
+                }
+                12 => 1200 + r.u8()? as u16,
+                _ => b0 as u16,
+            };
+
+            let n = self.stack.len();
+
+            match op {
+                // hstem, vstem, hstemhm, vstemhm
+                1 | 3 | 18 | 23 => {
+                    self.maybe_drop_width(n % 2 == 1);
+                    self.n_stems += self.stack.len() / 2;
+                }
+
+                // hintmask, cntrmask
+                19 | 20 => {
+                    // Any arguments here are an implicit vstem.
+                    self.maybe_drop_width(n % 2 == 1);
+                    self.n_stems += self.stack.len() / 2;
+                    r.skip(self.n_stems.div_ceil(8))?;
+                }
+
+                // rmoveto
+                21 => {
+                    self.maybe_drop_width(n > 2);
+                    self.move_to(self.arg(0)?, self.arg(1)?);
+                }
+
+                // hmoveto
+                22 => {
+                    self.maybe_drop_width(n > 1);
+                    self.move_to(self.arg(0)?, 0.);
+                }
+
+                // vmoveto
+                4 => {
+                    self.maybe_drop_width(n > 1);
+                    self.move_to(0., self.arg(0)?);
+                }
+
+                // rlineto
+                5 => {
+                    for i in (0..n - n % 2).step_by(2) {
+                        self.line_to(self.stack[i], self.stack[i + 1]);
+                    }
+                }
+
+                // hlineto, vlineto
+                6 | 7 => {
+                    let mut horizontal = op == 6;
+
+                    for i in 0..n {
+                        if horizontal {
+                            self.line_to(self.stack[i], 0.);
+                        } else {
+                            self.line_to(0., self.stack[i]);
+                        }
+
+                        horizontal = !horizontal;
+                    }
+                }
+
+                // rrcurveto
+                8 => {
+                    for i in (0..n - n % 6).step_by(6) {
+                        self.curve_to(self.six(i));
+                    }
+                }
+
+                // rcurveline
+                24 => {
+                    ensure!(n >= 8, "CFF charstring stack underflow");
+                    let n_curves = (n - 2) / 6;
+
+                    for i in 0..n_curves {
+                        self.curve_to(self.six(6 * i));
+                    }
+
+                    self.line_to(self.stack[n - 2], self.stack[n - 1]);
+                }
+
+                // rlinecurve
+                25 => {
+                    ensure!(n >= 8, "CFF charstring stack underflow");
+                    let n_lines = (n - 6) / 2;
+
+                    for i in 0..n_lines {
+                        self.line_to(self.stack[2 * i], self.stack[2 * i + 1]);
+                    }
+
+                    self.curve_to(self.six(n - 6));
+                }
+
+                // vvcurveto, hhcurveto
+                26 | 27 => {
+                    let mut i = 0;
+                    let mut d1 = 0.;
+
+                    if n % 2 == 1 {
+                        d1 = self.stack[0];
+                        i = 1;
+                    }
+
+                    while i + 4 <= n {
+                        let s = &self.stack;
+
+                        let d = if op == 26 {
+                            [d1, s[i], s[i + 1], s[i + 2], 0., s[i + 3]]
+                        } else {
+                            [s[i], d1, s[i + 1], s[i + 2], s[i + 3], 0.]
+                        };
+
+                        self.curve_to(d);
+                        d1 = 0.;
+                        i += 4;
+                    }
+                }
+
+                // vhcurveto, hvcurveto
+                30 | 31 => {
+                    let mut horizontal = op == 31;
+                    let mut i = 0;
+
+                    while i + 4 <= n {
+                        let s = &self.stack;
+                        let last = if n - i == 5 { s[i + 4] } else { 0. };
+
+                        let d = if horizontal {
+                            [s[i], 0., s[i + 1], s[i + 2], last, s[i + 3]]
+                        } else {
+                            [0., s[i], s[i + 1], s[i + 2], s[i + 3], last]
+                        };
+
+                        self.curve_to(d);
+                        horizontal = !horizontal;
+                        i += 4;
+                    }
+                }
+
+                // callsubr, callgsubr
+                10 | 29 => {
+                    let subrs = if op == 10 {
+                        a_ok_or!(self.local_subrs; ["CFF charstring uses missing local subroutines"])
+                    } else {
+                        self.global_subrs
+                    };
+
+                    let num = a_ok_or!(self.stack.pop(); ["CFF charstring stack underflow"]);
+                    let num = num as i32 + subrs.subr_bias();
+
+                    let code = a_ok_or!(
+                        usize::try_from(num).ok().and_then(|n| subrs.get(self.data, n));
+                        ["CFF charstring calls invalid subroutine {}", num]
+                    );
+
+                    // Subroutines operate on the same argument stack, so don't
+                    // clear it afterwards.
+                    match self.run(code, depth + 1)? {
+                        Flow::End => return Ok(Flow::End),
+                        _ => continue,
+                    }
+                }
+
+                // return
+                11 => return Ok(Flow::Return),
+
+                // endchar
+                14 => {
+                    ensure!(
+                        n < 4,
+                        "CFF charstrings with accented characters are not supported"
+                    );
+                    self.maybe_drop_width(n == 1);
+                    self.close();
+                    return Ok(Flow::End);
+                }
+
+                // hflex
+                1234 => {
+                    ensure!(n >= 7, "CFF charstring stack underflow");
+                    let s = &self.stack;
+                    let d1 = [s[0], 0., s[1], s[2], s[3], 0.];
+                    let d2 = [s[4], 0., s[5], -s[2], s[6], 0.];
+                    self.curve_to(d1);
+                    self.curve_to(d2);
+                }
+
+                // flex
+                1235 => {
+                    ensure!(n >= 13, "CFF charstring stack underflow");
+                    let (d1, d2) = (self.six(0), self.six(6));
+                    self.curve_to(d1);
+                    self.curve_to(d2);
+                }
+
+                // hflex1
+                1236 => {
+                    ensure!(n >= 9, "CFF charstring stack underflow");
+                    let s = &self.stack;
+                    let d1 = [s[0], s[1], s[2], s[3], s[4], 0.];
+                    let d2 = [s[5], 0., s[6], s[7], s[8], -(s[1] + s[3] + s[7])];
+                    self.curve_to(d1);
+                    self.curve_to(d2);
+                }
+
+                // flex1
+                1237 => {
+                    ensure!(n >= 11, "CFF charstring stack underflow");
+                    let s = &self.stack;
+                    let dx = s[0] + s[2] + s[4] + s[6] + s[8];
+                    let dy = s[1] + s[3] + s[5] + s[7] + s[9];
+
+                    let (dx6, dy6) = if dx.abs() > dy.abs() {
+                        (s[10], -dy)
+                    } else {
+                        (-dx, s[10])
+                    };
+
+                    let d1 = self.six(0);
+                    let d2 = [s[6], s[7], s[8], s[9], dx6, dy6];
+                    self.curve_to(d1);
+                    self.curve_to(d2);
+                }
+
+                _ => bail!("unsupported CFF charstring operator {}", op),
+            }
+
+            self.stack.clear();
+        }
+
+        Ok(Flow::Continue)
+    }
+
+    fn push(&mut self, v: f32) -> Result<()> {
+        ensure!(
+            self.stack.len() < MAX_CHARSTRING_ARGS,
+            "CFF charstring stack overflow"
+        );
+        self.stack.push(v);
+        Ok(())
+    }
+
+    fn six(&self, i: usize) -> [f32; 6] {
+        let s = &self.stack;
+        [s[i], s[i + 1], s[i + 2], s[i + 3], s[i + 4], s[i + 5]]
+    }
+}
+
+/// A simple cursor for reading big-endian font data.
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Reader { data, pos: 0 }
+    }
+
+    fn new_at(data: &'a [u8], pos: usize) -> Self {
+        Reader { data, pos }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.pos >= self.data.len()
+    }
+
+    fn take(&mut self, n: usize) -> Result<&'a [u8]> {
+        let bytes = a_ok_or!(
+            self.data.get(self.pos..self.pos + n);
+            ["unexpected end of font data"]
+        );
+        self.pos += n;
+        Ok(bytes)
+    }
+
+    fn skip(&mut self, n: usize) -> Result<()> {
+        self.take(n).map(|_| ())
+    }
+
+    fn u8(&mut self) -> Result<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16> {
+        Ok(BigEndian::read_u16(self.take(2)?))
+    }
+
+    fn i16(&mut self) -> Result<i16> {
+        Ok(BigEndian::read_i16(self.take(2)?))
+    }
+
+    fn i32(&mut self) -> Result<i32> {
+        Ok(BigEndian::read_i32(self.take(4)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run_charstring(code: &[u8]) -> String {
+        let global_subrs = Index {
+            count: 0,
+            off_size: 1,
+            offsets: 0,
+            data_base: 0,
+            end: 0,
+        };
+        let mut path = PathBuilder::default();
+
+        let mut interp = CharstringInterpreter {
+            data: &[],
+            global_subrs: &global_subrs,
+            local_subrs: None,
+            path: &mut path,
+            stack: Vec::new(),
+            x: 0.,
+            y: 0.,
+            n_stems: 0,
+            seen_width: false,
+            open: false,
+        };
+
+        interp.run(code, 0).unwrap();
+        interp.close();
+        path.d
+    }
+
+    /// Encode a small integer as a charstring operand.
+    fn num(v: i32) -> u8 {
+        (v + 139) as u8
+    }
+
+    #[test]
+    fn charstring_lines() {
+        // width 50, rmoveto 10 20, hlineto 30 40, endchar
+        let code = [num(50), num(10), num(20), 21, num(30), num(40), 6, 14];
+        assert_eq!(run_charstring(&code), "M10 20L40 20L40 60Z");
+    }
+
+    #[test]
+    fn charstring_curves() {
+        // hmoveto 5, hvcurveto 10 10 10 10 1, endchar
+        let code = [
+            num(5),
+            22,
+            num(10),
+            num(10),
+            num(10),
+            num(10),
+            num(1),
+            31,
+            14,
+        ];
+        assert_eq!(run_charstring(&code), "M5 0C15 0 25 10 26 20Z");
+    }
+
+    #[test]
+    fn quadratic_contour() {
+        let mut path = PathBuilder::default();
+        path.quadratic_contour(&[(0., 0., false), (10., 0., false), (10., 10., true)]);
+        assert_eq!(path.d, "M10 10Q0 0 5 0Q10 0 10 10Z");
+
+        let mut path = PathBuilder::default();
+        path.quadratic_contour(&[(0., 0., true), (10., 0., true), (10., 10., false)]);
+        assert_eq!(path.d, "M0 0L10 0Q10 10 0 0Z");
+    }
+}
@@ -0,0 +1,195 @@
+// Copyright 2026 the Tectonic Project
+// Licensed under the MIT License.
+
+//! Helpers for drawing glyph outlines in SVG documents.
+//!
+//! Each glyph used in a document is defined once, as a `<path>` inside the
+//! document's `<defs>`, and then drawn wherever it is needed with a `<use>`
+//! element that transforms it from font units into place.
+
+use std::{
+    collections::{hash_map::Entry, HashMap},
+    fmt::Write,
+    hash::Hash,
+};
+use tectonic_errors::prelude::*;
+
+use crate::GlyphOutline;
+
+/// The number of significant digits kept by [`svg_number`].
+const SIGNIFICANT_DIGITS: i32 = 6;
+
+/// Format a number compactly for SVG output.
+///
+/// Numbers are rounded to six significant digits, which is plenty for both
+/// coordinates and the small scale factors between font units and the
+/// document's units, and trailing zeros are dropped.
+pub fn svg_number(v: f64) -> String {
+    if v == 0. || !v.is_finite() {
+        return "0".to_owned();
+    }
+
+    let decimals = (SIGNIFICANT_DIGITS - 1 - v.abs().log10().floor() as i32).clamp(0, 12);
+    let mut s = format!("{:.*}", decimals as usize, v);
+
+    if s.contains('.') {
+        let trimmed = s.trim_end_matches('0').trim_end_matches('.').len();
+        s.truncate(trimmed);
+    }
+
+    if s == "-0" {
+        s = "0".to_owned();
+    }
+
+    s
+}
+
+/// A glyph outline that has been defined in a document.
+#[derive(Clone, Debug)]
+pub struct GlyphDef<T> {
+    /// The index of the definition, which is used in its ID.
+    pub index: usize,
+
+    /// The bounding box of the outline, as `[x_min, y_min, x_max, y_max]` in
+    /// font units with the Y axis pointing up.
+    pub bbox: [f32; 4],
+
+    /// Extra information stored by the caller when the glyph was defined.
+    pub extra: T,
+}
+
+impl<T> GlyphDef<T> {
+    /// Write a `<use>` element that draws this glyph.
+    ///
+    /// The *matrix* transforms font units into the document's coordinates, in
+    /// SVG's `[a b c d e f]` order. Any *attrs*, such as a `fill`, are added to
+    /// the element as-is, and should start with a space.
+    pub fn write_use(&self, dest: &mut String, matrix: [f64; 6], attrs: &str) {
+        write!(
+            dest,
+            "<use xlink:href=\"#g{}\" transform=\"matrix({} {} {} {} {} {})\"{}/>",
+            self.index,
+            svg_number(matrix[0]),
+            svg_number(matrix[1]),
+            svg_number(matrix[2]),
+            svg_number(matrix[3]),
+            svg_number(matrix[4]),
+            svg_number(matrix[5]),
+            attrs,
+        )
+        .unwrap();
+    }
+}
+
+/// The glyph definitions of an SVG document.
+///
+/// Glyphs are identified by keys of type `K`, which should identify both the
+/// font and the glyph within it. Callers can store extra information of type
+/// `T` with each definition, such as a font scale factor.
+#[derive(Debug)]
+pub struct GlyphDefs<K, T = ()> {
+    defs: String,
+
+    /// Map from glyphs to their definitions. Empty glyphs map to None.
+    ids: HashMap<K, Option<GlyphDef<T>>>,
+}
+
+impl<K, T> Default for GlyphDefs<K, T> {
+    fn default() -> Self {
+        GlyphDefs {
+            defs: String::new(),
+            ids: HashMap::new(),
+        }
+    }
+}
+
+impl<K: Eq + Hash, T> GlyphDefs<K, T> {
+    /// Get the definition of a glyph, adding it if needed.
+    ///
+    /// If the glyph hasn't been seen before, *load* is called to get its
+    /// outline and extra information. Glyphs with empty outlines don't need to
+    /// be drawn, and return None.
+    pub fn get_or_define<F>(&mut self, key: K, load: F) -> Result<Option<&GlyphDef<T>>>
+    where
+        F: FnOnce() -> Result<(GlyphOutline, T)>,
+    {
+        let GlyphDefs { defs, ids } = self;
+        let index = ids.len();
+
+        let def = match ids.entry(key) {
+            Entry::Occupied(e) => e.into_mut(),
+
+            Entry::Vacant(e) => {
+                let (outline, extra) = load()?;
+
+                let def = outline.bbox.map(|bbox| {
+                    write!(defs, "<path id=\"g{}\" d=\"{}\"/>", index, outline.path).unwrap();
+                    GlyphDef { index, bbox, extra }
+                });
+
+                e.insert(def)
+            }
+        };
+
+        Ok(def.as_ref())
+    }
+
+    /// Get the `<path>` elements defining the glyphs, to go in the document's
+    /// `<defs>`.
+    pub fn defs(&self) -> &str {
+        &self.defs
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn numbers() {
+        assert_eq!(svg_number(0.), "0");
+        assert_eq!(svg_number(-0.00000001), "-0.00000001");
+        assert_eq!(svg_number(612.), "612");
+        assert_eq!(svg_number(1.5), "1.5");
+        assert_eq!(svg_number(123.456789), "123.457");
+        assert_eq!(svg_number(0.010951), "0.010951");
+        assert_eq!(svg_number(-72.0000001), "-72");
+        assert_eq!(svg_number(f64::NAN), "0");
+    }
+
+    #[test]
+    fn defs() {
+        let mut defs = GlyphDefs::<u16, f32>::default();
+        let outline = |bbox| {
+            Ok((
+                GlyphOutline {
+                    path: "M0 0L1 1Z".to_owned(),
+                    bbox,
+                },
+                2.,
+            ))
+        };
+
+        let def = defs
+            .get_or_define(7, || outline(Some([0., 0., 1., 1.])))
+            .unwrap()
+            .unwrap();
+        assert_eq!(def.index, 0);
+        assert_eq!(def.extra, 2.);
+
+        let mut text = String::new();
+        def.write_use(&mut text, [2., 0., 0., -2., 10., 20.], " fill=\"red\"");
+        assert_eq!(
+            text,
+            "<use xlink:href=\"#g0\" transform=\"matrix(2 0 0 -2 10 20)\" fill=\"red\"/>"
+        );
+
+        // Defined glyphs aren't loaded again.
+        assert!(defs
+            .get_or_define(7, || panic!("reloaded glyph"))
+            .unwrap()
+            .is_some());
+        assert!(defs.get_or_define(8, || outline(None)).unwrap().is_none());
+        assert_eq!(defs.defs(), "<path id=\"g0\" d=\"M0 0L1 1Z\"/>");
+    }
+}
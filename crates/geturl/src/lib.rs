@@ -24,9 +24,18 @@
 
 use cfg_if::cfg_if;
 use std::io::Read;
-use tectonic_errors::Result;
+use tectonic_errors::{anyhow::bail, Result};
 use tectonic_status_base::StatusBackend;
 
+/// When coalescing byte-range reads, merge ranges separated by at most this
+/// many bytes. It's cheaper to download some unneeded data than to make an
+/// extra request.
+const MAX_COALESCE_GAP: u64 = 64 * 1024;
+
+/// When coalescing byte-range reads, don't make any single request longer
+/// than this many bytes.
+const MAX_COALESCED_LENGTH: u64 = 16 * 1024 * 1024;
+
 /// A trait for reading byte ranges from an HTTP resource.
 pub trait RangeReader {
     /// The readable type returned by the range request.
@@ -34,6 +43,65 @@ pub trait RangeReader {
 
     /// Read the specified range of bytes from this HTTP resource.
     fn read_range(&mut self, offset: u64, length: usize) -> Result<Self::Response>;
+
+    /// Read several ranges of bytes from this HTTP resource.
+    ///
+    /// Each range is specified as an offset and a length, and the data for
+    /// each range are returned in the same order as the input. The default
+    /// implementation coalesces ranges that are close together into single
+    /// requests using [`Self::read_range`], which helps a lot when fetching
+    /// many small files that are stored near each other.
+    fn read_ranges(&mut self, ranges: &[(u64, usize)]) -> Result<Vec<Vec<u8>>> {
+        let mut order: Vec<usize> = (0..ranges.len()).collect();
+        order.sort_by_key(|&i| ranges[i].0);
+
+        let mut results = vec![Vec::new(); ranges.len()];
+        let mut i = 0;
+
+        while i < order.len() {
+            let start = ranges[order[i]].0;
+            let mut end = start + ranges[order[i]].1 as u64;
+            let mut j = i + 1;
+
+            while j < order.len() {
+                let (offset, length) = ranges[order[j]];
+                let new_end = end.max(offset + length as u64);
+
+                if offset > end + MAX_COALESCE_GAP || new_end - start > MAX_COALESCED_LENGTH {
+                    break;
+                }
+
+                end = new_end;
+                j += 1;
+            }
+
+            // Zero-length range requests don't work well with some servers,
+            // but if there's no data to fetch, we're all set anyway.
+            if end > start {
+                let n = (end - start) as usize;
+                let mut buf = Vec::with_capacity(n);
+                self.read_range(start, n)?.read_to_end(&mut buf)?;
+
+                if buf.len() != n {
+                    bail!(
+                        "expected {} bytes from range request but got {}",
+                        n,
+                        buf.len()
+                    );
+                }
+
+                for &k in &order[i..j] {
+                    let (offset, length) = ranges[k];
+                    let rel = (offset - start) as usize;
+                    results[k] = buf[rel..rel + length].to_vec();
+                }
+            }
+
+            i = j;
+        }
+
+        Ok(results)
+    }
 }
 
 /// A trait for simple HTTP operations needed by the Tectonic backends.
@@ -77,3 +145,42 @@ cfg_if! {
 
 /// The range-reader type exposed by the default URL-get backend (for convenience).
 pub type DefaultRangeReader = <DefaultBackend as GetUrlBackend>::RangeReader;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    struct VecRangeReader {
+        data: Vec<u8>,
+        n_requests: usize,
+    }
+
+    impl RangeReader for VecRangeReader {
+        type Response = Cursor<Vec<u8>>;
+
+        fn read_range(&mut self, offset: u64, length: usize) -> Result<Self::Response> {
+            self.n_requests += 1;
+            let offset = offset as usize;
+            Ok(Cursor::new(self.data[offset..offset + length].to_vec()))
+        }
+    }
+
+    #[test]
+    fn read_ranges_coalesces() {
+        let mut rr = VecRangeReader {
+            data: (0..=255).cycle().take(200_000).collect(),
+            n_requests: 0,
+        };
+
+        let ranges = [(100, 10), (0, 5), (150_000, 3), (50, 0), (120, 20)];
+        let results = rr.read_ranges(&ranges).unwrap();
+
+        assert_eq!(rr.n_requests, 2);
+
+        for (&(offset, length), data) in ranges.iter().zip(&results) {
+            let offset = offset as usize;
+            assert_eq!(data[..], rr.data[offset..offset + length]);
+        }
+    }
+}
@@ -15,9 +15,11 @@
 use sha2::Digest;
 use std::{
     borrow::Cow,
+    cell::Cell,
     fs::File,
     io::{self, Cursor, Read, Seek, SeekFrom, Write},
     path::{Path, PathBuf},
+    rc::Rc,
 };
 use tectonic_errors::{
     anyhow::{bail, ensure},
@@ -122,6 +124,7 @@ pub struct InputHandle {
     ever_read: bool,
     did_unhandled_seek: bool,
     ungetc_char: Option<u8>,
+    read_counter: Option<Rc<Cell<u64>>>,
 }
 
 impl InputHandle {
@@ -141,6 +144,7 @@ impl InputHandle {
             ever_read: false,
             did_unhandled_seek: false,
             ungetc_char: None,
+            read_counter: None,
         }
     }
 
@@ -159,6 +163,7 @@ impl InputHandle {
             ever_read: false,
             did_unhandled_seek: false,
             ungetc_char: None,
+            read_counter: None,
         }
     }
 
@@ -172,6 +177,14 @@ impl InputHandle {
         self.origin
     }
 
+    /// Count the bytes read through this handle, adding them to *counter*.
+    ///
+    /// Data consumed by [`Self::scan_remainder`] aren't counted, so the total
+    /// reflects what the handle's user actually read.
+    pub fn count_reads(&mut self, counter: Rc<Cell<u64>>) {
+        self.read_counter = Some(counter);
+    }
+
     /// Consumes the object and returns the underlying readable handle that
     /// it references.
     pub fn into_inner(self) -> Box<dyn InputFeatures> {
@@ -267,6 +280,9 @@ impl Read for InputHandle {
 
         self.ever_read = true;
         let n = self.inner.read(buf)?;
+        if let Some(ref counter) = self.read_counter {
+            counter.set(counter.get() + n as u64);
+        }
         if !self.read_only {
             self.digest.update(&buf[..n]);
         }
@@ -755,4 +771,27 @@ mod tests {
             None
         );
     }
+
+    #[test]
+    fn count_reads() {
+        let counter = Rc::new(Cell::new(0));
+        let mut ih = InputHandle::new(
+            "test",
+            Cursor::new(b"hello world".to_vec()),
+            InputOrigin::Other,
+        );
+        ih.count_reads(counter.clone());
+
+        assert_eq!(ih.getc().unwrap(), b'h');
+        ih.ungetc(b'h').unwrap();
+
+        let mut buf = [0u8; 5];
+        ih.read_exact(&mut buf).unwrap();
+        assert_eq!(&buf, b"hello");
+        assert_eq!(counter.get(), 5);
+
+        // Scanning to the end for the digest doesn't count as reading.
+        ih.scan_remainder().unwrap();
+        assert_eq!(counter.get(), 5);
+    }
 }
@@ -149,6 +149,7 @@ fn main() {
         "pdf_io/dpx-spc_html.c",
         "pdf_io/dpx-spc_misc.c",
         "pdf_io/dpx-spc_pdfm.c",
+        "pdf_io/dpx-spc_tdux.c",
         "pdf_io/dpx-spc_tpic.c",
         "pdf_io/dpx-spc_util.c",
         "pdf_io/dpx-spc_xtx.c",
@@ -36,6 +36,7 @@
 #include <string.h>
 
 #include "dpx-error.h"
+#include "dpx-mem.h"
 #include "dpx-pdfdev.h"
 #include "dpx-pdfdoc.h"
 #include "dpx-pdfdraw.h"
@@ -101,6 +102,92 @@ get_page_content (pdf_file *pf, pdf_obj* page)
   return contents;
 }
 
+/* Tectonic: load a PDF file with the Rust reader, warning and returning NULL
+ * on failure. The handle is left positioned at the start of the file. The
+ * result must be freed with tectonic_pdf_document_free().
+ */
+TectonicPdfDocument *
+epdf_load_document (rust_input_handle_t handle, const char *ident)
+{
+  TectonicPdfDocument *doc;
+  char errbuf[256];
+  uint8_t *data;
+  size_t size;
+
+  size = ttstub_input_get_size(handle);
+  data = NEW(size + 1, uint8_t);
+  ttstub_input_seek(handle, 0, SEEK_SET);
+
+  if (ttstub_input_read(handle, (char *) data, size) != (ssize_t) size) {
+    free(data);
+    ttstub_input_seek(handle, 0, SEEK_SET);
+    dpx_warning("Failed to read PDF file \"%s\".", ident);
+    return NULL;
+  }
+
+  ttstub_input_seek(handle, 0, SEEK_SET);
+  doc = tectonic_pdf_document_load(data, size, errbuf, sizeof(errbuf));
+  free(data);
+
+  if (!doc)
+    dpx_warning("Cannot read PDF file \"%s\": %s", ident, errbuf);
+
+  return doc;
+}
+
+/* Tectonic: before handing an included PDF to the C parser, vet it with the
+ * Rust reader. A file that it cannot read, or can only read by reconstructing
+ * its cross-reference information, is skipped with a warning rather than
+ * taking down the whole run. Returns 0 if the file looks usable and
+ * EPDF_DAMAGED otherwise. If the requested page exists, its bounding box and
+ * the matrix that applies its rotation are stored in *bbox* and *matrix*.
+ */
+int
+epdf_check_file (rust_input_handle_t handle, const char *ident, int page_no, int bbox_type,
+                 pdf_rect *bbox, pdf_tmatrix *matrix)
+{
+  TectonicPdfDocument *doc;
+  TectonicPdfPageGeometry geom;
+  char errbuf[256];
+  int count, result = 0;
+
+  doc = epdf_load_document(handle, ident);
+  if (!doc) {
+    dpx_warning("Not including damaged PDF file \"%s\".", ident);
+    return EPDF_DAMAGED;
+  }
+
+  if (tectonic_pdf_document_was_repaired(doc)) {
+    dpx_warning("PDF file \"%s\" has a damaged cross-reference table; not including it.", ident);
+    result = EPDF_DAMAGED;
+  } else if ((count = tectonic_pdf_document_page_count(doc)) < 0) {
+    dpx_warning("PDF file \"%s\" has a damaged page tree; not including it.", ident);
+    result = EPDF_DAMAGED;
+  } else if (page_no >= 1 && page_no <= count) {
+    /* Nonexistent pages are reported by pdf_doc_get_page(), as ever. */
+    if (tectonic_pdf_document_page_geometry(doc, page_no, bbox_type, &geom,
+                                            errbuf, sizeof(errbuf))) {
+      dpx_warning("Cannot read page %d of PDF file \"%s\" (%s); not including it.",
+                  page_no, ident, errbuf);
+      result = EPDF_DAMAGED;
+    } else {
+      bbox->llx = geom.bbox.llx;
+      bbox->lly = geom.bbox.lly;
+      bbox->urx = geom.bbox.urx;
+      bbox->ury = geom.bbox.ury;
+      matrix->a = geom.matrix[0];
+      matrix->b = geom.matrix[1];
+      matrix->c = geom.matrix[2];
+      matrix->d = geom.matrix[3];
+      matrix->e = geom.matrix[4];
+      matrix->f = geom.matrix[5];
+    }
+  }
+
+  tectonic_pdf_document_free(doc);
+  return result;
+}
+
 /* ximage here is the result. DONT USE IT FOR PASSING OPTIONS! */
 int
 pdf_include_page (pdf_ximage        *ximage,
@@ -112,6 +199,15 @@ pdf_include_page (pdf_ximage        *ximage,
   xform_info info;
   pdf_obj *contents = NULL, *catalog;
   pdf_obj *page = NULL, *resources = NULL, *markinfo = NULL;
+  pdf_rect bbox;
+  pdf_tmatrix matrix;
+
+  if (options.page_no == 0)
+    options.page_no = 1;
+
+  if (epdf_check_file(handle, ident, options.page_no, options.bbox_type,
+                      &bbox, &matrix) != 0)
+    return EPDF_DAMAGED;
 
   pf = pdf_open(ident, handle);
   if (!pf)
@@ -119,8 +215,9 @@ pdf_include_page (pdf_ximage        *ximage,
 
   pdf_ximage_init_form_info(&info);
 
-  if (options.page_no == 0)
-    options.page_no = 1;
+  /* Tectonic: the C parser is still needed to import the page's content and
+   * resources into the output, but the geometry comes from the Rust reader,
+   * like it does for XeTeX's \XeTeXpdffile. */
   page = pdf_doc_get_page(pf,
                           options.page_no, options.bbox_type,
                           &info.bbox, &info.matrix, &resources);
@@ -128,6 +225,9 @@ pdf_include_page (pdf_ximage        *ximage,
   if(!page)
     goto error_silent;
 
+  info.bbox = bbox;
+  info.matrix = matrix;
+
   catalog = pdf_file_get_catalog(pf);
   markinfo = pdf_deref_obj(pdf_lookup_dict(catalog, "MarkInfo"));
   if (markinfo) {
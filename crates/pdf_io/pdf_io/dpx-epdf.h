@@ -24,6 +24,7 @@
 #define _EPDF_H_
 
 #include "tectonic_bridge_core.h"
+#include "tectonic_pdf_io.h"
 
 #include <stdio.h>
 
@@ -36,6 +37,15 @@
 #define pdfbox_trim  4
 #define pdfbox_art   5
 
+/* Tectonic: returned when an included PDF file is too damaged to use. */
+#define EPDF_DAMAGED -2
+
+TectonicPdfDocument *epdf_load_document (rust_input_handle_t handle,
+                                         const char *ident);
+int epdf_check_file (rust_input_handle_t handle, const char *ident,
+                     int page_no, int bbox_type,
+                     pdf_rect *bbox, pdf_tmatrix *matrix);
+
 int pdf_copy_clip (rust_input_handle_t image_file, int page_index,
                           double x_user, double y_user);
 
@@ -62,6 +62,7 @@
 #include "dpx-mfileio.h"
 #include "dpx-numbers.h"
 #include "dpx-pdfcolor.h"
+#include "dpx-pdfdoc.h"
 #include "dpx-pdfobj.h"
 #include "dpx-pdfximage.h"
 
@@ -322,6 +323,8 @@ jpeg_include_image (pdf_ximage *ximage, rust_input_handle_t handle)
             break;
         case PDF_COLORSPACE_TYPE_CMYK:
             colorspace = pdf_new_name("DeviceCMYK");
+            /* Tectonic: PDF/A checking */
+            pdf_doc_pdfa_violation("CMYK images are used, but the output intent is RGB");
             break;
         }
     }
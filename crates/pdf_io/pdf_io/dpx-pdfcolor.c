@@ -247,6 +247,11 @@ pdf_color_set_color (const pdf_color *color, char *buffer, size_t buffer_len,cha
     }
   }
 
+  /* Tectonic: our PDF/A output intent is RGB, so device CMYK colors are out. */
+  if (pdf_color_type(color) == PDF_COLORSPACE_TYPE_CMYK ||
+      pdf_color_type(color) == PDF_COLORSPACE_TYPE_DEVICECMYK)
+    pdf_doc_pdfa_violation("CMYK colors are used, but the output intent is RGB");
+
   switch (pdf_color_type(color)) {
   case PDF_COLORSPACE_TYPE_DEVICEGRAY:
     {
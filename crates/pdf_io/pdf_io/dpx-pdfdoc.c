@@ -28,7 +28,9 @@
 #include "dpx-pdfdoc.h"
 
 #include <assert.h>
+#include <ctype.h>
 #include <math.h>
+#include <stdarg.h>
 #include <stdint.h>
 #include <stdio.h>
 #include <stdlib.h>
@@ -54,6 +56,7 @@
 #include "dpx-pdfximage.h"
 #include "dpx-pngimage.h"
 #include "dpx-system.h"
+#include "dpx-unicode.h"
 
 #define PDFDOC_PAGES_ALLOC_SIZE   128u
 #define PDFDOC_ARTICLE_ALLOC_SIZE 16
@@ -223,6 +226,33 @@ typedef struct pdf_doc
   struct form_list_node *pending_forms;
 
   char *thumb_basename;
+
+  /* Tectonic: PDF/A conformance */
+  struct {
+    int                  part;
+    const unsigned char *icc_profile;
+    size_t               icc_profile_len;
+    char               **violations;
+    unsigned int         num_violations;
+    unsigned int         max_violations;
+  } pdfa;
+
+  /* Tectonic: metadata supplied by the caller */
+  struct pdf_metadata_setting metadata;
+
+  /* Tectonic: tagged PDF */
+  struct {
+    pdf_obj     *root;        /* NULL if tagging is disabled */
+    pdf_obj    **stack;       /* open elements, starting with Document */
+    unsigned int depth;
+    unsigned int max_depth;
+    pdf_obj     *role_map;
+    pdf_obj     *parent_tree; /* the ParentTree's /Nums array */
+    int          next_key;
+    pdf_obj     *page_mcids;  /* the element of each MCID on this page */
+    int          in_content;
+    int          num_elems;   /* elements created besides Document */
+  } structure;
 } pdf_doc;
 static pdf_doc pdoc;
 
@@ -362,6 +392,15 @@ static void pdf_doc_close_articles   (pdf_doc *p);
 static void pdf_doc_init_bookmarks   (pdf_doc *p, int bm_open_depth);
 static void pdf_doc_close_bookmarks  (pdf_doc *p);
 
+static void pdf_doc_close_pdfa       (pdf_doc *p);
+static void pdf_doc_apply_metadata   (pdf_doc *p, pdf_obj *docinfo);
+static int  pdf_doc_have_metadata    (pdf_doc *p);
+static void pdf_doc_add_xmp          (pdf_doc *p, pdf_obj *docinfo);
+static void pdf_doc_check_pdfa_annot (pdf_obj *annot_dict);
+
+static void pdf_doc_begin_struct_page (pdf_doc *p);
+static void pdf_doc_end_struct_page   (pdf_doc *p);
+
 void
 pdf_doc_set_bop_content (const char *content, unsigned int length)
 {
@@ -439,6 +478,8 @@ pdf_doc_close_docinfo (pdf_doc *p)
   pdf_obj *value;
   unsigned int i;
 
+  pdf_doc_apply_metadata(p, docinfo);
+
   for (i = 0; keys[i] != NULL; i++) {
     value = pdf_lookup_dict(docinfo, keys[i]);
     if (value) {
@@ -470,6 +511,12 @@ pdf_doc_close_docinfo (pdf_doc *p)
                  pdf_new_string(now, strlen(now)));
   }
 
+  if (p->pdfa.part || pdf_doc_have_metadata(p))
+    pdf_doc_add_xmp(p, docinfo);
+
+  if (p->pdfa.part)
+    pdf_doc_close_pdfa(p);
+
   pdf_release_obj(docinfo);
   p->info = NULL;
 
@@ -1683,6 +1730,14 @@ pdf_doc_close_names (pdf_doc *p)
       pdf_obj  *name_tree;
       int count;
 
+      if (p->pdfa.part && data->count > 0) {
+        if (!strcmp(p->names[i].category, "JavaScript"))
+          pdf_doc_pdfa_violation("JavaScript is not permitted");
+        else if (p->pdfa.part < 3 && !strcmp(p->names[i].category, "EmbeddedFiles"))
+          pdf_doc_pdfa_violation("embedded files are not permitted in PDF/A-%d (use PDF/A-3)",
+                                 p->pdfa.part);
+      }
+
       if (!pdoc.check_gotos || strcmp(p->names[i].category, "Dests"))
         name_tree = pdf_names_create_tree(data, &count, NULL);
       else {
@@ -1774,6 +1829,9 @@ pdf_doc_add_annot (unsigned page_no, const pdf_rect *rect,
   pdf_add_array(rect_array, pdf_new_number(ROUND(rect->ury, 0.001)));
   pdf_add_dict (annot_dict, pdf_new_name("Rect"), rect_array);
 
+  if (p->pdfa.part)
+    pdf_doc_check_pdfa_annot(annot_dict);
+
   pdf_add_array(page->annots, pdf_ref_obj(annot_dict));
 
   if (new_annot)
@@ -2408,6 +2466,8 @@ pdf_doc_begin_page (double scale, double x_origin, double y_origin)
   pdf_doc_new_page(p);
   pdf_dev_bop(&M);
 
+  pdf_doc_begin_struct_page(p);
+
   return;
 }
 
@@ -2416,6 +2476,8 @@ pdf_doc_end_page (void)
 {
   pdf_doc *p = &pdoc;
 
+  pdf_doc_end_struct_page(p);
+
   pdf_dev_eop();
   doc_fill_page_background(p);
 
@@ -2440,6 +2502,782 @@ pdf_doc_add_page_content (const char *buffer, unsigned int length)
   return;
 }
 
+/*
+ * Tectonic: PDF/A support.
+ *
+ * In PDF/A mode we add an output intent and an XMP metadata packet that
+ * mirrors the document information dictionary, and keep track of anything we
+ * see that would prevent the output from conforming. The checks are not a
+ * complete validation, but they cover the problems that TeX documents run into
+ * in practice.
+ */
+
+int
+pdf_doc_pdfa_part (void)
+{
+  return pdoc.pdfa.part;
+}
+
+void
+pdf_doc_pdfa_violation (const char *fmt, ...)
+{
+  pdf_doc     *p = &pdoc;
+  va_list      argp;
+  char         buf[1024];
+  unsigned int i;
+
+  if (!p->pdfa.part)
+    return;
+
+  va_start(argp, fmt);
+  vsnprintf(buf, sizeof(buf), fmt, argp);
+  va_end(argp);
+
+  /* Only report each distinct problem once. */
+  for (i = 0; i < p->pdfa.num_violations; i++) {
+    if (!strcmp(p->pdfa.violations[i], buf))
+      return;
+  }
+
+  if (p->pdfa.num_violations >= p->pdfa.max_violations) {
+    p->pdfa.max_violations += 16;
+    p->pdfa.violations = RENEW(p->pdfa.violations, p->pdfa.max_violations, char *);
+  }
+
+  p->pdfa.violations[p->pdfa.num_violations] = NEW(strlen(buf) + 1, char);
+  strcpy(p->pdfa.violations[p->pdfa.num_violations], buf);
+  p->pdfa.num_violations++;
+}
+
+static void
+pdf_doc_report_pdfa (pdf_doc *p)
+{
+  unsigned int i;
+
+  if (!p->pdfa.part)
+    return;
+
+  if (p->pdfa.num_violations == 0) {
+    dpx_message("\nPDF/A-%db conformance check: no problems found\n", p->pdfa.part);
+  } else {
+    dpx_warning("The output does not conform to PDF/A-%db; %u problem(s) found:",
+                p->pdfa.part, p->pdfa.num_violations);
+
+    for (i = 0; i < p->pdfa.num_violations; i++) {
+      dpx_warning("  PDF/A: %s", p->pdfa.violations[i]);
+      free(p->pdfa.violations[i]);
+    }
+  }
+
+  p->pdfa.violations = mfree(p->pdfa.violations);
+  p->pdfa.num_violations = 0;
+  p->pdfa.max_violations = 0;
+}
+
+static void
+pdf_doc_check_pdfa_annot (pdf_obj *annot_dict)
+{
+  const char *forbidden_subtypes[] = {
+    "Sound", "Movie", "Screen", "3D", "RichMedia", NULL
+  };
+  const char *forbidden_actions[] = {
+    "Launch", "Sound", "Movie", "ResetForm", "ImportData", "JavaScript",
+    "Hide", "SetOCGState", "Rendition", "Trans", "GoTo3DView", NULL
+  };
+  pdf_obj *tmp;
+  int      flags = 0, i;
+
+  tmp = pdf_lookup_dict(annot_dict, "Subtype");
+  if (PDF_OBJ_NAMETYPE(tmp)) {
+    const char *subtype = pdf_name_value(tmp);
+
+    for (i = 0; forbidden_subtypes[i] != NULL; i++) {
+      if (!strcmp(subtype, forbidden_subtypes[i]))
+        pdf_doc_pdfa_violation("%s annotations are not permitted", subtype);
+    }
+
+    if (strcmp(subtype, "Link") && strcmp(subtype, "Popup") &&
+        !pdf_lookup_dict(annot_dict, "AP"))
+      pdf_doc_pdfa_violation("%s annotations must have an appearance stream", subtype);
+  }
+
+  /* Annotations must be printed, and may not be hidden. We can just fix that. */
+  tmp = pdf_lookup_dict(annot_dict, "F");
+  if (PDF_OBJ_NUMBERTYPE(tmp))
+    flags = (int) pdf_number_value(tmp);
+  flags = (flags | 4) & ~(1 | 2 | 32);
+  pdf_add_dict(annot_dict, pdf_new_name("F"), pdf_new_number(flags));
+
+  tmp = pdf_lookup_dict(annot_dict, "A");
+  if (PDF_OBJ_DICTTYPE(tmp)) {
+    tmp = pdf_lookup_dict(tmp, "S");
+    if (PDF_OBJ_NAMETYPE(tmp)) {
+      const char *action = pdf_name_value(tmp);
+
+      for (i = 0; forbidden_actions[i] != NULL; i++) {
+        if (!strcmp(action, forbidden_actions[i]))
+          pdf_doc_pdfa_violation("%s actions are not permitted", action);
+      }
+    }
+  }
+}
+
+struct xmp_buf
+{
+  char  *data;
+  size_t len, max;
+};
+
+static void
+xmp_append (struct xmp_buf *b, const char *s, size_t n)
+{
+  if (b->len + n + 1 > b->max) {
+    b->max  = b->len + n + 1 + 1024;
+    b->data = RENEW(b->data, b->max, char);
+  }
+  memcpy(b->data + b->len, s, n);
+  b->len += n;
+  b->data[b->len] = '\0';
+}
+
+static void
+xmp_puts (struct xmp_buf *b, const char *s)
+{
+  xmp_append(b, s, strlen(s));
+}
+
+/* Append a character as UTF-8, escaping it for XML. */
+static void
+xmp_put_char (struct xmp_buf *b, int32_t c)
+{
+  char   tmp[4];
+  size_t n = 0;
+
+  switch (c) {
+  case '&': xmp_puts(b, "&amp;"); return;
+  case '<': xmp_puts(b, "&lt;"); return;
+  case '>': xmp_puts(b, "&gt;"); return;
+  case '"': xmp_puts(b, "&quot;"); return;
+  }
+
+  if (c < 0x20 && c != 0x09 && c != 0x0a && c != 0x0d)
+    return; /* Not allowed in XML */
+
+  if (c < 0x80) {
+    tmp[n++] = (char) c;
+  } else if (c < 0x800) {
+    tmp[n++] = (char) (0xc0 | (c >> 6));
+    tmp[n++] = (char) (0x80 | (c & 0x3f));
+  } else if (c < 0x10000) {
+    tmp[n++] = (char) (0xe0 | (c >> 12));
+    tmp[n++] = (char) (0x80 | ((c >> 6) & 0x3f));
+    tmp[n++] = (char) (0x80 | (c & 0x3f));
+  } else {
+    tmp[n++] = (char) (0xf0 | (c >> 18));
+    tmp[n++] = (char) (0x80 | ((c >> 12) & 0x3f));
+    tmp[n++] = (char) (0x80 | ((c >> 6) & 0x3f));
+    tmp[n++] = (char) (0x80 | (c & 0x3f));
+  }
+
+  xmp_append(b, tmp, n);
+}
+
+/* PDFDocEncoding differs from Latin-1 in these ranges. */
+static const int32_t pdfdoc_18_1f[] = {
+  0x02d8, 0x02c7, 0x02c6, 0x02d9, 0x02dd, 0x02db, 0x02da, 0x02dc
+};
+
+static const int32_t pdfdoc_80_a0[] = {
+  0x2022, 0x2020, 0x2021, 0x2026, 0x2014, 0x2013, 0x0192, 0x2044,
+  0x2039, 0x203a, 0x2212, 0x2030, 0x201e, 0x201c, 0x201d, 0x2018,
+  0x2019, 0x201a, 0x2122, 0xfb01, 0xfb02, 0x0141, 0x0152, 0x0160,
+  0x0178, 0x017d, 0x0131, 0x0142, 0x0153, 0x0161, 0x017e, 0xfffd,
+  0x20ac
+};
+
+/* Append the value of a PDF text string. */
+static void
+xmp_put_text (struct xmp_buf *b, pdf_obj *str)
+{
+  const unsigned char *s = pdf_string_value(str);
+  size_t len = pdf_string_length(str), i;
+
+  if (len >= 2 && s[0] == 0xfe && s[1] == 0xff) {
+    for (i = 2; i + 1 < len; i += 2) {
+      int32_t c = (s[i] << 8) | s[i + 1];
+
+      if (c >= 0xd800 && c < 0xdc00 && i + 3 < len) {
+        int32_t lo = (s[i + 2] << 8) | s[i + 3];
+
+        if (lo >= 0xdc00 && lo < 0xe000) {
+          c = 0x10000 + ((c - 0xd800) << 10) + (lo - 0xdc00);
+          i += 2;
+        }
+      }
+
+      if (c < 0xd800 || c >= 0xe000)
+        xmp_put_char(b, c);
+    }
+  } else {
+    for (i = 0; i < len; i++) {
+      if (s[i] >= 0x18 && s[i] <= 0x1f)
+        xmp_put_char(b, pdfdoc_18_1f[s[i] - 0x18]);
+      else if (s[i] >= 0x80 && s[i] <= 0xa0)
+        xmp_put_char(b, pdfdoc_80_a0[s[i] - 0x80]);
+      else
+        xmp_put_char(b, s[i]);
+    }
+  }
+}
+
+/* Convert a PDF date string to the XMP (ISO 8601) format. */
+static int
+xmp_format_date (char *out, pdf_obj *str)
+{
+  const char *s = pdf_string_value(str);
+  size_t len = pdf_string_length(str), i = 0;
+  int    fields[6] = { 0, 1, 1, 0, 0, 0 };
+  int    widths[6] = { 4, 2, 2, 2, 2, 2 };
+  int    f, k, tz_h = 0, tz_m = 0;
+  char   tz_sign = 0;
+
+  if (len >= 2 && s[0] == 'D' && s[1] == ':')
+    i = 2;
+
+  for (f = 0; f < 6 && i < len && isdigit((unsigned char) s[i]); f++) {
+    fields[f] = 0;
+    for (k = 0; k < widths[f]; k++, i++) {
+      if (i >= len || !isdigit((unsigned char) s[i]))
+        return -1;
+      fields[f] = fields[f] * 10 + (s[i] - '0');
+    }
+  }
+
+  if (f == 0)
+    return -1;
+
+  if (i < len && (s[i] == '+' || s[i] == '-')) {
+    tz_sign = s[i++];
+    for (k = 0; k < 2 && i < len && isdigit((unsigned char) s[i]); k++, i++)
+      tz_h = tz_h * 10 + (s[i] - '0');
+    if (i < len && s[i] == '\'')
+      i++;
+    for (k = 0; k < 2 && i < len && isdigit((unsigned char) s[i]); k++, i++)
+      tz_m = tz_m * 10 + (s[i] - '0');
+  } else if (i < len && s[i] == 'Z') {
+    tz_sign = 'Z';
+  }
+
+  k = sprintf(out, "%04d-%02d-%02dT%02d:%02d:%02d",
+              fields[0], fields[1], fields[2], fields[3], fields[4], fields[5]);
+
+  if (tz_sign == 'Z' || (tz_sign && tz_h == 0 && tz_m == 0))
+    strcpy(out + k, "Z");
+  else if (tz_sign)
+    sprintf(out + k, "%c%02d:%02d", tz_sign, tz_h, tz_m);
+
+  return 0;
+}
+
+#define XMP_SIMPLE 0
+#define XMP_ALT    1
+#define XMP_SEQ    2
+
+static void
+xmp_put_property (struct xmp_buf *b, pdf_obj *docinfo,
+                  const char *key, const char *elem, int kind)
+{
+  pdf_obj *value = pdf_lookup_dict(docinfo, key);
+
+  if (!PDF_OBJ_STRINGTYPE(value))
+    return;
+
+  xmp_puts(b, "<");
+  xmp_puts(b, elem);
+  xmp_puts(b, ">");
+
+  if (kind == XMP_ALT)
+    xmp_puts(b, "<rdf:Alt><rdf:li xml:lang=\"x-default\">");
+  else if (kind == XMP_SEQ)
+    xmp_puts(b, "<rdf:Seq><rdf:li>");
+
+  xmp_put_text(b, value);
+
+  if (kind == XMP_ALT)
+    xmp_puts(b, "</rdf:li></rdf:Alt>");
+  else if (kind == XMP_SEQ)
+    xmp_puts(b, "</rdf:li></rdf:Seq>");
+
+  xmp_puts(b, "</");
+  xmp_puts(b, elem);
+  xmp_puts(b, ">\n");
+}
+
+static void
+xmp_put_date_property (struct xmp_buf *b, pdf_obj *docinfo,
+                       const char *key, const char *elem)
+{
+  pdf_obj *value = pdf_lookup_dict(docinfo, key);
+  char     date[64];
+
+  if (!PDF_OBJ_STRINGTYPE(value))
+    return;
+
+  if (xmp_format_date(date, value) < 0) {
+    pdf_doc_pdfa_violation("the document information entry \"%s\" is not a valid date", key);
+    return;
+  }
+
+  xmp_puts(b, "<");
+  xmp_puts(b, elem);
+  xmp_puts(b, ">");
+  xmp_puts(b, date);
+  xmp_puts(b, "</");
+  xmp_puts(b, elem);
+  xmp_puts(b, ">\n");
+}
+
+/* Make a PDF text string from UTF-8 text. Pure ASCII is stored as is, and
+ * anything else as UTF-16BE. */
+static pdf_obj *
+pdf_doc_text_string (const char *utf8)
+{
+  const unsigned char *s   = (const unsigned char *) utf8;
+  const unsigned char *end = s + strlen(utf8);
+  const unsigned char *q;
+  unsigned char *buf, *dst, *bufend;
+  pdf_obj *str;
+
+  for (q = s; q < end && *q < 0x80; q++)
+    ;
+  if (q == end)
+    return pdf_new_string(utf8, end - s);
+
+  /* No UTF-8 sequence becomes more than twice as long in UTF-16. */
+  buf    = NEW(2 * (end - s) + 2, unsigned char);
+  bufend = buf + 2 * (end - s) + 2;
+  dst    = buf;
+  *dst++ = 0xfe;
+  *dst++ = 0xff;
+
+  while (s < end) {
+    int32_t c = UC_UTF8_decode_char(&s, end);
+
+    if (c < 0) {
+      s++;
+      c = 0xfffd;
+    } else if (!UC_is_valid(c)) {
+      c = 0xfffd;
+    }
+
+    UC_UTF16BE_encode_char(c, &dst, bufend);
+  }
+
+  str = pdf_new_string(buf, dst - buf);
+  free(buf);
+  return str;
+}
+
+static int
+pdf_doc_have_metadata (pdf_doc *p)
+{
+  return p->metadata.title || p->metadata.author || p->metadata.subject ||
+    p->metadata.keywords || p->metadata.lang || p->metadata.num_custom > 0;
+}
+
+/* Override the document information with the metadata from the caller. */
+static void
+pdf_doc_apply_metadata (pdf_doc *p, pdf_obj *docinfo)
+{
+  const struct {
+    const char *key;
+    const char *value;
+  } entries[] = {
+    {"Title",    p->metadata.title},
+    {"Author",   p->metadata.author},
+    {"Subject",  p->metadata.subject},
+    {"Keywords", p->metadata.keywords},
+  };
+  size_t i;
+
+  for (i = 0; i < sizeof(entries) / sizeof(entries[0]); i++) {
+    if (entries[i].value)
+      pdf_add_dict(docinfo, pdf_new_name(entries[i].key),
+                   pdf_doc_text_string(entries[i].value));
+  }
+
+  for (i = 0; i < p->metadata.num_custom; i++) {
+    pdf_add_dict(docinfo, pdf_new_name(p->metadata.custom_keys[i]),
+                 pdf_doc_text_string(p->metadata.custom_values[i]));
+  }
+
+  if (p->metadata.lang)
+    pdf_add_dict(p->root.dict, pdf_new_name("Lang"),
+                 pdf_doc_text_string(p->metadata.lang));
+}
+
+/* Add the XMP metadata stream to the catalog. It mirrors the document
+ * information dictionary, so this must be called once that is complete.
+ * Custom properties go in the namespace that Acrobat uses for custom
+ * document information entries. */
+static void
+pdf_doc_add_xmp (pdf_doc *p, pdf_obj *docinfo)
+{
+  struct xmp_buf b = { NULL, 0, 0 };
+  pdf_obj *stream, *lang;
+  char     tmp[256];
+  size_t   i;
+
+  xmp_puts(&b,
+           "<?xpacket begin=\"\xef\xbb\xbf\" id=\"W5M0MpCehiHzreSzNTczkc9d\"?>\n"
+           "<x:xmpmeta xmlns:x=\"adobe:ns:meta/\">\n"
+           "<rdf:RDF xmlns:rdf=\"http://www.w3.org/1999/02/22-rdf-syntax-ns#\">\n"
+           "<rdf:Description rdf:about=\"\"\n"
+           " xmlns:pdfaid=\"http://www.aiim.org/pdfa/ns/id/\"\n"
+           " xmlns:dc=\"http://purl.org/dc/elements/1.1/\"\n"
+           " xmlns:pdf=\"http://ns.adobe.com/pdf/1.3/\"\n"
+           " xmlns:pdfx=\"http://ns.adobe.com/pdfx/1.3/\"\n"
+           " xmlns:xmp=\"http://ns.adobe.com/xap/1.0/\">\n");
+
+  if (p->pdfa.part) {
+    sprintf(tmp, "<pdfaid:part>%d</pdfaid:part>\n", p->pdfa.part);
+    xmp_puts(&b, tmp);
+    xmp_puts(&b, "<pdfaid:conformance>B</pdfaid:conformance>\n");
+  }
+
+  xmp_puts(&b, "<dc:format>application/pdf</dc:format>\n");
+  xmp_put_property(&b, docinfo, "Title", "dc:title", XMP_ALT);
+  xmp_put_property(&b, docinfo, "Author", "dc:creator", XMP_SEQ);
+  xmp_put_property(&b, docinfo, "Subject", "dc:description", XMP_ALT);
+
+  lang = pdf_lookup_dict(p->root.dict, "Lang");
+  if (PDF_OBJ_STRINGTYPE(lang)) {
+    xmp_puts(&b, "<dc:language><rdf:Bag><rdf:li>");
+    xmp_put_text(&b, lang);
+    xmp_puts(&b, "</rdf:li></rdf:Bag></dc:language>\n");
+  }
+
+  xmp_put_property(&b, docinfo, "Keywords", "pdf:Keywords", XMP_SIMPLE);
+  xmp_put_property(&b, docinfo, "Producer", "pdf:Producer", XMP_SIMPLE);
+  xmp_put_property(&b, docinfo, "Creator", "xmp:CreatorTool", XMP_SIMPLE);
+  xmp_put_date_property(&b, docinfo, "CreationDate", "xmp:CreateDate");
+  xmp_put_date_property(&b, docinfo, "ModDate", "xmp:ModifyDate");
+
+  for (i = 0; i < p->metadata.num_custom; i++) {
+    snprintf(tmp, sizeof(tmp), "pdfx:%s", p->metadata.custom_keys[i]);
+    xmp_put_property(&b, docinfo, p->metadata.custom_keys[i], tmp, XMP_SIMPLE);
+  }
+
+  xmp_puts(&b,
+           "</rdf:Description>\n"
+           "</rdf:RDF>\n"
+           "</x:xmpmeta>\n"
+           "<?xpacket end=\"w\"?>");
+
+  /* PDF/A forbids compressing the metadata stream. */
+  stream = pdf_new_stream(0);
+  pdf_add_dict(pdf_stream_dict(stream), pdf_new_name("Type"), pdf_new_name("Metadata"));
+  pdf_add_dict(pdf_stream_dict(stream), pdf_new_name("Subtype"), pdf_new_name("XML"));
+  pdf_add_stream(stream, b.data, b.len);
+  pdf_add_dict(p->root.dict, pdf_new_name("Metadata"), pdf_ref_obj(stream));
+  pdf_release_obj(stream);
+  free(b.data);
+}
+
+/* Add the output intent required by PDF/A to the catalog. */
+static void
+pdf_doc_close_pdfa (pdf_doc *p)
+{
+  pdf_obj *stream, *intent, *intents;
+
+  if (!p->pdfa.icc_profile || p->pdfa.icc_profile_len == 0) {
+    pdf_doc_pdfa_violation("no ICC profile was provided for the output intent");
+    return;
+  }
+
+  stream = pdf_new_stream(STREAM_COMPRESS);
+  pdf_add_dict(pdf_stream_dict(stream), pdf_new_name("N"), pdf_new_number(3));
+  pdf_add_stream(stream, p->pdfa.icc_profile, p->pdfa.icc_profile_len);
+
+  intent = pdf_new_dict();
+  pdf_add_dict(intent, pdf_new_name("Type"), pdf_new_name("OutputIntent"));
+  pdf_add_dict(intent, pdf_new_name("S"), pdf_new_name("GTS_PDFA1"));
+  pdf_add_dict(intent, pdf_new_name("OutputConditionIdentifier"),
+               pdf_new_string("sRGB IEC61966-2.1", 17));
+  pdf_add_dict(intent, pdf_new_name("Info"),
+               pdf_new_string("sRGB IEC61966-2.1", 17));
+  pdf_add_dict(intent, pdf_new_name("RegistryName"),
+               pdf_new_string("http://www.color.org", 20));
+  pdf_add_dict(intent, pdf_new_name("DestOutputProfile"), pdf_ref_obj(stream));
+  pdf_release_obj(stream);
+
+  intents = pdf_new_array();
+  pdf_add_array(intents, intent);
+  pdf_add_dict(p->root.dict, pdf_new_name("OutputIntents"), intents);
+}
+
+/*
+ * Tectonic: tagged PDF.
+ *
+ * Structure elements are delimited by the same `tdux:` specials that the
+ * spx2html engine uses, so they're named after HTML elements. We use those
+ * names as the structure types, and map them to the standard PDF types with
+ * the role map. The content of each page is divided into marked-content
+ * sequences, each of which belongs to the innermost element that was open
+ * when it was drawn. Content outside of any element belongs to the top-level
+ * Document element.
+ */
+
+static const struct {
+  const char *name;
+  const char *role;
+} struct_roles[] = {
+  {"p", "P"},
+  {"h1", "H1"}, {"h2", "H2"}, {"h3", "H3"},
+  {"h4", "H4"}, {"h5", "H5"}, {"h6", "H6"},
+  {"ul", "L"}, {"ol", "L"}, {"dl", "L"},
+  {"li", "LI"}, {"dt", "Lbl"}, {"dd", "LBody"},
+  {"table", "Table"}, {"thead", "THead"}, {"tbody", "TBody"}, {"tfoot", "TFoot"},
+  {"tr", "TR"}, {"th", "TH"}, {"td", "TD"},
+  {"figure", "Div"}, {"figcaption", "Caption"}, {"caption", "Caption"},
+  {"img", "Figure"},
+  {"blockquote", "BlockQuote"}, {"q", "Quote"},
+  {"pre", "Code"}, {"code", "Code"},
+  {"math", "Formula"},
+  {"section", "Sect"}, {"article", "Art"}, {"header", "Sect"}, {"footer", "Sect"},
+  {"nav", "TOC"}, {"aside", "Note"},
+  {"a", "Span"}, {"span", "Span"}, {"em", "Span"}, {"strong", "Span"},
+  {"b", "Span"}, {"i", "Span"}, {"sup", "Span"}, {"sub", "Span"},
+  {NULL, NULL}
+};
+
+static void
+pdf_doc_init_structure (pdf_doc *p)
+{
+  pdf_obj *document;
+
+  p->structure.root = pdf_new_dict();
+  pdf_add_dict(p->structure.root, pdf_new_name("Type"), pdf_new_name("StructTreeRoot"));
+
+  document = pdf_new_dict();
+  pdf_add_dict(document, pdf_new_name("Type"), pdf_new_name("StructElem"));
+  pdf_add_dict(document, pdf_new_name("S"), pdf_new_name("Document"));
+  pdf_add_dict(document, pdf_new_name("P"), pdf_ref_obj(p->structure.root));
+  pdf_add_dict(document, pdf_new_name("K"), pdf_new_array());
+  pdf_add_dict(p->structure.root, pdf_new_name("K"), pdf_ref_obj(document));
+
+  p->structure.max_depth = 16;
+  p->structure.stack     = NEW(p->structure.max_depth, pdf_obj *);
+  p->structure.stack[0]  = document;
+  p->structure.depth     = 1;
+
+  p->structure.role_map    = pdf_new_dict();
+  p->structure.parent_tree = pdf_new_array();
+  p->structure.next_key    = 0;
+  p->structure.page_mcids  = NULL;
+  p->structure.in_content  = 0;
+  p->structure.num_elems   = 0;
+}
+
+/* Start a marked-content sequence belonging to the innermost open element. */
+static void
+pdf_doc_begin_struct_content (pdf_doc *p)
+{
+  pdf_obj *elem, *mcr;
+  char     buf[256];
+  int      mcid, len;
+
+  /* Marked content in form XObjects would need to be handled differently;
+   * anything drawn there belongs to the sequence that contains the form. */
+  if (!p->structure.page_mcids || p->pending_forms)
+    return;
+
+  elem = p->structure.stack[p->structure.depth - 1];
+  mcid = pdf_array_length(p->structure.page_mcids);
+  pdf_add_array(p->structure.page_mcids, pdf_ref_obj(elem));
+
+  mcr = pdf_new_dict();
+  pdf_add_dict(mcr, pdf_new_name("Type"), pdf_new_name("MCR"));
+  pdf_add_dict(mcr, pdf_new_name("Pg"), pdf_link_obj(LASTPAGE(p)->page_ref));
+  pdf_add_dict(mcr, pdf_new_name("MCID"), pdf_new_number(mcid));
+  pdf_add_array(pdf_lookup_dict(elem, "K"), mcr);
+
+  graphics_mode();
+  len = snprintf(buf, sizeof(buf), "\n/%s <</MCID %d>> BDC",
+                 pdf_name_value(pdf_lookup_dict(elem, "S")), mcid);
+  pdf_doc_add_page_content(buf, len);
+  p->structure.in_content = 1;
+}
+
+static void
+pdf_doc_end_struct_content (pdf_doc *p)
+{
+  if (!p->structure.in_content || p->pending_forms)
+    return;
+
+  graphics_mode();
+  pdf_doc_add_page_content(" EMC", 4);
+  p->structure.in_content = 0;
+}
+
+void
+pdf_doc_begin_struct (const char *type)
+{
+  pdf_doc    *p = &pdoc;
+  pdf_obj    *parent, *elem;
+  const char *c;
+  int         i;
+
+  if (!p->structure.root)
+    return;
+
+  /* The type becomes a name in content streams, so keep it simple. */
+  for (c = type; *c; c++) {
+    if (!isalnum((unsigned char) *c))
+      break;
+  }
+
+  if (*c || c == type) {
+    dpx_warning("Unsupported structure element name \"%s\"; using \"Div\".", type);
+    type = "Div";
+  }
+
+  pdf_doc_end_struct_content(p);
+
+  parent = p->structure.stack[p->structure.depth - 1];
+  elem = pdf_new_dict();
+  pdf_add_dict(elem, pdf_new_name("Type"), pdf_new_name("StructElem"));
+  pdf_add_dict(elem, pdf_new_name("S"), pdf_new_name(type));
+  pdf_add_dict(elem, pdf_new_name("P"), pdf_ref_obj(parent));
+  if (p->structure.page_mcids)
+    pdf_add_dict(elem, pdf_new_name("Pg"), pdf_link_obj(LASTPAGE(p)->page_ref));
+  pdf_add_dict(elem, pdf_new_name("K"), pdf_new_array());
+  pdf_add_array(pdf_lookup_dict(parent, "K"), pdf_ref_obj(elem));
+  p->structure.num_elems++;
+
+  if (p->structure.depth >= p->structure.max_depth) {
+    p->structure.max_depth += 16;
+    p->structure.stack = RENEW(p->structure.stack, p->structure.max_depth, pdf_obj *);
+  }
+  p->structure.stack[p->structure.depth++] = elem;
+
+  if (!pdf_lookup_dict(p->structure.role_map, type)) {
+    for (i = 0; struct_roles[i].name != NULL; i++) {
+      if (!strcmp(type, struct_roles[i].name)) {
+        pdf_add_dict(p->structure.role_map, pdf_new_name(type),
+                     pdf_new_name(struct_roles[i].role));
+        break;
+      }
+    }
+
+    /* Standard types are capitalized; anything else must be mapped to one. */
+    if (struct_roles[i].name == NULL && islower((unsigned char) type[0]))
+      pdf_add_dict(p->structure.role_map, pdf_new_name(type), pdf_new_name("Div"));
+  }
+
+  pdf_doc_begin_struct_content(p);
+}
+
+void
+pdf_doc_end_struct (const char *type)
+{
+  pdf_doc     *p = &pdoc;
+  unsigned int i;
+
+  if (!p->structure.root)
+    return;
+
+  /* Find the matching element, implicitly closing any inside of it. */
+  for (i = p->structure.depth - 1; i > 0; i--) {
+    if (!strcmp(pdf_name_value(pdf_lookup_dict(p->structure.stack[i], "S")), type))
+      break;
+  }
+
+  if (i == 0) {
+    dpx_warning("Ignoring the end of a \"%s\" structure element that isn't open.", type);
+    return;
+  }
+
+  pdf_doc_end_struct_content(p);
+
+  while (p->structure.depth > i)
+    pdf_release_obj(p->structure.stack[--p->structure.depth]);
+
+  pdf_doc_begin_struct_content(p);
+}
+
+static void
+pdf_doc_begin_struct_page (pdf_doc *p)
+{
+  if (!p->structure.root)
+    return;
+
+  p->structure.page_mcids = pdf_new_array();
+  pdf_doc_begin_struct_content(p);
+}
+
+static void
+pdf_doc_end_struct_page (pdf_doc *p)
+{
+  if (!p->structure.root)
+    return;
+
+  pdf_doc_end_struct_content(p);
+
+  if (pdf_array_length(p->structure.page_mcids) > 0) {
+    pdf_add_dict(LASTPAGE(p)->page_obj, pdf_new_name("StructParents"),
+                 pdf_new_number(p->structure.next_key));
+    pdf_add_array(p->structure.parent_tree, pdf_new_number(p->structure.next_key));
+    pdf_add_array(p->structure.parent_tree, pdf_ref_obj(p->structure.page_mcids));
+    p->structure.next_key++;
+  }
+
+  pdf_release_obj(p->structure.page_mcids);
+  p->structure.page_mcids = NULL;
+}
+
+static void
+pdf_doc_close_structure (pdf_doc *p)
+{
+  pdf_obj *parent_tree, *mark_info;
+
+  if (!p->structure.root)
+    return;
+
+  if (p->structure.depth > 1)
+    dpx_warning("%u structure element(s) were never closed.", p->structure.depth - 1);
+
+  while (p->structure.depth > 0)
+    pdf_release_obj(p->structure.stack[--p->structure.depth]);
+  p->structure.stack = mfree(p->structure.stack);
+
+  parent_tree = pdf_new_dict();
+  pdf_add_dict(parent_tree, pdf_new_name("Nums"), p->structure.parent_tree);
+  pdf_add_dict(p->structure.root, pdf_new_name("ParentTree"), pdf_ref_obj(parent_tree));
+  pdf_release_obj(parent_tree);
+  pdf_add_dict(p->structure.root, pdf_new_name("ParentTreeNextKey"),
+               pdf_new_number(p->structure.next_key));
+
+  pdf_add_dict(p->structure.root, pdf_new_name("RoleMap"), p->structure.role_map);
+
+  pdf_add_dict(p->root.dict, pdf_new_name("StructTreeRoot"), pdf_ref_obj(p->structure.root));
+  pdf_release_obj(p->structure.root);
+
+  /* A tree holding nothing but the Document element doesn't describe the
+   * document in any useful way, so don't claim that it's tagged. */
+  if (p->structure.num_elems > 0) {
+    mark_info = pdf_new_dict();
+    pdf_add_dict(mark_info, pdf_new_name("Marked"), pdf_new_boolean(1));
+    pdf_add_dict(p->root.dict, pdf_new_name("MarkInfo"), mark_info);
+  } else {
+    dpx_warning("No structure markup (tdux: specials) found; the PDF will not be marked as tagged.");
+  }
+
+  p->structure.root        = NULL;
+  p->structure.role_map    = NULL;
+  p->structure.parent_tree = NULL;
+}
+
 void
 pdf_open_document (const char *filename,
                    const char *creator,
@@ -2448,6 +3286,27 @@ pdf_open_document (const char *filename,
 {
   pdf_doc *p = &pdoc;
 
+  p->pdfa.part            = settings.pdfa.part;
+  p->pdfa.icc_profile     = settings.pdfa.icc_profile;
+  p->pdfa.icc_profile_len = settings.pdfa.icc_profile_len;
+  p->pdfa.violations      = NULL;
+  p->pdfa.num_violations  = 0;
+  p->pdfa.max_violations  = 0;
+
+  if (p->pdfa.part) {
+    if (settings.ver_major > 1 || settings.ver_minor > 7) {
+      dpx_warning("PDF/A-%d is based on PDF 1.7; ignoring request for PDF version %d.%d.",
+                  p->pdfa.part, settings.ver_major, settings.ver_minor);
+      settings.ver_major = 1;
+      settings.ver_minor = 7;
+    }
+
+    if (settings.enable_encrypt) {
+      dpx_warning("Encryption is not permitted in PDF/A output; it will be disabled.");
+      settings.enable_encrypt = 0;
+    }
+  }
+
   pdf_out_init(filename, id1, id2,
               settings.ver_major, settings.ver_minor,
               settings.object.compression_level,
@@ -2457,6 +3316,12 @@ pdf_open_document (const char *filename,
 
   pdf_doc_init_catalog(p);
 
+  p->metadata = settings.metadata;
+
+  p->structure.root = NULL;
+  if (settings.enable_tagging)
+    pdf_doc_init_structure(p);
+
   /* After Catalog is created... */
   if (settings.enable_encrypt) {
     pdf_out_set_encrypt(settings.encrypt.key_size, settings.encrypt.permission,
@@ -2530,6 +3395,7 @@ pdf_close_document (void)
   pdf_doc_close_names    (p);
   pdf_doc_close_bookmarks(p);
   pdf_doc_close_page_tree(p);
+  pdf_doc_close_structure(p);
   pdf_doc_close_docinfo  (p);
 
   pdf_doc_close_catalog  (p);
@@ -2543,6 +3409,8 @@ pdf_close_document (void)
   pdf_files_close();
   pdf_out_flush();
 
+  pdf_doc_report_pdfa(p);
+
   free(p->thumb_basename);
 
   return;
@@ -63,6 +63,31 @@ struct pdf_obj_setting {
     int compression_level;
 };
 
+/* Tectonic: PDF/A conformance. `part` is 0 if conformance is not requested,
+ * or the part number of the PDF/A standard (2 or 3) to target at level "b".
+ * The ICC profile is used for the document's output intent; it must be an
+ * sRGB profile. */
+struct pdf_pdfa_setting {
+    int                  part;
+    const unsigned char *icc_profile;
+    size_t               icc_profile_len;
+};
+
+/* Tectonic: document metadata supplied by the caller. All strings are UTF-8,
+ * and any of them may be NULL. These take precedence over any values that the
+ * document sets itself. The custom properties are added to the document
+ * information dictionary and, like the standard ones, to the XMP metadata. */
+struct pdf_metadata_setting {
+    const char         *title;
+    const char         *author;
+    const char         *subject;
+    const char         *keywords;
+    const char         *lang;
+    const char *const  *custom_keys;
+    const char *const  *custom_values;
+    size_t              num_custom;
+};
+
 struct pdf_setting
 {
     int ver_major, ver_minor;
@@ -77,6 +102,9 @@ struct pdf_setting
     struct pdf_enc_setting encrypt;
     struct pdf_dev_setting device;
     struct pdf_obj_setting object;
+    struct pdf_pdfa_setting pdfa;
+    int enable_tagging; /* Tectonic: build a structure tree from tdux: specials */
+    struct pdf_metadata_setting metadata;
 };
 
 void pdf_open_document (const char *filename,
@@ -169,6 +197,18 @@ void     pdf_doc_expand_box  (const pdf_rect *rect);
 /* Manual thumbnail */
 void     pdf_doc_enable_manual_thumbnails (void);
 
+/* Tectonic: PDF/A conformance checking. The part number is 0 if PDF/A output
+ * has not been requested. Problems that prevent the output from conforming
+ * are recorded with pdf_doc_pdfa_violation() and reported when the document
+ * is closed. */
+int      pdf_doc_pdfa_part      (void);
+PRINTF_FUNC(1,2) void pdf_doc_pdfa_violation (const char *fmt, ...);
+
+/* Tectonic: tagged PDF. Elements are named after the HTML elements that the
+ * tdux: specials describe. These do nothing unless tagging is enabled. */
+void pdf_doc_begin_struct (const char *type);
+void pdf_doc_end_struct   (const char *type);
+
 /* Similar to bop_content */
 #include "dpx-pdfcolor.h"
 void     pdf_doc_set_bgcolor   (const pdf_color *color);
@@ -43,6 +43,7 @@
 #include "dpx-error.h"
 #include "dpx-mem.h"
 #include "dpx-pdfencoding.h"
+#include "dpx-pdfdoc.h"
 #include "dpx-pdflimits.h"
 #include "dpx-pdfobj.h"
 #include "dpx-pkfont.h"
@@ -173,9 +174,12 @@ pdf_flush_font (pdf_font *font)
       break;
     case PDF_FONT_FONTTYPE_CIDTYPE0:
     case PDF_FONT_FONTTYPE_CIDTYPE2:
+      if (!font->cid.options.embed)
+        pdf_doc_pdfa_violation("font \"%s\" is not embedded", font->fontname);
       break;
     default:
       if (font->flags & PDF_FONT_FLAG_NOEMBED) {
+        pdf_doc_pdfa_violation("font \"%s\" is not embedded", font->fontname);
         pdf_add_dict(font->resource, pdf_new_name("BaseFont"), pdf_new_name(font->fontname));
         if (font->descriptor) {
           pdf_add_dict(font->descriptor, pdf_new_name("FontName"), pdf_new_name(font->fontname));
@@ -301,6 +301,10 @@ load_image (const char *ident, const char *filename, const char *fullname,
         {
             int result = pdf_include_page(I, handle, fullname, options);
             /* Tectonic: this used to try ps_include_page() */
+            if (result == EPDF_DAMAGED) {
+                pdf_clean_ximage_struct(I);
+                return EPDF_DAMAGED;
+            }
             if (result != 0)
                 goto error;
         }
@@ -401,6 +405,11 @@ pdf_ximage_load_image (const char *ident, const char *filename, load_options opt
     if (dpx_conf.verbose_level > 0)
         dpx_message(")");
 
+    /* Tectonic: damaged figures have already been warned about; leave a
+     * blank space rather than aborting. */
+    if (id == EPDF_DAMAGED)
+        return -1;
+
     if (id < 0) {
         if (dpx_conf.compat_mode == dpx_mode_compat_mode) {
             if (format == IMAGE_TYPE_PDF || format == IMAGE_TYPE_EPS) {
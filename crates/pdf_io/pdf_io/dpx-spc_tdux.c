@@ -0,0 +1,166 @@
+/* Copyright 2026 the Tectonic Project
+ * Licensed under the MIT License.
+*/
+
+/* Tectonic: the `tdux:` specials that mark up the semantic structure of a
+ * document for the spx2html engine. In PDF output we use the ones that
+ * delimit elements to build the structure tree of a tagged PDF, and quietly
+ * ignore the rest, which only make sense for HTML.
+ */
+
+#include "dpx-spc_tdux.h"
+
+#include <assert.h>
+#include <stdbool.h>
+#include <stdlib.h>
+#include <string.h>
+
+#include "dpx-mem.h"
+#include "dpx-pdfdoc.h"
+#include "dpx-pdfparse.h"
+#include "dpx-specials.h"
+
+static int
+spc_handler_tdux_ignore (struct spc_env *spe, struct spc_arg *args)
+{
+  args->curptr = args->endptr;
+
+  return 0;
+}
+
+/* Read the element name that starts the rest of the special. For `mfs`, any
+ * following lines specify attributes, which we don't need. */
+static char *
+read_tag_name (struct spc_arg *args)
+{
+  const char *start;
+  char       *name;
+
+  skip_white(&args->curptr, args->endptr);
+  start = args->curptr;
+  while (args->curptr < args->endptr &&
+         *args->curptr != ' ' && *args->curptr != '\n' && *args->curptr != '\r')
+    args->curptr++;
+
+  if (args->curptr == start)
+    return NULL;
+
+  name = NEW(args->curptr - start + 1, char);
+  memcpy(name, start, args->curptr - start);
+  name[args->curptr - start] = '\0';
+  args->curptr = args->endptr;
+
+  return name;
+}
+
+static int
+spc_handler_tdux_asp (struct spc_env *spe, struct spc_arg *args)
+{
+  pdf_doc_begin_struct("p");
+  args->curptr = args->endptr;
+
+  return 0;
+}
+
+static int
+spc_handler_tdux_aep (struct spc_env *spe, struct spc_arg *args)
+{
+  pdf_doc_end_struct("p");
+  args->curptr = args->endptr;
+
+  return 0;
+}
+
+static int
+spc_handler_tdux_mfs (struct spc_env *spe, struct spc_arg *args)
+{
+  char *name = read_tag_name(args);
+
+  if (!name) {
+    spc_warn(spe, "Missing element name in tdux:mfs special.");
+    return -1;
+  }
+
+  pdf_doc_begin_struct(name);
+  free(name);
+
+  return 0;
+}
+
+static int
+spc_handler_tdux_me (struct spc_env *spe, struct spc_arg *args)
+{
+  char *name = read_tag_name(args);
+
+  if (!name) {
+    spc_warn(spe, "Missing element name in tdux:me special.");
+    return -1;
+  }
+
+  pdf_doc_end_struct(name);
+  free(name);
+
+  return 0;
+}
+
+static struct spc_handler tdux_handlers[] = {
+  {"asp", spc_handler_tdux_asp},
+  {"aep", spc_handler_tdux_aep},
+  {"mfs", spc_handler_tdux_mfs},
+  {"me",  spc_handler_tdux_me},
+};
+
+bool
+spc_tdux_check_special (const char *buf, int len)
+{
+  const char *p, *endptr;
+
+  p      = buf;
+  endptr = p + len;
+
+  skip_white(&p, endptr);
+  if (p + strlen("tdux:") <= endptr &&
+      !memcmp(p, "tdux:", strlen("tdux:"))) {
+    return true;
+  }
+
+  return false;
+}
+
+int
+spc_tdux_setup_handler (struct spc_handler *sph,
+                        struct spc_env *spe, struct spc_arg *ap)
+{
+  const char *start;
+  size_t      i, len;
+
+  assert(sph && spe && ap);
+
+  skip_white(&ap->curptr, ap->endptr);
+  if (ap->curptr + strlen("tdux:") > ap->endptr ||
+      memcmp(ap->curptr, "tdux:", strlen("tdux:"))) {
+    spc_warn(spe, "Not tdux: special???");
+    return  -1;
+  }
+  ap->curptr += strlen("tdux:");
+
+  start = ap->curptr;
+  while (ap->curptr < ap->endptr &&
+         *ap->curptr != ' ' && *ap->curptr != '\n' && *ap->curptr != '\r')
+    ap->curptr++;
+  len = ap->curptr - start;
+
+  sph->key   = "tdux:";
+  sph->exec  = spc_handler_tdux_ignore;
+
+  for (i = 0; i < sizeof(tdux_handlers) / sizeof(struct spc_handler); i++) {
+    if (strlen(tdux_handlers[i].key) == len &&
+        !memcmp(start, tdux_handlers[i].key, len)) {
+      ap->command = tdux_handlers[i].key;
+      sph->exec   = tdux_handlers[i].exec;
+      break;
+    }
+  }
+
+  return 0;
+}
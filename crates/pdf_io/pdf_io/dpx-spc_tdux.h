@@ -0,0 +1,18 @@
+/* Copyright 2026 the Tectonic Project
+ * Licensed under the MIT License.
+*/
+
+#ifndef _SPC_TDUX_H_
+#define _SPC_TDUX_H_
+
+#include "tectonic_bridge_core.h"
+
+#include <stdbool.h>
+
+#include "dpx-specials.h"
+
+bool spc_tdux_check_special (const char *buf, int len);
+int  spc_tdux_setup_handler (struct spc_handler *sph,
+                             struct spc_env *spe, struct spc_arg *ap);
+
+#endif /* _SPC_TDUX_H_ */
@@ -48,6 +48,7 @@
 #include "dpx-spc_html.h"
 #include "dpx-spc_misc.h"
 #include "dpx-spc_pdfm.h"
+#include "dpx-spc_tdux.h"
 #include "dpx-spc_tpic.h"
 #include "dpx-spc_xtx.h"
 
@@ -612,6 +613,18 @@ static struct {
    spc_misc_setup_handler
   },
 
+  /* Tectonic: semantic structure specials */
+  {"tdux:",
+   NULL,
+   NULL,
+   NULL,
+   NULL,
+   NULL,
+   NULL,
+   spc_tdux_check_special,
+   spc_tdux_setup_handler
+  },
+
   {NULL} /* end */
 };
 
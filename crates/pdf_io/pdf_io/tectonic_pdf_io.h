@@ -0,0 +1,133 @@
+#ifndef TECTONIC_PDF_IO_H
+#define TECTONIC_PDF_IO_H
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+/**
+ * A PDF document, held in memory.
+ *
+ * Documents are loaded in full: every object is parsed when the file is
+ * read. This suits our uses, which involve reorganizing documents rather
+ * than looking at small parts of large ones.
+ */
+typedef struct TectonicPdfDocument TectonicPdfDocument;
+
+/**
+ * A rectangle in PDF user space, in big points.
+ */
+typedef struct {
+  /**
+   * The X coordinate of the lower left corner.
+   */
+  double llx;
+  /**
+   * The Y coordinate of the lower left corner.
+   */
+  double lly;
+  /**
+   * The X coordinate of the upper right corner.
+   */
+  double urx;
+  /**
+   * The Y coordinate of the upper right corner.
+   */
+  double ury;
+} TectonicPdfRect;
+
+/**
+ * The geometry of a page of a PDF file.
+ */
+typedef struct {
+  /**
+   * The selected page boundary box, clipped to the media box.
+   */
+  TectonicPdfRect bbox;
+  /**
+   * The transformation that applies the page's rotation to the box, in
+   * PDF's `[a b c d e f]` order.
+   */
+  double matrix[6];
+  /**
+   * The selected box as it appears once the page's rotation is applied.
+   */
+  TectonicPdfRect displayed;
+} TectonicPdfPageGeometry;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+/**
+ * Load a PDF document from memory.
+ *
+ * Returns NULL on failure, in which case an error message is written into
+ * `error_buf`, which may be NULL. The document must be freed with
+ * `tectonic_pdf_document_free()`.
+ *
+ * # Safety
+ *
+ * This is a C API function, so it is unsafe.
+ */
+TectonicPdfDocument *tectonic_pdf_document_load(const uint8_t *data,
+                                                uintptr_t len,
+                                                char *error_buf,
+                                                uintptr_t error_len);
+
+/**
+ * Free a PDF document.
+ *
+ * # Safety
+ *
+ * This is a C API function, so it is unsafe.
+ */
+void tectonic_pdf_document_free(TectonicPdfDocument *doc);
+
+/**
+ * Check whether a PDF document had to be reconstructed because its
+ * cross-reference information was damaged.
+ *
+ * # Safety
+ *
+ * This is a C API function, so it is unsafe.
+ */
+bool tectonic_pdf_document_was_repaired(const TectonicPdfDocument *doc);
+
+/**
+ * Get the number of pages in a PDF document.
+ *
+ * Returns a negative number if the document's page tree is unusable.
+ *
+ * # Safety
+ *
+ * This is a C API function, so it is unsafe.
+ */
+int tectonic_pdf_document_page_count(const TectonicPdfDocument *doc);
+
+/**
+ * Get the geometry of a page of a PDF document.
+ *
+ * Pages are numbered from 1. The box type uses the same numbering as
+ * xdvipdfmx’s `enum pdf_page_boundary`.
+ *
+ * Returns zero on success. On failure, returns nonzero and writes an error
+ * message into `error_buf`, which may be NULL.
+ *
+ * # Safety
+ *
+ * This is a C API function, so it is unsafe.
+ */
+int tectonic_pdf_document_page_geometry(const TectonicPdfDocument *doc,
+                                        int page_no,
+                                        int box_type,
+                                        TectonicPdfPageGeometry *geometry,
+                                        char *error_buf,
+                                        uintptr_t error_len);
+
+#ifdef __cplusplus
+} // extern "C"
+#endif // __cplusplus
+
+#endif /* TECTONIC_PDF_IO_H */
@@ -0,0 +1,204 @@
+// Copyright 2026 the Tectonic Project
+// Licensed under the MIT License.
+
+//! A C API to the PDF reader, used by xdvipdfmx and XeTeX to examine included
+//! PDF graphics.
+//!
+//! If you change the interfaces here, rerun cbindgen as described in the README!
+
+use std::{
+    os::raw::{c_char, c_int},
+    panic::{self, AssertUnwindSafe},
+    ptr, slice,
+};
+
+use crate::{
+    document::Document,
+    page::{PageBox, Rect},
+};
+
+/// The geometry of a page of a PDF file.
+#[repr(C)]
+pub struct PdfPageGeometry {
+    /// The selected page boundary box, clipped to the media box.
+    pub bbox: Rect,
+
+    /// The transformation that applies the page's rotation to the box, in
+    /// PDF's `[a b c d e f]` order.
+    pub matrix: [f64; 6],
+
+    /// The selected box as it appears once the page's rotation is applied.
+    pub displayed: Rect,
+}
+
+/// Copy an error message into a C buffer, truncating it if needed.
+unsafe fn report_error(message: &str, buf: *mut c_char, len: usize) {
+    if buf.is_null() || len == 0 {
+        return;
+    }
+
+    let mut n = message.len().min(len - 1);
+
+    while !message.is_char_boundary(n) {
+        n -= 1;
+    }
+
+    ptr::copy_nonoverlapping(message.as_ptr() as *const c_char, buf, n);
+    *buf.add(n) = 0;
+}
+
+/// Load a PDF document from memory.
+///
+/// Returns NULL on failure, in which case an error message is written into
+/// `error_buf`, which may be NULL. The document must be freed with
+/// `tectonic_pdf_document_free()`.
+///
+/// # Safety
+///
+/// This is a C API function, so it is unsafe.
+#[no_mangle]
+pub unsafe extern "C" fn tectonic_pdf_document_load(
+    data: *const u8,
+    len: usize,
+    error_buf: *mut c_char,
+    error_len: usize,
+) -> *mut Document {
+    if data.is_null() {
+        report_error("no PDF data", error_buf, error_len);
+        return ptr::null_mut();
+    }
+
+    let data = slice::from_raw_parts(data, len);
+
+    // We never expect to panic, but damaged files are exactly where bugs
+    // would lurk, and unwinding into C would be far worse than an error.
+    match panic::catch_unwind(|| Document::load(data)) {
+        Ok(Ok(doc)) => Box::into_raw(Box::new(doc)),
+
+        Ok(Err(e)) => {
+            report_error(&e.to_string(), error_buf, error_len);
+            ptr::null_mut()
+        }
+
+        Err(_) => {
+            report_error("internal error reading PDF", error_buf, error_len);
+            ptr::null_mut()
+        }
+    }
+}
+
+/// Free a PDF document.
+///
+/// # Safety
+///
+/// This is a C API function, so it is unsafe.
+#[no_mangle]
+pub unsafe extern "C" fn tectonic_pdf_document_free(doc: *mut Document) {
+    if !doc.is_null() {
+        drop(Box::from_raw(doc));
+    }
+}
+
+/// Check whether a PDF document had to be reconstructed because its
+/// cross-reference information was damaged.
+///
+/// # Safety
+///
+/// This is a C API function, so it is unsafe.
+#[no_mangle]
+pub unsafe extern "C" fn tectonic_pdf_document_was_repaired(doc: *const Document) -> bool {
+    (*doc).was_repaired()
+}
+
+/// Get the number of pages in a PDF document.
+///
+/// Returns a negative number if the document's page tree is unusable.
+///
+/// # Safety
+///
+/// This is a C API function, so it is unsafe.
+#[no_mangle]
+pub unsafe extern "C" fn tectonic_pdf_document_page_count(doc: *const Document) -> c_int {
+    match (*doc).page_ids() {
+        Ok(pages) => pages.len().min(c_int::MAX as usize) as c_int,
+        Err(_) => -1,
+    }
+}
+
+/// Get the geometry of a page of a PDF document.
+///
+/// Pages are numbered from 1. The box type uses the same numbering as
+/// xdvipdfmx’s `enum pdf_page_boundary`.
+///
+/// Returns zero on success. On failure, returns nonzero and writes an error
+/// message into `error_buf`, which may be NULL.
+///
+/// # Safety
+///
+/// This is a C API function, so it is unsafe.
+#[no_mangle]
+pub unsafe extern "C" fn tectonic_pdf_document_page_geometry(
+    doc: *const Document,
+    page_no: c_int,
+    box_type: c_int,
+    geometry: *mut PdfPageGeometry,
+    error_buf: *mut c_char,
+    error_len: usize,
+) -> c_int {
+    let which = match box_type {
+        1 => PageBox::Crop,
+        2 => PageBox::Media,
+        3 => PageBox::Art,
+        4 => PageBox::Trim,
+        5 => PageBox::Bleed,
+        _ => PageBox::Auto,
+    };
+
+    let doc = &*doc;
+    let result = panic::catch_unwind(AssertUnwindSafe(|| doc.page_info(page_no.max(0) as usize)));
+
+    match result {
+        Ok(Ok(info)) => {
+            let bbox = info.page_box(which);
+            *geometry = PdfPageGeometry {
+                bbox,
+                matrix: info.matrix(&bbox),
+                displayed: info.displayed_box(which),
+            };
+            0
+        }
+
+        Ok(Err(e)) => {
+            report_error(&e.to_string(), error_buf, error_len);
+            1
+        }
+
+        Err(_) => {
+            report_error("internal error reading PDF", error_buf, error_len);
+            1
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn errors() {
+        let mut buf = [1 as c_char; 16];
+
+        unsafe {
+            let doc =
+                tectonic_pdf_document_load(b"%PDF-1.4\ngarbage".as_ptr(), 16, buf.as_mut_ptr(), 16);
+            assert!(doc.is_null());
+        }
+
+        let message: Vec<u8> = buf
+            .iter()
+            .take_while(|c| **c != 0)
+            .map(|c| *c as u8)
+            .collect();
+        assert_eq!(message.len(), 15);
+    }
+}
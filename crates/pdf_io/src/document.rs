@@ -0,0 +1,954 @@
+// Copyright 2026 the Tectonic Project
+// Licensed under the MIT License.
+
+//! Reading and writing whole PDF files.
+
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    io::Write,
+};
+use tectonic_errors::prelude::*;
+
+use crate::{
+    object::{Dictionary, Object, ObjectId, Stream},
+    parser::{self, Parser},
+};
+
+/// The deepest page tree that we'll descend into.
+const MAX_PAGE_TREE_DEPTH: usize = 64;
+
+/// Where to find an object, according to a cross-reference section.
+#[derive(Clone, Copy, Debug)]
+enum XrefEntry {
+    Free,
+    Offset(usize, u16),
+    Compressed(u32, usize),
+}
+
+/// A PDF document, held in memory.
+///
+/// Documents are loaded in full: every object is parsed when the file is
+/// read. This suits our uses, which involve reorganizing documents rather
+/// than looking at small parts of large ones.
+#[derive(Clone, Debug)]
+pub struct Document {
+    /// The PDF version, such as `"1.7"`.
+    pub version: String,
+
+    /// The trailer dictionary. When a document is written, only the objects
+    /// reachable from its `Root` and `Info` entries are included.
+    pub trailer: Dictionary,
+
+    /// The document's indirect objects.
+    pub objects: BTreeMap<ObjectId, Object>,
+
+    repaired: bool,
+}
+
+impl Default for Document {
+    fn default() -> Self {
+        Document {
+            version: "1.7".to_owned(),
+            trailer: Dictionary::new(),
+            objects: BTreeMap::new(),
+            repaired: false,
+        }
+    }
+}
+
+impl Document {
+    /// Create an empty document, with no catalog.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Load a document from the contents of a PDF file.
+    ///
+    /// This handles cross-reference tables and streams, object streams, and
+    /// incremental updates. If the cross-reference information is damaged,
+    /// the objects are located by scanning the file instead. Encrypted
+    /// documents aren't supported.
+    pub fn load(data: &[u8]) -> Result<Self> {
+        let header = data.len().min(1024);
+        let hpos = a_ok_or!(
+            parser::find(&data[..header], b"%PDF-");
+            ["not a PDF file: no `%PDF-` header"]
+        );
+        let version: String = data[hpos + 5..]
+            .iter()
+            .take_while(|b| b.is_ascii_digit() || **b == b'.')
+            .map(|b| *b as char)
+            .collect();
+
+        let mut doc = match Loader::new(data).load_with_xref() {
+            Ok(doc) => doc,
+            Err(_) => Loader::new(data).load_by_scanning()?,
+        };
+
+        doc.version = if version.is_empty() {
+            "1.4".to_owned()
+        } else {
+            version
+        };
+
+        // A document can override the header version in its catalog.
+        if let Some(v) = doc.catalog().ok().and_then(|c| c.get(b"Version")) {
+            if let Some(v) = v.as_name() {
+                let v = String::from_utf8_lossy(v).into_owned();
+
+                if version_key(&v) > version_key(&doc.version) {
+                    doc.version = v;
+                }
+            }
+        }
+
+        ensure!(
+            doc.trailer.get(b"Encrypt").is_none(),
+            "encrypted PDF files are not supported"
+        );
+        a_ok_or!(
+            doc.trailer.get(b"Root");
+            ["the PDF file has no document catalog"]
+        );
+        Ok(doc)
+    }
+
+    /// Check whether the document had to be reconstructed because its
+    /// cross-reference information was damaged.
+    pub fn was_repaired(&self) -> bool {
+        self.repaired
+    }
+
+    /// Get an object.
+    pub fn get(&self, id: ObjectId) -> Option<&Object> {
+        self.objects.get(&id)
+    }
+
+    /// Resolve an object that might be a reference.
+    ///
+    /// References to missing objects resolve to the null object, as the PDF
+    /// specification requires. Chains of references are followed, within
+    /// reason.
+    pub fn resolve<'a>(&'a self, mut obj: &'a Object) -> &'a Object {
+        for _ in 0..32 {
+            match obj {
+                Object::Reference(id) => match self.objects.get(id) {
+                    Some(o) => obj = o,
+                    None => return &Object::Null,
+                },
+                _ => return obj,
+            }
+        }
+
+        &Object::Null
+    }
+
+    /// Look up a dictionary entry, resolving it if it's a reference.
+    pub fn dict_get<'a>(&'a self, dict: &'a Dictionary, key: &[u8]) -> Option<&'a Object> {
+        match self.resolve(dict.get(key)?) {
+            Object::Null => None,
+            o => Some(o),
+        }
+    }
+
+    /// Add an object to the document, returning its identifier.
+    pub fn add_object(&mut self, obj: impl Into<Object>) -> ObjectId {
+        let num = self.objects.keys().next_back().map_or(1, |(n, _)| n + 1);
+        let id = (num, 0);
+        self.objects.insert(id, obj.into());
+        id
+    }
+
+    /// Get the document catalog.
+    pub fn catalog(&self) -> Result<&Dictionary> {
+        let root = a_ok_or!(self.trailer.get(b"Root"); ["the PDF file has no document catalog"]);
+        Ok(a_ok_or!(
+            self.resolve(root).as_dict();
+            ["the PDF document catalog is not a dictionary"]
+        ))
+    }
+
+    /// Get the identifier of the document catalog.
+    pub fn catalog_id(&self) -> Result<ObjectId> {
+        Ok(a_ok_or!(
+            self.trailer.get(b"Root").and_then(Object::as_reference);
+            ["the PDF document catalog is not an indirect object"]
+        ))
+    }
+
+    /// Get the identifiers of the document's pages, in order.
+    pub fn page_ids(&self) -> Result<Vec<ObjectId>> {
+        let root = a_ok_or!(
+            self.catalog()?.get(b"Pages").and_then(Object::as_reference);
+            ["the PDF document has no page tree"]
+        );
+
+        let mut pages = Vec::new();
+        let mut seen = HashSet::new();
+        self.collect_pages(root, 0, &mut seen, &mut pages)?;
+        Ok(pages)
+    }
+
+    fn collect_pages(
+        &self,
+        id: ObjectId,
+        depth: usize,
+        seen: &mut HashSet<ObjectId>,
+        pages: &mut Vec<ObjectId>,
+    ) -> Result<()> {
+        ensure!(
+            depth < MAX_PAGE_TREE_DEPTH,
+            "the PDF page tree is nested too deeply"
+        );
+        ensure!(seen.insert(id), "the PDF page tree contains a cycle");
+
+        let node = a_ok_or!(
+            self.get(id).and_then(Object::as_dict);
+            ["PDF page tree node {} {} R is missing", id.0, id.1]
+        );
+
+        // Some writers omit the `Type` of page objects, so treat anything
+        // without kids as a page.
+        match node.get(b"Kids") {
+            Some(kids) if !node.has_name(b"Type", b"Page") => {
+                for kid in self.resolve(kids).as_array().unwrap_or_default() {
+                    if let Some(kid) = kid.as_reference() {
+                        self.collect_pages(kid, depth + 1, seen, pages)?;
+                    }
+                }
+            }
+
+            _ => pages.push(id),
+        }
+
+        Ok(())
+    }
+
+    /// Look up a page attribute, following the page tree upwards for
+    /// attributes that can be inherited.
+    pub fn page_attribute(&self, page: ObjectId, key: &[u8]) -> Option<&Object> {
+        let mut node = self.get(page)?.as_dict()?;
+
+        for _ in 0..MAX_PAGE_TREE_DEPTH {
+            if let Some(v) = self.dict_get(node, key) {
+                return Some(v);
+            }
+
+            node = self.dict_get(node, b"Parent")?.as_dict()?;
+        }
+
+        None
+    }
+
+    /// Write the document out as a PDF file.
+    ///
+    /// Only objects reachable from the trailer are written, and they are
+    /// renumbered consecutively.
+    pub fn save(&self, dest: &mut dyn Write) -> Result<()> {
+        // Work out which objects to write, and their new numbers.
+        let mut numbers: HashMap<ObjectId, u32> = HashMap::new();
+        let mut order = Vec::new();
+        let mut queue: Vec<ObjectId> = Vec::new();
+
+        fn scan(obj: &Object, queue: &mut Vec<ObjectId>) {
+            match obj {
+                Object::Reference(id) => queue.push(*id),
+                Object::Array(a) => a.iter().for_each(|o| scan(o, queue)),
+                Object::Dictionary(d) => d.iter().for_each(|(_, o)| scan(o, queue)),
+                Object::Stream(s) => s.dict.iter().for_each(|(_, o)| scan(o, queue)),
+                _ => {}
+            }
+        }
+
+        for key in [&b"Root"[..], b"Info"] {
+            if let Some(o) = self.trailer.get(key) {
+                scan(o, &mut queue);
+            }
+        }
+        queue.reverse();
+
+        while let Some(id) = queue.pop() {
+            if numbers.contains_key(&id) {
+                continue;
+            }
+
+            if let Some(obj) = self.objects.get(&id) {
+                numbers.insert(id, order.len() as u32 + 1);
+                order.push(id);
+
+                let start = queue.len();
+                scan(obj, &mut queue);
+                queue[start..].reverse();
+            }
+        }
+
+        let renumber = |obj: &Object| renumbered(obj, &numbers);
+
+        let mut out = CountingWriter {
+            inner: dest,
+            count: 0,
+        };
+
+        write!(out, "%PDF-{}\n%\u{e2}\u{e3}\u{cf}\u{d3}\n", self.version)?;
+
+        let mut offsets = Vec::with_capacity(order.len());
+
+        for (i, id) in order.iter().enumerate() {
+            offsets.push(out.count);
+            writeln!(out, "{} 0 obj", i + 1)?;
+
+            match renumber(&self.objects[id]) {
+                Object::Stream(mut s) => {
+                    s.dict.set("Length", Object::Integer(s.data.len() as i64));
+                    write!(out, "{}\nstream\n", s.dict)?;
+                    out.write_all(&s.data)?;
+                    out.write_all(b"\nendstream")?;
+                }
+                other => write!(out, "{other}")?,
+            }
+
+            out.write_all(b"\nendobj\n")?;
+        }
+
+        let xref_offset = out.count;
+        write!(out, "xref\n0 {}\n0000000000 65535 f \n", order.len() + 1)?;
+
+        for offset in offsets {
+            writeln!(out, "{offset:010} 00000 n ")?;
+        }
+
+        let mut trailer = Dictionary::new();
+        trailer.set("Size", Object::Integer(order.len() as i64 + 1));
+
+        for key in [&b"Root"[..], b"Info", b"ID"] {
+            if let Some(o) = self.trailer.get(key) {
+                trailer.set(key, renumber(o));
+            }
+        }
+
+        writeln!(out, "trailer\n{trailer}\nstartxref\n{xref_offset}\n%%EOF")?;
+        Ok(())
+    }
+}
+
+/// Replace the references in an object according to a renumbering. References
+/// to objects that aren't included become null.
+fn renumbered(obj: &Object, numbers: &HashMap<ObjectId, u32>) -> Object {
+    match obj {
+        Object::Reference(id) => match numbers.get(id) {
+            Some(n) => Object::Reference((*n, 0)),
+            None => Object::Null,
+        },
+        Object::Array(a) => Object::Array(a.iter().map(|o| renumbered(o, numbers)).collect()),
+        Object::Dictionary(d) => Object::Dictionary(renumbered_dict(d, numbers)),
+        Object::Stream(s) => Object::Stream(Stream::new(
+            renumbered_dict(&s.dict, numbers),
+            s.data.clone(),
+        )),
+        other => other.clone(),
+    }
+}
+
+fn renumbered_dict(dict: &Dictionary, numbers: &HashMap<ObjectId, u32>) -> Dictionary {
+    let mut new = Dictionary::new();
+
+    for (k, v) in dict.iter() {
+        match renumbered(v, numbers) {
+            Object::Null => {}
+            v => new.set(k, v),
+        }
+    }
+
+    new
+}
+
+/// Turn a version like `1.7` into something comparable.
+pub(crate) fn version_key(v: &str) -> (u32, u32) {
+    let (major, minor) = v.split_once('.').unwrap_or((v, "0"));
+    (major.parse().unwrap_or(0), minor.parse().unwrap_or(0))
+}
+
+struct CountingWriter<'a> {
+    inner: &'a mut dyn Write,
+    count: usize,
+}
+
+impl Write for CountingWriter<'_> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let n = self.inner.write(buf)?;
+        self.count += n;
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// State for loading a document.
+struct Loader<'a> {
+    data: &'a [u8],
+    xref: HashMap<u32, XrefEntry>,
+    trailer: Dictionary,
+}
+
+impl<'a> Loader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Loader {
+            data,
+            xref: HashMap::new(),
+            trailer: Dictionary::new(),
+        }
+    }
+
+    /// Look up the value of an integer object, for stream lengths.
+    fn length_of(&self, id: ObjectId) -> Option<i64> {
+        match self.xref.get(&id.0)? {
+            XrefEntry::Offset(ofs, _) => Parser::new(self.data, *ofs)
+                .parse_indirect(&|_| None)
+                .ok()?
+                .1
+                .as_i64(),
+            _ => None,
+        }
+    }
+
+    fn load_with_xref(mut self) -> Result<Document> {
+        let tail_start = self.data.len().saturating_sub(2048);
+        let sx = a_ok_or!(
+            parser::rfind(&self.data[tail_start..], b"startxref");
+            ["no `startxref` in PDF file"]
+        );
+        let mut p = Parser::new(self.data, tail_start + sx + 9);
+        let mut offset = a_ok_or!(p.read_unsigned(); ["invalid `startxref` offset"]) as usize;
+        let mut seen = HashSet::new();
+
+        // Follow the chain of cross-reference sections, from the newest to
+        // the oldest. Entries in newer sections take precedence.
+        loop {
+            ensure!(seen.insert(offset), "cycle in PDF cross-reference sections");
+            let trailer = self.read_xref_section(offset)?;
+
+            if let Some(stm) = trailer.get(b"XRefStm").and_then(Object::as_i64) {
+                // A hybrid-reference file. The stream holds the compressed
+                // objects, which the table lists as free.
+                let stm = stm as usize;
+                if seen.insert(stm) {
+                    let table_entries = std::mem::take(&mut self.xref);
+                    self.read_xref_section(stm)?;
+
+                    for (num, entry) in table_entries {
+                        if !matches!(entry, XrefEntry::Free) || !self.xref.contains_key(&num) {
+                            self.xref.insert(num, entry);
+                        }
+                    }
+                }
+            }
+
+            let prev = trailer.get(b"Prev").and_then(Object::as_i64);
+
+            for (k, v) in trailer.iter() {
+                if self.trailer.get(k).is_none() {
+                    self.trailer.set(k, v.clone());
+                }
+            }
+
+            match prev {
+                Some(p) if p >= 0 => offset = p as usize,
+                _ => break,
+            }
+        }
+
+        let mut doc = Document {
+            version: String::new(),
+            trailer: std::mem::take(&mut self.trailer),
+            objects: BTreeMap::new(),
+            repaired: false,
+        };
+
+        let mut objstms: HashMap<u32, Vec<(ObjectId, Object)>> = HashMap::new();
+        let entries: Vec<(u32, XrefEntry)> = self.xref.iter().map(|(k, v)| (*k, *v)).collect();
+
+        for (num, entry) in entries {
+            match entry {
+                XrefEntry::Free => {}
+
+                XrefEntry::Offset(ofs, gen) => {
+                    ensure!(ofs < self.data.len(), "PDF object offset out of range");
+                    let mut p = Parser::new(self.data, ofs);
+                    let (id, obj) = p.parse_indirect(&|id| self.length_of(id))?;
+                    ensure!(
+                        id == (num, gen),
+                        "PDF cross-reference entry for object {} points to the wrong place",
+                        num
+                    );
+                    doc.objects.insert(id, obj);
+                }
+
+                XrefEntry::Compressed(stm, index) => {
+                    if let std::collections::hash_map::Entry::Vacant(e) = objstms.entry(stm) {
+                        let stream = match self.xref.get(&stm) {
+                            Some(XrefEntry::Offset(ofs, _)) => {
+                                Parser::new(self.data, *ofs)
+                                    .parse_indirect(&|id| self.length_of(id))?
+                                    .1
+                            }
+                            _ => bail!("PDF object stream {} is missing", stm),
+                        };
+                        e.insert(parse_object_stream(&stream)?);
+                    }
+
+                    let (id, obj) = a_ok_or!(
+                        objstms[&stm].get(index);
+                        ["PDF object stream {} is too short", stm]
+                    );
+                    ensure!(
+                        id.0 == num,
+                        "PDF object stream entry for object {} is wrong",
+                        num
+                    );
+                    doc.objects.insert(*id, obj.clone());
+                }
+            }
+        }
+
+        strip_structural_objects(&mut doc);
+        Ok(doc)
+    }
+
+    /// Read one cross-reference section, returning its trailer.
+    fn read_xref_section(&mut self, offset: usize) -> Result<Dictionary> {
+        ensure!(
+            offset < self.data.len(),
+            "PDF cross-reference offset out of range"
+        );
+        let mut p = Parser::new(self.data, offset);
+
+        if p.eat_keyword(b"xref") {
+            loop {
+                if p.eat_keyword(b"trailer") {
+                    break;
+                }
+
+                let first = a_ok_or!(p.read_unsigned(); ["invalid PDF cross-reference table"]);
+                let count = a_ok_or!(p.read_unsigned(); ["invalid PDF cross-reference table"]);
+
+                for num in first..first.saturating_add(count) {
+                    let ofs = a_ok_or!(p.read_unsigned(); ["invalid PDF cross-reference entry"]);
+                    let gen = a_ok_or!(p.read_unsigned(); ["invalid PDF cross-reference entry"]);
+
+                    let entry = if p.eat_keyword(b"n") {
+                        XrefEntry::Offset(ofs as usize, gen as u16)
+                    } else if p.eat_keyword(b"f") {
+                        XrefEntry::Free
+                    } else {
+                        bail!("invalid PDF cross-reference entry");
+                    };
+
+                    // Some writers number the table from 1 instead of 0.
+                    // The free head entry gives this away.
+                    if num > u32::MAX as u64 {
+                        break;
+                    }
+                    self.xref.entry(num as u32).or_insert(entry);
+                }
+            }
+
+            match p.parse_object()? {
+                Object::Dictionary(d) => Ok(d),
+                _ => bail!("the PDF trailer is not a dictionary"),
+            }
+        } else {
+            let (_, obj) = p.parse_indirect(&|id| self.length_of(id))?;
+            let stream = a_ok_or!(
+                obj.as_stream();
+                ["expected a PDF cross-reference stream at byte offset {}", offset]
+            );
+            ensure!(
+                stream.dict.has_name(b"Type", b"XRef"),
+                "expected a PDF cross-reference stream at byte offset {}",
+                offset
+            );
+            self.read_xref_stream(stream)?;
+            Ok(stream.dict.clone())
+        }
+    }
+
+    fn read_xref_stream(&mut self, stream: &Stream) -> Result<()> {
+        let data = stream.decoded_data()?;
+        let widths: Vec<usize> = a_ok_or!(
+            stream.dict.get(b"W").and_then(Object::as_array);
+            ["PDF cross-reference stream has no field widths"]
+        )
+        .iter()
+        .map(|o| o.as_i64().unwrap_or(0).clamp(0, 8) as usize)
+        .collect();
+        ensure!(
+            widths.len() == 3,
+            "invalid PDF cross-reference stream field widths"
+        );
+
+        let size = stream
+            .dict
+            .get(b"Size")
+            .and_then(Object::as_i64)
+            .unwrap_or(0);
+        let index: Vec<i64> = match stream.dict.get(b"Index").and_then(Object::as_array) {
+            Some(a) => a.iter().filter_map(Object::as_i64).collect(),
+            None => vec![0, size],
+        };
+
+        let entry_len: usize = widths.iter().sum();
+        ensure!(entry_len > 0, "invalid PDF cross-reference stream");
+        let mut entries = data.chunks_exact(entry_len);
+
+        let field = |bytes: &[u8]| bytes.iter().fold(0u64, |acc, b| (acc << 8) | *b as u64);
+
+        for pair in index.chunks_exact(2) {
+            let (first, count) = (pair[0].max(0) as u64, pair[1].max(0) as u64);
+
+            for num in first..first.saturating_add(count) {
+                let Some(entry) = entries.next() else {
+                    return Ok(());
+                };
+
+                let (f1, rest) = entry.split_at(widths[0]);
+                let (f2, f3) = rest.split_at(widths[1]);
+                // The type defaults to 1 if its field is absent.
+                let kind = if widths[0] == 0 { 1 } else { field(f1) };
+
+                let entry = match kind {
+                    0 => XrefEntry::Free,
+                    1 => XrefEntry::Offset(field(f2) as usize, field(f3) as u16),
+                    2 => XrefEntry::Compressed(field(f2) as u32, field(f3) as usize),
+                    _ => continue, // reserved; treat as a null reference
+                };
+
+                if num <= u32::MAX as u64 {
+                    self.xref.entry(num as u32).or_insert(entry);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Load a document whose cross-reference information is unusable, by
+    /// scanning for object definitions.
+    fn load_by_scanning(self) -> Result<Document> {
+        let data = self.data;
+        let mut offsets: BTreeMap<ObjectId, usize> = BTreeMap::new();
+        let mut pos = 0;
+
+        while let Some(rel) = parser::find(&data[pos..], b"obj") {
+            let kw = pos + rel;
+            pos = kw + 3;
+
+            if data
+                .get(pos)
+                .is_some_and(|b| !parser::is_whitespace(*b) && !parser::is_delimiter(*b))
+            {
+                continue;
+            }
+
+            if let Some(start) = object_header_start(data, kw) {
+                let mut p = Parser::new(data, start);
+                if let (Some(num), Some(gen)) = (p.read_unsigned(), p.read_unsigned()) {
+                    if num <= u32::MAX as u64 && gen <= u16::MAX as u64 {
+                        // Later definitions, from incremental updates, win.
+                        offsets.insert((num as u32, gen as u16), start);
+                    }
+                }
+            }
+        }
+
+        let mut doc = Document::new();
+        doc.repaired = true;
+        let lengths: HashMap<ObjectId, usize> = offsets.iter().map(|(k, v)| (*k, *v)).collect();
+        let length_of = |id: ObjectId| {
+            let ofs = *lengths.get(&id)?;
+            Parser::new(data, ofs)
+                .parse_indirect(&|_| None)
+                .ok()?
+                .1
+                .as_i64()
+        };
+
+        for ofs in offsets.values() {
+            if let Ok((id, obj)) = Parser::new(data, *ofs).parse_indirect(&length_of) {
+                doc.objects.insert(id, obj);
+            }
+        }
+
+        // Unpack any object streams.
+        let mut unpacked = Vec::new();
+
+        for obj in doc.objects.values() {
+            if let Some(s) = obj.as_stream() {
+                if s.dict.has_name(b"Type", b"ObjStm") {
+                    if let Ok(objs) = parse_object_stream(obj) {
+                        unpacked.extend(objs);
+                    }
+                }
+            }
+        }
+
+        for (id, obj) in unpacked {
+            doc.objects.entry(id).or_insert(obj);
+        }
+
+        // Reassemble the trailer from the trailer dictionaries and
+        // cross-reference streams, newest last.
+        let mut trailers = Vec::new();
+        pos = 0;
+
+        while let Some(rel) = parser::find(&data[pos..], b"trailer") {
+            pos += rel + 7;
+            if let Ok(Object::Dictionary(d)) = Parser::new(data, pos).parse_object() {
+                trailers.push(d);
+            }
+        }
+
+        for obj in doc.objects.values() {
+            if let Some(s) = obj.as_stream() {
+                if s.dict.has_name(b"Type", b"XRef") {
+                    trailers.push(s.dict.clone());
+                }
+            }
+        }
+
+        for t in trailers.iter().rev() {
+            for key in [&b"Root"[..], b"Info", b"ID", b"Encrypt"] {
+                if doc.trailer.get(key).is_none() {
+                    if let Some(v) = t.get(key) {
+                        doc.trailer.set(key, v.clone());
+                    }
+                }
+            }
+        }
+
+        if doc.trailer.get(b"Root").is_none() {
+            let catalog = doc
+                .objects
+                .iter()
+                .find(|(_, o)| o.as_dict().is_some_and(|d| d.has_name(b"Type", b"Catalog")))
+                .map(|(id, _)| *id);
+
+            if let Some(id) = catalog {
+                doc.trailer.set("Root", Object::Reference(id));
+            }
+        }
+
+        strip_structural_objects(&mut doc);
+        Ok(doc)
+    }
+}
+
+/// Find the start of the `N G` before an `obj` keyword at *kw*, if it's there.
+fn object_header_start(data: &[u8], kw: usize) -> Option<usize> {
+    let mut i = kw;
+
+    for _ in 0..2 {
+        let end = i;
+        while i > 0 && parser::is_whitespace(data[i - 1]) {
+            i -= 1;
+        }
+        if i == end {
+            return None;
+        }
+
+        let digits_end = i;
+        while i > 0 && data[i - 1].is_ascii_digit() {
+            i -= 1;
+        }
+        if i == digits_end {
+            return None;
+        }
+    }
+
+    if i > 0 && !parser::is_whitespace(data[i - 1]) && !parser::is_delimiter(data[i - 1]) {
+        return None;
+    }
+
+    Some(i)
+}
+
+/// Parse the objects in an object stream.
+fn parse_object_stream(obj: &Object) -> Result<Vec<(ObjectId, Object)>> {
+    let stream = a_ok_or!(obj.as_stream(); ["PDF object stream is not a stream"]);
+    let n = stream.dict.get(b"N").and_then(Object::as_i64).unwrap_or(0);
+    let first = a_ok_or!(
+        stream.dict.get(b"First").and_then(Object::as_i64);
+        ["PDF object stream has no `First` offset"]
+    ) as usize;
+    let data = stream.decoded_data()?;
+
+    let mut header = Parser::new(&data, 0);
+    let mut objects = Vec::new();
+
+    for _ in 0..n.max(0) {
+        let (Some(num), Some(ofs)) = (header.read_unsigned(), header.read_unsigned()) else {
+            bail!("invalid PDF object stream header");
+        };
+        let ofs = first.saturating_add(ofs as usize);
+        ensure!(ofs < data.len(), "PDF object stream offset out of range");
+
+        let obj = Parser::new(&data, ofs).parse_object()?;
+        objects.push(((num as u32, 0), obj));
+    }
+
+    Ok(objects)
+}
+
+/// Remove objects that only describe the file structure, which we'll
+/// regenerate when writing.
+fn strip_structural_objects(doc: &mut Document) {
+    doc.objects.retain(|_, o| match o.as_stream() {
+        Some(s) => !s.dict.has_name(b"Type", b"XRef") && !s.dict.has_name(b"Type", b"ObjStm"),
+        None => true,
+    });
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    pub(crate) fn sample() -> Document {
+        let mut doc = Document::new();
+        let content = doc.add_object(Stream::new(Dictionary::new(), b"0 0 m 10 10 l S".to_vec()));
+        let pages_id = (100, 0);
+
+        let mut page = Dictionary::new();
+        page.set("Type", Object::name("Page"));
+        page.set("Parent", Object::Reference(pages_id));
+        page.set("Contents", Object::Reference(content));
+        let page1 = doc.add_object(page.clone());
+        let page2 = doc.add_object(page);
+
+        let mut pages = Dictionary::new();
+        pages.set("Type", Object::name("Pages"));
+        pages.set(
+            "Kids",
+            vec![Object::Reference(page1), Object::Reference(page2)],
+        );
+        pages.set("Count", Object::Integer(2));
+        pages.set(
+            "MediaBox",
+            vec![
+                Object::Integer(0),
+                Object::Integer(0),
+                Object::Integer(612),
+                Object::Integer(792),
+            ],
+        );
+        doc.objects.insert(pages_id, pages.into());
+
+        let mut catalog = Dictionary::new();
+        catalog.set("Type", Object::name("Catalog"));
+        catalog.set("Pages", Object::Reference(pages_id));
+        let catalog = doc.add_object(catalog);
+        doc.trailer.set("Root", Object::Reference(catalog));
+        doc
+    }
+
+    #[test]
+    fn round_trip() {
+        let doc = sample();
+        let mut buf = Vec::new();
+        doc.save(&mut buf).unwrap();
+
+        let loaded = Document::load(&buf).unwrap();
+        assert_eq!(loaded.version, "1.7");
+        assert!(!loaded.was_repaired());
+        assert_eq!(loaded.objects.len(), 5);
+
+        let pages = loaded.page_ids().unwrap();
+        assert_eq!(pages.len(), 2);
+        let mb = loaded.page_attribute(pages[0], b"MediaBox").unwrap();
+        assert_eq!(mb.as_array().unwrap()[2], Object::Integer(612));
+    }
+
+    #[test]
+    fn damaged_xref() {
+        let mut buf = Vec::new();
+        sample().save(&mut buf).unwrap();
+
+        // Point `startxref` into the weeds.
+        let sx = parser::rfind(&buf, b"startxref").unwrap();
+        buf.truncate(sx);
+        buf.extend_from_slice(b"startxref\n12\n%%EOF\n");
+
+        let loaded = Document::load(&buf).unwrap();
+        assert_eq!(loaded.page_ids().unwrap().len(), 2);
+        assert!(loaded.was_repaired());
+    }
+
+    #[test]
+    fn xref_and_object_streams() {
+        // An object stream holding the catalog and page tree, and an
+        // uncompressed cross-reference stream with an incremental update
+        // that replaces the page.
+        let objstm_data =
+            b"1 0 2 29 <</Type/Catalog/Pages 2 0 R>> <</Type/Pages/Kids[3 0 R]/Count 1>>";
+        let mut pdf = b"%PDF-1.5\n".to_vec();
+
+        let ofs4 = pdf.len();
+        pdf.extend_from_slice(
+            format!(
+                "4 0 obj <</Type/ObjStm/N 2/First 9/Length {}>> stream\n",
+                objstm_data.len()
+            )
+            .as_bytes(),
+        );
+        pdf.extend_from_slice(objstm_data);
+        pdf.extend_from_slice(b"\nendstream endobj\n");
+
+        let ofs3 = pdf.len();
+        pdf.extend_from_slice(b"3 0 obj <</Type/Page/Parent 2 0 R/Rotate 0>> endobj\n");
+
+        let mut xref = Vec::new();
+        for (kind, f2, f3) in [
+            (0u8, 0u32, 255u8),
+            (2, 4, 0),
+            (2, 4, 1),
+            (1, ofs3 as u32, 0),
+            (1, ofs4 as u32, 0),
+        ] {
+            xref.push(kind);
+            xref.extend_from_slice(&f2.to_be_bytes());
+            xref.push(f3);
+        }
+
+        let ofs5 = pdf.len();
+        pdf.extend_from_slice(
+            format!(
+                "5 0 obj <</Type/XRef/Size 6/W[1 4 1]/Index[0 5]/Root 1 0 R/Length {}>> stream\n",
+                xref.len()
+            )
+            .as_bytes(),
+        );
+        pdf.extend_from_slice(&xref);
+        pdf.extend_from_slice(b"\nendstream endobj\n");
+
+        // The update.
+        let ofs3b = pdf.len();
+        pdf.extend_from_slice(b"3 0 obj <</Type/Page/Parent 2 0 R/Rotate 90>> endobj\n");
+        let ofs_xref = pdf.len();
+        pdf.extend_from_slice(
+            format!(
+                "xref\n3 1\n{ofs3b:010} 00000 n \ntrailer <</Size 6/Root 1 0 R/Prev {ofs5}>>\nstartxref\n{ofs_xref}\n%%EOF\n"
+            )
+            .as_bytes(),
+        );
+
+        let doc = Document::load(&pdf).unwrap();
+        assert_eq!(doc.version, "1.5");
+        let pages = doc.page_ids().unwrap();
+        assert_eq!(pages, [(3, 0)]);
+        assert_eq!(
+            doc.page_attribute(pages[0], b"Rotate"),
+            Some(&Object::Integer(90))
+        );
+        assert!(doc.get((4, 0)).is_none());
+    }
+}
@@ -0,0 +1,127 @@
+// Copyright 2026 the Tectonic Project
+// Licensed under the MIT License.
+
+//! Stream filters.
+
+use flate2::{read::ZlibDecoder, write::ZlibEncoder, Compression};
+use std::io::{Read, Write};
+use tectonic_errors::prelude::*;
+
+use crate::object::Dictionary;
+
+pub(crate) fn flate_encode(data: &[u8]) -> Vec<u8> {
+    let mut enc = ZlibEncoder::new(Vec::new(), Compression::default());
+    // Writing into a Vec can't fail.
+    enc.write_all(data).unwrap();
+    enc.finish().unwrap()
+}
+
+pub(crate) fn flate_decode(data: &[u8]) -> Result<Vec<u8>> {
+    let mut out = Vec::new();
+    let mut dec = ZlibDecoder::new(data);
+
+    if let Err(e) = dec.read_to_end(&mut out) {
+        // Many PDF writers produce streams that are truncated or lack a
+        // checksum. Accept whatever could be decoded, as other readers do.
+        ensure!(!out.is_empty(), "couldn't decompress stream data: {}", e);
+    }
+
+    Ok(out)
+}
+
+/// Undo the predictor described by a stream's decode parameters.
+pub(crate) fn apply_predictor(data: Vec<u8>, parms: &Dictionary) -> Result<Vec<u8>> {
+    let get = |key: &[u8], default: i64| parms.get(key).and_then(|o| o.as_i64()).unwrap_or(default);
+
+    let predictor = get(b"Predictor", 1);
+
+    if predictor == 1 {
+        return Ok(data);
+    }
+
+    ensure!(
+        predictor >= 10,
+        "unsupported stream predictor {}",
+        predictor
+    );
+
+    let colors = get(b"Colors", 1);
+    let bpc = get(b"BitsPerComponent", 8);
+    let columns = get(b"Columns", 1);
+    ensure!(
+        (1..=32).contains(&colors) && (1..=16).contains(&bpc) && (1..=1 << 20).contains(&columns),
+        "invalid stream predictor parameters"
+    );
+
+    // PNG predictors: each row is prefixed with the type of prediction used.
+    let bpp = ((colors * bpc + 7) / 8) as usize;
+    let row_len = ((colors * bpc * columns + 7) / 8) as usize;
+    let mut out = Vec::with_capacity(data.len());
+    let mut prev = vec![0u8; row_len];
+
+    for chunk in data.chunks(row_len + 1) {
+        let (kind, row) = (chunk[0], &chunk[1..]);
+        let mut cur = row.to_vec();
+        cur.resize(row_len, 0);
+
+        for i in 0..row_len {
+            let left = if i >= bpp { cur[i - bpp] } else { 0 };
+            let up = prev[i];
+            let upleft = if i >= bpp { prev[i - bpp] } else { 0 };
+
+            cur[i] = cur[i].wrapping_add(match kind {
+                0 => 0,
+                1 => left,
+                2 => up,
+                3 => ((left as u16 + up as u16) / 2) as u8,
+                4 => paeth(left, up, upleft),
+                _ => bail!("invalid PNG predictor type {}", kind),
+            });
+        }
+
+        out.extend_from_slice(&cur[..row.len()]);
+        prev = cur;
+    }
+
+    Ok(out)
+}
+
+fn paeth(a: u8, b: u8, c: u8) -> u8 {
+    let p = a as i16 + b as i16 - c as i16;
+    let pa = (p - a as i16).abs();
+    let pb = (p - b as i16).abs();
+    let pc = (p - c as i16).abs();
+
+    if pa <= pb && pa <= pc {
+        a
+    } else if pb <= pc {
+        b
+    } else {
+        c
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::object::Object;
+
+    #[test]
+    fn png_up() {
+        let mut parms = Dictionary::new();
+        parms.set("Predictor", Object::Integer(12));
+        parms.set("Columns", Object::Integer(3));
+
+        let data = vec![2, 1, 2, 3, 2, 1, 1, 1, 2, 5, 5, 5];
+        assert_eq!(
+            apply_predictor(data, &parms).unwrap(),
+            [1, 2, 3, 2, 3, 4, 7, 8, 9]
+        );
+    }
+
+    #[test]
+    fn flate_round_trip() {
+        let data = b"hello hello hello hello";
+        assert_eq!(flate_decode(&flate_encode(data)).unwrap(), data);
+    }
+}
@@ -1,13 +1,34 @@
 // Copyright 2021 the Tectonic Project
 // Licensed under the MIT License.
 
-//! This crate contains no Rust code, for now. It exists to export a *C* API for
-//! I/O on PDF, XDV, and graphics files, within the Cargo build framework used
-//! by [Tectonic]. Ideally, it will migrate to become a cbindgen C API to a Rust
-//! implementation.
+//! This crate exports a *C* API for I/O on PDF, XDV, and graphics files,
+//! within the Cargo build framework used by [Tectonic]. Ideally, it will
+//! migrate to become a cbindgen C API to a Rust implementation.
+//!
+//! It also provides a small Rust PDF object layer, with the ability to load,
+//! modify, and write whole documents. [`Document::page_info`] provides the
+//! page geometry and resources needed to include a page as a graphic; this
+//! is also available to C code through a cbindgen API. The [`tools`] module
+//! builds on this to merge documents, extract pages, lay out pages n-up, and
+//! attach files.
 //!
 //! [Tectonic]: https://tectonic-typesetting.github.io/
 
+mod c_api;
+mod document;
+mod filters;
+mod object;
+mod page;
+mod parser;
+pub mod tools;
+
+pub use crate::{
+    c_api::PdfPageGeometry,
+    document::Document,
+    object::{Dictionary, Object, ObjectId, Stream},
+    page::{PageBox, PageInfo, Rect},
+};
+
 /// Import things from our bridge crates to ensure that we actually link with
 /// them.
 mod linkage {
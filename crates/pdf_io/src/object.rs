@@ -0,0 +1,419 @@
+// Copyright 2026 the Tectonic Project
+// Licensed under the MIT License.
+
+//! The PDF object model.
+
+use std::fmt;
+use tectonic_errors::prelude::*;
+
+use crate::filters;
+
+/// The identifier of an indirect object: its object number and generation.
+pub type ObjectId = (u32, u16);
+
+/// A PDF object.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Object {
+    /// The null object.
+    Null,
+
+    /// A boolean.
+    Boolean(bool),
+
+    /// An integer.
+    Integer(i64),
+
+    /// A real number.
+    Real(f64),
+
+    /// A string, as raw bytes.
+    String(Vec<u8>),
+
+    /// A name, without the leading slash and with `#` escapes decoded.
+    Name(Vec<u8>),
+
+    /// An array.
+    Array(Vec<Object>),
+
+    /// A dictionary.
+    Dictionary(Dictionary),
+
+    /// A stream. Streams can only appear as indirect objects.
+    Stream(Stream),
+
+    /// A reference to an indirect object.
+    Reference(ObjectId),
+}
+
+impl Object {
+    /// Create a name object.
+    pub fn name(name: &str) -> Self {
+        Object::Name(name.as_bytes().to_vec())
+    }
+
+    /// Create a string object from text, encoding it as UTF-16 if it isn't
+    /// plain ASCII.
+    pub fn text_string(text: &str) -> Self {
+        if text.is_ascii() {
+            Object::String(text.as_bytes().to_vec())
+        } else {
+            let mut buf = vec![0xfe, 0xff];
+
+            for unit in text.encode_utf16() {
+                buf.extend_from_slice(&unit.to_be_bytes());
+            }
+
+            Object::String(buf)
+        }
+    }
+
+    /// Get the value of an integer, or of a real number with no fractional
+    /// part.
+    pub fn as_i64(&self) -> Option<i64> {
+        match *self {
+            Object::Integer(i) => Some(i),
+            Object::Real(r) if r.fract() == 0.0 => Some(r as i64),
+            _ => None,
+        }
+    }
+
+    /// Get the value of a number, whether integer or real.
+    pub fn as_f64(&self) -> Option<f64> {
+        match *self {
+            Object::Integer(i) => Some(i as f64),
+            Object::Real(r) => Some(r),
+            _ => None,
+        }
+    }
+
+    /// Get the bytes of a name.
+    pub fn as_name(&self) -> Option<&[u8]> {
+        match self {
+            Object::Name(n) => Some(n),
+            _ => None,
+        }
+    }
+
+    /// Get the elements of an array.
+    pub fn as_array(&self) -> Option<&[Object]> {
+        match self {
+            Object::Array(a) => Some(a),
+            _ => None,
+        }
+    }
+
+    /// Get a dictionary, or the dictionary of a stream.
+    pub fn as_dict(&self) -> Option<&Dictionary> {
+        match self {
+            Object::Dictionary(d) => Some(d),
+            Object::Stream(s) => Some(&s.dict),
+            _ => None,
+        }
+    }
+
+    /// Get a mutable dictionary, or the dictionary of a stream.
+    pub fn as_dict_mut(&mut self) -> Option<&mut Dictionary> {
+        match self {
+            Object::Dictionary(d) => Some(d),
+            Object::Stream(s) => Some(&mut s.dict),
+            _ => None,
+        }
+    }
+
+    /// Get a stream.
+    pub fn as_stream(&self) -> Option<&Stream> {
+        match self {
+            Object::Stream(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    /// Get the target of a reference.
+    pub fn as_reference(&self) -> Option<ObjectId> {
+        match *self {
+            Object::Reference(id) => Some(id),
+            _ => None,
+        }
+    }
+}
+
+impl From<bool> for Object {
+    fn from(b: bool) -> Self {
+        Object::Boolean(b)
+    }
+}
+
+impl From<i64> for Object {
+    fn from(i: i64) -> Self {
+        Object::Integer(i)
+    }
+}
+
+impl From<f64> for Object {
+    fn from(r: f64) -> Self {
+        Object::Real(r)
+    }
+}
+
+impl From<ObjectId> for Object {
+    fn from(id: ObjectId) -> Self {
+        Object::Reference(id)
+    }
+}
+
+impl From<Vec<Object>> for Object {
+    fn from(a: Vec<Object>) -> Self {
+        Object::Array(a)
+    }
+}
+
+impl From<Dictionary> for Object {
+    fn from(d: Dictionary) -> Self {
+        Object::Dictionary(d)
+    }
+}
+
+impl From<Stream> for Object {
+    fn from(s: Stream) -> Self {
+        Object::Stream(s)
+    }
+}
+
+/// A PDF dictionary. Entries are kept in the order in which they were added.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Dictionary(Vec<(Vec<u8>, Object)>);
+
+impl Dictionary {
+    /// Create an empty dictionary.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Look up a key.
+    pub fn get(&self, key: &[u8]) -> Option<&Object> {
+        self.0.iter().find(|(k, _)| k == key).map(|(_, v)| v)
+    }
+
+    /// Look up a key, for modification.
+    pub fn get_mut(&mut self, key: &[u8]) -> Option<&mut Object> {
+        self.0.iter_mut().find(|(k, _)| k == key).map(|(_, v)| v)
+    }
+
+    /// Set a key, replacing any existing value.
+    pub fn set(&mut self, key: impl Into<Vec<u8>>, value: impl Into<Object>) {
+        let key = key.into();
+        let value = value.into();
+
+        if let Some(slot) = self.get_mut(&key) {
+            *slot = value;
+        } else {
+            self.0.push((key, value));
+        }
+    }
+
+    /// Remove a key, returning its value.
+    pub fn remove(&mut self, key: &[u8]) -> Option<Object> {
+        let idx = self.0.iter().position(|(k, _)| k == key)?;
+        Some(self.0.remove(idx).1)
+    }
+
+    /// Check whether the dictionary has a name-valued key with the given
+    /// value, such as `/Type /Page`.
+    pub fn has_name(&self, key: &[u8], value: &[u8]) -> bool {
+        self.get(key).and_then(Object::as_name) == Some(value)
+    }
+
+    /// Iterate over the entries of the dictionary.
+    pub fn iter(&self) -> impl Iterator<Item = (&[u8], &Object)> {
+        self.0.iter().map(|(k, v)| (&k[..], v))
+    }
+
+    /// Iterate over the values of the dictionary, for modification.
+    pub fn values_mut(&mut self) -> impl Iterator<Item = &mut Object> {
+        self.0.iter_mut().map(|(_, v)| v)
+    }
+
+    /// The number of entries in the dictionary.
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Check whether the dictionary is empty.
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+/// A PDF stream: a dictionary and some data, which may be encoded.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Stream {
+    /// The stream dictionary. Its `Length` entry is set when the stream is
+    /// written out, so it needn't be kept accurate.
+    pub dict: Dictionary,
+
+    /// The stream data, encoded according to the dictionary's `Filter`.
+    pub data: Vec<u8>,
+}
+
+impl Stream {
+    /// Create a stream with unencoded data.
+    pub fn new(dict: Dictionary, data: Vec<u8>) -> Self {
+        Stream { dict, data }
+    }
+
+    /// Create a stream with data that will be compressed.
+    pub fn new_compressed(mut dict: Dictionary, data: &[u8]) -> Self {
+        dict.set("Filter", Object::name("FlateDecode"));
+        Stream {
+            dict,
+            data: filters::flate_encode(data),
+        }
+    }
+
+    /// Get the decoded stream data.
+    ///
+    /// Only the `FlateDecode` filter is supported, since that's the only one
+    /// that we need to look inside of. Other filters result in an error.
+    pub fn decoded_data(&self) -> Result<Vec<u8>> {
+        let filters: Vec<&[u8]> = match self.dict.get(b"Filter") {
+            None => Vec::new(),
+            Some(Object::Name(n)) => vec![n],
+            Some(Object::Array(a)) => a.iter().filter_map(Object::as_name).collect(),
+            Some(_) => bail!("invalid stream filter"),
+        };
+
+        let parms: Vec<Option<&Dictionary>> = match self.dict.get(b"DecodeParms") {
+            Some(Object::Array(a)) => a.iter().map(Object::as_dict).collect(),
+            Some(o) => vec![o.as_dict()],
+            None => Vec::new(),
+        };
+
+        let mut data = self.data.clone();
+
+        for (i, filter) in filters.iter().enumerate() {
+            match *filter {
+                b"FlateDecode" | b"Fl" => {
+                    data = filters::flate_decode(&data)?;
+
+                    if let Some(parms) = parms.get(i).copied().flatten() {
+                        data = filters::apply_predictor(data, parms)?;
+                    }
+                }
+
+                other => bail!(
+                    "unsupported stream filter `{}`",
+                    String::from_utf8_lossy(other)
+                ),
+            }
+        }
+
+        Ok(data)
+    }
+}
+
+/// Write a name, escaping characters as needed.
+fn write_name(f: &mut fmt::Formatter, name: &[u8]) -> fmt::Result {
+    f.write_str("/")?;
+
+    for &b in name {
+        if b.is_ascii_graphic() && !b"#%()<>[]{}/".contains(&b) {
+            write!(f, "{}", b as char)?;
+        } else {
+            write!(f, "#{b:02X}")?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Write a string, as a literal if it's mostly printable, or otherwise in
+/// hex.
+fn write_string(f: &mut fmt::Formatter, s: &[u8]) -> fmt::Result {
+    let printable = s.iter().filter(|b| b.is_ascii_graphic() || **b == b' ');
+
+    if printable.count() * 4 < s.len() * 3 {
+        f.write_str("<")?;
+        for b in s {
+            write!(f, "{b:02X}")?;
+        }
+        return f.write_str(">");
+    }
+
+    f.write_str("(")?;
+
+    for &b in s {
+        match b {
+            b'(' | b')' | b'\\' => write!(f, "\\{}", b as char)?,
+            b'\n' => f.write_str("\\n")?,
+            b'\r' => f.write_str("\\r")?,
+            b' '..=b'~' => write!(f, "{}", b as char)?,
+            _ => write!(f, "\\{b:03o}")?,
+        }
+    }
+
+    f.write_str(")")
+}
+
+/// Objects are displayed in PDF syntax, except that stream data are not
+/// included.
+impl fmt::Display for Object {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Object::Null => f.write_str("null"),
+            Object::Boolean(b) => write!(f, "{b}"),
+            Object::Integer(i) => write!(f, "{i}"),
+            Object::Real(r) => {
+                // PDF doesn't allow exponential notation, so round to a
+                // reasonable precision and trim the result.
+                let s = format!("{r:.5}");
+                let s = s.trim_end_matches('0').trim_end_matches('.');
+                f.write_str(if s == "-0" || s.is_empty() { "0" } else { s })
+            }
+            Object::String(s) => write_string(f, s),
+            Object::Name(n) => write_name(f, n),
+            Object::Array(a) => {
+                f.write_str("[")?;
+                for (i, item) in a.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(" ")?;
+                    }
+                    write!(f, "{item}")?;
+                }
+                f.write_str("]")
+            }
+            Object::Dictionary(d) => write!(f, "{d}"),
+            Object::Stream(s) => write!(f, "{}", s.dict),
+            Object::Reference((num, gen)) => write!(f, "{num} {gen} R"),
+        }
+    }
+}
+
+impl fmt::Display for Dictionary {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("<<")?;
+        for (k, v) in &self.0 {
+            write_name(f, k)?;
+            write!(f, " {v}")?;
+        }
+        f.write_str(">>")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn display() {
+        let mut d = Dictionary::new();
+        d.set("Type", Object::name("Page"));
+        d.set("A b", Object::Array(vec![1.5.into(), (-0.000001).into()]));
+        d.set("S", Object::String(b"a(b)\n".to_vec()));
+        d.set("P", Object::Reference((3, 0)));
+        assert_eq!(
+            Object::from(d).to_string(),
+            "<</Type /Page/A#20b [1.5 0]/S (a\\(b\\)\\n)/P 3 0 R>>"
+        );
+        assert_eq!(Object::String(vec![0, 1, 2]).to_string(), "<000102>");
+    }
+}
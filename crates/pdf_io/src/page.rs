@@ -0,0 +1,277 @@
+// Copyright 2026 the Tectonic Project
+// Licensed under the MIT License.
+
+//! Page geometry and resources, as needed to include a page of a PDF file as a
+//! graphic.
+
+use tectonic_errors::prelude::*;
+
+use crate::{
+    document::Document,
+    object::{Dictionary, Object},
+};
+
+/// A rectangle in PDF user space, in big points.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[repr(C)]
+pub struct Rect {
+    /// The X coordinate of the lower left corner.
+    pub llx: f64,
+
+    /// The Y coordinate of the lower left corner.
+    pub lly: f64,
+
+    /// The X coordinate of the upper right corner.
+    pub urx: f64,
+
+    /// The Y coordinate of the upper right corner.
+    pub ury: f64,
+}
+
+impl Rect {
+    /// The width of the rectangle.
+    pub fn width(&self) -> f64 {
+        self.urx - self.llx
+    }
+
+    /// The height of the rectangle.
+    pub fn height(&self) -> f64 {
+        self.ury - self.lly
+    }
+
+    /// Parse a rectangle from a PDF array, normalizing the order of its
+    /// corners.
+    fn from_object(doc: &Document, obj: &Object) -> Option<Self> {
+        let a = obj.as_array()?;
+
+        if a.len() != 4 {
+            return None;
+        }
+
+        let mut v = [0.; 4];
+
+        for (slot, item) in v.iter_mut().zip(a) {
+            *slot = doc.resolve(item).as_f64()?;
+        }
+
+        Some(Rect {
+            llx: v[0].min(v[2]),
+            lly: v[1].min(v[3]),
+            urx: v[0].max(v[2]),
+            ury: v[1].max(v[3]),
+        })
+    }
+
+    fn intersect(&self, other: &Rect) -> Rect {
+        Rect {
+            llx: self.llx.max(other.llx),
+            lly: self.lly.max(other.lly),
+            urx: self.urx.min(other.urx),
+            ury: self.ury.min(other.ury),
+        }
+    }
+}
+
+/// The page boundaries defined by the PDF specification.
+///
+/// The numeric values agree with the ones used by XeTeX and xdvipdfmx.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[repr(C)]
+pub enum PageBox {
+    /// The first of the crop, art, trim, and bleed boxes that the page
+    /// defines, or else its media box.
+    Auto = 0,
+
+    /// The crop box: the region that viewers display.
+    Crop = 1,
+
+    /// The media box: the whole physical medium.
+    Media = 2,
+
+    /// The art box: the page's meaningful content.
+    Art = 3,
+
+    /// The trim box: the intended size of the finished page.
+    Trim = 4,
+
+    /// The bleed box: the region to which content is clipped in production.
+    Bleed = 5,
+}
+
+/// The geometry and resources of a page.
+#[derive(Clone, Debug)]
+pub struct PageInfo {
+    /// The media box. Pages lacking one are assumed to be US Letter size.
+    pub media_box: Rect,
+
+    /// The crop box, if specified.
+    pub crop_box: Option<Rect>,
+
+    /// The bleed box, if specified.
+    pub bleed_box: Option<Rect>,
+
+    /// The trim box, if specified.
+    pub trim_box: Option<Rect>,
+
+    /// The art box, if specified.
+    pub art_box: Option<Rect>,
+
+    /// The clockwise rotation of the page when displayed: 0, 90, 180, or 270
+    /// degrees.
+    pub rotate: i32,
+
+    /// The page's resource dictionary, which is empty if the page has none.
+    pub resources: Dictionary,
+}
+
+impl PageInfo {
+    /// Get one of the page's boundary boxes, following the defaulting rules
+    /// of the PDF specification. The result is clipped to the media box.
+    pub fn page_box(&self, which: PageBox) -> Rect {
+        let crop = self.crop_box.unwrap_or(self.media_box);
+
+        let b = match which {
+            PageBox::Auto => self
+                .crop_box
+                .or(self.art_box)
+                .or(self.trim_box)
+                .or(self.bleed_box)
+                .unwrap_or(self.media_box),
+            PageBox::Crop => crop,
+            PageBox::Media => self.media_box,
+            PageBox::Art => self.art_box.unwrap_or(crop),
+            PageBox::Trim => self.trim_box.unwrap_or(crop),
+            PageBox::Bleed => self.bleed_box.unwrap_or(crop),
+        };
+
+        b.intersect(&self.media_box)
+    }
+
+    /// Get the transformation that applies the page's rotation to the given
+    /// box, in PDF's `[a b c d e f]` order. The lower left corner of the box
+    /// stays where it is.
+    pub fn matrix(&self, bbox: &Rect) -> [f64; 6] {
+        match self.rotate {
+            90 => [0., -1., 1., 0., bbox.llx - bbox.lly, bbox.lly + bbox.urx],
+            180 => [-1., 0., 0., -1., bbox.llx + bbox.urx, bbox.lly + bbox.ury],
+            270 => [0., 1., -1., 0., bbox.llx + bbox.ury, bbox.lly - bbox.llx],
+            _ => [1., 0., 0., 1., 0., 0.],
+        }
+    }
+
+    /// Get one of the page's boundary boxes as it appears once the page's
+    /// rotation is applied. This determines the size of the page when it is
+    /// included as a graphic.
+    pub fn displayed_box(&self, which: PageBox) -> Rect {
+        let b = self.page_box(which);
+
+        if self.rotate % 180 == 0 {
+            b
+        } else {
+            Rect {
+                llx: b.llx,
+                lly: b.lly,
+                urx: b.llx + b.height(),
+                ury: b.lly + b.width(),
+            }
+        }
+    }
+}
+
+impl Document {
+    /// Get the geometry and resources of a page. Pages are numbered from 1.
+    pub fn page_info(&self, page_no: usize) -> Result<PageInfo> {
+        let pages = self.page_ids()?;
+        ensure!(
+            page_no >= 1 && page_no <= pages.len(),
+            "page {} does not exist; the document has {} pages",
+            page_no,
+            pages.len()
+        );
+        let page = pages[page_no - 1];
+
+        let rect = |key: &[u8]| -> Result<Option<Rect>> {
+            match self.page_attribute(page, key) {
+                None => Ok(None),
+                Some(o) => Ok(Some(a_ok_or!(
+                    Rect::from_object(self, o);
+                    ["invalid {} in PDF page {}", String::from_utf8_lossy(key), page_no]
+                ))),
+            }
+        };
+
+        let media_box = rect(b"MediaBox")?.unwrap_or(Rect {
+            llx: 0.,
+            lly: 0.,
+            urx: 612.,
+            ury: 792.,
+        });
+
+        let rotate = match self.page_attribute(page, b"Rotate") {
+            None => 0,
+            Some(o) => {
+                let r = a_ok_or!(
+                    o.as_i64().filter(|r| r % 90 == 0);
+                    ["invalid Rotate in PDF page {}", page_no]
+                );
+                r.rem_euclid(360) as i32
+            }
+        };
+
+        let resources = self
+            .page_attribute(page, b"Resources")
+            .and_then(Object::as_dict)
+            .cloned()
+            .unwrap_or_default();
+
+        Ok(PageInfo {
+            media_box,
+            crop_box: rect(b"CropBox")?,
+            bleed_box: rect(b"BleedBox")?,
+            trim_box: rect(b"TrimBox")?,
+            art_box: rect(b"ArtBox")?,
+            rotate,
+            resources,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::object::ObjectId;
+
+    fn rect(v: [i64; 4]) -> Object {
+        Object::Array(v.iter().map(|x| Object::Integer(*x)).collect())
+    }
+
+    #[test]
+    fn boxes() {
+        let mut doc = crate::document::tests::sample();
+        let pages = doc.page_ids().unwrap();
+        let page: ObjectId = pages[1];
+        let dict = doc.objects.get_mut(&page).unwrap().as_dict_mut().unwrap();
+        dict.set("CropBox", rect([700, 10, 100, 500]));
+        dict.set("TrimBox", rect([120, 20, 200, 60]));
+        dict.set("Rotate", Object::Integer(-90));
+
+        let info = doc.page_info(2).unwrap();
+        assert_eq!(info.rotate, 270);
+
+        // The crop box is normalized and clipped to the media box.
+        let crop = info.page_box(PageBox::Crop);
+        assert_eq!(
+            (crop.llx, crop.lly, crop.urx, crop.ury),
+            (100., 10., 612., 500.)
+        );
+        assert_eq!(info.page_box(PageBox::Art), crop);
+        assert_eq!(info.page_box(PageBox::Auto), crop);
+
+        let trim = info.displayed_box(PageBox::Trim);
+        assert_eq!((trim.width(), trim.height()), (40., 80.));
+
+        let media = doc.page_info(1).unwrap().displayed_box(PageBox::Auto);
+        assert_eq!((media.width(), media.height()), (612., 792.));
+        assert!(doc.page_info(3).is_err());
+    }
+}
@@ -0,0 +1,532 @@
+// Copyright 2026 the Tectonic Project
+// Licensed under the MIT License.
+
+//! Parsing the PDF object syntax.
+
+use std::convert::TryFrom;
+use tectonic_errors::prelude::*;
+
+use crate::object::{Dictionary, Object, ObjectId, Stream};
+
+/// The deepest nesting of arrays and dictionaries that we accept.
+const MAX_DEPTH: usize = 256;
+
+pub(crate) fn is_whitespace(b: u8) -> bool {
+    matches!(b, b'\0' | b'\t' | b'\n' | b'\x0c' | b'\r' | b' ')
+}
+
+pub(crate) fn is_delimiter(b: u8) -> bool {
+    b"()<>[]{}/%".contains(&b)
+}
+
+fn is_regular(b: u8) -> bool {
+    !is_whitespace(b) && !is_delimiter(b)
+}
+
+/// A parser working through PDF data.
+pub(crate) struct Parser<'a> {
+    data: &'a [u8],
+    pub pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    pub fn new(data: &'a [u8], pos: usize) -> Self {
+        Parser { data, pos }
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.data.get(self.pos).copied()
+    }
+
+    pub fn skip_whitespace(&mut self) {
+        while let Some(b) = self.peek() {
+            if is_whitespace(b) {
+                self.pos += 1;
+            } else if b == b'%' {
+                while !matches!(self.peek(), None | Some(b'\n') | Some(b'\r')) {
+                    self.pos += 1;
+                }
+            } else {
+                break;
+            }
+        }
+    }
+
+    /// Check whether the next token is the given keyword, and consume it if
+    /// so.
+    pub fn eat_keyword(&mut self, kw: &[u8]) -> bool {
+        self.skip_whitespace();
+
+        let end = self.pos + kw.len();
+        let matches = self.data.get(self.pos..end) == Some(kw)
+            && self.data.get(end).is_none_or(|b| !is_regular(*b));
+
+        if matches {
+            self.pos = end;
+        }
+
+        matches
+    }
+
+    pub fn expect_keyword(&mut self, kw: &[u8]) -> Result<()> {
+        ensure!(
+            self.eat_keyword(kw),
+            "expected `{}` at byte offset {}",
+            String::from_utf8_lossy(kw),
+            self.pos
+        );
+        Ok(())
+    }
+
+    /// Read an unsigned integer token, if there is one.
+    pub fn read_unsigned(&mut self) -> Option<u64> {
+        self.skip_whitespace();
+        let start = self.pos;
+
+        while self.peek().is_some_and(|b| b.is_ascii_digit()) {
+            self.pos += 1;
+        }
+
+        if self.pos == start || self.peek().is_some_and(is_regular) {
+            self.pos = start;
+            return None;
+        }
+
+        std::str::from_utf8(&self.data[start..self.pos])
+            .ok()?
+            .parse()
+            .ok()
+    }
+
+    /// Parse a direct object.
+    pub fn parse_object(&mut self) -> Result<Object> {
+        self.parse_nested(0)
+    }
+
+    fn parse_nested(&mut self, depth: usize) -> Result<Object> {
+        ensure!(depth < MAX_DEPTH, "PDF objects are nested too deeply");
+        self.skip_whitespace();
+
+        let b = a_ok_or!(self.peek(); ["unexpected end of PDF data"]);
+
+        match b {
+            b'/' => {
+                self.pos += 1;
+                Ok(Object::Name(self.parse_name_body()))
+            }
+
+            b'(' => {
+                self.pos += 1;
+                self.parse_literal_string()
+            }
+
+            b'<' if self.data.get(self.pos + 1) == Some(&b'<') => {
+                self.pos += 2;
+                Ok(Object::Dictionary(self.parse_dict_body(depth)?))
+            }
+
+            b'<' => {
+                self.pos += 1;
+                self.parse_hex_string()
+            }
+
+            b'[' => {
+                self.pos += 1;
+                let mut items = Vec::new();
+
+                loop {
+                    self.skip_whitespace();
+
+                    match self.peek() {
+                        Some(b']') => {
+                            self.pos += 1;
+                            break;
+                        }
+                        None => bail!("unterminated PDF array"),
+                        _ => items.push(self.parse_nested(depth + 1)?),
+                    }
+                }
+
+                Ok(Object::Array(items))
+            }
+
+            b'+' | b'-' | b'.' | b'0'..=b'9' => self.parse_number(),
+
+            _ => {
+                let start = self.pos;
+
+                while self.peek().is_some_and(is_regular) {
+                    self.pos += 1;
+                }
+
+                match &self.data[start..self.pos] {
+                    b"true" => Ok(Object::Boolean(true)),
+                    b"false" => Ok(Object::Boolean(false)),
+                    b"null" => Ok(Object::Null),
+                    b"" => bail!("unexpected character in PDF data at byte offset {}", start),
+                    other => bail!(
+                        "unexpected keyword `{}` in PDF data at byte offset {}",
+                        String::from_utf8_lossy(other),
+                        start
+                    ),
+                }
+            }
+        }
+    }
+
+    fn parse_number(&mut self) -> Result<Object> {
+        let start = self.pos;
+
+        while self
+            .peek()
+            .is_some_and(|b| b.is_ascii_digit() || b"+-.".contains(&b))
+        {
+            self.pos += 1;
+        }
+
+        let text = std::str::from_utf8(&self.data[start..self.pos]).unwrap();
+
+        if !text.contains('.') {
+            if let Ok(i) = text.parse::<i64>() {
+                // This might be the start of an indirect reference.
+                let save = self.pos;
+
+                if i >= 0 && i <= u32::MAX as i64 {
+                    if let Some(gen) = self.read_unsigned() {
+                        if gen <= u16::MAX as u64 && self.eat_keyword(b"R") {
+                            return Ok(Object::Reference((i as u32, gen as u16)));
+                        }
+                    }
+                }
+
+                self.pos = save;
+                return Ok(Object::Integer(i));
+            }
+        }
+
+        // Be lenient about malformed numbers like `--1` or `1.2.3`, which
+        // some writers produce.
+        let trimmed = text.trim_start_matches(['+', '-']);
+        let negative = text.len() - trimmed.len() > 0 && text.starts_with('-');
+        let mut parts = trimmed.splitn(3, '.');
+        let int_part = parts.next().unwrap_or("");
+        let frac_part = parts.next().unwrap_or("");
+        let value: f64 = format!("0{int_part}.{frac_part}0").parse().unwrap_or(0.0);
+
+        Ok(Object::Real(if negative { -value } else { value }))
+    }
+
+    fn parse_name_body(&mut self) -> Vec<u8> {
+        let mut name = Vec::new();
+
+        while let Some(b) = self.peek() {
+            if !is_regular(b) {
+                break;
+            }
+
+            self.pos += 1;
+
+            if b == b'#' {
+                let hex = self
+                    .data
+                    .get(self.pos..self.pos + 2)
+                    .and_then(|h| std::str::from_utf8(h).ok())
+                    .and_then(|h| u8::from_str_radix(h, 16).ok());
+
+                if let Some(v) = hex {
+                    name.push(v);
+                    self.pos += 2;
+                    continue;
+                }
+            }
+
+            name.push(b);
+        }
+
+        name
+    }
+
+    fn parse_literal_string(&mut self) -> Result<Object> {
+        let mut s = Vec::new();
+        let mut depth = 1;
+
+        loop {
+            let b = a_ok_or!(self.peek(); ["unterminated PDF string"]);
+            self.pos += 1;
+
+            match b {
+                b'(' => {
+                    depth += 1;
+                    s.push(b);
+                }
+
+                b')' => {
+                    depth -= 1;
+                    if depth == 0 {
+                        break;
+                    }
+                    s.push(b);
+                }
+
+                b'\\' => {
+                    let e = a_ok_or!(self.peek(); ["unterminated PDF string"]);
+                    self.pos += 1;
+
+                    match e {
+                        b'n' => s.push(b'\n'),
+                        b'r' => s.push(b'\r'),
+                        b't' => s.push(b'\t'),
+                        b'b' => s.push(0x08),
+                        b'f' => s.push(0x0c),
+                        b'0'..=b'7' => {
+                            let mut v = (e - b'0') as u32;
+
+                            for _ in 0..2 {
+                                match self.peek() {
+                                    Some(d @ b'0'..=b'7') => {
+                                        v = v * 8 + (d - b'0') as u32;
+                                        self.pos += 1;
+                                    }
+                                    _ => break,
+                                }
+                            }
+
+                            s.push(v as u8);
+                        }
+                        b'\r' => {
+                            // A line continuation.
+                            if self.peek() == Some(b'\n') {
+                                self.pos += 1;
+                            }
+                        }
+                        b'\n' => {}
+                        other => s.push(other),
+                    }
+                }
+
+                b'\r' => {
+                    // End-of-line markers are normalized to a line feed.
+                    if self.peek() == Some(b'\n') {
+                        self.pos += 1;
+                    }
+                    s.push(b'\n');
+                }
+
+                _ => s.push(b),
+            }
+        }
+
+        Ok(Object::String(s))
+    }
+
+    fn parse_hex_string(&mut self) -> Result<Object> {
+        let mut s = Vec::new();
+        let mut pending = None;
+
+        loop {
+            let b = a_ok_or!(self.peek(); ["unterminated PDF hex string"]);
+            self.pos += 1;
+
+            let v = match b {
+                b'>' => break,
+                b'0'..=b'9' => b - b'0',
+                b'a'..=b'f' => b - b'a' + 10,
+                b'A'..=b'F' => b - b'A' + 10,
+                _ if is_whitespace(b) => continue,
+                _ => bail!("invalid character in PDF hex string"),
+            };
+
+            match pending.take() {
+                Some(hi) => s.push((hi << 4) | v),
+                None => pending = Some(v),
+            }
+        }
+
+        if let Some(hi) = pending {
+            s.push(hi << 4);
+        }
+
+        Ok(Object::String(s))
+    }
+
+    fn parse_dict_body(&mut self, depth: usize) -> Result<Dictionary> {
+        let mut dict = Dictionary::new();
+
+        loop {
+            self.skip_whitespace();
+
+            match self.peek() {
+                Some(b'>') if self.data.get(self.pos + 1) == Some(&b'>') => {
+                    self.pos += 2;
+                    break;
+                }
+
+                Some(b'/') => {
+                    self.pos += 1;
+                    let key = self.parse_name_body();
+                    let value = self.parse_nested(depth + 1)?;
+
+                    // Per the specification, null values are the same as
+                    // missing entries.
+                    if value != Object::Null {
+                        dict.set(key, value);
+                    }
+                }
+
+                None => bail!("unterminated PDF dictionary"),
+                _ => bail!(
+                    "expected a name as a PDF dictionary key at byte offset {}",
+                    self.pos
+                ),
+            }
+        }
+
+        Ok(dict)
+    }
+
+    /// Parse an indirect object definition: `N G obj ... endobj`.
+    ///
+    /// The *length* callback is used to resolve the lengths of streams that
+    /// are given as indirect references.
+    pub fn parse_indirect(
+        &mut self,
+        length: &dyn Fn(ObjectId) -> Option<i64>,
+    ) -> Result<(ObjectId, Object)> {
+        let num = a_ok_or!(self.read_unsigned(); ["expected an object number at byte offset {}", self.pos]);
+        let gen = a_ok_or!(self.read_unsigned(); ["expected a generation number at byte offset {}", self.pos]);
+        ensure!(
+            num <= u32::MAX as u64 && gen <= u16::MAX as u64,
+            "invalid PDF object identifier"
+        );
+        self.expect_keyword(b"obj")?;
+
+        let id = (num as u32, gen as u16);
+        let obj = self.parse_object()?;
+
+        let obj = match obj {
+            Object::Dictionary(dict) if self.eat_keyword(b"stream") => {
+                Object::Stream(self.parse_stream_body(dict, length)?)
+            }
+            other => other,
+        };
+
+        // Don't insist on `endobj`: it's often missing in damaged files, and
+        // we've got what we need.
+        self.eat_keyword(b"endobj");
+        Ok((id, obj))
+    }
+
+    fn parse_stream_body(
+        &mut self,
+        dict: Dictionary,
+        length: &dyn Fn(ObjectId) -> Option<i64>,
+    ) -> Result<Stream> {
+        // The `stream` keyword is followed by CRLF or LF, but some writers use
+        // a lone CR.
+        if self.peek() == Some(b'\r') {
+            self.pos += 1;
+        }
+        if self.peek() == Some(b'\n') {
+            self.pos += 1;
+        }
+
+        let start = self.pos;
+
+        let declared = match dict.get(b"Length") {
+            Some(Object::Reference(id)) => length(*id),
+            Some(o) => o.as_i64(),
+            None => None,
+        };
+
+        // Trust the declared length if `endstream` follows it.
+        if let Some(len) = declared.and_then(|l| usize::try_from(l).ok()) {
+            if let Some(end) = start.checked_add(len).filter(|e| *e <= self.data.len()) {
+                self.pos = end;
+
+                if self.eat_keyword(b"endstream") {
+                    return Ok(Stream::new(dict, self.data[start..end].to_vec()));
+                }
+            }
+        }
+
+        // Otherwise, look for it.
+        let rel = a_ok_or!(
+            find(&self.data[start..], b"endstream");
+            ["unterminated PDF stream at byte offset {}", start]
+        );
+        let mut end = start + rel;
+
+        if end > start && self.data[end - 1] == b'\n' {
+            end -= 1;
+        }
+        if end > start && self.data[end - 1] == b'\r' {
+            end -= 1;
+        }
+
+        self.pos = start + rel + b"endstream".len();
+        Ok(Stream::new(dict, self.data[start..end].to_vec()))
+    }
+}
+
+/// Find the first occurrence of a byte sequence.
+pub(crate) fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len()).position(|w| w == needle)
+}
+
+/// Find the last occurrence of a byte sequence.
+pub(crate) fn rfind(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len()).rposition(|w| w == needle)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(text: &str) -> Object {
+        Parser::new(text.as_bytes(), 0).parse_object().unwrap()
+    }
+
+    #[test]
+    fn objects() {
+        assert_eq!(parse("  42 "), Object::Integer(42));
+        assert_eq!(parse("-.5"), Object::Real(-0.5));
+        assert_eq!(parse("12 0 R"), Object::Reference((12, 0)));
+        assert_eq!(parse("/A#20B"), Object::Name(b"A B".to_vec()));
+        assert_eq!(
+            parse("(a\\(b\\) (c)\\101\\\nd)"),
+            Object::String(b"a(b) (c)Ad".to_vec())
+        );
+        assert_eq!(parse("<48 65 6c6>"), Object::String(b"Hel`".to_vec()));
+        assert_eq!(
+            parse("[1 2 R 3 /X]"),
+            Object::Array(vec![
+                Object::Reference((1, 2)),
+                Object::Integer(3),
+                Object::name("X")
+            ])
+        );
+
+        let d = parse("<</Type/Page/Kids[1 0 R 2 0 R]/N null>>");
+        let d = d.as_dict().unwrap();
+        assert!(d.has_name(b"Type", b"Page"));
+        assert_eq!(d.get(b"Kids").unwrap().as_array().unwrap().len(), 2);
+        assert!(d.get(b"N").is_none());
+    }
+
+    #[test]
+    fn streams() {
+        let data = b"7 0 obj <</Length 8 0 R>> stream\r\nabc\nendstream endobj";
+        let (id, obj) = Parser::new(data, 0).parse_indirect(&|_| None).unwrap();
+        assert_eq!(id, (7, 0));
+        assert_eq!(obj.as_stream().unwrap().data, b"abc");
+
+        let (_, obj) = Parser::new(data, 0).parse_indirect(&|_| Some(4)).unwrap();
+        assert_eq!(obj.as_stream().unwrap().data, b"abc\n");
+    }
+
+    #[test]
+    fn nesting_limit() {
+        let deep = "[".repeat(10_000);
+        assert!(Parser::new(deep.as_bytes(), 0).parse_object().is_err());
+    }
+}
@@ -0,0 +1,707 @@
+// Copyright 2026 the Tectonic Project
+// Licensed under the MIT License.
+
+//! Whole-document operations: merging, page extraction, n-up layout, and file
+//! attachments.
+//!
+//! Pages are copied along with everything that they refer to. Document-level
+//! structures that refer to pages, such as outlines and structure trees, are
+//! not carried over, since they generally won't make sense for the new
+//! document.
+
+use std::collections::{HashMap, HashSet};
+use tectonic_errors::prelude::*;
+
+use crate::{
+    document::{version_key, Document},
+    object::{Dictionary, Object, ObjectId, Stream},
+};
+
+/// Page attributes that can be inherited from the page tree.
+const INHERITABLE: &[&[u8]] = &[b"Resources", b"MediaBox", b"CropBox", b"Rotate"];
+
+/// Page attributes that aren't copied, since they refer to structures that
+/// aren't.
+const DROPPED: &[&[u8]] = &[b"Parent", b"StructParents", b"B", b"PieceInfo"];
+
+/// Copies objects from one document into another.
+struct Importer<'a> {
+    src: &'a Document,
+    map: HashMap<ObjectId, ObjectId>,
+    pages: HashMap<ObjectId, ObjectId>,
+    queue: Vec<(ObjectId, ObjectId)>,
+}
+
+impl<'a> Importer<'a> {
+    fn new(src: &'a Document) -> Self {
+        Importer {
+            src,
+            map: HashMap::new(),
+            pages: HashMap::new(),
+            queue: Vec::new(),
+        }
+    }
+
+    /// Import some of the source document's pages, in order. Pages may be
+    /// repeated. Returns the identifiers of the new pages, whose `Parent`
+    /// entries are set to *parent*.
+    fn import_pages(
+        &mut self,
+        dest: &mut Document,
+        pages: &[ObjectId],
+        parent: ObjectId,
+    ) -> Result<Vec<ObjectId>> {
+        // Assign the page identifiers first, so that links between the
+        // selected pages are preserved. Links to the first copy of a
+        // repeated page.
+        let new_ids: Vec<ObjectId> = pages
+            .iter()
+            .map(|id| {
+                let new = dest.add_object(Object::Null);
+                self.pages.entry(*id).or_insert(new);
+                new
+            })
+            .collect();
+
+        for (src_id, new_id) in pages.iter().zip(&new_ids) {
+            let page = a_ok_or!(
+                self.src.get(*src_id).and_then(Object::as_dict);
+                ["PDF page object {} {} R is missing", src_id.0, src_id.1]
+            );
+
+            let mut copy = Dictionary::new();
+
+            for (k, v) in page.iter() {
+                if !DROPPED.contains(&k) {
+                    copy.set(k, self.translate(dest, v));
+                }
+            }
+
+            for key in INHERITABLE {
+                if copy.get(key).is_none() {
+                    if let Some(v) = self.src.page_attribute(*src_id, key) {
+                        let v = self.translate(dest, v);
+                        copy.set(*key, v);
+                    }
+                }
+            }
+
+            copy.set("Type", Object::name("Page"));
+            copy.set("Parent", Object::Reference(parent));
+            dest.objects.insert(*new_id, copy.into());
+        }
+
+        self.finish(dest);
+        Ok(new_ids)
+    }
+
+    /// Import an arbitrary object and everything that it refers to.
+    fn import(&mut self, dest: &mut Document, obj: &Object) -> Object {
+        let obj = self.translate(dest, obj);
+        self.finish(dest);
+        obj
+    }
+
+    /// Copy the objects that have been referred to but not yet copied. This
+    /// uses a worklist rather than recursion, since reference chains can be
+    /// very long.
+    fn finish(&mut self, dest: &mut Document) {
+        let src = self.src;
+
+        while let Some((src_id, new_id)) = self.queue.pop() {
+            let obj = self.translate(dest, &src.objects[&src_id]);
+            dest.objects.insert(new_id, obj);
+        }
+    }
+
+    /// Translate a direct object, rewriting references to point into the
+    /// destination document.
+    fn translate(&mut self, dest: &mut Document, obj: &Object) -> Object {
+        match obj {
+            Object::Reference(id) => self.translate_ref(dest, *id),
+            Object::Array(a) => Object::Array(a.iter().map(|o| self.translate(dest, o)).collect()),
+            Object::Dictionary(d) => Object::Dictionary(self.translate_dict(dest, d)),
+            Object::Stream(s) => Object::Stream(Stream::new(
+                self.translate_dict(dest, &s.dict),
+                s.data.clone(),
+            )),
+            other => other.clone(),
+        }
+    }
+
+    fn translate_dict(&mut self, dest: &mut Document, dict: &Dictionary) -> Dictionary {
+        let mut new = Dictionary::new();
+
+        for (k, v) in dict.iter() {
+            new.set(k, self.translate(dest, v));
+        }
+
+        new
+    }
+
+    fn translate_ref(&mut self, dest: &mut Document, id: ObjectId) -> Object {
+        if let Some(new) = self.map.get(&id) {
+            return Object::Reference(*new);
+        }
+
+        let Some(obj) = self.src.get(id) else {
+            return Object::Null;
+        };
+
+        // References to pages that aren't being copied, and to the page
+        // tree, are dropped.
+        if let Some(d) = obj.as_dict() {
+            if d.has_name(b"Type", b"Page") || d.has_name(b"Type", b"Pages") {
+                return self
+                    .pages
+                    .get(&id)
+                    .map_or(Object::Null, |n| Object::Reference(*n));
+            }
+        }
+
+        let new = dest.add_object(Object::Null);
+        self.map.insert(id, new);
+        self.queue.push((id, new));
+        Object::Reference(new)
+    }
+}
+
+/// Add a page tree with the given pages, and a catalog, to a new document.
+fn finish_document(dest: &mut Document, pages_id: ObjectId, kids: Vec<ObjectId>) {
+    let mut pages = Dictionary::new();
+    pages.set("Type", Object::name("Pages"));
+    pages.set("Count", kids.len() as i64);
+    pages.set(
+        "Kids",
+        kids.into_iter().map(Object::Reference).collect::<Vec<_>>(),
+    );
+    dest.objects.insert(pages_id, pages.into());
+
+    let mut catalog = Dictionary::new();
+    catalog.set("Type", Object::name("Catalog"));
+    catalog.set("Pages", Object::Reference(pages_id));
+    let catalog = dest.add_object(catalog);
+    dest.trailer.set("Root", Object::Reference(catalog));
+}
+
+fn newest_version<'a>(docs: impl Iterator<Item = &'a Document>) -> String {
+    let mut best = "1.4";
+
+    for doc in docs {
+        if version_key(&doc.version) > version_key(best) {
+            best = &doc.version;
+        }
+    }
+
+    best.to_owned()
+}
+
+/// Concatenate the pages of several documents.
+///
+/// The document information dictionary of the first document is kept.
+pub fn merge(docs: &[Document]) -> Result<Document> {
+    let mut dest = Document::new();
+    dest.version = newest_version(docs.iter());
+    let pages_id = dest.add_object(Object::Null);
+    let mut kids = Vec::new();
+
+    for (i, doc) in docs.iter().enumerate() {
+        let mut importer = Importer::new(doc);
+        kids.extend(importer.import_pages(&mut dest, &doc.page_ids()?, pages_id)?);
+
+        if i == 0 {
+            if let Some(info) = doc.trailer.get(b"Info") {
+                let info = importer.import(&mut dest, info);
+                dest.trailer.set("Info", info);
+            }
+        }
+    }
+
+    finish_document(&mut dest, pages_id, kids);
+    Ok(dest)
+}
+
+/// Make a new document out of some of the pages of another.
+///
+/// The page numbers start at 1. Pages may be repeated.
+pub fn extract(doc: &Document, pages: &[usize]) -> Result<Document> {
+    let all = doc.page_ids()?;
+    let mut selected = Vec::with_capacity(pages.len());
+
+    for &n in pages {
+        ensure!(
+            n >= 1 && n <= all.len(),
+            "page {} is out of range; the document has {} pages",
+            n,
+            all.len()
+        );
+        selected.push(all[n - 1]);
+    }
+
+    let mut dest = Document::new();
+    dest.version = doc.version.clone();
+    let pages_id = dest.add_object(Object::Null);
+
+    let mut importer = Importer::new(doc);
+    let kids = importer.import_pages(&mut dest, &selected, pages_id)?;
+
+    if let Some(info) = doc.trailer.get(b"Info") {
+        let info = importer.import(&mut dest, info);
+        dest.trailer.set("Info", info);
+    }
+
+    finish_document(&mut dest, pages_id, kids);
+    Ok(dest)
+}
+
+/// How to arrange pages for [`nup`].
+#[derive(Clone, Debug)]
+pub struct NupLayout {
+    /// The number of pages across each sheet.
+    pub columns: usize,
+
+    /// The number of pages down each sheet.
+    pub rows: usize,
+
+    /// The width and height of each sheet, in points. If unspecified, the
+    /// size of the first page is used, turned to suit the grid.
+    pub sheet_size: Option<(f64, f64)>,
+}
+
+/// An affine transformation, in PDF's `[a b c d e f]` order.
+type Matrix = [f64; 6];
+
+/// The transformation that applies *first* and then *second*.
+fn concat(first: &Matrix, second: &Matrix) -> Matrix {
+    let [a1, b1, c1, d1, e1, f1] = *first;
+    let [a2, b2, c2, d2, e2, f2] = *second;
+    [
+        a1 * a2 + b1 * c2,
+        a1 * b2 + b1 * d2,
+        c1 * a2 + d1 * c2,
+        c1 * b2 + d1 * d2,
+        e1 * a2 + f1 * c2 + e2,
+        e1 * b2 + f1 * d2 + f2,
+    ]
+}
+
+/// The geometry of a page as it's displayed.
+struct PageGeometry {
+    bbox: [f64; 4],
+    rotate: i64,
+}
+
+impl PageGeometry {
+    fn new(dest: &Document, page: ObjectId) -> Result<Self> {
+        let dict = a_ok_or!(dest.get(page).and_then(Object::as_dict); ["PDF page is missing"]);
+        let rect = |key: &[u8]| -> Option<[f64; 4]> {
+            let a = dest.dict_get(dict, key)?.as_array()?;
+            let v: Vec<f64> = a.iter().filter_map(|o| dest.resolve(o).as_f64()).collect();
+
+            if v.len() != 4 {
+                return None;
+            }
+
+            let r = [
+                v[0].min(v[2]),
+                v[1].min(v[3]),
+                v[0].max(v[2]),
+                v[1].max(v[3]),
+            ];
+            (r[2] > r[0] && r[3] > r[1]).then_some(r)
+        };
+
+        let bbox = rect(b"CropBox")
+            .or_else(|| rect(b"MediaBox"))
+            .unwrap_or([0., 0., 612., 792.]);
+        let rotate = dest
+            .dict_get(dict, b"Rotate")
+            .and_then(Object::as_i64)
+            .unwrap_or(0)
+            .rem_euclid(360)
+            / 90
+            * 90;
+
+        Ok(PageGeometry { bbox, rotate })
+    }
+
+    fn width(&self) -> f64 {
+        self.bbox[2] - self.bbox[0]
+    }
+
+    fn height(&self) -> f64 {
+        self.bbox[3] - self.bbox[1]
+    }
+
+    /// The width and height of the page as displayed.
+    fn displayed_size(&self) -> (f64, f64) {
+        if self.rotate % 180 == 0 {
+            (self.width(), self.height())
+        } else {
+            (self.height(), self.width())
+        }
+    }
+
+    /// The transformation from page space to displayed space, with the
+    /// origin at the lower left of the displayed page.
+    fn display_matrix(&self) -> Matrix {
+        let (w, h) = (self.width(), self.height());
+        let origin = [1., 0., 0., 1., -self.bbox[0], -self.bbox[1]];
+        let rotation = match self.rotate {
+            90 => [0., -1., 1., 0., 0., w],
+            180 => [-1., 0., 0., -1., w, h],
+            270 => [0., 1., -1., 0., h, 0.],
+            _ => [1., 0., 0., 1., 0., 0.],
+        };
+        concat(&origin, &rotation)
+    }
+}
+
+/// Turn an imported page into a form XObject that draws it.
+fn page_to_form(dest: &Document, page: ObjectId, geom: &PageGeometry) -> Result<Stream> {
+    let dict = a_ok_or!(dest.get(page).and_then(Object::as_dict); ["PDF page is missing"]);
+    let mut content = Vec::new();
+
+    let streams: Vec<&Object> = match dest.dict_get(dict, b"Contents") {
+        Some(Object::Array(a)) => a.iter().map(|o| dest.resolve(o)).collect(),
+        Some(o) => vec![o],
+        None => Vec::new(),
+    };
+
+    for s in streams {
+        if let Some(s) = s.as_stream() {
+            content.extend(atry!(
+                s.decoded_data();
+                ["couldn't decode the contents of a PDF page"]
+            ));
+            content.push(b'\n');
+        }
+    }
+
+    let mut form = Dictionary::new();
+    form.set("Type", Object::name("XObject"));
+    form.set("Subtype", Object::name("Form"));
+    form.set(
+        "BBox",
+        geom.bbox
+            .iter()
+            .map(|v| Object::Real(*v))
+            .collect::<Vec<_>>(),
+    );
+
+    if let Some(res) = dict.get(b"Resources") {
+        form.set("Resources", res.clone());
+    }
+
+    if let Some(group) = dict.get(b"Group") {
+        form.set("Group", group.clone());
+    }
+
+    Ok(Stream::new_compressed(form, &content))
+}
+
+/// Arrange the pages of a document in a grid, several to a sheet.
+///
+/// Each page is scaled to fit its cell, keeping its proportions, and centred.
+/// Pages are placed left to right, then top to bottom. Annotations are not
+/// carried over.
+pub fn nup(doc: &Document, layout: &NupLayout) -> Result<Document> {
+    ensure!(
+        layout.columns > 0 && layout.rows > 0,
+        "the n-up grid must have at least one row and column"
+    );
+    let per_sheet = layout.columns * layout.rows;
+    let src_pages = doc.page_ids()?;
+
+    let mut dest = Document::new();
+    dest.version = doc.version.clone();
+
+    // Import the pages into the new document, and then convert them into
+    // form XObjects. The page objects themselves are left unreferenced, so
+    // they won't be written out.
+    let scratch_parent = dest.add_object(Object::Null);
+    let mut importer = Importer::new(doc);
+    let imported = importer.import_pages(&mut dest, &src_pages, scratch_parent)?;
+
+    let mut forms = Vec::with_capacity(imported.len());
+
+    for page in &imported {
+        let geom = PageGeometry::new(&dest, *page)?;
+        let form = page_to_form(&dest, *page, &geom)?;
+        forms.push((dest.add_object(form), geom));
+    }
+
+    let (sheet_w, sheet_h) = match (layout.sheet_size, forms.first()) {
+        (Some(size), _) => size,
+        (None, Some((_, geom))) => {
+            let (w, h) = geom.displayed_size();
+            let (short, long) = (w.min(h), w.max(h));
+
+            if layout.columns > layout.rows {
+                (long, short)
+            } else if layout.rows > layout.columns {
+                (short, long)
+            } else {
+                (w, h)
+            }
+        }
+        (None, None) => (612., 792.),
+    };
+    ensure!(
+        sheet_w > 0. && sheet_h > 0.,
+        "the n-up sheet size must be positive"
+    );
+
+    let cell_w = sheet_w / layout.columns as f64;
+    let cell_h = sheet_h / layout.rows as f64;
+    let pages_id = dest.add_object(Object::Null);
+    let mut kids = Vec::new();
+
+    for sheet in forms.chunks(per_sheet) {
+        let mut content = String::new();
+        let mut xobjects = Dictionary::new();
+
+        for (i, (form, geom)) in sheet.iter().enumerate() {
+            let col = (i % layout.columns) as f64;
+            let row = (i / layout.columns) as f64;
+            let (w, h) = geom.displayed_size();
+            let scale = (cell_w / w).min(cell_h / h);
+            let x = col * cell_w + (cell_w - w * scale) / 2.;
+            let y = sheet_h - (row + 1.) * cell_h + (cell_h - h * scale) / 2.;
+
+            let m = concat(&geom.display_matrix(), &[scale, 0., 0., scale, x, y]);
+            let m: Vec<String> = m.iter().map(|v| Object::Real(*v).to_string()).collect();
+            content.push_str(&format!("q {} cm /P{} Do Q\n", m.join(" "), i));
+            xobjects.set(format!("P{i}"), Object::Reference(*form));
+        }
+
+        let mut resources = Dictionary::new();
+        resources.set("XObject", xobjects);
+        let contents = dest.add_object(Stream::new_compressed(
+            Dictionary::new(),
+            content.as_bytes(),
+        ));
+
+        let mut page = Dictionary::new();
+        page.set("Type", Object::name("Page"));
+        page.set("Parent", Object::Reference(pages_id));
+        page.set(
+            "MediaBox",
+            vec![
+                Object::Integer(0),
+                Object::Integer(0),
+                Object::Real(sheet_w),
+                Object::Real(sheet_h),
+            ],
+        );
+        page.set("Resources", resources);
+        page.set("Contents", Object::Reference(contents));
+        kids.push(dest.add_object(page));
+    }
+
+    if let Some(info) = doc.trailer.get(b"Info") {
+        let info = importer.import(&mut dest, info);
+        dest.trailer.set("Info", info);
+    }
+
+    finish_document(&mut dest, pages_id, kids);
+    Ok(dest)
+}
+
+/// Attach a file to a document, as an embedded file listed in the document
+/// catalog. An existing attachment with the same name is replaced.
+pub fn attach_file(
+    doc: &mut Document,
+    name: &str,
+    data: &[u8],
+    description: Option<&str>,
+) -> Result<()> {
+    let catalog_id = doc.catalog_id()?;
+
+    let mut params = Dictionary::new();
+    params.set("Size", data.len() as i64);
+    let mut ef_dict = Dictionary::new();
+    ef_dict.set("Type", Object::name("EmbeddedFile"));
+    ef_dict.set("Params", params);
+    let file = doc.add_object(Stream::new_compressed(ef_dict, data));
+
+    let mut ef = Dictionary::new();
+    ef.set("F", Object::Reference(file));
+    let mut spec = Dictionary::new();
+    spec.set("Type", Object::name("Filespec"));
+    spec.set("F", Object::text_string(name));
+    spec.set("UF", Object::text_string(name));
+    spec.set("EF", ef);
+    spec.set("AFRelationship", Object::name("Unspecified"));
+
+    if let Some(desc) = description {
+        spec.set("Desc", Object::text_string(desc));
+    }
+
+    let spec = doc.add_object(spec);
+
+    // Gather the existing entries of the name tree, and rebuild it as a
+    // single node.
+    let names_obj = doc.catalog()?.get(b"Names").cloned();
+    let names_dict = names_obj
+        .as_ref()
+        .and_then(|o| doc.resolve(o).as_dict())
+        .cloned()
+        .unwrap_or_default();
+
+    let mut entries = Vec::new();
+
+    if let Some(tree) = doc.dict_get(&names_dict, b"EmbeddedFiles") {
+        collect_name_tree(doc, tree, &mut entries);
+    }
+
+    let key = Object::text_string(name);
+    entries.retain(|(k, _)| *k != key);
+    entries.push((key, Object::Reference(spec)));
+    entries.sort_by(|a, b| match (&a.0, &b.0) {
+        (Object::String(a), Object::String(b)) => a.cmp(b),
+        _ => std::cmp::Ordering::Equal,
+    });
+
+    let mut tree = Dictionary::new();
+    tree.set(
+        "Names",
+        entries
+            .into_iter()
+            .flat_map(|(k, v)| [k, v])
+            .collect::<Vec<_>>(),
+    );
+
+    let mut names_dict = names_dict;
+    names_dict.set("EmbeddedFiles", tree);
+
+    match names_obj.and_then(|o| o.as_reference()) {
+        Some(id) if doc.get(id).is_some() => {
+            doc.objects.insert(id, names_dict.into());
+        }
+
+        _ => {
+            let catalog = a_ok_or!(
+                doc.objects.get_mut(&catalog_id).and_then(Object::as_dict_mut);
+                ["the PDF document catalog is not a dictionary"]
+            );
+            catalog.set("Names", names_dict);
+        }
+    }
+
+    Ok(())
+}
+
+/// Collect the key-value pairs of a name tree.
+fn collect_name_tree(doc: &Document, root: &Object, entries: &mut Vec<(Object, Object)>) {
+    let mut seen = HashSet::new();
+    let mut stack = vec![root];
+
+    while let Some(node) = stack.pop() {
+        if let Object::Reference(id) = node {
+            if !seen.insert(*id) {
+                continue;
+            }
+        }
+
+        let Some(node) = doc.resolve(node).as_dict() else {
+            continue;
+        };
+
+        if let Some(names) = doc.dict_get(node, b"Names").and_then(Object::as_array) {
+            for pair in names.chunks_exact(2) {
+                entries.push((doc.resolve(&pair[0]).clone(), pair[1].clone()));
+            }
+        }
+
+        if let Some(kids) = doc.dict_get(node, b"Kids").and_then(Object::as_array) {
+            stack.extend(kids.iter().rev());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::document::tests::sample;
+
+    fn round_trip(doc: &Document) -> Document {
+        let mut buf = Vec::new();
+        doc.save(&mut buf).unwrap();
+        Document::load(&buf).unwrap()
+    }
+
+    #[test]
+    fn merge_and_extract() {
+        let merged = round_trip(&merge(&[sample(), sample()]).unwrap());
+        let pages = merged.page_ids().unwrap();
+        assert_eq!(pages.len(), 4);
+
+        // Inherited attributes are copied to the pages.
+        let page = merged.get(pages[3]).unwrap().as_dict().unwrap();
+        assert!(page.get(b"MediaBox").is_some());
+
+        let extracted = round_trip(&extract(&merged, &[4, 1, 1]).unwrap());
+        assert_eq!(extracted.page_ids().unwrap().len(), 3);
+        assert!(extract(&merged, &[5]).is_err());
+    }
+
+    #[test]
+    fn nup_two_up() {
+        let layout = NupLayout {
+            columns: 2,
+            rows: 1,
+            sheet_size: None,
+        };
+        let doc = round_trip(&nup(&sample(), &layout).unwrap());
+        let pages = doc.page_ids().unwrap();
+        assert_eq!(pages.len(), 1);
+
+        let mb = doc.page_attribute(pages[0], b"MediaBox").unwrap();
+        let mb: Vec<f64> = mb
+            .as_array()
+            .unwrap()
+            .iter()
+            .filter_map(Object::as_f64)
+            .collect();
+        assert_eq!(mb, [0., 0., 792., 612.]);
+
+        let page = doc.get(pages[0]).unwrap().as_dict().unwrap();
+        let contents = doc
+            .dict_get(page, b"Contents")
+            .unwrap()
+            .as_stream()
+            .unwrap();
+        let contents = String::from_utf8(contents.decoded_data().unwrap()).unwrap();
+        assert!(contents.contains("/P0 Do"));
+        assert!(contents.contains("/P1 Do"));
+    }
+
+    #[test]
+    fn attachments() {
+        let mut doc = sample();
+        attach_file(&mut doc, "b.txt", b"first", None).unwrap();
+        attach_file(&mut doc, "a.txt", b"second", Some("A file")).unwrap();
+        attach_file(&mut doc, "b.txt", b"third", None).unwrap();
+        let doc = round_trip(&doc);
+
+        let names = doc.dict_get(doc.catalog().unwrap(), b"Names").unwrap();
+        let tree = doc
+            .dict_get(names.as_dict().unwrap(), b"EmbeddedFiles")
+            .unwrap();
+        let entries = tree
+            .as_dict()
+            .unwrap()
+            .get(b"Names")
+            .unwrap()
+            .as_array()
+            .unwrap();
+        assert_eq!(entries.len(), 4);
+        assert_eq!(entries[0], Object::String(b"a.txt".to_vec()));
+
+        let spec = doc.resolve(&entries[3]).as_dict().unwrap();
+        let ef = doc.dict_get(spec, b"EF").unwrap().as_dict().unwrap();
+        let file = doc.dict_get(ef, b"F").unwrap().as_stream().unwrap();
+        assert_eq!(file.decoded_data().unwrap(), b"third");
+    }
+}
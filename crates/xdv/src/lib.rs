@@ -136,6 +136,11 @@ pub trait XdvEvents {
         Ok(())
     }
 
+    /// End the current page.
+    fn handle_end_page(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
     /// Handle a `\special`.
     #[allow(unused)]
     fn handle_special(&mut self, x: i32, y: i32, contents: &[u8]) -> Result<(), Self::Error> {
@@ -741,6 +746,14 @@ impl<T: XdvEvents> XdvParser<T> {
             return Err(XdvError::Malformed(cursor.global_offset()).into_internal());
         }
 
+        // Make sure that any pending characters are attributed to this page.
+        if !self.cur_char_run.is_empty() {
+            self.events
+                .handle_char_run(self.cur_font_num, &self.cur_char_run)?;
+            self.cur_char_run.clear();
+        }
+
+        self.events.handle_end_page()?;
         self.state = ParserState::BetweenPages;
         Ok(())
     }
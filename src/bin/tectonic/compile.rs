@@ -12,11 +12,15 @@ use std::{
 use structopt::StructOpt;
 use tectonic_bridge_core::{SecuritySettings, SecurityStance};
 
+use crate::interaction::TerminalErrorHandler;
+
 use tectonic::{
     config::PersistentConfig,
     driver::{OutputFormat, PassSetting, ProcessingSession, ProcessingSessionBuilder},
+    engines::xdv2png::PageSelection,
     errmsg,
     errors::{ErrorKind, Result},
+    profile::ProfileFormat,
     status::StatusBackend,
     tt_error, tt_note,
     unstable_opts::{UnstableArg, UnstableOptions},
@@ -46,13 +50,33 @@ pub struct CompileOptions {
     only_cached: bool,
 
     /// The kind of output to generate
-    #[structopt(long, name = "format", default_value = "pdf", possible_values(&["pdf", "html", "xdv", "aux", "fmt"]))]
+    #[structopt(long, name = "format", default_value = "pdf", possible_values(&["pdf", "html", "xdv", "aux", "fmt", "svg", "png", "epub"]))]
     outfmt: String,
 
+    /// The resolution of PNG output, in pixels per inch [default: 96]
+    #[structopt(long, name = "dpi")]
+    png_dpi: Option<f64>,
+
+    /// Only render these pages in PNG output, such as "1,3-5"
+    #[structopt(long, name = "pages")]
+    pages: Option<PageSelection>,
+
+    /// Omit characters that SVG or PNG output can't draw, instead of failing
+    #[structopt(long)]
+    allow_missing_glyphs: bool,
+
     /// Write Makefile-format rules expressing the dependencies of this run to <dest_path>
     #[structopt(long, name = "dest_path")]
     makefile_rules: Option<PathBuf>,
 
+    /// Write a report of the time and resources used by each engine pass to <profile_path>
+    #[structopt(long, name = "profile_path")]
+    profile: Option<PathBuf>,
+
+    /// The format of the profiling report
+    #[structopt(long, default_value = "json", possible_values(&["json", "chrome"]))]
+    profile_format: ProfileFormat,
+
     /// Which engines to run
     #[structopt(long, default_value = "default", possible_values(&["default", "tex", "bibtex_first"]))]
     pass: String,
@@ -81,6 +105,10 @@ pub struct CompileOptions {
     #[structopt(long = "print", short)]
     print_stdout: bool,
 
+    /// Stop at TeX errors and ask how to proceed
+    #[structopt(long)]
+    interactive: bool,
+
     /// The directory in which to place output files [default: the directory containing <input>]
     #[structopt(name = "outdir", short, long, parse(from_os_str))]
     outdir: Option<PathBuf>,
@@ -122,6 +150,16 @@ impl CompileOptions {
 
         sess_builder.output_format(OutputFormat::from_str(&self.outfmt).unwrap());
 
+        if let Some(dpi) = self.png_dpi {
+            sess_builder.png_dpi(dpi);
+        }
+
+        if let Some(pages) = self.pages {
+            sess_builder.png_pages(pages);
+        }
+
+        sess_builder.allow_missing_glyphs(self.allow_missing_glyphs);
+
         let pass = PassSetting::from_str(&self.pass).unwrap();
         sess_builder.pass(pass);
 
@@ -133,10 +171,20 @@ impl CompileOptions {
             sess_builder.makefile_output_path(p);
         }
 
+        if let Some(p) = self.profile {
+            sess_builder.profile(p, self.profile_format);
+        }
+
         // Input and path setup
 
         let input_path = self.input;
         if input_path == "-" {
+            if self.interactive {
+                return Err(errmsg!(
+                    "cannot recover from errors interactively when reading input from standard input"
+                ));
+            }
+
             // Don't provide an input path to the ProcessingSession, so it will default to stdin.
             sess_builder.tex_input_name("texput.tex");
             sess_builder.output_dir(Path::new(""));
@@ -180,6 +228,9 @@ impl CompileOptions {
         // Set up the rest of I/O.
 
         sess_builder.print_stdout(self.print_stdout);
+        if self.interactive {
+            sess_builder.error_handler(Box::new(TerminalErrorHandler::default()));
+        }
 
         if let Some(items) = self.hide {
             for v in items {
@@ -208,6 +259,15 @@ pub(crate) fn run_and_report(
     status: &mut dyn StatusBackend,
 ) -> Result<ProcessingSession> {
     let mut sess = sess_builder.create(status)?;
+    run_session_and_report(&mut sess, status).map(|_| sess)
+}
+
+/// Like [`run_and_report`], but for a session that has already been created.
+/// The caller keeps the session even if processing fails.
+pub(crate) fn run_session_and_report(
+    sess: &mut ProcessingSession,
+    status: &mut dyn StatusBackend,
+) -> Result<()> {
     let result = sess.run(status);
 
     if let Err(e) = &result {
@@ -231,5 +291,5 @@ pub(crate) fn run_and_report(
         }
     }
 
-    result.map(|_| sess)
+    result
 }
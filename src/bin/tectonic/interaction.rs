@@ -0,0 +1,233 @@
+// Copyright 2026 the Tectonic Project
+// Licensed under the MIT License.
+
+//! Asking the user how to recover from TeX errors, as classic TeX does at its
+//! `?` prompt.
+
+use std::io::{self, Write};
+use tectonic::{
+    driver::{ErrorHandler, ErrorResponse},
+    status::StatusBackend,
+    tt_note,
+};
+
+/// Prompt the user for a line of input on the terminal. Returns None if
+/// standard input has been closed or can't be read.
+fn prompt_for_line(prompt: &str) -> Option<String> {
+    eprint!("{prompt}");
+    io::stderr().flush().ok();
+
+    let mut line = String::new();
+
+    match io::stdin().read_line(&mut line) {
+        Ok(0) | Err(_) => None,
+        Ok(_) => Some(line.trim_end_matches(&['\r', '\n'][..]).to_owned()),
+    }
+}
+
+/// An error handler that asks the user at the terminal.
+///
+/// The error context and any messages go through the status backend, while
+/// the responses are read with the *prompt* function, which reads from
+/// standard input by default.
+pub struct TerminalErrorHandler<F = fn(&str) -> Option<String>> {
+    prompt: F,
+}
+
+impl Default for TerminalErrorHandler {
+    fn default() -> Self {
+        TerminalErrorHandler {
+            prompt: prompt_for_line,
+        }
+    }
+}
+
+impl<F: FnMut(&str) -> Option<String>> ErrorHandler for TerminalErrorHandler<F> {
+    fn handle_error(
+        &mut self,
+        context: &str,
+        help: &str,
+        deletions_allowed: bool,
+        status: &mut dyn StatusBackend,
+    ) -> ErrorResponse {
+        show_error_context(context, status);
+
+        loop {
+            let line = match (self.prompt)("? ") {
+                Some(l) => l,
+                None => return ErrorResponse::Quit,
+            };
+
+            let mut chars = line.chars();
+
+            match chars.next() {
+                None => return ErrorResponse::Proceed,
+
+                Some('0'..='9') => match line.parse::<usize>() {
+                    Ok(n @ 1..=99) if deletions_allowed => return ErrorResponse::Delete(n),
+                    Ok(_) if !deletions_allowed => {
+                        tt_note!(status, "Sorry, I don't allow deletions now.");
+                    }
+                    _ => {
+                        tt_note!(
+                            status,
+                            "Type a number from 1 to 99 to delete that many tokens."
+                        );
+                    }
+                },
+
+                Some('I' | 'i') => {
+                    let text = chars.as_str();
+
+                    if !text.is_empty() {
+                        return ErrorResponse::Insert(text.to_owned());
+                    }
+
+                    return match (self.prompt)("insert>") {
+                        Some(text) => ErrorResponse::Insert(text),
+                        None => ErrorResponse::Quit,
+                    };
+                }
+
+                Some('H' | 'h') => {
+                    if help.is_empty() {
+                        tt_note!(status, "Sorry, I don't know how to help in this situation.");
+                    } else {
+                        tt_note!(status, "{}", help);
+                    }
+                }
+
+                Some('S' | 's') => return ErrorResponse::Scroll,
+                Some('X' | 'x') => return ErrorResponse::Quit,
+
+                _ => {
+                    tt_note!(
+                        status,
+                        "Type <return> to proceed, S to scroll future error messages,"
+                    );
+                    tt_note!(status, "I to insert something, H for help, X to quit.");
+
+                    if deletions_allowed {
+                        tt_note!(
+                            status,
+                            "1 or ... or 99 to ignore the next 1 to 99 tokens of input."
+                        );
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Show the context of a TeX error, highlighting the text that the engine had
+/// read when it ran into trouble.
+///
+/// TeX prints each level of the context as a pair of lines, where the second
+/// line holds the text that hasn't been read yet, indented so that it starts
+/// where the first line ends. We keep that layout, and only highlight the
+/// first line of each pair.
+fn show_error_context(context: &str, status: &mut dyn StatusBackend) {
+    let mut lines = context.lines().filter(|l| !l.trim().is_empty()).peekable();
+
+    while let Some(read) = lines.next() {
+        status.note_highlighted("", read, "");
+
+        let width = read.chars().count();
+
+        if let Some(unread) =
+            lines.next_if(|next| next.len() - next.trim_start_matches(' ').len() == width)
+        {
+            status.note_highlighted(unread, "", "");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{collections::VecDeque, fmt::Arguments};
+    use tectonic::status::MessageKind;
+    use tectonic_errors::Error;
+
+    /// A status backend that records the messages sent to it.
+    #[derive(Default)]
+    struct RecordingStatusBackend(Vec<String>);
+
+    impl StatusBackend for RecordingStatusBackend {
+        fn report(&mut self, _kind: MessageKind, args: Arguments, _err: Option<&Error>) {
+            self.0.push(args.to_string());
+        }
+
+        fn note_highlighted(&mut self, before: &str, highlighted: &str, after: &str) {
+            self.0.push(format!("{before}{highlighted}{after}"));
+        }
+
+        fn dump_error_logs(&mut self, _output: &[u8]) {}
+    }
+
+    /// Handle an error, answering the prompts with the given responses.
+    fn respond(
+        responses: &[&str],
+        help: &str,
+        deletions_allowed: bool,
+    ) -> (ErrorResponse, Vec<String>) {
+        let mut responses: VecDeque<_> = responses.iter().map(|r| r.to_string()).collect();
+        let mut handler = TerminalErrorHandler {
+            prompt: |_prompt: &str| responses.pop_front(),
+        };
+        let mut status = RecordingStatusBackend::default();
+
+        let response = handler.handle_error(
+            "l.3 \\foo\n       bar\n",
+            help,
+            deletions_allowed,
+            &mut status,
+        );
+        (response, status.0)
+    }
+
+    #[test]
+    fn responses() {
+        assert_eq!(respond(&[""], "", true).0, ErrorResponse::Proceed);
+        assert_eq!(respond(&["5"], "", true).0, ErrorResponse::Delete(5));
+        assert_eq!(respond(&["s"], "", true).0, ErrorResponse::Scroll);
+        assert_eq!(respond(&["X"], "", true).0, ErrorResponse::Quit);
+        assert_eq!(respond(&[], "", true).0, ErrorResponse::Quit);
+        assert_eq!(
+            respond(&["I\\relax"], "", true).0,
+            ErrorResponse::Insert("\\relax".to_owned())
+        );
+        assert_eq!(
+            respond(&["i", "\\relax"], "", true).0,
+            ErrorResponse::Insert("\\relax".to_owned())
+        );
+    }
+
+    #[test]
+    fn messages() {
+        let (response, messages) = respond(&["3", "100", "h", "?", "x"], "Try again.", false);
+        assert_eq!(response, ErrorResponse::Quit);
+        assert_eq!(
+            messages,
+            [
+                "l.3 \\foo",
+                "       bar",
+                "Sorry, I don't allow deletions now.",
+                "Sorry, I don't allow deletions now.",
+                "Try again.",
+                "Type <return> to proceed, S to scroll future error messages,",
+                "I to insert something, H for help, X to quit.",
+            ]
+        );
+
+        let (response, messages) = respond(&["100", "H", ""], "", true);
+        assert_eq!(response, ErrorResponse::Proceed);
+        assert_eq!(
+            messages[2..],
+            [
+                "Type a number from 1 to 99 to delete that many tokens.",
+                "Sorry, I don't know how to help in this situation.",
+            ]
+        );
+    }
+}
@@ -18,6 +18,14 @@ use tectonic::{
 };
 
 mod compile;
+mod interaction;
+#[cfg(feature = "serve")]
+mod pdf;
+#[cfg(feature = "serve")]
+mod preview;
+#[cfg(feature = "serve")]
+mod serve;
+#[cfg(feature = "serve")]
 mod watch;
 
 #[cfg(feature = "serialization")]
@@ -0,0 +1,251 @@
+// Copyright 2026 the Tectonic Project
+// Licensed under the MIT License.
+
+//! The `pdf` subcommand of the "V2" / "cargo-like" interface, for
+//! post-processing PDF files.
+
+use std::{
+    fs::File,
+    io::{BufWriter, Write},
+    path::{Path, PathBuf},
+};
+use structopt::StructOpt;
+use tectonic_pdf_io::{
+    tools::{self, NupLayout},
+    Document,
+};
+
+use tectonic::{
+    config::PersistentConfig, ctry, errmsg, errors::Result, status::StatusBackend, tt_note,
+};
+
+/// Post-process PDF files
+#[derive(Debug, StructOpt)]
+pub struct PdfCommand {
+    #[structopt(subcommand)]
+    command: PdfCommands,
+}
+
+#[derive(Debug, StructOpt)]
+enum PdfCommands {
+    #[structopt(name = "merge")]
+    /// Concatenate the pages of several PDF files
+    Merge(PdfMergeCommand),
+
+    #[structopt(name = "extract")]
+    /// Copy selected pages of a PDF file into a new one
+    Extract(PdfExtractCommand),
+
+    #[structopt(name = "nup")]
+    /// Lay out the pages of a PDF file several to a sheet
+    Nup(PdfNupCommand),
+
+    #[structopt(name = "attach")]
+    /// Embed files in a PDF file as attachments
+    Attach(PdfAttachCommand),
+}
+
+impl PdfCommand {
+    pub fn execute(self, _config: PersistentConfig, status: &mut dyn StatusBackend) -> Result<i32> {
+        match self.command {
+            PdfCommands::Merge(c) => c.execute(status),
+            PdfCommands::Extract(c) => c.execute(status),
+            PdfCommands::Nup(c) => c.execute(status),
+            PdfCommands::Attach(c) => c.execute(status),
+        }
+    }
+}
+
+fn load(path: &Path) -> Result<Document> {
+    let data = ctry!(std::fs::read(path); "failed to read `{}`", path.display());
+    Ok(ctry!(Document::load(&data); "failed to load PDF file `{}`", path.display()))
+}
+
+fn save(doc: &Document, path: &Path, status: &mut dyn StatusBackend) -> Result<i32> {
+    let file = ctry!(File::create(path); "failed to create `{}`", path.display());
+    let mut dest = BufWriter::new(file);
+    ctry!(doc.save(&mut dest); "failed to write `{}`", path.display());
+    ctry!(dest.flush(); "failed to write `{}`", path.display());
+
+    let n_pages = doc.page_ids()?.len();
+    tt_note!(
+        status,
+        "wrote `{}` ({} page{})",
+        path.display(),
+        n_pages,
+        if n_pages == 1 { "" } else { "s" }
+    );
+    Ok(0)
+}
+
+#[derive(Debug, StructOpt)]
+struct PdfMergeCommand {
+    /// The file to create
+    #[structopt(short, long, parse(from_os_str))]
+    output: PathBuf,
+
+    /// The files to merge, in order
+    #[structopt(parse(from_os_str), required = true)]
+    inputs: Vec<PathBuf>,
+}
+
+impl PdfMergeCommand {
+    fn execute(self, status: &mut dyn StatusBackend) -> Result<i32> {
+        let docs = self
+            .inputs
+            .iter()
+            .map(|p| load(p))
+            .collect::<Result<Vec<_>>>()?;
+        let merged = tools::merge(&docs)?;
+        save(&merged, &self.output, status)
+    }
+}
+
+/// Parse a list of pages such as `3,1-2,5-`, keeping its order.
+fn parse_page_list(spec: &str, n_pages: usize) -> Result<Vec<usize>> {
+    let mut pages = Vec::new();
+
+    for item in spec.split(',') {
+        let item = item.trim();
+        let parse = |t: &str| -> Result<usize> {
+            match t.trim().parse::<usize>() {
+                Ok(n) if n > 0 => Ok(n),
+                _ => Err(errmsg!("invalid page number `{}`", t.trim())),
+            }
+        };
+
+        match item.split_once('-') {
+            Some((lo, hi)) => {
+                let lo = parse(lo)?;
+                let hi = if hi.trim().is_empty() {
+                    n_pages
+                } else {
+                    parse(hi)?
+                };
+
+                if lo <= hi {
+                    pages.extend(lo..=hi);
+                } else {
+                    pages.extend((hi..=lo).rev());
+                }
+            }
+
+            None => pages.push(parse(item)?),
+        }
+    }
+
+    Ok(pages)
+}
+
+#[derive(Debug, StructOpt)]
+struct PdfExtractCommand {
+    /// The pages to extract, in order, such as "3,1-2,5-". Pages may be repeated.
+    #[structopt(long)]
+    pages: String,
+
+    /// The file to create
+    #[structopt(short, long, parse(from_os_str))]
+    output: PathBuf,
+
+    /// The file to extract pages from
+    #[structopt(parse(from_os_str))]
+    input: PathBuf,
+}
+
+impl PdfExtractCommand {
+    fn execute(self, status: &mut dyn StatusBackend) -> Result<i32> {
+        let doc = load(&self.input)?;
+        let pages = parse_page_list(&self.pages, doc.page_ids()?.len())?;
+        let extracted = tools::extract(&doc, &pages)?;
+        save(&extracted, &self.output, status)
+    }
+}
+
+#[derive(Debug, StructOpt)]
+struct PdfNupCommand {
+    /// The arrangement of pages on each sheet, as COLUMNSxROWS
+    #[structopt(long, default_value = "2x1")]
+    grid: String,
+
+    /// The size of each sheet, such as "a4" or "842bp,595bp" [default: based on the first page]
+    #[structopt(long)]
+    paper: Option<String>,
+
+    /// The file to create
+    #[structopt(short, long, parse(from_os_str))]
+    output: PathBuf,
+
+    /// The file to rearrange
+    #[structopt(parse(from_os_str))]
+    input: PathBuf,
+}
+
+impl PdfNupCommand {
+    fn execute(self, status: &mut dyn StatusBackend) -> Result<i32> {
+        let grid = self
+            .grid
+            .split_once(['x', 'X'])
+            .and_then(|(c, r)| Some((c.trim().parse().ok()?, r.trim().parse().ok()?)))
+            .filter(|(c, r): &(usize, usize)| *c > 0 && *r > 0);
+
+        let Some((columns, rows)) = grid else {
+            return Err(errmsg!(
+                "invalid n-up grid `{}`; expected something like `2x2`",
+                self.grid
+            ));
+        };
+
+        let sheet_size = match self.paper {
+            Some(ref spec) => match tectonic_engine_xdv2svg::specials::parse_paper_spec(spec) {
+                Some(size) => Some(size),
+                None => return Err(errmsg!("unrecognized paper size `{}`", spec)),
+            },
+            None => None,
+        };
+
+        let doc = load(&self.input)?;
+        let layout = NupLayout {
+            columns,
+            rows,
+            sheet_size,
+        };
+        let result = tools::nup(&doc, &layout)?;
+        save(&result, &self.output, status)
+    }
+}
+
+#[derive(Debug, StructOpt)]
+struct PdfAttachCommand {
+    /// A description of the attached files
+    #[structopt(long)]
+    description: Option<String>,
+
+    /// The file to create
+    #[structopt(short, long, parse(from_os_str))]
+    output: PathBuf,
+
+    /// The PDF file to attach files to
+    #[structopt(parse(from_os_str))]
+    input: PathBuf,
+
+    /// The files to attach
+    #[structopt(parse(from_os_str), required = true)]
+    files: Vec<PathBuf>,
+}
+
+impl PdfAttachCommand {
+    fn execute(self, status: &mut dyn StatusBackend) -> Result<i32> {
+        let mut doc = load(&self.input)?;
+
+        for path in &self.files {
+            let data = ctry!(std::fs::read(path); "failed to read `{}`", path.display());
+            let Some(name) = path.file_name() else {
+                return Err(errmsg!("cannot attach `{}`: not a file", path.display()));
+            };
+            let name = name.to_string_lossy();
+            tools::attach_file(&mut doc, &name, &data, self.description.as_deref())?;
+        }
+
+        save(&doc, &self.output, status)
+    }
+}
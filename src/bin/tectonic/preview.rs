@@ -0,0 +1,558 @@
+//! Serving a document's build directory with live reloading, for
+//! `tectonic -X watch --serve`.
+//!
+//! Every HTML page that the server sends out gets a small script added to it.
+//! The script listens for server-sent events from [`EVENTS_PATH`]: a `reload`
+//! event after each successful rebuild, and a `build-error` event, carrying the
+//! error messages, after each failed one. The errors are shown in an overlay
+//! on top of the page. Outputs that aren't HTML, such as PDFs, are shown in a
+//! frame inside a generated HTML page so that the script can do its job.
+
+use hyper::{
+    body::Bytes,
+    header::{self, HeaderValue},
+    service::{make_service_fn, service_fn},
+    Body, Method, Request, Response, Server, StatusCode,
+};
+use std::{
+    convert::Infallible,
+    fmt::{Arguments, Write as _},
+    net::SocketAddr,
+    path::{Component, Path, PathBuf},
+    sync::{
+        mpsc::{self, Receiver},
+        Arc, Mutex,
+    },
+};
+use tectonic::{
+    ctry,
+    errors::Result,
+    status::{MessageKind, StatusBackend},
+    tt_error,
+};
+use tectonic_errors::Error;
+use tokio::{runtime, sync::broadcast};
+
+/// The path of the server-sent event stream.
+const EVENTS_PATH: &str = "/__tectonic/events";
+
+/// The path of the live reload script.
+const SCRIPT_PATH: &str = "/__tectonic/live.js";
+
+/// The live reload script.
+const SCRIPT: &str = r#"(function () {
+  var overlay = null;
+
+  function showError(message) {
+    if (overlay === null) {
+      overlay = document.createElement("pre");
+      overlay.style.cssText =
+        "position: fixed; inset: 0; z-index: 2147483647; margin: 0; padding: 2em; " +
+        "overflow: auto; background: rgba(32, 0, 0, 0.92); color: #fdd; " +
+        "font: 14px/1.4 monospace; white-space: pre-wrap;";
+      overlay.title = "Click to dismiss";
+      overlay.addEventListener("click", function () {
+        overlay.remove();
+        overlay = null;
+      });
+      document.body.appendChild(overlay);
+    }
+
+    overlay.textContent = "The document failed to build:\n\n" + message;
+  }
+
+  var source = new EventSource("/__tectonic/events");
+  source.addEventListener("reload", function () {
+    location.reload();
+  });
+  source.addEventListener("build-error", function (e) {
+    showError(JSON.parse(e.data));
+  });
+})();
+"#;
+
+/// The tag that loads the live reload script.
+const SCRIPT_TAG: &str = r#"<script src="/__tectonic/live.js"></script>"#;
+
+/// The outcome of a build, as announced to the browsers.
+#[derive(Clone, Debug)]
+enum BuildEvent {
+    Reload,
+    Failed(String),
+}
+
+impl BuildEvent {
+    fn to_sse(&self) -> Bytes {
+        match self {
+            BuildEvent::Reload => Bytes::from_static(b"event: reload\ndata:\n\n"),
+            BuildEvent::Failed(message) => {
+                let data = serde_json::to_string(message).unwrap();
+                format!("event: build-error\ndata: {data}\n\n").into()
+            }
+        }
+    }
+}
+
+/// The state shared between the server tasks and the watch loop.
+#[derive(Debug)]
+struct Shared {
+    build_dir: PathBuf,
+
+    /// The URL path of the document's main output, relative to the build
+    /// directory.
+    landing: String,
+
+    /// The errors from the most recent build, if it failed, so that pages
+    /// loaded afterwards can show them too.
+    current_error: Mutex<Option<String>>,
+
+    events: broadcast::Sender<BuildEvent>,
+}
+
+/// A server for previewing a document's outputs, which tells the browsers
+/// viewing them when they have been rebuilt.
+pub(crate) struct PreviewServer {
+    shared: Arc<Shared>,
+
+    /// The errors that stopped the server, to be reported by the watch loop.
+    failures: Receiver<Error>,
+
+    /// The runtime running the server. The server stops when it's dropped.
+    _rt: runtime::Runtime,
+}
+
+impl PreviewServer {
+    /// Start serving the files in `build_dir` on the given address.
+    ///
+    /// `main_file` is the main output file of the document, which is shown at
+    /// the root URL. The server runs in the background until this object is
+    /// dropped.
+    pub(crate) fn start(build_dir: &Path, main_file: &Path, addr: SocketAddr) -> Result<Self> {
+        let landing = main_file
+            .strip_prefix(build_dir)
+            .unwrap_or(main_file)
+            .components()
+            .filter_map(|c| match c {
+                Component::Normal(s) => Some(percent_encode(&s.to_string_lossy())),
+                _ => None,
+            })
+            .collect::<Vec<_>>()
+            .join("/");
+
+        let (events, _) = broadcast::channel(16);
+
+        let shared = Arc::new(Shared {
+            build_dir: build_dir.to_owned(),
+            landing,
+            current_error: Mutex::new(None),
+            events,
+        });
+
+        let rt = runtime::Builder::new_multi_thread().enable_all().build()?;
+
+        let server = {
+            let _guard = rt.enter();
+            ctry!(Server::try_bind(&addr); "failed to listen on {}", addr)
+        };
+
+        let service_shared = Arc::clone(&shared);
+        let (failure_tx, failures) = mpsc::channel();
+
+        rt.spawn(async move {
+            let make_service = make_service_fn(move |_| {
+                let shared = Arc::clone(&service_shared);
+
+                async move {
+                    Ok::<_, Infallible>(service_fn(move |req| {
+                        let shared = Arc::clone(&shared);
+                        async move { Ok::<_, Infallible>(shared.respond(req).await) }
+                    }))
+                }
+            });
+
+            if let Err(e) = server.serve(make_service).await {
+                let _ignored = failure_tx.send(e.into());
+            }
+        });
+
+        Ok(PreviewServer {
+            shared,
+            failures,
+            _rt: rt,
+        })
+    }
+
+    /// Get the URL of the document preview.
+    pub(crate) fn url(addr: &SocketAddr) -> String {
+        format!("http://{addr}/")
+    }
+
+    /// Report any errors that have stopped the server since the last call.
+    pub(crate) fn report_failures(&self, status: &mut dyn StatusBackend) {
+        for e in self.failures.try_iter() {
+            tt_error!(status, "the preview server failed"; e);
+        }
+    }
+
+    /// Tell the browsers to reload the document.
+    pub(crate) fn build_succeeded(&self) {
+        *self.shared.current_error.lock().unwrap() = None;
+        let _ignored = self.shared.events.send(BuildEvent::Reload);
+    }
+
+    /// Tell the browsers to show an error message.
+    pub(crate) fn build_failed(&self, message: String) {
+        *self.shared.current_error.lock().unwrap() = Some(message.clone());
+        let _ignored = self.shared.events.send(BuildEvent::Failed(message));
+    }
+}
+
+impl Shared {
+    async fn respond(self: Arc<Self>, req: Request<Body>) -> Response<Body> {
+        let is_head = match *req.method() {
+            Method::GET => false,
+            Method::HEAD => true,
+            _ => return simple_response(StatusCode::METHOD_NOT_ALLOWED),
+        };
+
+        let mut resp = match req.uri().path() {
+            EVENTS_PATH => self.respond_events(),
+
+            SCRIPT_PATH => content_response(SCRIPT.into(), "text/javascript; charset=utf-8"),
+
+            "/" if self.landing.ends_with(".html") => {
+                let mut resp = simple_response(StatusCode::FOUND);
+                resp.headers_mut()
+                    .insert(header::LOCATION, header_value(format!("/{}", self.landing)));
+                resp
+            }
+
+            "/" => content_response(self.frame_page().into(), "text/html; charset=utf-8"),
+
+            path => self.respond_file(path).await,
+        };
+
+        resp.headers_mut()
+            .insert(header::CACHE_CONTROL, HeaderValue::from_static("no-cache"));
+
+        if is_head {
+            *resp.body_mut() = Body::empty();
+        }
+
+        resp
+    }
+
+    /// Start a stream of server-sent events.
+    fn respond_events(&self) -> Response<Body> {
+        let mut receiver = self.events.subscribe();
+        let current = self
+            .current_error
+            .lock()
+            .unwrap()
+            .clone()
+            .map(BuildEvent::Failed);
+        let (mut sender, body) = Body::channel();
+
+        tokio::spawn(async move {
+            if let Some(event) = current {
+                if sender.send_data(event.to_sse()).await.is_err() {
+                    return;
+                }
+            }
+
+            loop {
+                let event = match receiver.recv().await {
+                    Ok(e) => e,
+                    Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                    Err(broadcast::error::RecvError::Closed) => return,
+                };
+
+                if sender.send_data(event.to_sse()).await.is_err() {
+                    return;
+                }
+            }
+        });
+
+        content_response(body, "text/event-stream")
+    }
+
+    /// Serve a file from the build directory.
+    async fn respond_file(&self, url_path: &str) -> Response<Body> {
+        let path = match resolve_path(&self.build_dir, url_path) {
+            Ok(p) => p,
+            Err(status) => return simple_response(status),
+        };
+
+        let data = match tokio::fs::read(&path).await {
+            Ok(d) => d,
+            Err(_) => return simple_response(StatusCode::NOT_FOUND),
+        };
+
+        let content_type = content_type(&path);
+
+        if content_type.starts_with("text/html") {
+            content_response(inject_script(data).into(), content_type)
+        } else {
+            content_response(data.into(), content_type)
+        }
+    }
+
+    /// Generate the page used to show outputs that aren't HTML.
+    fn frame_page(&self) -> String {
+        let src = html_escape(&format!("/{}", self.landing));
+
+        format!(
+            "<!DOCTYPE html>
+<html>
+<head>
+<meta charset=\"utf-8\">
+<title>{src}</title>
+<style>html, body, iframe {{ width: 100%; height: 100%; margin: 0; border: 0; }}</style>
+</head>
+<body>
+<iframe src=\"{src}\"></iframe>
+{SCRIPT_TAG}
+</body>
+</html>
+"
+        )
+    }
+}
+
+/// Map the path of a request to the file in the build directory that it
+/// refers to, or the status of the response if it doesn't refer to one.
+fn resolve_path(build_dir: &Path, url_path: &str) -> std::result::Result<PathBuf, StatusCode> {
+    let mut path = build_dir.to_owned();
+
+    for piece in url_path.split('/').filter(|p| !p.is_empty()) {
+        let piece = percent_decode(piece).ok_or(StatusCode::BAD_REQUEST)?;
+
+        // Don't let requests escape the build directory.
+        if piece == "." || piece == ".." || piece.contains(['/', '\\']) {
+            return Err(StatusCode::NOT_FOUND);
+        }
+
+        path.push(piece);
+    }
+
+    if path.is_dir() {
+        path.push("index.html");
+    }
+
+    Ok(path)
+}
+
+/// Add the live reload script to an HTML page, at the end of its body.
+fn inject_script(mut html: Vec<u8>) -> Vec<u8> {
+    let pos = html
+        .windows(7)
+        .rposition(|w| w.eq_ignore_ascii_case(b"</body>"))
+        .unwrap_or(html.len());
+
+    html.splice(pos..pos, SCRIPT_TAG.bytes());
+    html
+}
+
+fn content_type(path: &Path) -> &'static str {
+    let ext = path
+        .extension()
+        .map(|e| e.to_string_lossy().to_ascii_lowercase());
+
+    match ext.as_deref() {
+        Some("html" | "htm") => "text/html; charset=utf-8",
+        Some("css") => "text/css; charset=utf-8",
+        Some("js") => "text/javascript; charset=utf-8",
+        Some("json") => "application/json",
+        Some("svg") => "image/svg+xml",
+        Some("png") => "image/png",
+        Some("jpg" | "jpeg") => "image/jpeg",
+        Some("gif") => "image/gif",
+        Some("pdf") => "application/pdf",
+        Some("epub") => "application/epub+zip",
+        Some("otf") => "font/otf",
+        Some("ttf") => "font/ttf",
+        Some("woff") => "font/woff",
+        Some("woff2") => "font/woff2",
+        _ => "application/octet-stream",
+    }
+}
+
+/// Escape a piece of a URL path.
+fn percent_encode(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+
+    for b in text.bytes() {
+        if b.is_ascii_alphanumeric() || b"-._~".contains(&b) {
+            escaped.push(b as char);
+        } else {
+            let _ = write!(escaped, "%{b:02X}");
+        }
+    }
+
+    escaped
+}
+
+/// Decode the percent escapes in a piece of a URL path.
+fn percent_decode(text: &str) -> Option<String> {
+    let mut bytes = Vec::with_capacity(text.len());
+    let mut iter = text.bytes();
+
+    while let Some(b) = iter.next() {
+        if b == b'%' {
+            let hex = [iter.next()?, iter.next()?];
+
+            if !hex.iter().all(u8::is_ascii_hexdigit) {
+                return None;
+            }
+
+            let hex = std::str::from_utf8(&hex).ok()?;
+            bytes.push(u8::from_str_radix(hex, 16).ok()?);
+        } else {
+            bytes.push(b);
+        }
+    }
+
+    String::from_utf8(bytes).ok()
+}
+
+fn html_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn simple_response(status: StatusCode) -> Response<Body> {
+    let mut resp = Response::new(Body::empty());
+    *resp.status_mut() = status;
+    resp
+}
+
+fn content_response(body: Body, content_type: &'static str) -> Response<Body> {
+    let mut resp = Response::new(body);
+    resp.headers_mut()
+        .insert(header::CONTENT_TYPE, HeaderValue::from_static(content_type));
+    resp
+}
+
+fn header_value(text: String) -> HeaderValue {
+    HeaderValue::from_str(&text).expect("generated an invalid header value")
+}
+
+/// A status backend that passes everything along to another one, while
+/// keeping a copy of the error messages and logs, to be shown in the browser.
+pub(crate) struct ErrorCollectingStatusBackend<'a> {
+    inner: &'a mut dyn StatusBackend,
+    errors: String,
+}
+
+impl<'a> ErrorCollectingStatusBackend<'a> {
+    pub(crate) fn new(inner: &'a mut dyn StatusBackend) -> Self {
+        ErrorCollectingStatusBackend {
+            inner,
+            errors: String::new(),
+        }
+    }
+
+    /// Get the text of the errors that have been reported.
+    pub(crate) fn into_errors(self) -> String {
+        self.errors
+    }
+}
+
+impl StatusBackend for ErrorCollectingStatusBackend<'_> {
+    fn report(&mut self, kind: MessageKind, args: Arguments, err: Option<&Error>) {
+        if kind == MessageKind::Error {
+            let _ = writeln!(self.errors, "error: {args}");
+
+            if let Some(e) = err {
+                for item in e.chain() {
+                    let _ = writeln!(self.errors, "caused by: {item}");
+                }
+            }
+        }
+
+        self.inner.report(kind, args, err);
+    }
+
+    fn dump_error_logs(&mut self, output: &[u8]) {
+        self.errors.push_str(&String::from_utf8_lossy(output));
+        self.errors.push('\n');
+        self.inner.dump_error_logs(output);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decoding() {
+        assert_eq!(percent_decode("plain").as_deref(), Some("plain"));
+        assert_eq!(percent_decode("a%20b").as_deref(), Some("a b"));
+        assert_eq!(percent_decode("%E2%9c%93").as_deref(), Some("\u{2713}"));
+        assert_eq!(percent_decode("%2e%2E").as_deref(), Some(".."));
+        assert_eq!(percent_decode("%2"), None);
+        assert_eq!(percent_decode("%zz"), None);
+        assert_eq!(percent_decode("%+f"), None);
+        assert_eq!(percent_decode("%ff"), None);
+
+        let text = "caf\u{e9} & co/x";
+        assert_eq!(percent_decode(&percent_encode(text)).as_deref(), Some(text));
+    }
+
+    #[test]
+    fn paths() {
+        let build_dir = Path::new("/nonexistent/build");
+
+        assert_eq!(
+            resolve_path(build_dir, "/default/default.pdf"),
+            Ok(build_dir.join("default").join("default.pdf"))
+        );
+        assert_eq!(
+            resolve_path(build_dir, "/my%20doc.pdf"),
+            Ok(build_dir.join("my doc.pdf"))
+        );
+
+        for escape in [
+            "/../secret",
+            "/default/../../secret",
+            "/%2e%2e/secret",
+            "/./default.pdf",
+            "/..%2fsecret",
+            "/..%5csecret",
+        ] {
+            assert_eq!(
+                resolve_path(build_dir, escape),
+                Err(StatusCode::NOT_FOUND),
+                "{escape}"
+            );
+        }
+
+        assert_eq!(
+            resolve_path(build_dir, "/bad%zzescape"),
+            Err(StatusCode::BAD_REQUEST)
+        );
+    }
+
+    #[test]
+    fn injection() {
+        let html = b"<html><body><p>Hi</p></BODY></html>".to_vec();
+        assert_eq!(
+            String::from_utf8(inject_script(html)).unwrap(),
+            format!("<html><body><p>Hi</p>{SCRIPT_TAG}</BODY></html>")
+        );
+
+        // Only the last closing tag counts, and pages without one get the
+        // script at the end.
+        let html = b"<p>&lt;/body&gt; </body> x </body>".to_vec();
+        assert_eq!(
+            String::from_utf8(inject_script(html)).unwrap(),
+            format!("<p>&lt;/body&gt; </body> x {SCRIPT_TAG}</body>")
+        );
+        assert_eq!(
+            String::from_utf8(inject_script(b"<p>fragment</p>".to_vec())).unwrap(),
+            format!("<p>fragment</p>{SCRIPT_TAG}")
+        );
+    }
+}
@@ -0,0 +1,308 @@
+//! Serving bundles over HTTP, for `tectonic -X bundle serve`.
+//!
+//! The server exposes a bundle in the "indexed tar" format understood by
+//! `tectonic_bundles::itar::IndexedTarBackend`: the tar file itself, which must
+//! support HTTP Range requests, and its gzipped index, whose URL is the tar
+//! file's with `.index.gz` appended. There is also a redirect from a
+//! dot-free "bundle" URL to the tar file, like the one the default bundle URL
+//! uses, so that the backend's URL resolution logic gets exercised the same way.
+
+use hyper::{
+    header::{self, HeaderValue},
+    service::{make_service_fn, service_fn},
+    Body, Method, Request, Response, Server, StatusCode,
+};
+use std::{
+    convert::Infallible,
+    fs::File,
+    io::{Read, Seek, SeekFrom},
+    net::SocketAddr,
+    sync::{Arc, Mutex},
+};
+use tectonic::{
+    ctry,
+    errors::{Error, Result},
+};
+use tokio::{runtime, task};
+
+/// The path of the redirecting bundle URL.
+const BUNDLE_PATH: &str = "/bundle";
+
+/// The path of the tar file.
+const TAR_PATH: &str = "/bundle.tar";
+
+/// The path of the index file.
+const INDEX_PATH: &str = "/bundle.tar.index.gz";
+
+/// The size of the chunks used when sending the entire tar file.
+const CHUNK_SIZE: u64 = 1024 * 1024;
+
+/// An indexed tar bundle being served over HTTP.
+#[derive(Debug)]
+pub(crate) struct BundleServer {
+    tar: Mutex<File>,
+    tar_len: u64,
+    index: Vec<u8>,
+}
+
+impl BundleServer {
+    /// Create a new server for the given tar file and gzipped index data.
+    pub(crate) fn new(tar: File, index: Vec<u8>) -> Result<Self> {
+        let tar_len = tar.metadata()?.len();
+
+        Ok(BundleServer {
+            tar: Mutex::new(tar),
+            tar_len,
+            index,
+        })
+    }
+
+    /// Get the URL that Tectonic should be pointed at to use this server.
+    pub(crate) fn bundle_url(addr: &SocketAddr) -> String {
+        format!("http://{addr}{BUNDLE_PATH}")
+    }
+
+    /// Serve the bundle on the given address until the process is killed.
+    pub(crate) fn run(self, addr: SocketAddr) -> Result<()> {
+        let server = Arc::new(self);
+
+        let rt = runtime::Builder::new_multi_thread().enable_all().build()?;
+
+        rt.block_on(async move {
+            let make_service = make_service_fn(move |_| {
+                let server = Arc::clone(&server);
+
+                async move {
+                    Ok::<_, Infallible>(service_fn(move |req| {
+                        let server = Arc::clone(&server);
+                        async move { Ok::<_, Infallible>(server.respond(req).await) }
+                    }))
+                }
+            });
+
+            let builder = ctry!(Server::try_bind(&addr); "failed to listen on {}", addr);
+            ctry!(builder.serve(make_service).await; "error serving bundle");
+            Ok::<_, Error>(())
+        })
+    }
+
+    async fn respond(self: Arc<Self>, req: Request<Body>) -> Response<Body> {
+        let is_head = match *req.method() {
+            Method::GET => false,
+            Method::HEAD => true,
+            _ => return simple_response(StatusCode::METHOD_NOT_ALLOWED),
+        };
+
+        match req.uri().path() {
+            "/" | BUNDLE_PATH => {
+                // A relative redirect; the URL resolution of both of our
+                // HTTP backends handles this fine.
+                let mut resp = simple_response(StatusCode::FOUND);
+                resp.headers_mut()
+                    .insert(header::LOCATION, HeaderValue::from_static(TAR_PATH));
+                resp
+            }
+
+            INDEX_PATH => {
+                let mut resp = if is_head {
+                    Response::new(Body::empty())
+                } else {
+                    Response::new(Body::from(self.index.clone()))
+                };
+                resp.headers_mut()
+                    .insert(header::CONTENT_LENGTH, self.index.len().into());
+                resp
+            }
+
+            TAR_PATH => {
+                let range = req
+                    .headers()
+                    .get(header::RANGE)
+                    .and_then(|v| v.to_str().ok());
+
+                match range {
+                    Some(r) => self.respond_range(r, is_head).await,
+                    None => self.respond_all(is_head),
+                }
+            }
+
+            _ => simple_response(StatusCode::NOT_FOUND),
+        }
+    }
+
+    /// Respond to a request for a byte range of the tar file.
+    async fn respond_range(self: Arc<Self>, range: &str, is_head: bool) -> Response<Body> {
+        let (start, end) = match parse_byte_range(range, self.tar_len) {
+            Some(r) => r,
+            None => {
+                return with_content_range(
+                    simple_response(StatusCode::RANGE_NOT_SATISFIABLE),
+                    format!("bytes */{}", self.tar_len),
+                );
+            }
+        };
+
+        let body = if is_head {
+            Body::empty()
+        } else {
+            let server = Arc::clone(&self);
+
+            match task::spawn_blocking(move || server.read_range(start, end - start + 1)).await {
+                Ok(Ok(data)) => Body::from(data),
+                _ => return simple_response(StatusCode::INTERNAL_SERVER_ERROR),
+            }
+        };
+
+        let mut resp = Response::new(body);
+        *resp.status_mut() = StatusCode::PARTIAL_CONTENT;
+        resp.headers_mut()
+            .insert(header::CONTENT_LENGTH, (end - start + 1).into());
+        with_content_range(resp, format!("bytes {}-{}/{}", start, end, self.tar_len))
+    }
+
+    /// Respond to a request for the entire tar file, streaming it out.
+    fn respond_all(self: Arc<Self>, is_head: bool) -> Response<Body> {
+        let len = self.tar_len;
+
+        let body = if is_head {
+            Body::empty()
+        } else {
+            let (mut sender, body) = Body::channel();
+
+            tokio::spawn(async move {
+                let mut offset = 0;
+
+                while offset < len {
+                    let n = CHUNK_SIZE.min(len - offset);
+                    let server = Arc::clone(&self);
+
+                    let chunk =
+                        match task::spawn_blocking(move || server.read_range(offset, n)).await {
+                            Ok(Ok(c)) => c,
+                            _ => {
+                                sender.abort();
+                                return;
+                            }
+                        };
+
+                    if sender.send_data(chunk.into()).await.is_err() {
+                        return;
+                    }
+
+                    offset += n;
+                }
+            });
+
+            body
+        };
+
+        let mut resp = Response::new(body);
+        let headers = resp.headers_mut();
+        headers.insert(header::CONTENT_LENGTH, len.into());
+        headers.insert(header::ACCEPT_RANGES, HeaderValue::from_static("bytes"));
+        resp
+    }
+
+    fn read_range(&self, offset: u64, length: u64) -> std::io::Result<Vec<u8>> {
+        let mut tar = self.tar.lock().unwrap();
+        tar.seek(SeekFrom::Start(offset))?;
+        let mut buf = vec![0; length as usize];
+        tar.read_exact(&mut buf)?;
+        Ok(buf)
+    }
+}
+
+fn simple_response(status: StatusCode) -> Response<Body> {
+    let mut resp = Response::new(Body::empty());
+    *resp.status_mut() = status;
+    resp
+}
+
+/// Add a `Content-Range` header to a response. If the header value somehow
+/// isn't valid, the response becomes an internal server error instead.
+fn with_content_range(mut resp: Response<Body>, range: String) -> Response<Body> {
+    match HeaderValue::from_str(&range) {
+        Ok(v) => {
+            resp.headers_mut().insert(header::CONTENT_RANGE, v);
+            resp
+        }
+        Err(_) => simple_response(StatusCode::INTERNAL_SERVER_ERROR),
+    }
+}
+
+/// Parse an HTTP `Range` header value for a resource of the given length.
+///
+/// Only a single range is supported, which is all that the Tectonic backends
+/// ever ask for. The return value gives the first and last bytes of the range,
+/// inclusive, or None if the range is invalid, unsupported, or unsatisfiable.
+fn parse_byte_range(text: &str, len: u64) -> Option<(u64, u64)> {
+    let spec = text.trim().strip_prefix("bytes=")?;
+
+    if spec.contains(',') {
+        return None;
+    }
+
+    let (start, end) = spec.split_once('-')?;
+    let (start, end) = (start.trim(), end.trim());
+
+    let (start, end) = if start.is_empty() {
+        // A suffix range: the last N bytes.
+        let n: u64 = end.parse().ok()?;
+
+        if n == 0 {
+            return None;
+        }
+
+        (len.saturating_sub(n), len.checked_sub(1)?)
+    } else {
+        let start: u64 = start.parse().ok()?;
+        let end = if end.is_empty() {
+            len.checked_sub(1)?
+        } else {
+            end.parse::<u64>().ok()?.min(len.checked_sub(1)?)
+        };
+        (start, end)
+    };
+
+    if start > end {
+        return None;
+    }
+
+    Some((start, end))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn byte_ranges() {
+        assert_eq!(parse_byte_range("bytes=0-99", 1000), Some((0, 99)));
+        assert_eq!(parse_byte_range("bytes=900-2000", 1000), Some((900, 999)));
+
+        // Open-ended.
+        assert_eq!(parse_byte_range("bytes=500-", 1000), Some((500, 999)));
+
+        // Suffix.
+        assert_eq!(parse_byte_range("bytes=-100", 1000), Some((900, 999)));
+        assert_eq!(parse_byte_range("bytes=-2000", 1000), Some((0, 999)));
+        assert_eq!(parse_byte_range("bytes=-0", 1000), None);
+
+        // Out of range or unsupported.
+        assert_eq!(parse_byte_range("bytes=1000-", 1000), None);
+        assert_eq!(parse_byte_range("bytes=5-2", 1000), None);
+        assert_eq!(parse_byte_range("bytes=0-1,5-6", 1000), None);
+        assert_eq!(parse_byte_range("items=0-1", 1000), None);
+        assert_eq!(parse_byte_range("bytes=0-", 0), None);
+    }
+
+    #[test]
+    fn content_ranges() {
+        let resp = with_content_range(simple_response(StatusCode::OK), "bytes 0-9/10".into());
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(resp.headers()[header::CONTENT_RANGE], "bytes 0-9/10");
+
+        let resp = with_content_range(simple_response(StatusCode::OK), "bytes\n0-9".into());
+        assert_eq!(resp.status(), StatusCode::INTERNAL_SERVER_ERROR);
+    }
+}
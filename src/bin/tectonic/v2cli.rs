@@ -4,10 +4,7 @@
 //! The "v2cli" command-line interface -- a "multitool" interface resembling
 //! Cargo, as compared to the classic "rustc-like" CLI.
 
-use std::{
-    convert::Infallible, env, ffi::OsString, io::Write, path::PathBuf, process, str::FromStr,
-    sync::Arc,
-};
+use std::{env, ffi::OsString, io::Write, path::PathBuf, process, str::FromStr};
 use structopt::{clap::AppSettings, StructOpt};
 use tectonic::{
     self,
@@ -16,6 +13,7 @@ use tectonic::{
     docmodel::{DocumentExt, DocumentSetupOptions, WorkspaceCreatorExt},
     driver::PassSetting,
     errors::{Result, SyncError},
+    profile::ProfileFormat,
     status::{termcolor::TermcolorStatusBackend, ChatterLevel, StatusBackend},
     tt_error, tt_note,
 };
@@ -23,15 +21,31 @@ use tectonic_bridge_core::{SecuritySettings, SecurityStance};
 use tectonic_bundles::Bundle;
 use tectonic_docmodel::workspace::{Workspace, WorkspaceCreator};
 use tectonic_status_base::plain::PlainStatusBackend;
+
+use crate::interaction::TerminalErrorHandler;
+
+#[cfg(feature = "serve")]
+use crate::{
+    preview::{ErrorCollectingStatusBackend, PreviewServer},
+    serve::BundleServer,
+};
+#[cfg(feature = "serve")]
+use std::{collections::BTreeMap, convert::Infallible, fs::File, net::SocketAddr, sync::Arc};
+#[cfg(feature = "serve")]
+use tectonic::errmsg;
+#[cfg(feature = "serve")]
 use tokio::runtime;
-use watchexec::event::ProcessEnd;
+#[cfg(feature = "serve")]
 use watchexec::{
     action::{Action, Outcome, PreSpawn},
     command::{Command, Shell},
     config::InitConfig,
+    event::ProcessEnd,
     Watchexec,
 };
+#[cfg(feature = "serve")]
 use watchexec_filterer_globset::GlobsetFilterer;
+#[cfg(feature = "serve")]
 use watchexec_signals::Signal;
 
 /// The main options for the "V2" command-line interface.
@@ -175,10 +189,16 @@ enum Commands {
     #[structopt(name = "init")]
     Init(InitCommand),
 
+    #[cfg(feature = "serve")]
+    #[structopt(name = "pdf")]
+    /// Post-process PDF files: merge, extract pages, n-up, and attach files
+    Pdf(crate::pdf::PdfCommand),
+
     #[structopt(name = "show")]
     /// Display various useful pieces of information
     Show(ShowCommand),
 
+    #[cfg(feature = "serve")]
     #[structopt(name = "watch")]
     /// Watch input files and execute commands on change
     Watch(WatchCommand),
@@ -193,7 +213,10 @@ impl Commands {
             Commands::Dump(o) => o.customize(cc),
             Commands::New(o) => o.customize(cc),
             Commands::Init(o) => o.customize(cc),
+            #[cfg(feature = "serve")]
+            Commands::Pdf(_) => {}
             Commands::Show(o) => o.customize(cc),
+            #[cfg(feature = "serve")]
             Commands::Watch(o) => o.customize(cc),
         }
     }
@@ -206,7 +229,10 @@ impl Commands {
             Commands::Dump(o) => o.execute(config, status),
             Commands::New(o) => o.execute(config, status),
             Commands::Init(o) => o.execute(config, status),
+            #[cfg(feature = "serve")]
+            Commands::Pdf(o) => o.execute(config, status),
             Commands::Show(o) => o.execute(config, status),
+            #[cfg(feature = "serve")]
             Commands::Watch(o) => o.execute(config, status),
         }
     }
@@ -235,6 +261,10 @@ pub struct BuildCommand {
     #[structopt(long = "print", short)]
     print_stdout: bool,
 
+    /// Stop at TeX errors and ask how to proceed
+    #[structopt(long)]
+    interactive: bool,
+
     /// Open built document using system handler
     #[structopt(long)]
     open: bool,
@@ -242,6 +272,14 @@ pub struct BuildCommand {
     /// Specify a target to be used by the build
     #[structopt(long, help = "Specify the target of the build.")]
     target: Option<String>,
+
+    /// Write a report of the time and resources used by each engine pass to <profile_path>
+    #[structopt(long, name = "profile_path", parse(from_os_str))]
+    profile: Option<PathBuf>,
+
+    /// The format of the profiling report
+    #[structopt(long, default_value = "json", possible_values(&["json", "chrome"]))]
+    profile_format: ProfileFormat,
 }
 
 impl BuildCommand {
@@ -265,6 +303,8 @@ impl BuildCommand {
             DocumentSetupOptions::new_with_security(SecuritySettings::new(stance));
         setup_options.only_cached(self.only_cached);
 
+        let multiple_outputs = self.target.is_none() && doc.output_names().count() > 1;
+
         for output_name in doc.output_names() {
             if let Some(out) = self.target.as_ref() {
                 if out != output_name {
@@ -280,6 +320,27 @@ impl BuildCommand {
                 .keep_logs(self.keep_logs)
                 .print_stdout(self.print_stdout);
 
+            if self.interactive {
+                builder.error_handler(Box::new(TerminalErrorHandler::default()));
+            }
+
+            if let Some(mut path) = self.profile.clone() {
+                // Don't have each output overwrite the previous one's report.
+                if multiple_outputs {
+                    let mut name = path.file_stem().unwrap_or_default().to_owned();
+                    name.push(format!("-{output_name}"));
+
+                    if let Some(ext) = path.extension() {
+                        name.push(".");
+                        name.push(ext);
+                    }
+
+                    path.set_file_name(name);
+                }
+
+                builder.profile(path, self.profile_format);
+            }
+
             crate::compile::run_and_report(builder, status)?;
 
             if self.open {
@@ -318,23 +379,38 @@ enum BundleCommands {
     /// Dump the contents of a file in the bundle
     Cat(BundleCatCommand),
 
+    #[structopt(name = "fetch")]
+    /// Download files from the bundle into the local cache
+    Fetch(BundleFetchCommand),
+
     #[structopt(name = "search")]
     /// Filter the list of filenames contained in the bundle
     Search(BundleSearchCommand),
+
+    #[cfg(feature = "serve")]
+    #[structopt(name = "serve")]
+    /// Serve a bundle over HTTP, for use as a local mirror
+    Serve(BundleServeCommand),
 }
 
 impl BundleCommand {
     fn customize(&self, cc: &mut CommandCustomizations) {
         match &self.command {
             BundleCommands::Cat(c) => c.customize(cc),
+            BundleCommands::Fetch(c) => c.customize(cc),
             BundleCommands::Search(c) => c.customize(cc),
+            #[cfg(feature = "serve")]
+            BundleCommands::Serve(c) => c.customize(cc),
         }
     }
 
     fn execute(self, config: PersistentConfig, status: &mut dyn StatusBackend) -> Result<i32> {
         match self.command {
             BundleCommands::Cat(c) => c.execute(config, status),
+            BundleCommands::Fetch(c) => c.execute(config, status),
             BundleCommands::Search(c) => c.execute(config, status),
+            #[cfg(feature = "serve")]
+            BundleCommands::Serve(c) => c.execute(config, status),
         }
     }
 }
@@ -397,12 +473,74 @@ impl BundleCatCommand {
     }
 }
 
+#[derive(Debug, Eq, PartialEq, StructOpt)]
+struct BundleFetchCommand {
+    /// Read the names of the files to fetch from this file
+    #[structopt(long, parse(from_os_str))]
+    list: Vec<PathBuf>,
+
+    #[structopt(help = "The names of the files to fetch")]
+    files: Vec<String>,
+}
+
+impl BundleFetchCommand {
+    fn customize(&self, cc: &mut CommandCustomizations) {
+        cc.always_stderr = true;
+    }
+
+    fn execute(self, config: PersistentConfig, status: &mut dyn StatusBackend) -> Result<i32> {
+        let mut names = self.files;
+
+        for path in &self.list {
+            let text = ctry!(
+                std::fs::read_to_string(path);
+                "failed to read the file list `{}`", path.display()
+            );
+            names.extend(parse_file_list(&text));
+        }
+
+        if names.is_empty() {
+            return Err("no files to fetch were specified".into());
+        }
+
+        let mut bundle = get_a_bundle(config, false, status)?;
+        let n = bundle.prefetch(&names, status)?;
+        tt_note!(status, "downloaded {} uncached files", n);
+        Ok(0)
+    }
+}
+
+/// Parse a list of bundle files for `bundle fetch --list`.
+///
+/// Lists have one name per line, and only the first word of each line is used,
+/// so that a cache manifest from a previous build can be used directly.
+fn parse_file_list(text: &str) -> Vec<String> {
+    text.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .filter_map(|line| line.split_whitespace().next())
+        .map(str::to_owned)
+        .collect()
+}
+
 #[derive(Debug, Eq, PartialEq, StructOpt)]
 struct BundleSearchCommand {
     /// Use only resource files cached locally
     #[structopt(short = "C", long)]
     only_cached: bool,
 
+    /// Find the package files that define the command or environment named by the term
+    #[structopt(short = "d", long, conflicts_with = "package")]
+    defines: bool,
+
+    /// Find the package files that load the file named by the term
+    #[structopt(short = "p", long)]
+    package: bool,
+
+    /// Rebuild the cached definition index instead of reusing it
+    #[structopt(long)]
+    rebuild_index: bool,
+
     #[structopt(help = "The search term")]
     term: Option<String>,
 }
@@ -414,6 +552,11 @@ impl BundleSearchCommand {
 
     fn execute(self, config: PersistentConfig, status: &mut dyn StatusBackend) -> Result<i32> {
         let mut bundle = get_a_bundle(config, self.only_cached, status)?;
+
+        if self.defines || self.package {
+            return self.execute_indexed(&mut *bundle, status);
+        }
+
         let files = bundle.all_files(status)?;
 
         // Is there a better way to do this?
@@ -431,6 +574,131 @@ impl BundleSearchCommand {
 
         Ok(0)
     }
+
+    /// Search using the bundle's definition index, building it if needed.
+    fn execute_indexed(
+        self,
+        bundle: &mut dyn Bundle,
+        status: &mut dyn StatusBackend,
+    ) -> Result<i32> {
+        use tectonic_bundles::{cache::Cache, search::SearchIndex};
+
+        let term = self
+            .term
+            .ok_or("a search term is required with --defines or --package")?;
+        let cache = Cache::get_user_default()?;
+        let index = SearchIndex::load_or_build(bundle, &cache, self.rebuild_index, status)?;
+        let mut found_any = false;
+
+        if self.defines {
+            for def in index.find_definitions(&term, None) {
+                found_any = true;
+                println!("{def}");
+            }
+        } else {
+            for loader in index.find_loaders(&term) {
+                found_any = true;
+                println!("{loader}");
+            }
+        }
+
+        if !found_any {
+            tt_error!(status, "found no matches for `{}` in the bundle", term);
+            return Ok(1);
+        }
+
+        Ok(0)
+    }
+}
+
+#[cfg(feature = "serve")]
+#[derive(Debug, Eq, PartialEq, StructOpt)]
+struct BundleServeCommand {
+    /// Use only resource files cached locally
+    #[structopt(short = "C", long)]
+    only_cached: bool,
+
+    /// The network address and port to listen on
+    #[structopt(long, default_value = "127.0.0.1:8000")]
+    address: SocketAddr,
+
+    /// The bundle to serve: an indexed tar file, ZIP file, or directory
+    #[structopt(parse(from_os_str))]
+    path: Option<PathBuf>,
+}
+
+#[cfg(feature = "serve")]
+impl BundleServeCommand {
+    fn customize(&self, _cc: &mut CommandCustomizations) {}
+
+    fn execute(self, config: PersistentConfig, status: &mut dyn StatusBackend) -> Result<i32> {
+        use tectonic_bundles::{dir::DirBundle, itar::write_indexed_tar, zip::ZipBundle};
+
+        let mut bundle: Box<dyn Bundle> = match self.path {
+            Some(ref path) => {
+                let mut index_path = path.clone().into_os_string();
+                index_path.push(".index.gz");
+                let index_path = PathBuf::from(index_path);
+
+                if path.is_file() && index_path.is_file() {
+                    // Already in the right format; serve it directly.
+                    let tar = ctry!(
+                        File::open(path);
+                        "failed to open bundle file `{}`", path.display()
+                    );
+                    let index = ctry!(
+                        std::fs::read(&index_path);
+                        "failed to read bundle index `{}`", index_path.display()
+                    );
+                    return self.serve(BundleServer::new(tar, index)?, status);
+                } else if path.is_dir() {
+                    Box::new(DirBundle::new(path))
+                } else {
+                    Box::new(ZipBundle::open(path)?)
+                }
+            }
+
+            None => {
+                let mut bundle = get_a_bundle(config, self.only_cached, status)?;
+
+                // Fetch the whole bundle in bulk before converting it,
+                // rather than one file at a time as the conversion asks for
+                // them. The files are written into the cache in batches of
+                // a few megabytes as they arrive, so they don't all have to
+                // fit in memory.
+                if !self.only_cached {
+                    let names = bundle.all_files(status)?;
+                    bundle.prefetch(&names, status)?;
+                }
+
+                bundle
+            }
+        };
+
+        // Convert the bundle into the indexed tar format on the fly. The
+        // archive can be large, so it goes into a temporary file.
+        tt_note!(status, "converting the bundle into indexed tar format");
+        let mut tar = tempfile::tempfile()?;
+        let mut index = Vec::new();
+        write_indexed_tar(
+            &mut *bundle,
+            std::io::BufWriter::new(&mut tar),
+            &mut index,
+            status,
+        )?;
+
+        self.serve(BundleServer::new(tar, index)?, status)
+    }
+
+    fn serve(&self, server: BundleServer, status: &mut dyn StatusBackend) -> Result<i32> {
+        tt_note!(
+            status,
+            "serving the bundle at {}; press Ctrl-C to stop",
+            BundleServer::bundle_url(&self.address)
+        );
+        server.run(self.address)?;
+        Ok(0)
+    }
 }
 
 /// `dump`: Run a partial build and dump an intermediate file
@@ -531,17 +799,144 @@ impl DumpCommand {
     }
 }
 
+/// Choose the output to preview in `watch --serve`.
+///
+/// If the user didn't pick one, the document must have only one output, since
+/// there's no good reason to prefer any of the others.
+#[cfg(feature = "serve")]
+fn preview_target<'a>(output_names: &'a [String], target: Option<&str>) -> Result<&'a str> {
+    match target {
+        Some(t) => output_names
+            .iter()
+            .find(|n| *n == t)
+            .map(|n| n.as_str())
+            .ok_or_else(|| errmsg!("the document doesn't define an output named `{}`", t)),
+
+        None => match output_names {
+            [] => Err(errmsg!(
+                "the document doesn't define any outputs to preview"
+            )),
+            [only] => Ok(only),
+            _ => {
+                let mut names = output_names.to_vec();
+                names.sort();
+                Err(errmsg!(
+                    "the document defines several outputs ({}); choose one to preview with `--target`",
+                    names.join(", ")
+                ))
+            }
+        },
+    }
+}
+
 /// `watch`: Watch input files and execute commands on change
+#[cfg(feature = "serve")]
 #[derive(Debug, Eq, PartialEq, StructOpt)]
 pub struct WatchCommand {
-    /// Tectonic commands to execute on build [default: build]
+    /// Tectonic commands to execute on build, instead of rebuilding in-process
     #[structopt(long = "exec", short = "x")]
     execute: Vec<String>,
+
+    /// Use only resource files cached locally
+    #[structopt(short = "C", long)]
+    only_cached: bool,
+
+    /// Document is untrusted -- disable all known-insecure features
+    #[structopt(long)]
+    untrusted: bool,
+
+    /// Serve the build directory over HTTP, reloading browsers after each rebuild
+    #[structopt(long, conflicts_with = "execute")]
+    serve: bool,
+
+    /// The network address and port to serve on
+    #[structopt(long, default_value = "127.0.0.1:8000")]
+    address: SocketAddr,
+
+    /// The output to serve, if the document has more than one
+    #[structopt(long, requires = "serve")]
+    target: Option<String>,
 }
 
+#[cfg(feature = "serve")]
 impl WatchCommand {
     fn customize(&self, _cc: &mut CommandCustomizations) {}
 
+    /// Build the document in-process, then rebuild the outputs whose inputs
+    /// change.
+    fn execute_in_process(
+        self,
+        config: PersistentConfig,
+        status: &mut dyn StatusBackend,
+    ) -> Result<i32> {
+        let stance = if self.untrusted {
+            SecurityStance::DisableInsecures
+        } else {
+            SecurityStance::MaybeAllowInsecures
+        };
+
+        let mut setup_options =
+            DocumentSetupOptions::new_with_security(SecuritySettings::new(stance));
+        setup_options.only_cached(self.only_cached);
+
+        let mut watcher = crate::watch::DocumentWatcher::new(&config, setup_options)?;
+        let mut stale = watcher.output_names();
+
+        let server = if self.serve {
+            let doc = watcher.document();
+            let main_output = preview_target(&stale, self.target.as_deref())?;
+            let main_file = doc.output_main_file(main_output);
+            let server = PreviewServer::start(doc.build_dir(), &main_file, self.address)?;
+            tt_note!(
+                status,
+                "previewing `{}` at {}",
+                main_file.display(),
+                PreviewServer::url(&self.address)
+            );
+            Some(server)
+        } else {
+            None
+        };
+
+        // The errors from the latest build of each output that failed.
+        let mut errors = BTreeMap::new();
+
+        loop {
+            for output_name in &stale {
+                let mut collector = ErrorCollectingStatusBackend::new(status);
+
+                // Keep watching after errors, so that they can be fixed.
+                if let Err(e) = watcher.build(output_name, &mut collector) {
+                    collector.report_error(&SyncError::new(e).into());
+                    errors.insert(output_name.clone(), collector.into_errors());
+                } else {
+                    errors.remove(output_name);
+                }
+            }
+
+            if let Some(ref server) = server {
+                server.report_failures(status);
+
+                if errors.is_empty() {
+                    server.build_succeeded();
+                } else {
+                    server.build_failed(errors.values().cloned().collect::<Vec<_>>().join("\n"));
+                }
+            }
+
+            tt_note!(
+                status,
+                "watching {} input files for changes",
+                watcher.n_watched_files()
+            );
+            stale = watcher.wait_for_changes(status, &mut |status| {
+                if let Some(ref server) = server {
+                    server.report_failures(status);
+                }
+            })?;
+        }
+    }
+
     async fn execute_inner(self, status: &mut dyn StatusBackend) -> Result<i32> {
         let exe_name = crate::watch::get_trimmed_exe_name()
             .into_os_string()
@@ -654,7 +1049,11 @@ impl WatchCommand {
         }
     }
 
-    fn execute(self, _config: PersistentConfig, status: &mut dyn StatusBackend) -> Result<i32> {
+    fn execute(self, config: PersistentConfig, status: &mut dyn StatusBackend) -> Result<i32> {
+        if self.execute.is_empty() {
+            return self.execute_in_process(config, status);
+        }
+
         let rt = runtime::Builder::new_multi_thread()
             .enable_all()
             .build()
@@ -757,3 +1156,27 @@ impl ShowUserCacheDirCommand {
         Ok(0)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn file_lists() {
+        let manifest = "# a comment\nlatex.ltx 12345 0123abcd\n\narticle.cls 678 4567ef\n";
+        assert_eq!(parse_file_list(manifest), ["latex.ltx", "article.cls"]);
+    }
+
+    #[cfg(feature = "serve")]
+    #[test]
+    fn preview_targets() {
+        let one = ["default".to_owned()];
+        let two = ["html".to_owned(), "pdf".to_owned()];
+
+        assert_eq!(preview_target(&one, None).unwrap(), "default");
+        assert_eq!(preview_target(&two, Some("pdf")).unwrap(), "pdf");
+        assert!(preview_target(&two, None).is_err());
+        assert!(preview_target(&two, Some("epub")).is_err());
+        assert!(preview_target(&[], None).is_err());
+    }
+}
@@ -1,5 +1,21 @@
-use std::env;
-use std::path::PathBuf;
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use std::{
+    collections::{HashMap, HashSet},
+    env, fs,
+    path::{Path, PathBuf},
+    sync::mpsc::{self, Receiver, RecvTimeoutError},
+    time::Duration,
+};
+use tectonic::{
+    config::PersistentConfig,
+    ctry,
+    docmodel::{DocumentExt, DocumentSetupOptions},
+    errmsg,
+    errors::Result,
+    status::StatusBackend,
+    tt_error, tt_warning,
+};
+use tectonic_docmodel::{document::Document, workspace::Workspace};
 
 /// Obtain the executable name without a prefix if the executable is available in the PATH, e.g.
 /// most cases. Otherwise, use the full path e.g. in development.
@@ -16,3 +32,325 @@ pub(crate) fn get_trimmed_exe_name() -> PathBuf {
     }
     exe_name
 }
+
+/// How long the inputs have to be left alone before we rebuild, so that a
+/// burst of changes only causes one build.
+const DEBOUNCE: Duration = Duration::from_millis(300);
+
+/// How often [`DocumentWatcher::wait_for_changes`] checks in with its caller
+/// while nothing is changing.
+const IDLE_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Builds the outputs of a document, and rebuilds them when the files that
+/// they were built from change.
+///
+/// Rather than watching the whole document directory, we watch exactly the
+/// filesystem inputs that the engines used when each output was last built,
+/// as reported by [`ProcessingSession::input_paths`], as well as
+/// `Tectonic.toml`. This means that unrelated changes don't cause rebuilds,
+/// and that inputs outside the document tree, such as files in extra search
+/// paths, are noticed.
+pub(crate) struct DocumentWatcher {
+    ws: Workspace,
+    setup_options: DocumentSetupOptions,
+    format_cache_path: PathBuf,
+    toml_path: PathBuf,
+
+    /// The inputs of each output, as of the last time it was built.
+    inputs: HashMap<String, HashSet<PathBuf>>,
+
+    watcher: RecommendedWatcher,
+    events: Receiver<notify::Result<Event>>,
+
+    /// The directories being watched. We watch the directories containing the
+    /// inputs, rather than the inputs themselves, so that we notice files
+    /// that don't exist yet and files that editors replace by renaming.
+    watched_dirs: HashSet<PathBuf>,
+}
+
+impl DocumentWatcher {
+    /// Set up to watch the document in the current workspace.
+    pub(crate) fn new(
+        config: &PersistentConfig,
+        setup_options: DocumentSetupOptions,
+    ) -> Result<Self> {
+        let ws = Workspace::open_from_environment()?;
+        let toml_path = canonical(&ws.first_document().src_dir().join("Tectonic.toml"));
+        let (tx, events) = mpsc::channel();
+        let watcher = ctry!(notify::recommended_watcher(tx); "failed to set up file watching");
+
+        let mut w = DocumentWatcher {
+            ws,
+            setup_options,
+            format_cache_path: config.format_cache_path()?,
+            toml_path,
+            inputs: HashMap::new(),
+            watcher,
+            events,
+            watched_dirs: HashSet::new(),
+        };
+
+        w.update_watches();
+        Ok(w)
+    }
+
+    /// Get the document being watched.
+    pub(crate) fn document(&self) -> &Document {
+        self.ws.first_document()
+    }
+
+    /// Get the names of the document's outputs.
+    pub(crate) fn output_names(&self) -> Vec<String> {
+        self.document()
+            .output_names()
+            .map(|s| s.to_owned())
+            .collect()
+    }
+
+    /// Build one of the document's outputs, and start watching its inputs.
+    ///
+    /// Even if the build fails, we keep track of the inputs that it got around
+    /// to reading, so that fixing them will trigger a rebuild.
+    pub(crate) fn build(
+        &mut self,
+        output_name: &str,
+        status: &mut dyn StatusBackend,
+    ) -> Result<()> {
+        let result = (|| {
+            let mut builder =
+                self.ws
+                    .first_document()
+                    .setup_session(output_name, &self.setup_options, status)?;
+            builder.format_cache_path(&self.format_cache_path);
+
+            let mut sess = builder.create(status)?;
+            let result = crate::compile::run_session_and_report(&mut sess, status);
+            let inputs = sess.input_paths().iter().map(|p| canonical(p)).collect();
+            self.inputs.insert(output_name.to_owned(), inputs);
+            result
+        })();
+
+        self.update_watches();
+        result
+    }
+
+    /// Wait until some inputs have changed, and return the names of the
+    /// outputs that need to be rebuilt.
+    ///
+    /// If `Tectonic.toml` changes, the document is reloaded and all of its
+    /// outputs need rebuilding. While waiting, `on_idle` is called every so
+    /// often, so that the caller can report on anything else that it's
+    /// looking after.
+    pub(crate) fn wait_for_changes(
+        &mut self,
+        status: &mut dyn StatusBackend,
+        on_idle: &mut dyn FnMut(&mut dyn StatusBackend),
+    ) -> Result<Vec<String>> {
+        loop {
+            let mut changed = HashSet::new();
+            let mut event = match self.events.recv_timeout(IDLE_INTERVAL) {
+                Ok(e) => Some(e),
+                Err(RecvTimeoutError::Timeout) => {
+                    on_idle(status);
+                    continue;
+                }
+                Err(RecvTimeoutError::Disconnected) => {
+                    return Err(errmsg!("file watching stopped unexpectedly"))
+                }
+            };
+
+            while let Some(e) = event {
+                match e {
+                    Ok(e) => {
+                        if !matches!(e.kind, EventKind::Access(_)) {
+                            changed.extend(e.paths.iter().map(|p| canonical(p)));
+                        }
+                    }
+
+                    Err(e) => tt_warning!(status, "error while watching for changes"; e.into()),
+                }
+
+                event = match self.events.recv_timeout(DEBOUNCE) {
+                    Ok(e) => Some(e),
+                    Err(RecvTimeoutError::Timeout) => None,
+                    Err(RecvTimeoutError::Disconnected) => break,
+                };
+            }
+
+            if changed.is_empty() {
+                continue;
+            }
+
+            if changed.contains(&self.toml_path) {
+                status.note_highlighted("Reloading ", "Tectonic.toml", " ...");
+
+                match Workspace::open_from_environment() {
+                    Ok(ws) => self.ws = ws,
+                    Err(e) => {
+                        tt_error!(status, "failed to reload the document"; e);
+                        continue;
+                    }
+                }
+
+                self.inputs.clear();
+                self.update_watches();
+                return Ok(self.output_names());
+            }
+
+            let stale = stale_outputs(self.output_names(), &self.inputs, &changed);
+
+            for (name, cause) in &stale {
+                status.note_highlighted(
+                    "Rebuilding ",
+                    name,
+                    &format!(" because `{}` changed", cause.display()),
+                );
+            }
+
+            if !stale.is_empty() {
+                return Ok(stale.into_iter().map(|(name, _)| name).collect());
+            }
+        }
+    }
+
+    /// Make sure that we're watching the directories of all of the inputs,
+    /// and only those.
+    fn update_watches(&mut self) {
+        let mut wanted = HashSet::new();
+
+        for path in self.inputs.values().flatten().chain(Some(&self.toml_path)) {
+            if let Some(dir) = path.parent() {
+                if dir.is_dir() {
+                    wanted.insert(dir.to_owned());
+                }
+            }
+        }
+
+        for dir in self.watched_dirs.difference(&wanted) {
+            let _ignored = self.watcher.unwatch(dir);
+        }
+
+        self.watched_dirs.retain(|d| wanted.contains(d));
+
+        for dir in wanted {
+            if !self.watched_dirs.contains(&dir)
+                && self
+                    .watcher
+                    .watch(&dir, RecursiveMode::NonRecursive)
+                    .is_ok()
+            {
+                self.watched_dirs.insert(dir);
+            }
+        }
+    }
+
+    /// Get the number of files being watched.
+    pub(crate) fn n_watched_files(&self) -> usize {
+        self.inputs.values().flatten().collect::<HashSet<_>>().len()
+    }
+}
+
+/// Find the outputs that need rebuilding because some of their inputs have
+/// changed, along with one of the changed inputs of each.
+///
+/// The paths should have been passed through [`canonical`].
+fn stale_outputs(
+    output_names: Vec<String>,
+    inputs: &HashMap<String, HashSet<PathBuf>>,
+    changed: &HashSet<PathBuf>,
+) -> Vec<(String, PathBuf)> {
+    output_names
+        .into_iter()
+        .filter_map(|name| {
+            let cause = inputs
+                .get(&name)
+                .and_then(|inputs| changed.iter().find(|p| inputs.contains(*p)))?
+                .clone();
+            Some((name, cause))
+        })
+        .collect()
+}
+
+/// Canonicalize a path, so that the inputs recorded by the engines can be
+/// compared with the paths of the change events even if they're relative or
+/// reached through symbolic links.
+///
+/// Files that don't exist, such as ones that have just been deleted, can't be
+/// canonicalized themselves, so we canonicalize their directories instead.
+fn canonical(path: &Path) -> PathBuf {
+    if let Ok(p) = fs::canonicalize(path) {
+        return p;
+    }
+
+    let path = match env::current_dir() {
+        Ok(cwd) => cwd.join(path),
+        Err(_) => path.to_owned(),
+    };
+
+    match (path.parent(), path.file_name()) {
+        (Some(dir), Some(name)) => match fs::canonicalize(dir) {
+            Ok(dir) => dir.join(name),
+            Err(_) => path,
+        },
+        _ => path,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stale() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        fs::create_dir(root.join("src")).unwrap();
+        fs::write(root.join("src/index.tex"), "").unwrap();
+        fs::write(root.join("src/other.tex"), "").unwrap();
+
+        // The engine reports the input with a roundabout path ...
+        let inputs = HashMap::from([
+            (
+                "default".to_owned(),
+                HashSet::from([canonical(&root.join("src/../src/index.tex"))]),
+            ),
+            (
+                "other".to_owned(),
+                HashSet::from([canonical(&root.join("src/other.tex"))]),
+            ),
+        ]);
+
+        // ... but the change event has the direct one.
+        let changed = HashSet::from([canonical(&root.join("src/index.tex"))]);
+        let names = vec!["default".to_owned(), "other".to_owned()];
+        let stale = stale_outputs(names.clone(), &inputs, &changed);
+        assert_eq!(stale.len(), 1);
+        assert_eq!(stale[0].0, "default");
+
+        // Deleted files are still matched.
+        fs::remove_file(root.join("src/other.tex")).unwrap();
+        let changed = HashSet::from([canonical(&root.join("src/other.tex"))]);
+        let stale = stale_outputs(names.clone(), &inputs, &changed);
+        assert_eq!(stale.len(), 1);
+        assert_eq!(stale[0].0, "other");
+
+        assert!(stale_outputs(names, &inputs, &HashSet::new()).is_empty());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn stale_through_symlink() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        fs::write(root.join("real.tex"), "").unwrap();
+        std::os::unix::fs::symlink(root.join("real.tex"), root.join("link.tex")).unwrap();
+
+        let inputs = HashMap::from([(
+            "default".to_owned(),
+            HashSet::from([canonical(&root.join("link.tex"))]),
+        )]);
+        let changed = HashSet::from([canonical(&root.join("real.tex"))]);
+        let stale = stale_outputs(vec!["default".to_owned()], &inputs, &changed);
+        assert_eq!(stale.len(), 1);
+    }
+}
@@ -17,7 +17,7 @@ use tectonic_bundles::{
     cache::Cache, dir::DirBundle, itar::IndexedTarBackend, zip::ZipBundle, Bundle,
 };
 use tectonic_docmodel::{
-    document::{BuildTargetType, Document},
+    document::{BuildTargetType, Document, EncryptionSettings, PdfAConformance, PdfPermission},
     workspace::{Workspace, WorkspaceCreator},
 };
 use tectonic_geturl::{DefaultBackend, GetUrlBackend};
@@ -25,7 +25,7 @@ use url::Url;
 
 use crate::{
     config, ctry,
-    driver::{OutputFormat, PassSetting, ProcessingSessionBuilder},
+    driver::{DocumentMetadata, OutputFormat, PassSetting, ProcessingSessionBuilder},
     errors::{ErrorKind, Result},
     status::StatusBackend,
     test_util, tt_note,
@@ -147,6 +147,7 @@ impl DocumentExt for Document {
         })?;
 
         let output_format = match profile.target_type {
+            BuildTargetType::Epub => OutputFormat::Epub,
             BuildTargetType::Html => OutputFormat::Html,
             BuildTargetType::Pdf => OutputFormat::Pdf,
         };
@@ -175,7 +176,35 @@ impl DocumentExt for Document {
             })
             .pass(PassSetting::Default)
             .primary_input_buffer(input_buffer.as_bytes())
-            .tex_input_name(output_profile);
+            .tex_input_name(output_profile)
+            .html_compat_layer(profile.html_compat)
+            .pdf_tagging(profile.tagged_pdf)
+            .metadata(DocumentMetadata {
+                title: profile.metadata.title.clone(),
+                author: profile.metadata.author.clone(),
+                subject: profile.metadata.subject.clone(),
+                keywords: profile.metadata.keywords.clone(),
+                language: profile.metadata.language.clone(),
+                custom: profile.metadata.custom.clone(),
+            });
+
+        if let Some(path) = &profile.asset_manifest {
+            sess_builder.html_asset_manifest(path);
+        }
+
+        match profile.pdfa {
+            Some(PdfAConformance::A2b) => {
+                sess_builder.pdfa_conformance(crate::PdfAConformance::A2b);
+            }
+            Some(PdfAConformance::A3b) => {
+                sess_builder.pdfa_conformance(crate::PdfAConformance::A3b);
+            }
+            None => {}
+        }
+
+        if let Some(enc) = &profile.encryption {
+            sess_builder.pdf_encryption(pdf_encryption_from_settings(enc)?);
+        }
 
         if profile.shell_escape {
             // For now, this is the only option we allow.
@@ -207,6 +236,43 @@ impl DocumentExt for Document {
     }
 }
 
+/// Build the engine's encryption settings from a document's, reading the
+/// passwords from the environment.
+fn pdf_encryption_from_settings(settings: &EncryptionSettings) -> Result<crate::PdfEncryption> {
+    let read_password = |var: &str| -> Result<String> {
+        Ok(ctry!(
+            std::env::var(var);
+            "couldn't read the PDF password from the environment variable `{}`", var
+        ))
+    };
+
+    let mut enc = crate::PdfEncryption::new(read_password(&settings.owner_password_env)?);
+
+    if let Some(var) = &settings.user_password_env {
+        enc.user_password = read_password(var)?;
+    }
+
+    enc.key_bits = settings.key_bits;
+    enc.permissions = crate::PdfPermissions::none();
+
+    for perm in &settings.permissions {
+        let p = &mut enc.permissions;
+
+        match perm {
+            PdfPermission::Print => p.print = true,
+            PdfPermission::PrintHighQuality => p.print_high_quality = true,
+            PdfPermission::Modify => p.modify = true,
+            PdfPermission::Copy => p.copy = true,
+            PdfPermission::Accessibility => p.accessibility = true,
+            PdfPermission::Annotate => p.annotate = true,
+            PdfPermission::FillForms => p.fill_forms = true,
+            PdfPermission::Assemble => p.assemble = true,
+        }
+    }
+
+    Ok(enc)
+}
+
 /// Extension methods for [`WorkspaceCreator`].
 pub trait WorkspaceCreatorExt {
     /// Create the new workspace with a good default for the bundle location.
@@ -18,7 +18,8 @@
 use byte_unit::Byte;
 use quick_xml::{events::Event, NsReader};
 use std::{
-    collections::{HashMap, HashSet},
+    cell::Cell,
+    collections::{BTreeMap, HashMap, HashSet},
     fs::File,
     io::{Cursor, Read, Write},
     path::{Path, PathBuf},
@@ -26,11 +27,12 @@ use std::{
     rc::Rc,
     result::Result as StdResult,
     str::FromStr,
-    time::{Duration, SystemTime},
+    time::{Duration, Instant, SystemTime},
 };
 use tectonic_bridge_core::{CoreBridgeLauncher, DriverHooks, SecuritySettings, SystemRequestError};
 use tectonic_bundles::Bundle;
-use tectonic_engine_spx2html::AssetSpecification;
+use tectonic_engine_spx2html::{compat::CompatLayerIo, AssetSpecification};
+use tectonic_engine_xdv2png::PageSelection;
 use tectonic_io_base::{
     digest::DigestData,
     filesystem::{FilesystemIo, FilesystemPrimaryInputIo},
@@ -40,18 +42,22 @@ use tectonic_io_base::{
 
 use crate::{
     ctry, errmsg,
-    errors::{ChainErrCompatExt, ErrorKind, Result},
+    errors::{ChainErrCompatExt, ErrorKind, Result, SyncError},
     io::{
         format_cache::FormatCache,
         memory::{MemoryFileCollection, MemoryIo},
         InputOrigin,
     },
+    profile::{InputBytes, MemoryStats, PassProfile, ProfileFormat, Profiler},
     status::StatusBackend,
     tt_error, tt_note, tt_warning,
     unstable_opts::UnstableOptions,
-    BibtexEngine, Spx2HtmlEngine, TexEngine, TexOutcome, XdvipdfmxEngine,
+    BibtexEngine, PdfAConformance, PdfEncryption, PdfMetadata, Spx2HtmlEngine, TexEngine,
+    TexOutcome, Xdv2PngEngine, Xdv2SvgEngine, XdvipdfmxEngine,
 };
 
+pub use tectonic_bridge_core::ErrorResponse;
+
 /// Different patterns with which files may have been accessed by the
 /// underlying engines. Once a file is marked as ReadThenWritten or
 /// WrittenThenRead, its pattern does not evolve further.
@@ -87,6 +93,10 @@ struct FileSummary {
     /// If this file was read, where did it come from?
     pub input_origin: InputOrigin,
 
+    /// If this file was read from the filesystem, this is its absolute path,
+    /// if known.
+    pub input_path: Option<PathBuf>,
+
     /// If this file was read, this is the digest of its contents at the time it was *first* read.
     /// The "first" is significant for files that were read and then written (for example, `.aux`
     /// files).
@@ -108,6 +118,7 @@ impl FileSummary {
         FileSummary {
             access_pattern,
             input_origin,
+            input_path: None,
             read_digest: None,
             write_digest: None,
             got_written_to_disk: false,
@@ -129,6 +140,12 @@ pub enum OutputFormat {
     Pdf,
     /// A '.fmt' file, for initializing the TeX engine.
     Format,
+    /// A series of '.svg' files, one per page.
+    Svg,
+    /// A series of '.png' files, one per page.
+    Png,
+    /// An '.epub' file.
+    Epub,
 }
 
 impl FromStr for OutputFormat {
@@ -141,11 +158,66 @@ impl FromStr for OutputFormat {
             "xdv" => Ok(OutputFormat::Xdv),
             "pdf" => Ok(OutputFormat::Pdf),
             "fmt" => Ok(OutputFormat::Format),
+            "svg" => Ok(OutputFormat::Svg),
+            "png" => Ok(OutputFormat::Png),
+            "epub" => Ok(OutputFormat::Epub),
             _ => Err("unsupported or unknown format"),
         }
     }
 }
 
+/// Metadata describing a document, supplied from outside of its TeX source.
+///
+/// In PDF output, these are written into the document information dictionary
+/// and an XMP metadata stream, taking precedence over any values that the
+/// document sets itself. In HTML output, they're available to templates as the
+/// `tduxMetadata` variable, an object with a field for each item below.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct DocumentMetadata {
+    /// The document title.
+    pub title: Option<String>,
+
+    /// The name of the document's author.
+    pub author: Option<String>,
+
+    /// The subject of the document.
+    pub subject: Option<String>,
+
+    /// Keywords associated with the document.
+    pub keywords: Vec<String>,
+
+    /// The language of the document, as a language tag such as `en-US`.
+    pub language: Option<String>,
+
+    /// Custom properties. See [`PdfMetadata::custom`] for the restrictions on
+    /// their names.
+    pub custom: BTreeMap<String, String>,
+}
+
+impl DocumentMetadata {
+    fn to_pdf(&self) -> PdfMetadata {
+        PdfMetadata {
+            title: self.title.clone(),
+            author: self.author.clone(),
+            subject: self.subject.clone(),
+            keywords: self.keywords.clone(),
+            language: self.language.clone(),
+            custom: self.custom.clone(),
+        }
+    }
+
+    fn to_template_value(&self) -> serde_json::Value {
+        serde_json::json!({
+            "title": self.title,
+            "author": self.author,
+            "subject": self.subject,
+            "keywords": self.keywords,
+            "language": self.language,
+            "custom": self.custom,
+        })
+    }
+}
+
 /// The different types of "passes" that [`ProcessingSession`] knows how to run. See
 /// [`ProcessingSession::run`] for more details.
 #[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
@@ -203,6 +275,9 @@ enum OutputDestination {
     Nowhere,
 }
 
+/// The origin of an input file, and a running count of the bytes read from it.
+type InputByteCount = (InputOrigin, Rc<Cell<u64>>);
+
 /// The subset of the driver state that is captured when running a C/C++ engine.
 ///
 /// The main purpose of this type is to implement the [`DriverHooks`] trait,
@@ -254,6 +329,17 @@ struct BridgeState {
 
     /// The I/O events that occurred while processing.
     events: HashMap<String, FileSummary>,
+
+    /// The origins of the files opened for input during the current pass, and
+    /// counts of the bytes read from them. This is only tracked if we're
+    /// profiling.
+    input_bytes: Option<HashMap<String, InputByteCount>>,
+
+    /// Library users' observers of the session's progress.
+    observers: Vec<Box<dyn SessionObserver>>,
+
+    /// How to respond to TeX errors, if the user is being asked.
+    error_handler: Option<Box<dyn ErrorHandler>>,
 }
 
 impl BridgeState {
@@ -271,6 +357,24 @@ impl BridgeState {
         self.format_primary = None;
     }
 
+    /// Tell any observers about something that has happened.
+    fn notify(&mut self, mut event: impl FnMut(&mut dyn SessionObserver)) {
+        for o in &mut self.observers {
+            event(o.as_mut());
+        }
+    }
+
+    /// Start counting the bytes read from a newly opened input file, if we're
+    /// keeping track.
+    fn count_input_bytes(&mut self, name: &str, ih: &mut InputHandle) {
+        if let Some(ref mut counts) = self.input_bytes {
+            let (_origin, counter) = counts
+                .entry(name.to_owned())
+                .or_insert_with(|| (ih.origin(), Rc::new(Cell::new(0))));
+            ih.count_reads(counter.clone());
+        }
+    }
+
     /// Invoke an external tool as a pass in the processing pipeline.
     fn external_tool_pass(
         &mut self,
@@ -500,6 +604,8 @@ impl IoProvider for BridgeState {
                     FileSummary::new(AccessPattern::Written, InputOrigin::NotInput),
                 );
             }
+
+            self.notify(|o| o.output_opened(name));
         }
 
         r
@@ -525,6 +631,8 @@ impl IoProvider for BridgeState {
                     FileSummary::new(AccessPattern::Written, InputOrigin::NotInput),
                 );
             }
+
+            self.notify(|o| o.output_opened(""));
         }
 
         r
@@ -547,12 +655,16 @@ impl IoProvider for BridgeState {
         name: &str,
         status: &mut dyn StatusBackend,
     ) -> OpenResult<(InputHandle, Option<PathBuf>)> {
-        let r = (|| {
+        let mut r = (|| {
             bridgestate_ioprovider_cascade!(self, input_open_name_with_abspath(name, status));
         })();
 
+        if let OpenResult::Ok((ref mut ih, _)) = r {
+            self.count_input_bytes(name, ih);
+        }
+
         match r {
-            OpenResult::Ok((ref ih, ref _path)) => {
+            OpenResult::Ok((ref ih, ref path)) => {
                 if let Some(summ) = self.events.get_mut(name) {
                     summ.access_pattern = match summ.access_pattern {
                         AccessPattern::Written => AccessPattern::WrittenThenRead,
@@ -564,6 +676,17 @@ impl IoProvider for BridgeState {
                         FileSummary::new(AccessPattern::Read, ih.origin()),
                     );
                 }
+
+                if ih.origin() == InputOrigin::Filesystem {
+                    let summ = self.events.get_mut(name).unwrap();
+
+                    if summ.input_path.is_none() {
+                        summ.input_path = path.clone();
+                    }
+                }
+
+                let origin = ih.origin();
+                self.notify(|o| o.input_opened(name, origin));
             }
 
             OpenResult::NotAvailable => {
@@ -586,6 +709,8 @@ impl IoProvider for BridgeState {
                     fs.read_digest = Some(DigestData::of_nothing());
                     self.events.insert(name.to_owned(), fs);
                 }
+
+                self.notify(|o| o.input_not_found(name));
             }
 
             OpenResult::Err(_) => {}
@@ -606,7 +731,19 @@ impl IoProvider for BridgeState {
         &mut self,
         status: &mut dyn StatusBackend,
     ) -> OpenResult<(InputHandle, Option<PathBuf>)> {
-        bridgestate_ioprovider_cascade!(self, input_open_primary_with_abspath(status));
+        let mut r = (|| {
+            bridgestate_ioprovider_cascade!(self, input_open_primary_with_abspath(status));
+        })();
+
+        if let OpenResult::Ok((ref mut ih, _)) = r {
+            let name = ih.name().to_owned();
+            self.count_input_bytes(&name, ih);
+
+            let origin = ih.origin();
+            self.notify(|o| o.input_opened(&name, origin));
+        }
+
+        r
     }
 
     fn input_open_format(
@@ -614,11 +751,13 @@ impl IoProvider for BridgeState {
         name: &str,
         status: &mut dyn StatusBackend,
     ) -> OpenResult<InputHandle> {
-        let r = (|| {
+        let mut r = (|| {
             bridgestate_ioprovider_cascade!(self, input_open_format(name, status));
         })();
 
-        if let OpenResult::Ok(ref ih) = r {
+        if let OpenResult::Ok(ref mut ih) = r {
+            self.count_input_bytes(name, ih);
+
             if let Some(summ) = self.events.get_mut(name) {
                 summ.access_pattern = match summ.access_pattern {
                     AccessPattern::Written => AccessPattern::WrittenThenRead,
@@ -630,6 +769,9 @@ impl IoProvider for BridgeState {
                     FileSummary::new(AccessPattern::Read, ih.origin()),
                 );
             }
+
+            let origin = ih.origin();
+            self.notify(|o| o.input_opened(name, origin));
         }
 
         r
@@ -647,6 +789,8 @@ impl DriverHooks for BridgeState {
         digest: DigestData,
         _status: &mut dyn StatusBackend,
     ) {
+        self.notify(|o| o.output_closed(&name, &digest));
+
         let summ = self
             .events
             .get_mut(&name)
@@ -660,6 +804,8 @@ impl DriverHooks for BridgeState {
         digest: Option<DigestData>,
         _status: &mut dyn StatusBackend,
     ) {
+        self.notify(|o| o.input_closed(&name, digest.as_ref()));
+
         let summ = self
             .events
             .get_mut(&name)
@@ -755,6 +901,20 @@ impl DriverHooks for BridgeState {
             Err(SystemRequestError::NotAllowed)
         }
     }
+
+    fn sysrq_error_interaction(
+        &mut self,
+        context: &str,
+        help: &str,
+        deletions_allowed: bool,
+        status: &mut dyn StatusBackend,
+    ) -> ErrorResponse {
+        // The engine only asks if the session was given an error handler.
+        match self.error_handler {
+            Some(ref mut handler) => handler.handle_error(context, help, deletions_allowed, status),
+            None => ErrorResponse::Proceed,
+        }
+    }
 }
 
 /// Possible modes for handling shell-escape functionality
@@ -788,6 +948,80 @@ struct ExternalToolPass {
     extra_requires: HashSet<String>,
 }
 
+/// An observer that is told about the progress of a [`ProcessingSession`] as
+/// it happens.
+///
+/// Observers are added with [`ProcessingSessionBuilder::observer`]. They are
+/// intended for things like progress displays, so they can only watch, not
+/// influence, the processing. All of the methods have default implementations
+/// that do nothing.
+///
+/// Files are reported with the names that the engines use for them, which
+/// means that the primary input file and standard output have empty names.
+pub trait SessionObserver {
+    /// An engine pass is starting.
+    ///
+    /// The *name* identifies the engine, such as `tex`, `bibtex`, or
+    /// `xdvipdfmx`. The *detail* may give more information, such as the reason
+    /// that TeX is being rerun or the file that BibTeX is processing.
+    fn pass_started(&mut self, _name: &str, _detail: Option<&str>) {}
+
+    /// An engine pass has finished, successfully or not.
+    fn pass_finished(&mut self, _name: &str, _succeeded: bool) {}
+
+    /// The session has decided whether to run TeX again.
+    ///
+    /// This is called each time the session considers whether the TeX output
+    /// has settled down. If TeX is going to be rerun, *reason* explains why;
+    /// if it's `None`, the session is moving on.
+    fn rerun_decided(&mut self, _reason: Option<&str>) {}
+
+    /// A file has been opened for input.
+    fn input_opened(&mut self, _name: &str, _origin: InputOrigin) {}
+
+    /// An engine tried to open a file for input, but it doesn't exist.
+    fn input_not_found(&mut self, _name: &str) {}
+
+    /// A file that was opened for input has been closed. The digest of its
+    /// contents is provided if it's available.
+    fn input_closed(&mut self, _name: &str, _digest: Option<&DigestData>) {}
+
+    /// A file has been opened for output.
+    ///
+    /// Outputs are held in memory until the end of the session, so this does
+    /// not mean that anything has been written to disk.
+    fn output_opened(&mut self, _name: &str) {}
+
+    /// A file that was opened for output has been closed, with the given
+    /// digest of the data written to it.
+    fn output_closed(&mut self, _name: &str, _digest: &DigestData) {}
+}
+
+/// Something that decides how to recover from errors in the TeX engine.
+///
+/// Handlers are set with [`ProcessingSessionBuilder::error_handler`]. When
+/// one is set, TeX stops at each error and asks the handler what to do, much
+/// like classic TeX asks the user at its `?` prompt.
+pub trait ErrorHandler {
+    /// Decide how to respond to an error.
+    ///
+    /// The *context* shows where the error occurred, in TeX's format: pairs
+    /// of lines, where the first line of each pair shows the text that has
+    /// been read and the second, indented to start where the first one ends,
+    /// shows the text that hasn't. The *help* is the help text for the error,
+    /// which may be empty. If *deletions_allowed* is false, a
+    /// [`ErrorResponse::Delete`] response is treated as
+    /// [`ErrorResponse::Proceed`]. If tokens are deleted, the handler is
+    /// called again for the same error once that's been done.
+    fn handle_error(
+        &mut self,
+        context: &str,
+        help: &str,
+        deletions_allowed: bool,
+        status: &mut dyn StatusBackend,
+    ) -> ErrorResponse;
+}
+
 /// A builder-style interface for creating a [`ProcessingSession`].
 ///
 /// This uses standard builder patterns. The `Default` implementation defaults
@@ -821,6 +1055,18 @@ pub struct ProcessingSessionBuilder {
     html_precomputed_assets: Option<AssetSpecification>,
     html_do_not_emit_files: bool,
     html_do_not_emit_assets: bool,
+    html_compat_layer: bool,
+    html_asset_manifest: Option<String>,
+    png_dpi: Option<f64>,
+    png_pages: Option<PageSelection>,
+    allow_missing_glyphs: bool,
+    pdfa: Option<PdfAConformance>,
+    pdf_tagging: bool,
+    pdf_encryption: Option<PdfEncryption>,
+    metadata: DocumentMetadata,
+    profile: Option<(PathBuf, ProfileFormat)>,
+    error_handler: Option<Box<dyn ErrorHandler>>,
+    observers: Vec<Box<dyn SessionObserver>>,
 }
 
 impl ProcessingSessionBuilder {
@@ -1107,6 +1353,127 @@ impl ProcessingSessionBuilder {
         self
     }
 
+    /// Set whether HTML-based outputs should load the LaTeX compatibility
+    /// layer.
+    ///
+    /// The layer is a bundled LaTeX package that maps standard constructs such
+    /// as sectioning commands, lists, tables, footnotes, and cross-references
+    /// to semantic HTML. When activated, the primary input is modified to load
+    /// it before anything else. This has no effect on PDF and other non-HTML
+    /// outputs. It can also be activated with the `-Z html-compat` unstable
+    /// option.
+    pub fn html_compat_layer(&mut self, enabled: bool) -> &mut Self {
+        self.html_compat_layer = enabled;
+        self
+    }
+
+    /// Give HTML font assets content-hashed names, recording them in a
+    /// manifest.
+    ///
+    /// The manifest is a JSON file mapping each plain asset name to its hashed
+    /// counterpart. Its path is relative to the HTML output directory. Hashed
+    /// names let web servers tell clients to cache the assets indefinitely.
+    pub fn html_asset_manifest<S: ToString>(&mut self, path: S) -> &mut Self {
+        self.html_asset_manifest = Some(path.to_string());
+        self
+    }
+
+    /// Set the resolution of PNG output, in pixels per inch.
+    ///
+    /// The default is 96.
+    pub fn png_dpi(&mut self, dpi: f64) -> &mut Self {
+        self.png_dpi = Some(dpi);
+        self
+    }
+
+    /// Set which pages should be rendered in PNG output.
+    ///
+    /// By default, all pages are rendered.
+    pub fn png_pages(&mut self, pages: PageSelection) -> &mut Self {
+        self.png_pages = Some(pages);
+        self
+    }
+
+    /// Set whether characters that can't be drawn in SVG or PNG output are
+    /// allowed.
+    ///
+    /// Characters set in TFM-based fonts can't be drawn. By default they are
+    /// an error; if this is true, they are omitted with a warning instead.
+    pub fn allow_missing_glyphs(&mut self, allow: bool) -> &mut Self {
+        self.allow_missing_glyphs = allow;
+        self
+    }
+
+    /// Target a level of PDF/A conformance in PDF output.
+    ///
+    /// By default, no attempt is made to create PDF/A output. When a level is
+    /// set, xdvipdfmx adds the required metadata and output intent, and warns
+    /// about anything in the document that prevents it from conforming.
+    pub fn pdfa_conformance(&mut self, level: PdfAConformance) -> &mut Self {
+        self.pdfa = Some(level);
+        self
+    }
+
+    /// Set whether PDF output should be tagged.
+    ///
+    /// A tagged PDF includes a structure tree describing the logical
+    /// organization of the document, which accessibility tools rely on. The
+    /// tree is built from the `tdux:` specials that delimit semantic elements
+    /// like paragraphs and headings, which also drive HTML output. This has no
+    /// effect on non-PDF outputs. It can also be activated with the
+    /// `-Z tagged-pdf` unstable option.
+    pub fn pdf_tagging(&mut self, enabled: bool) -> &mut Self {
+        self.pdf_tagging = enabled;
+        self
+    }
+
+    /// Encrypt PDF output with the given settings.
+    ///
+    /// By default, PDF output is not encrypted. This has no effect on non-PDF
+    /// outputs.
+    pub fn pdf_encryption(&mut self, encryption: PdfEncryption) -> &mut Self {
+        self.pdf_encryption = Some(encryption);
+        self
+    }
+
+    /// Set the document metadata.
+    ///
+    /// By default, documents are described only by the metadata that they set
+    /// themselves. See [`DocumentMetadata`] for how these are used by the
+    /// different output formats.
+    pub fn metadata(&mut self, metadata: DocumentMetadata) -> &mut Self {
+        self.metadata = metadata;
+        self
+    }
+
+    /// Profile the processing session, saving a report to the given path.
+    ///
+    /// The report records the time taken by each engine pass, the amount of
+    /// input that it read, the time spent waiting on bundle downloads, and the
+    /// TeX engine's memory usage. It is written at the end of
+    /// [`ProcessingSession::run`], even if processing fails.
+    pub fn profile<P: AsRef<Path>>(&mut self, path: P, format: ProfileFormat) -> &mut Self {
+        self.profile = Some((path.as_ref().to_owned(), format));
+        self
+    }
+
+    /// Sets a handler that the TeX engine will ask how to proceed when it
+    /// runs into an error, as classic TeX asks the user. (By default, errors
+    /// are fatal, unless the `continue-on-errors` unstable option is set.)
+    pub fn error_handler(&mut self, handler: Box<dyn ErrorHandler>) -> &mut Self {
+        self.error_handler = Some(handler);
+        self
+    }
+
+    /// Adds an observer that will be told about the session's progress as it
+    /// happens: engine passes starting and finishing, decisions about whether
+    /// to rerun TeX, and files being opened and closed. Any number of
+    /// observers may be added.
+    pub fn observer(&mut self, observer: Box<dyn SessionObserver>) -> &mut Self {
+        self.observers.push(observer);
+        self
+    }
+
     /// Creates a `ProcessingSession`.
     pub fn create(self, status: &mut dyn StatusBackend) -> Result<ProcessingSession> {
         // First, work on the "bridge state", which gathers the subset of our
@@ -1157,6 +1524,16 @@ impl ProcessingSessionBuilder {
             }
         };
 
+        let use_compat_layer = (self.html_compat_layer || self.unstables.html_compat)
+            && matches!(self.output_format, OutputFormat::Html | OutputFormat::Epub);
+        let pdf_tagging = self.pdf_tagging || self.unstables.tagged_pdf;
+
+        let pio: Box<dyn IoProvider> = if use_compat_layer {
+            Box::new(CompatLayerIo::new(pio))
+        } else {
+            pio
+        };
+
         let format_cache_path = self
             .format_cache_path
             .unwrap_or_else(|| filesystem_root.clone());
@@ -1199,6 +1576,9 @@ impl ProcessingSessionBuilder {
             genuine_stdout,
             format_primary: None,
             events: HashMap::new(),
+            input_bytes: self.profile.as_ref().map(|_| HashMap::new()),
+            observers: self.observers,
+            error_handler: self.error_handler,
         };
 
         // Now we can do the rest.
@@ -1215,10 +1595,9 @@ impl ProcessingSessionBuilder {
         let mut aux_path = PathBuf::from(tex_input_name.clone());
         aux_path.set_extension("aux");
         let mut xdv_path = aux_path.clone();
-        xdv_path.set_extension(if self.output_format == OutputFormat::Html {
-            "spx"
-        } else {
-            "xdv"
+        xdv_path.set_extension(match self.output_format {
+            OutputFormat::Html | OutputFormat::Epub => "spx",
+            _ => "xdv",
         });
         let mut pdf_path = aux_path.clone();
         pdf_path.set_extension("pdf");
@@ -1265,6 +1644,17 @@ impl ProcessingSessionBuilder {
             html_precomputed_assets: self.html_precomputed_assets,
             html_emit_files: !self.html_do_not_emit_files,
             html_emit_assets: !self.html_do_not_emit_assets,
+            html_asset_manifest: self.html_asset_manifest,
+            png_dpi: self.png_dpi,
+            png_pages: self.png_pages,
+            allow_missing_glyphs: self.allow_missing_glyphs,
+            pdfa: self.pdfa,
+            pdf_tagging,
+            pdf_encryption: self.pdf_encryption,
+            metadata: self.metadata,
+            profiler: self
+                .profile
+                .map(|(path, format)| Profiler::new(path, format)),
         })
     }
 }
@@ -1336,6 +1726,17 @@ pub struct ProcessingSession {
     html_precomputed_assets: Option<AssetSpecification>,
     html_emit_files: bool,
     html_emit_assets: bool,
+    html_asset_manifest: Option<String>,
+    png_dpi: Option<f64>,
+    png_pages: Option<PageSelection>,
+    allow_missing_glyphs: bool,
+    pdfa: Option<PdfAConformance>,
+    pdf_tagging: bool,
+    pdf_encryption: Option<PdfEncryption>,
+    metadata: DocumentMetadata,
+
+    /// If we're profiling, this gathers the information for the report.
+    profiler: Option<Profiler>,
 }
 
 const DEFAULT_MAX_TEX_PASSES: usize = 6;
@@ -1440,6 +1841,10 @@ impl ProcessingSession {
 
         self.bs.shell_escape_work = shell_escape_work;
 
+        if let Some(ref mut profiler) = self.profiler {
+            profiler.begin();
+        }
+
         // Go-time!
         let result = self.run_inner(status);
 
@@ -1455,6 +1860,24 @@ impl ProcessingSession {
             }
         }
 
+        // Save the profile, if requested. This is most interesting when things
+        // are going wrong, so do it whether or not the run succeeded.
+
+        if let Some(ref mut profiler) = self.profiler {
+            profiler.end();
+
+            match profiler.write() {
+                Ok(()) => tt_note!(
+                    status,
+                    "wrote profiling report to `{}`",
+                    profiler.path().display()
+                ),
+                Err(e) => {
+                    tt_warning!(status, "couldn't write the profiling report"; SyncError::new(e).into())
+                }
+            }
+        }
+
         // Propagate the actual result.
         result
     }
@@ -1480,13 +1903,13 @@ impl ProcessingSession {
 
         if generate_format {
             tt_note!(status, "generating format \"{}\"", self.format_name);
-            self.make_format_pass(status)?;
+            self.run_pass("format", None, |s| s.make_format_pass(status))?;
         }
 
         // Do the meat of the work.
 
         let result = match self.pass {
-            PassSetting::Tex => match self.tex_pass(None, status) {
+            PassSetting::Tex => match self.run_pass("tex", None, |s| s.tex_pass(None, status)) {
                 Ok(Some(warnings)) => {
                     tt_warning!(status, "{}", warnings);
                     Ok(0)
@@ -1677,11 +2100,11 @@ impl ProcessingSession {
             self.bibtex_pass(status)?;
             Some(RerunReason::Bibtex)
         } else {
-            warnings = self.tex_pass(None, status)?;
+            warnings = self.run_pass("tex", None, |s| s.tex_pass(None, status))?;
             let maybe_biber = self.check_biber_requirement()?;
 
             if let Some(biber) = maybe_biber {
-                self.bs.external_tool_pass(&biber, status)?;
+                self.run_pass("biber", None, |s| s.bs.external_tool_pass(&biber, status))?;
                 Some(RerunReason::Biber)
             } else if self.is_bibtex_needed() {
                 self.bibtex_pass(status)?;
@@ -1700,14 +2123,21 @@ impl ProcessingSession {
 
         for i in 0..pass_count {
             let rerun_explanation = if reruns_fixed {
-                "I was told to".to_owned()
+                Some("I was told to".to_owned())
             } else {
-                match rerun_result {
-                    Some(RerunReason::Biber) => "biber was run".to_owned(),
-                    Some(RerunReason::Bibtex) => "bibtex was run".to_owned(),
-                    Some(RerunReason::FileChange(ref s)) => format!("\"{s}\" changed"),
-                    None => break,
-                }
+                rerun_result.as_ref().map(|r| match r {
+                    RerunReason::Biber => "biber was run".to_owned(),
+                    RerunReason::Bibtex => "bibtex was run".to_owned(),
+                    RerunReason::FileChange(s) => format!("\"{s}\" changed"),
+                })
+            };
+
+            self.bs
+                .notify(|o| o.rerun_decided(rerun_explanation.as_deref()));
+
+            let rerun_explanation = match rerun_explanation {
+                Some(s) => s,
+                None => break,
             };
 
             // We're restarting the engine afresh, so clear the read inputs.
@@ -1720,7 +2150,9 @@ impl ProcessingSession {
                 summ.read_digest = None;
             }
 
-            warnings = self.tex_pass(Some(&rerun_explanation), status)?;
+            warnings = self.run_pass("tex", Some(rerun_explanation.clone()), |s| {
+                s.tex_pass(Some(&rerun_explanation), status)
+            })?;
 
             if !reruns_fixed {
                 rerun_result = self.is_rerun_needed(status);
@@ -1731,6 +2163,7 @@ impl ProcessingSession {
                         "TeX rerun seems needed, but stopping at {} passes",
                         DEFAULT_MAX_TEX_PASSES
                     );
+                    self.bs.notify(|o| o.rerun_decided(None));
                     break;
                 }
             }
@@ -1741,12 +2174,23 @@ impl ProcessingSession {
             tt_warning!(status, "{}", warnings);
         }
 
-        // And finally, xdvipdfmx or spx2html. Maybe.
+        // And finally, xdvipdfmx, spx2html, or one of the page renderers.
+        // Maybe.
 
-        if let OutputFormat::Pdf = self.output_format {
-            self.xdvipdfmx_pass(status)?;
-        } else if let OutputFormat::Html = self.output_format {
-            self.spx2html_pass(status)?;
+        match self.output_format {
+            OutputFormat::Pdf => {
+                self.run_pass("xdvipdfmx", None, |s| s.xdvipdfmx_pass(status))?;
+            }
+            OutputFormat::Html | OutputFormat::Epub => {
+                self.run_pass("spx2html", None, |s| s.spx2html_pass(status))?;
+            }
+            OutputFormat::Svg => {
+                self.run_pass("xdv2svg", None, |s| s.xdv2svg_pass(status))?;
+            }
+            OutputFormat::Png => {
+                self.run_pass("xdv2png", None, |s| s.xdv2png_pass(status))?;
+            }
+            _ => {}
         }
 
         Ok(0)
@@ -1768,6 +2212,75 @@ impl ProcessingSession {
             .unwrap_or(false)
     }
 
+    /// Run one engine pass, telling any observers about it and recording
+    /// information about it if we're profiling.
+    fn run_pass<T>(
+        &mut self,
+        name: &'static str,
+        detail: Option<String>,
+        pass: impl FnOnce(&mut Self) -> Result<T>,
+    ) -> Result<T> {
+        self.bs.notify(|o| o.pass_started(name, detail.as_deref()));
+
+        let start = match self.profiler {
+            Some(ref p) => p.elapsed(),
+            None => {
+                let result = pass(self);
+                self.bs.notify(|o| o.pass_finished(name, result.is_ok()));
+                return result;
+            }
+        };
+
+        let network_start = self.bs.bundle.network_time();
+
+        if let Some(ref mut counts) = self.bs.input_bytes {
+            counts.clear();
+        }
+
+        let t0 = Instant::now();
+        let result = pass(self);
+        let duration = t0.elapsed();
+
+        let mut input_bytes = InputBytes::default();
+
+        for (origin, n) in self.bs.input_bytes.iter().flat_map(|c| c.values()) {
+            if *origin == InputOrigin::Filesystem {
+                input_bytes.filesystem += n.get();
+            } else {
+                input_bytes.other += n.get();
+            }
+        }
+
+        // The TeX engine puts its memory statistics at the end of its log.
+        let memory = if name == "tex" {
+            let log_path = format!("{}.log", self.tex_aux_path.trim_end_matches(".aux"));
+            self.bs
+                .mem
+                .files
+                .borrow()
+                .get(&log_path)
+                .and_then(|file| MemoryStats::from_log(&file.data))
+        } else {
+            None
+        };
+
+        let network_time = self.bs.bundle.network_time().saturating_sub(network_start);
+
+        self.profiler.as_mut().unwrap().record(PassProfile {
+            name,
+            detail,
+            start,
+            duration,
+            network_time,
+            input_bytes,
+            memory,
+            succeeded: result.is_ok(),
+        });
+
+        self.bs.notify(|o| o.pass_finished(name, result.is_ok()));
+        result
+    }
+
     /// Use the TeX engine to generate a format file.
     #[allow(clippy::manual_split_once)] // requires Rust 1.52 (note that we don't actually define our MSRV)
     fn make_format_pass(&mut self, status: &mut dyn StatusBackend) -> Result<i32> {
@@ -1849,6 +2362,7 @@ impl ProcessingSession {
                 status.note_highlighted("Running ", "TeX", " ...");
             }
 
+            let interactive_errors = self.bs.error_handler.is_some();
             let mut launcher =
                 CoreBridgeLauncher::new_with_security(&mut self.bs, status, self.security.clone());
 
@@ -1865,11 +2379,17 @@ impl ProcessingSession {
             }
 
             TexEngine::default()
-                .halt_on_error_mode(!self.unstables.continue_on_errors)
+                .halt_on_error_mode(!(self.unstables.continue_on_errors || interactive_errors))
+                .interactive_errors(interactive_errors)
                 .initex_mode(self.output_format == OutputFormat::Format)
                 .synctex(self.synctex_enabled)
-                .semantic_pagination(self.output_format == OutputFormat::Html)
+                .semantic_pagination(matches!(
+                    self.output_format,
+                    OutputFormat::Html | OutputFormat::Epub
+                ))
                 .shell_escape(self.shell_escape_mode != ShellEscapeMode::Disabled)
+                .memory_stats(self.profiler.is_some())
+                .macro_profile(self.unstables.macro_profile)
                 .build_date(self.build_date)
                 .process(
                     &mut launcher,
@@ -1949,7 +2469,9 @@ impl ProcessingSession {
         }
 
         for f in aux_files {
-            let _r = self.bibtex_pass_for_one_aux_file(status, &f)?;
+            let _r = self.run_pass("bibtex", Some(f.clone()), |s| {
+                s.bibtex_pass_for_one_aux_file(status, &f)
+            })?;
         }
 
         Ok(0)
@@ -1964,6 +2486,10 @@ impl ProcessingSession {
             let mut engine = XdvipdfmxEngine::default();
 
             engine.build_date(self.build_date);
+            engine.pdfa_conformance(self.pdfa);
+            engine.enable_tagging(self.pdf_tagging);
+            engine.encryption(self.pdf_encryption.clone());
+            engine.metadata(self.metadata.to_pdf());
 
             if let Some(ref ps) = self.unstables.paper_size {
                 engine.paper_spec(ps.clone());
@@ -1979,12 +2505,37 @@ impl ProcessingSession {
     fn spx2html_pass(&mut self, status: &mut dyn StatusBackend) -> Result<i32> {
         {
             let mut engine = Spx2HtmlEngine::default();
+            let mut _staging = None;
+
+            if self.output_format == OutputFormat::Epub {
+                // The HTML tree is only an intermediate product here, so stage
+                // it in a temporary directory and save just the packaged book.
+                let out_dir = match (self.html_emit_files, self.output_path.as_ref()) {
+                    (true, Some(p)) => p,
+                    _ => return Err(errmsg!("EPUB output must be saved directly to disk")),
+                };
 
-            match (self.html_emit_files, self.output_path.as_ref()) {
-                (true, Some(p)) => engine.output_base(p),
-                (false, _) => engine.do_not_emit_files(),
-                (true, None) => return Err(errmsg!("HTML output must be saved directly to disk")),
-            };
+                let staging = ctry!(
+                    tempfile::Builder::new().tempdir();
+                    "can't create temporary directory for EPUB output"
+                );
+                let stem = self.tex_pdf_path.trim_end_matches(".pdf");
+
+                engine
+                    .output_base(staging.path())
+                    .epub_output(out_dir.join(format!("{stem}.epub")))
+                    .build_date(self.build_date)
+                    .svg_canvases(true);
+                _staging = Some(staging);
+            } else {
+                match (self.html_emit_files, self.output_path.as_ref()) {
+                    (true, Some(p)) => engine.output_base(p),
+                    (false, _) => engine.do_not_emit_files(),
+                    (true, None) => {
+                        return Err(errmsg!("HTML output must be saved directly to disk"))
+                    }
+                };
+            }
 
             if let Some(p) = self.html_assets_spec_path.as_ref() {
                 engine.assets_spec_path(p);
@@ -1996,6 +2547,12 @@ impl ProcessingSession {
                 engine.precomputed_assets(a.clone());
             }
 
+            if let Some(p) = self.html_asset_manifest.as_ref() {
+                engine.asset_manifest(p);
+            }
+
+            engine.template_variable("tduxMetadata", self.metadata.to_template_value());
+
             status.note_highlighted("Running ", "spx2html", " ...");
             engine.process_to_filesystem(&mut self.bs, status, &self.tex_xdv_path)?;
         }
@@ -2004,6 +2561,72 @@ impl ProcessingSession {
         Ok(0)
     }
 
+    fn xdv2svg_pass(&mut self, status: &mut dyn StatusBackend) -> Result<i32> {
+        {
+            status.note_highlighted("Running ", "xdv2svg", " ...");
+
+            let mut engine = Xdv2SvgEngine::default();
+
+            if let Some(ref ps) = self.unstables.paper_size {
+                engine.paper_spec(ps.clone());
+            }
+
+            engine.allow_missing_glyphs(self.allow_missing_glyphs);
+
+            // Pages are named like `{stem}-{n}.svg`, next to where the PDF
+            // would have gone.
+            let stem = self.tex_pdf_path.trim_end_matches(".pdf");
+            engine.process(&mut self.bs, status, &self.tex_xdv_path, stem)?;
+        }
+
+        self.bs.mem.files.borrow_mut().remove(&self.tex_xdv_path);
+        Ok(0)
+    }
+
+    fn xdv2png_pass(&mut self, status: &mut dyn StatusBackend) -> Result<i32> {
+        {
+            status.note_highlighted("Running ", "xdv2png", " ...");
+
+            let mut engine = Xdv2PngEngine::default();
+
+            if let Some(ref ps) = self.unstables.paper_size {
+                engine.paper_spec(ps.clone());
+            }
+
+            if let Some(dpi) = self.png_dpi {
+                engine.dpi(dpi);
+            }
+
+            if let Some(ref pages) = self.png_pages {
+                engine.pages(pages.clone());
+            }
+
+            engine.allow_missing_glyphs(self.allow_missing_glyphs);
+
+            let stem = self.tex_pdf_path.trim_end_matches(".pdf");
+            let outcome = engine.process(&mut self.bs, status, &self.tex_xdv_path, stem)?;
+
+            if outcome.n_written == 0 {
+                tt_warning!(status, "no pages were selected for PNG output");
+            }
+
+            if outcome.omitted_chars > 0 {
+                tt_warning!(
+                    status,
+                    "{} characters in TFM-based fonts were left out of the PNG output",
+                    outcome.omitted_chars
+                );
+            }
+
+            for path in &outcome.omitted_images {
+                tt_warning!(status, "image `{}` was left out of the PNG output", path);
+            }
+        }
+
+        self.bs.mem.files.borrow_mut().remove(&self.tex_xdv_path);
+        Ok(0)
+    }
+
     /// Get what was printed to standard output, if anything.
     pub fn get_stdout_content(&self) -> Vec<u8> {
         self.bs
@@ -2015,6 +2638,40 @@ impl ProcessingSession {
             .unwrap_or_else(Vec::new)
     }
 
+    /// Get the paths of the files on the filesystem that processing depended
+    /// on.
+    ///
+    /// This includes the primary input file, every file that the engines read
+    /// from the filesystem or the extra search paths, and the files that the
+    /// engines looked for in the filesystem root but didn't find, since
+    /// creating them could change the results. Files that the session itself
+    /// wrote out are excluded. This is what needs to be watched to know when
+    /// to rerun the session.
+    pub fn input_paths(&self) -> HashSet<PathBuf> {
+        let mut paths = HashSet::new();
+
+        if let Some(ref p) = self.primary_input_path {
+            paths.insert(p.clone());
+        }
+
+        for (name, info) in &self.bs.events {
+            if info.got_written_to_disk {
+                continue;
+            }
+
+            if let Some(ref p) = info.input_path {
+                paths.insert(p.clone());
+            } else if info.input_origin == InputOrigin::NotInput
+                && info.access_pattern == AccessPattern::Read
+                && !name.is_empty()
+            {
+                paths.insert(self.bs.filesystem.root().join(name));
+            }
+        }
+
+        paths
+    }
+
     /// Consume this session and return the current set of files in memory.
     ///
     /// This convenience function tries to help with the annoyances of getting
@@ -12,8 +12,11 @@
 pub mod bibtex;
 pub mod spx2html;
 pub mod tex;
+pub mod xdv2png;
+pub mod xdv2svg;
 pub mod xdvipdfmx;
 
 pub use self::{
-    bibtex::BibtexEngine, spx2html::Spx2HtmlEngine, tex::TexEngine, xdvipdfmx::XdvipdfmxEngine,
+    bibtex::BibtexEngine, spx2html::Spx2HtmlEngine, tex::TexEngine, xdv2png::Xdv2PngEngine,
+    xdv2svg::Xdv2SvgEngine, xdvipdfmx::XdvipdfmxEngine,
 };
@@ -2,4 +2,6 @@
 // Copyright 2017-2021 the Tectonic Project
 // Licensed under the MIT License.
 
-pub use tectonic_engine_xdvipdfmx::XdvipdfmxEngine;
+pub use tectonic_engine_xdvipdfmx::{
+    PdfAConformance, PdfEncryption, PdfMetadata, PdfPermissions, XdvipdfmxEngine,
+};
@@ -85,6 +85,7 @@ pub mod driver;
 pub mod engines;
 pub mod errors;
 pub mod io;
+pub mod profile;
 pub mod status;
 pub mod unstable_opts;
 
@@ -96,7 +97,11 @@ pub mod test_util;
 pub use crate::engines::bibtex::BibtexEngine;
 pub use crate::engines::spx2html::Spx2HtmlEngine;
 pub use crate::engines::tex::{TexEngine, TexOutcome};
-pub use crate::engines::xdvipdfmx::XdvipdfmxEngine;
+pub use crate::engines::xdv2png::Xdv2PngEngine;
+pub use crate::engines::xdv2svg::Xdv2SvgEngine;
+pub use crate::engines::xdvipdfmx::{
+    PdfAConformance, PdfEncryption, PdfMetadata, PdfPermissions, XdvipdfmxEngine,
+};
 pub use crate::errors::{Error, ErrorKind, Result};
 
 // Convenienece re-exports for migration into our multi-crate setup
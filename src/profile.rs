@@ -0,0 +1,342 @@
+// Copyright 2026 the Tectonic Project
+// Licensed under the MIT License.
+
+//! Timing and resource reports for processing sessions.
+//!
+//! When a [`crate::driver::ProcessingSession`] is set up with
+//! [`crate::driver::ProcessingSessionBuilder::profile`], it records how long
+//! each engine pass took, how much input it read, how long it spent waiting on
+//! the network, and, for TeX passes, the engine’s memory usage. At the end of
+//! the run, these data are saved in one of the formats described by
+//! [`ProfileFormat`].
+
+use serde_json::{json, Value};
+use std::{
+    fs::File,
+    io::{BufWriter, Write},
+    path::{Path, PathBuf},
+    result::Result as StdResult,
+    str::FromStr,
+    time::{Duration, Instant},
+};
+
+use crate::{ctry, errors::Result};
+
+/// The file formats in which a profiling report can be saved.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum ProfileFormat {
+    /// A JSON summary of the run and each of its passes.
+    #[default]
+    Json,
+
+    /// A trace in the Chrome “Trace Event” format, which can be loaded into
+    /// `chrome://tracing`, Perfetto, or speedscope.
+    ChromeTrace,
+}
+
+impl FromStr for ProfileFormat {
+    type Err = &'static str;
+
+    fn from_str(a_str: &str) -> StdResult<Self, Self::Err> {
+        match a_str {
+            "json" => Ok(ProfileFormat::Json),
+            "chrome" => Ok(ProfileFormat::ChromeTrace),
+            _ => Err("unsupported or unknown profile format"),
+        }
+    }
+}
+
+/// Counts of input bytes, broken down by where the inputs came from.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub(crate) struct InputBytes {
+    /// Bytes from files on the filesystem.
+    pub filesystem: u64,
+
+    /// Bytes from everywhere else, mostly the bundle.
+    pub other: u64,
+}
+
+impl InputBytes {
+    fn add(&mut self, other: &InputBytes) {
+        self.filesystem += other.filesystem;
+        self.other += other.other;
+    }
+
+    fn to_value(self) -> Value {
+        json!({
+            "filesystem": self.filesystem,
+            "other": self.other,
+        })
+    }
+}
+
+/// The memory usage summary that XeTeX writes at the end of its log.
+///
+/// Each item is a pair of the amount used and the amount available. The
+/// available control sequences include the `hash_extra` allocation.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub(crate) struct MemoryStats {
+    strings: (u64, u64),
+    string_characters: (u64, u64),
+    words_of_memory: (u64, u64),
+    control_sequences: (u64, u64),
+    font_info_words: (u64, u64),
+    fonts: u64,
+    hyphenation_exceptions: (u64, u64),
+}
+
+impl MemoryStats {
+    const HEADER: &'static str = "Here is how much of TeX's memory you used:";
+
+    /// Extract the memory usage summary from a log file, if it has one.
+    pub fn from_log(log: &[u8]) -> Option<Self> {
+        let log = String::from_utf8_lossy(log);
+        let start = log.rfind(Self::HEADER)? + Self::HEADER.len();
+        let mut stats = MemoryStats::default();
+
+        for line in log[start..].lines().skip(1) {
+            let words: Vec<&str> = line.split_whitespace().collect();
+
+            let (used, rest) = match words.split_first() {
+                Some((n, rest)) => match n.parse::<u64>() {
+                    Ok(n) => (n, rest),
+                    Err(_) => break,
+                },
+                None => break,
+            };
+
+            let avail = rest
+                .iter()
+                .rev()
+                .find_map(|w| {
+                    w.split('+')
+                        .map(str::parse::<u64>)
+                        .sum::<StdResult<u64, _>>()
+                        .ok()
+                })
+                .unwrap_or(0);
+
+            match rest {
+                ["string", "characters", ..] => stats.string_characters = (used, avail),
+                ["string" | "strings", ..] => stats.strings = (used, avail),
+                ["words", "of", "memory", ..] => stats.words_of_memory = (used, avail),
+                ["multiletter", ..] => stats.control_sequences = (used, avail),
+                ["words", "of", "font", "info", "for", n, ..] => {
+                    stats.font_info_words = (used, rest[rest.len() - 3].parse().unwrap_or(0));
+                    stats.fonts = n.parse().unwrap_or(0);
+                }
+                ["hyphenation", ..] => stats.hyphenation_exceptions = (used, avail),
+                _ => {}
+            }
+        }
+
+        Some(stats)
+    }
+
+    fn to_value(&self) -> Value {
+        let pair = |(used, avail): (u64, u64)| json!({ "used": used, "available": avail });
+
+        json!({
+            "strings": pair(self.strings),
+            "string_characters": pair(self.string_characters),
+            "words_of_memory": pair(self.words_of_memory),
+            "control_sequences": pair(self.control_sequences),
+            "font_info_words": pair(self.font_info_words),
+            "fonts": self.fonts,
+            "hyphenation_exceptions": pair(self.hyphenation_exceptions),
+        })
+    }
+}
+
+/// Information about one pass of one engine.
+#[derive(Clone, Debug)]
+pub(crate) struct PassProfile {
+    /// The name of the engine, such as `tex` or `xdvipdfmx`.
+    pub name: &'static str,
+
+    /// Extra information about the pass, such as why TeX was rerun.
+    pub detail: Option<String>,
+
+    /// When the pass started, relative to the start of the run.
+    pub start: Duration,
+
+    /// How long the pass took.
+    pub duration: Duration,
+
+    /// How much of that time was spent waiting on the bundle's network
+    /// backend.
+    pub network_time: Duration,
+
+    /// The sizes of the distinct files that the pass opened for input.
+    pub input_bytes: InputBytes,
+
+    /// The engine's memory usage, for TeX passes.
+    pub memory: Option<MemoryStats>,
+
+    /// Whether the pass completed successfully.
+    pub succeeded: bool,
+}
+
+/// Collects [`PassProfile`]s over the course of a run and writes them out.
+#[derive(Debug)]
+pub(crate) struct Profiler {
+    path: PathBuf,
+    format: ProfileFormat,
+    origin: Instant,
+    total: Duration,
+    passes: Vec<PassProfile>,
+}
+
+impl Profiler {
+    pub fn new(path: PathBuf, format: ProfileFormat) -> Self {
+        Profiler {
+            path,
+            format,
+            origin: Instant::now(),
+            total: Duration::ZERO,
+            passes: Vec::new(),
+        }
+    }
+
+    /// The path that the report will be written to.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Mark the beginning of a run, discarding anything recorded previously.
+    pub fn begin(&mut self) {
+        self.origin = Instant::now();
+        self.total = Duration::ZERO;
+        self.passes.clear();
+    }
+
+    /// Mark the end of a run.
+    pub fn end(&mut self) {
+        self.total = self.origin.elapsed();
+    }
+
+    /// The time since the start of the run.
+    pub fn elapsed(&self) -> Duration {
+        self.origin.elapsed()
+    }
+
+    pub fn record(&mut self, pass: PassProfile) {
+        self.passes.push(pass);
+    }
+
+    /// Write the report in the requested format.
+    pub fn write(&self) -> Result<()> {
+        let report = match self.format {
+            ProfileFormat::Json => self.json_report(),
+            ProfileFormat::ChromeTrace => self.chrome_trace(),
+        };
+
+        let f = ctry!(File::create(&self.path); "couldn't create profile file `{}`", self.path.display());
+        let mut f = BufWriter::new(f);
+        ctry!(serde_json::to_writer_pretty(&mut f, &report); "couldn't write profile file `{}`", self.path.display());
+        ctry!(writeln!(f); "couldn't write profile file `{}`", self.path.display());
+        Ok(())
+    }
+
+    fn json_report(&self) -> Value {
+        let mut input_bytes = InputBytes::default();
+        let mut network_time = Duration::ZERO;
+
+        let passes: Vec<Value> = self
+            .passes
+            .iter()
+            .map(|p| {
+                input_bytes.add(&p.input_bytes);
+                network_time += p.network_time;
+
+                json!({
+                    "name": p.name,
+                    "detail": p.detail,
+                    "start_seconds": p.start.as_secs_f64(),
+                    "seconds": p.duration.as_secs_f64(),
+                    "network_seconds": p.network_time.as_secs_f64(),
+                    "input_bytes": p.input_bytes.to_value(),
+                    "memory": p.memory.as_ref().map(MemoryStats::to_value),
+                    "succeeded": p.succeeded,
+                })
+            })
+            .collect();
+
+        json!({
+            "total_seconds": self.total.as_secs_f64(),
+            "network_seconds": network_time.as_secs_f64(),
+            "input_bytes": input_bytes.to_value(),
+            "passes": passes,
+        })
+    }
+
+    fn chrome_trace(&self) -> Value {
+        let mut events = vec![json!({
+            "name": "tectonic",
+            "cat": "session",
+            "ph": "X",
+            "ts": 0,
+            "dur": self.total.as_micros() as u64,
+            "pid": 1,
+            "tid": 1,
+        })];
+
+        for p in &self.passes {
+            let name = match p.detail {
+                Some(ref d) => format!("{} ({})", p.name, d),
+                None => p.name.to_owned(),
+            };
+
+            events.push(json!({
+                "name": name,
+                "cat": "pass",
+                "ph": "X",
+                "ts": p.start.as_micros() as u64,
+                "dur": p.duration.as_micros() as u64,
+                "pid": 1,
+                "tid": 1,
+                "args": {
+                    "network_ms": p.network_time.as_secs_f64() * 1000.,
+                    "input_bytes": p.input_bytes.to_value(),
+                    "memory": p.memory.as_ref().map(MemoryStats::to_value),
+                    "succeeded": p.succeeded,
+                },
+            }));
+        }
+
+        json!({
+            "traceEvents": events,
+            "displayTimeUnit": "ms",
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn memory_stats() {
+        let log = b"Output written on a.xdv (1 page, 228 bytes).
+ \nHere is how much of TeX's memory you used:
+ 27 strings out of 478287
+ 312 string characters out of 5848008
+ 1896 words of memory out of 5000000
+ 9 multiletter control sequences out of 15000+600000
+ 532009 words of font info for 48 fonts, out of 8000000 for 9000
+ 14 hyphenation exceptions out of 8191
+ 7i,4n,5p,180b,30s stack positions out of 10000i,1000n,20000p,200000b,200000s
+";
+
+        let stats = MemoryStats::from_log(log).unwrap();
+        assert_eq!(stats.strings, (27, 478287));
+        assert_eq!(stats.string_characters, (312, 5848008));
+        assert_eq!(stats.words_of_memory, (1896, 5000000));
+        assert_eq!(stats.control_sequences, (9, 615000));
+        assert_eq!(stats.font_info_words, (532009, 8000000));
+        assert_eq!(stats.fonts, 48);
+        assert_eq!(stats.hyphenation_exceptions, (14, 8191));
+
+        assert_eq!(MemoryStats::from_log(b"no stats here"), None);
+    }
+}
@@ -34,6 +34,14 @@ const HELPMSG: &str = r#"Available unstable options:
                                     and this option makes some extra functionality trade-offs.
                                     Specifically, deterministic mode breaks SyncTeX's auxiliary files
                                     as they include and rely on absolute file paths
+    -Z html-compat              In HTML and EPUB output, load a compatibility layer that maps standard
+                                    LaTeX sectioning, lists, tables, footnotes, figures, and
+                                    cross-references to semantic HTML
+    -Z tagged-pdf               In PDF output, build a tagged PDF from the document's structure
+                                    specials
+    -Z macro-profile            Sample where the TeX engine spends its time, by macro and by input
+                                    file line, and save the results as a flame graph in the
+                                    "folded" format to <jobname>.folded
 "#;
 
 // Each entry of this should correspond to a field of UnstableOptions.
@@ -47,6 +55,9 @@ pub enum UnstableArg {
     ShellEscapeEnabled,
     ShellEscapeCwd(String),
     DeterministicModeEnabled,
+    HtmlCompatEnabled,
+    TaggedPdfEnabled,
+    MacroProfileEnabled,
 }
 
 impl FromStr for UnstableArg {
@@ -105,6 +116,12 @@ impl FromStr for UnstableArg {
 
             "deterministic-mode" => require_no_value(value, UnstableArg::DeterministicModeEnabled),
 
+            "html-compat" => require_no_value(value, UnstableArg::HtmlCompatEnabled),
+
+            "tagged-pdf" => require_no_value(value, UnstableArg::TaggedPdfEnabled),
+
+            "macro-profile" => require_no_value(value, UnstableArg::MacroProfileEnabled),
+
             _ => Err(format!("Unknown unstable option '{arg}'").into()),
         }
     }
@@ -130,6 +147,21 @@ pub struct UnstableOptions {
     /// `/dev/urandom`), but anything else (especially behaviour in TeXLive
     /// packages) is considered a bug.
     pub deterministic_mode: bool,
+
+    /// Load the LaTeX compatibility layer for HTML-based outputs.
+    ///
+    /// See [`crate::driver::ProcessingSessionBuilder::html_compat_layer`].
+    pub html_compat: bool,
+
+    /// Create tagged PDF output.
+    ///
+    /// See [`crate::driver::ProcessingSessionBuilder::pdf_tagging`].
+    pub tagged_pdf: bool,
+
+    /// Profile TeX macro expansion.
+    ///
+    /// See [`crate::engines::tex::TexEngine::macro_profile`].
+    pub macro_profile: bool,
 }
 
 impl UnstableOptions {
@@ -153,6 +185,9 @@ impl UnstableOptions {
                     opts.shell_escape = true;
                 }
                 DeterministicModeEnabled => opts.deterministic_mode = true,
+                HtmlCompatEnabled => opts.html_compat = true,
+                TaggedPdfEnabled => opts.tagged_pdf = true,
+                MacroProfileEnabled => opts.macro_profile = true,
             }
         }
 
@@ -8,8 +8,10 @@
 //! ProcessingSessionBuilder will need to learn how to tell `xdvipdfmx` to
 //! enable the reproducibility options used in the `tex-outputs` test rig.
 
+use std::{cell::RefCell, rc::Rc};
 use tectonic::config::PersistentConfig;
-use tectonic::driver::ProcessingSessionBuilder;
+use tectonic::driver::{ProcessingSessionBuilder, SessionObserver};
+use tectonic::io::InputOrigin;
 use tectonic::status::termcolor::TermcolorStatusBackend;
 use tectonic::status::ChatterLevel;
 
@@ -50,3 +52,84 @@ fn the_letter_a() {
         .run(&mut status)
         .expect("failed to execute processing session");
 }
+
+#[derive(Clone, Default)]
+struct RecordingObserver(Rc<RefCell<Vec<String>>>);
+
+impl SessionObserver for RecordingObserver {
+    fn pass_started(&mut self, name: &str, _detail: Option<&str>) {
+        self.0.borrow_mut().push(format!("start {name}"));
+    }
+
+    fn pass_finished(&mut self, name: &str, succeeded: bool) {
+        self.0
+            .borrow_mut()
+            .push(format!("finish {name} {succeeded}"));
+    }
+
+    fn input_opened(&mut self, name: &str, _origin: InputOrigin) {
+        self.0.borrow_mut().push(format!("read {name}"));
+    }
+
+    fn input_closed(&mut self, name: &str, _digest: Option<&tectonic::digest::DigestData>) {
+        self.0.borrow_mut().push(format!("closed {name}"));
+    }
+
+    fn output_closed(&mut self, name: &str, _digest: &tectonic::digest::DigestData) {
+        self.0.borrow_mut().push(format!("wrote {name}"));
+    }
+}
+
+#[test]
+fn observer() {
+    util::set_test_root();
+
+    let mut status = TermcolorStatusBackend::new(ChatterLevel::Minimal);
+    let bundle = util::TestBundle::default();
+    let observer = RecordingObserver::default();
+
+    let tempdir = tempfile::Builder::new()
+        .prefix("tectonic_driver_test")
+        .tempdir()
+        .unwrap();
+
+    let mut pbuilder = ProcessingSessionBuilder::default();
+    pbuilder
+        .primary_input_path(util::test_path(&["tex-outputs", "the_letter_a.tex"]))
+        .tex_input_name("the_letter_a.tex")
+        .format_name("plain")
+        .format_cache_path(util::test_path(&[]))
+        .output_dir(tempdir.path())
+        .bundle(Box::new(bundle))
+        .observer(Box::new(observer.clone()));
+
+    let mut session = pbuilder
+        .create(&mut status)
+        .expect("couldn't create processing session");
+
+    session
+        .run(&mut status)
+        .expect("failed to execute processing session");
+
+    let events = observer.0.borrow();
+    let position = |e: &str| events.iter().position(|x| x == e);
+
+    let tex_start = position("start tex").expect("no TeX pass seen");
+    let tex_end = position("finish tex true").expect("no TeX pass end seen");
+    assert!(tex_start < tex_end);
+    assert!(position("wrote the_letter_a.xdv").unwrap() < tex_end);
+    assert!(position("start xdvipdfmx").unwrap() > tex_end);
+    assert!(position("finish xdvipdfmx true").is_some());
+
+    // Every opening of the primary input, which has an empty name, is
+    // reported and followed by its closing.
+    let primary: Vec<_> = events
+        .iter()
+        .filter(|e| *e == "read " || *e == "closed ")
+        .collect();
+    assert!(!primary.is_empty());
+    assert_eq!(primary.len() % 2, 0);
+    for pair in primary.chunks(2) {
+        assert_eq!(pair, ["read ", "closed "]);
+    }
+}
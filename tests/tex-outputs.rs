@@ -10,8 +10,9 @@ use tectonic::errors::DefinitelySame;
 use tectonic::io::testing::SingleInputFileIo;
 use tectonic::io::{FilesystemIo, FilesystemPrimaryInputIo, IoProvider, IoStack, MemoryIo};
 use tectonic::unstable_opts::UnstableOptions;
-use tectonic::{TexEngine, XdvipdfmxEngine};
+use tectonic::{Spx2HtmlEngine, TexEngine, XdvipdfmxEngine};
 use tectonic_bridge_core::{CoreBridgeLauncher, MinimalDriver};
+use tectonic_engine_spx2html::compat::CompatLayerIo;
 use tectonic_errors::{anyhow::anyhow, Result};
 use tectonic_status_base::NoopStatusBackend;
 
@@ -180,6 +181,81 @@ impl TestCase {
     }
 }
 
+/// Typeset a test file and convert it to an uncompressed, tagged PDF.
+fn tagged_pdf_output(stem: &str) -> String {
+    util::set_test_root();
+
+    let mut fmt =
+        SingleInputFileIo::new(&ensure_plain_format().expect("couldn't write format file"));
+    let mut tex =
+        FilesystemPrimaryInputIo::new(test_path(&["tex-outputs", &format!("{stem}.tex")]));
+    let mut mem = MemoryIo::new(true);
+    let mut assets = FilesystemIo::new(&test_path(&["assets"]), false, false, HashSet::new());
+    let xdvname = format!("{stem}.xdv");
+    let pdfname = format!("{stem}.pdf");
+
+    {
+        let io = IoStack::new(vec![&mut mem, &mut tex, &mut fmt, &mut assets]);
+        let mut hooks = MinimalDriver::new(io);
+        let mut status = NoopStatusBackend::default();
+        let mut launcher = CoreBridgeLauncher::new(&mut hooks, &mut status);
+
+        TexEngine::default()
+            .process(&mut launcher, "plain.fmt", &format!("{stem}.tex"))
+            .unwrap();
+
+        XdvipdfmxEngine::default()
+            .enable_compression(false)
+            .enable_deterministic_tags(true)
+            .enable_tagging(true)
+            .process(&mut launcher, &xdvname, &pdfname)
+            .unwrap();
+    }
+
+    let files = mem.files.borrow();
+    String::from_utf8_lossy(&files.get(&pdfname).unwrap().data).into_owned()
+}
+
+/// Typeset a test file in HTML mode, with the files of the HTML compatibility
+/// layer available, and return the main HTML output.
+fn html_output(stem: &str) -> String {
+    util::set_test_root();
+
+    let mut fmt =
+        SingleInputFileIo::new(&ensure_plain_format().expect("couldn't write format file"));
+    let mut tex = CompatLayerIo::new(Box::new(FilesystemPrimaryInputIo::new(test_path(&[
+        "tex-outputs",
+        &format!("{stem}.tex"),
+    ]))));
+    let mut mem = MemoryIo::new(true);
+    let mut assets = FilesystemIo::new(&test_path(&["assets"]), false, false, HashSet::new());
+    let mut inputs = FilesystemIo::new(&test_path(&["tex-outputs"]), false, false, HashSet::new());
+    let out_dir = tempfile::tempdir().unwrap();
+
+    {
+        let io = IoStack::new(vec![&mut mem, &mut tex, &mut fmt, &mut assets, &mut inputs]);
+        let mut hooks = MinimalDriver::new(io);
+        let mut status = NoopStatusBackend::default();
+
+        {
+            let mut launcher = CoreBridgeLauncher::new(&mut hooks, &mut status);
+
+            TexEngine::default()
+                .semantic_pagination(true)
+                .process(&mut launcher, "plain.fmt", &format!("{stem}.tex"))
+                .unwrap();
+        }
+
+        Spx2HtmlEngine::default()
+            .output_base(out_dir.path())
+            .do_not_emit_assets()
+            .process_to_filesystem(&mut hooks, &mut status, &format!("{stem}.spx"))
+            .unwrap();
+    }
+
+    std::fs::read_to_string(out_dir.path().join("index.html")).unwrap()
+}
+
 // Keep these alphabetized.
 
 #[test]
@@ -214,6 +290,16 @@ fn issue393_ungetc() {
         .go()
 }
 
+#[test]
+fn mathml_structure() {
+    let html = html_output("mathml_structure");
+    assert!(html.contains(
+        "<math><msup><mi>x</mi><mn>2</mn></msup><mo>+</mo>\
+         <mfrac><mrow><mi>a</mi></mrow><mrow><mi>b</mi></mrow></mfrac><mo>=</mo>\
+         <msqrt><msub><mi>y</mi><mn>10</mn></msub></msqrt></math>"
+    ));
+}
+
 #[test]
 fn md5_of_hello() {
     TestCase::new("md5_of_hello").check_pdf(true).go()
@@ -314,6 +400,44 @@ fn synctex() {
     TestCase::new("synctex").check_synctex(true).go()
 }
 
+#[test]
+fn tagged_pdf() {
+    let pdf = tagged_pdf_output("tagged_pdf");
+
+    assert!(pdf.contains("/StructTreeRoot"));
+    assert!(pdf.contains("/ParentTree"));
+    assert!(pdf.contains("/ParentTreeNextKey 1"));
+    assert!(pdf.contains("/StructParents 0"));
+    assert!(pdf.contains("/MarkInfo<</Marked true>>"));
+    assert!(pdf.contains("/h1/H1"));
+    assert!(pdf.contains("/p/P"));
+
+    // The page content is split into marked-content sequences, alternating
+    // between the elements and the Document that contains them.
+    let mcids = [
+        "/Document <</MCID 0>> BDC",
+        "/h1 <</MCID 1>> BDC",
+        "/Document <</MCID 2>> BDC",
+        "/p <</MCID 3>> BDC",
+        "/Document <</MCID 4>> BDC",
+    ];
+    let positions: Vec<_> = mcids
+        .iter()
+        .map(|m| pdf.find(m).unwrap_or_else(|| panic!("missing `{}`", m)))
+        .collect();
+    assert!(positions.windows(2).all(|w| w[0] < w[1]));
+    assert_eq!(pdf.matches(" BDC").count(), pdf.matches(" EMC").count());
+}
+
+#[test]
+fn tagged_pdf_without_structure() {
+    // Without any structure markup, the output isn't claimed to be tagged.
+    let pdf = tagged_pdf_output("the_letter_a");
+
+    assert!(pdf.contains("/StructTreeRoot"));
+    assert!(!pdf.contains("/MarkInfo"));
+}
+
 #[test]
 fn unicode_file_name() {
     TestCase::new("hallöchen 🐨 welt 🌍.tex")